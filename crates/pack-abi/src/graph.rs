@@ -0,0 +1,507 @@
+//! Node-level (DAG-aware) view of a CGRF buffer.
+//!
+//! [`decode`](crate::decode) unfolds a buffer into a [`Value`] tree: a node
+//! referenced from two parents is materialised twice, and a cycle is an error.
+//! [`decode_graph`] instead keeps the buffer's node table as-is — every node
+//! becomes one [`NodeValue`] whose children are node indices — so shared
+//! subtrees stay shared and cyclic buffers (which `Value` cannot express) can
+//! be inspected, rewritten and re-encoded with [`ValueGraph::to_bytes`].
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use hashbrown::{HashMap, HashSet};
+
+use crate::{
+    decode_value, decode_value_type, encode_value_type, fixed_width, AbiError, Cursor, Decoder,
+    Encoder, GraphBuffer, GraphCodec, Limits, Node, NodeKind, Value, ValueType,
+};
+
+/// A decoded buffer with its node sharing intact. `nodes[root]` is the root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueGraph {
+    pub nodes: Vec<NodeValue>,
+    pub root: u32,
+}
+
+/// One node of a [`ValueGraph`]. Compound nodes refer to their children by
+/// index into [`ValueGraph::nodes`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeValue {
+    /// A node with no children: a scalar, a string, or a primitive list (an
+    /// `Array` node).
+    Leaf(Value),
+    List {
+        elem_type: ValueType,
+        items: Vec<u32>,
+    },
+    Tuple(Vec<u32>),
+    Option {
+        inner_type: ValueType,
+        value: Option<u32>,
+    },
+    Result {
+        ok_type: ValueType,
+        err_type: ValueType,
+        value: Result<u32, u32>,
+    },
+    Record {
+        type_name: String,
+        fields: Vec<(String, u32)>,
+    },
+    Variant {
+        type_name: String,
+        case_name: String,
+        tag: usize,
+        payload: Vec<u32>,
+    },
+}
+
+impl NodeValue {
+    /// Child node indices, in payload order.
+    pub fn children(&self) -> Vec<u32> {
+        match self {
+            NodeValue::Leaf(_) => Vec::new(),
+            NodeValue::List { items, .. } => items.clone(),
+            NodeValue::Tuple(items) => items.clone(),
+            NodeValue::Option { value, .. } => value.iter().copied().collect(),
+            NodeValue::Result { value, .. } => match value {
+                Ok(child) | Err(child) => alloc::vec![*child],
+            },
+            NodeValue::Record { fields, .. } => fields.iter().map(|(_, child)| *child).collect(),
+            NodeValue::Variant { payload, .. } => payload.clone(),
+        }
+    }
+}
+
+impl ValueGraph {
+    /// A graph of `value` with structurally equal subtrees merged into one
+    /// node (see [`Encoder::deduplicating`]).
+    pub fn from_value(value: &Value) -> Result<Self, AbiError> {
+        let mut encoder = Encoder::deduplicating();
+        let root = value.encode_graph(&mut encoder)?;
+        Self::from_buffer(&encoder.finish(root))
+    }
+
+    fn from_buffer(buffer: &GraphBuffer) -> Result<Self, AbiError> {
+        let decoder = Decoder::new(buffer);
+        let count = buffer.nodes.len();
+        let mut nodes = Vec::with_capacity(count);
+        for (index, node) in buffer.nodes.iter().enumerate() {
+            nodes.push(decode_node(&decoder, node, index as u32, count)?);
+        }
+        Ok(Self {
+            nodes,
+            root: buffer.root,
+        })
+    }
+
+    /// Unfold the graph into a `Value` tree, duplicating shared subtrees.
+    /// Fails on a cycle.
+    pub fn to_value(&self) -> Result<Value, AbiError> {
+        let mut cache = HashMap::new();
+        let mut visiting = HashSet::new();
+        self.unfold(self.root, &mut cache, &mut visiting)
+    }
+
+    fn unfold(
+        &self,
+        index: u32,
+        cache: &mut HashMap<u32, Value>,
+        visiting: &mut HashSet<u32>,
+    ) -> Result<Value, AbiError> {
+        if let Some(value) = cache.get(&index) {
+            return Ok(value.clone());
+        }
+        if !visiting.insert(index) {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Cycle detected in value graph",
+            )));
+        }
+        let node = self
+            .nodes
+            .get(index as usize)
+            .ok_or_else(|| AbiError::InvalidEncoding(format!("Node index {index} out of range")))?;
+        let mut child = |index: u32| self.unfold(index, cache, visiting);
+        let value = match node {
+            NodeValue::Leaf(value) => value.clone(),
+            NodeValue::List { elem_type, items } => Value::List {
+                elem_type: elem_type.clone(),
+                items: items.iter().map(|&i| child(i)).collect::<Result<_, _>>()?,
+            },
+            NodeValue::Tuple(items) => {
+                Value::Tuple(items.iter().map(|&i| child(i)).collect::<Result<_, _>>()?)
+            }
+            NodeValue::Option { inner_type, value } => Value::Option {
+                inner_type: inner_type.clone(),
+                value: match value {
+                    Some(i) => Some(alloc::boxed::Box::new(child(*i)?)),
+                    None => None,
+                },
+            },
+            NodeValue::Result {
+                ok_type,
+                err_type,
+                value,
+            } => Value::Result {
+                ok_type: ok_type.clone(),
+                err_type: err_type.clone(),
+                value: match value {
+                    Ok(i) => Ok(alloc::boxed::Box::new(child(*i)?)),
+                    Err(i) => Err(alloc::boxed::Box::new(child(*i)?)),
+                },
+            },
+            NodeValue::Record { type_name, fields } => Value::Record {
+                type_name: type_name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(name, i)| Ok((name.clone(), child(*i)?)))
+                    .collect::<Result<_, AbiError>>()?,
+            },
+            NodeValue::Variant {
+                type_name,
+                case_name,
+                tag,
+                payload,
+            } => Value::Variant {
+                type_name: type_name.clone(),
+                case_name: case_name.clone(),
+                tag: *tag,
+                payload: payload
+                    .iter()
+                    .map(|&i| child(i))
+                    .collect::<Result<_, _>>()?,
+            },
+        };
+        visiting.remove(&index);
+        cache.insert(index, value.clone());
+        Ok(value)
+    }
+
+    /// Encode the graph node-for-node, preserving sharing and cycles.
+    ///
+    /// Every child index must be in range, and every `Leaf` must be a value
+    /// that encodes to a single node (a scalar, string or primitive list).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, AbiError> {
+        let count = self.nodes.len();
+        if self.root as usize >= count {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Root index out of range",
+            )));
+        }
+        let mut nodes = Vec::with_capacity(count);
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.children().iter().any(|&child| child as usize >= count) {
+                return Err(AbiError::InvalidEncoding(format!(
+                    "Child index out of range at node {index}"
+                )));
+            }
+            nodes.push(encode_node(node, index)?);
+        }
+        Ok(GraphBuffer {
+            nodes,
+            root: self.root,
+        }
+        .to_bytes())
+    }
+}
+
+/// Decode bytes into a [`ValueGraph`], keeping shared nodes and cycles.
+pub fn decode_graph(bytes: &[u8]) -> Result<ValueGraph, AbiError> {
+    decode_graph_with_limits(bytes, &Limits::default())
+}
+
+/// Decode bytes into a [`ValueGraph`] with custom limits.
+pub fn decode_graph_with_limits(bytes: &[u8], limits: &Limits) -> Result<ValueGraph, AbiError> {
+    let buffer = GraphBuffer::from_bytes_with_limits(bytes, limits)?;
+    buffer.validate_basic_with_limits(limits)?;
+    ValueGraph::from_buffer(&buffer)
+}
+
+fn decode_node(
+    decoder: &Decoder<'_>,
+    node: &Node,
+    index: u32,
+    count: usize,
+) -> Result<NodeValue, AbiError> {
+    let mut cursor = Cursor::new(&node.payload);
+    let child = |cursor: &mut Cursor<'_>| -> Result<u32, AbiError> {
+        let child = cursor.read_u32()?;
+        if child as usize >= count {
+            return Err(AbiError::InvalidEncoding(format!(
+                "Child index out of range at node {index}"
+            )));
+        }
+        Ok(child)
+    };
+    let value = match node.kind {
+        NodeKind::List => {
+            let elem_type = decode_value_type(&mut cursor)?;
+            if fixed_width(&elem_type).is_some() {
+                return Err(AbiError::InvalidEncoding(String::from(
+                    "List node with primitive elem_type; expected Array node",
+                )));
+            }
+            let len = cursor.read_u32()? as usize;
+            let items = (0..len)
+                .map(|_| child(&mut cursor))
+                .collect::<Result<_, _>>()?;
+            NodeValue::List { elem_type, items }
+        }
+        NodeKind::Tuple => {
+            let len = cursor.read_u32()? as usize;
+            NodeValue::Tuple(
+                (0..len)
+                    .map(|_| child(&mut cursor))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        NodeKind::Option => {
+            let inner_type = decode_value_type(&mut cursor)?;
+            let value = match cursor.read_u8()? {
+                1 => Some(child(&mut cursor)?),
+                _ => None,
+            };
+            NodeValue::Option { inner_type, value }
+        }
+        NodeKind::Result => {
+            let ok_type = decode_value_type(&mut cursor)?;
+            let err_type = decode_value_type(&mut cursor)?;
+            let tag = cursor.read_u32()?;
+            if cursor.read_u8()? != 1 {
+                return Err(AbiError::InvalidEncoding(String::from(
+                    "Result must have payload",
+                )));
+            }
+            let inner = child(&mut cursor)?;
+            NodeValue::Result {
+                ok_type,
+                err_type,
+                value: if tag == 0 { Ok(inner) } else { Err(inner) },
+            }
+        }
+        NodeKind::Record => {
+            let type_name = read_string(&mut cursor)?;
+            let len = cursor.read_u32()? as usize;
+            let names = (0..len)
+                .map(|_| read_string(&mut cursor))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut fields = Vec::with_capacity(len);
+            for name in names {
+                fields.push((name, child(&mut cursor)?));
+            }
+            NodeValue::Record { type_name, fields }
+        }
+        NodeKind::Variant => {
+            let type_name = read_string(&mut cursor)?;
+            let case_name = read_string(&mut cursor)?;
+            let tag = cursor.read_u32()? as usize;
+            let len = cursor.read_u32()? as usize;
+            let payload = (0..len)
+                .map(|_| child(&mut cursor))
+                .collect::<Result<_, _>>()?;
+            NodeValue::Variant {
+                type_name,
+                case_name,
+                tag,
+                payload,
+            }
+        }
+        // Scalars, strings and arrays carry no child indices.
+        _ => {
+            let value = decode_value(
+                decoder,
+                index,
+                &HashSet::new(),
+                &mut HashMap::new(),
+                &mut HashSet::new(),
+            )?;
+            return Ok(NodeValue::Leaf(value));
+        }
+    };
+    if !cursor.is_eof() {
+        return Err(AbiError::InvalidEncoding(format!(
+            "Trailing payload bytes at node {index}"
+        )));
+    }
+    Ok(value)
+}
+
+fn read_string(cursor: &mut Cursor<'_>) -> Result<String, AbiError> {
+    let len = cursor.read_u32()? as usize;
+    let bytes = cursor.read_bytes(len)?;
+    core::str::from_utf8(bytes)
+        .map(String::from)
+        .map_err(|_| AbiError::InvalidEncoding(String::from("Invalid UTF-8 in name")))
+}
+
+fn encode_node(node: &NodeValue, index: usize) -> Result<Node, AbiError> {
+    let mut payload = Vec::new();
+    let put_u32 =
+        |payload: &mut Vec<u8>, value: u32| payload.extend_from_slice(&value.to_le_bytes());
+    let put_str = |payload: &mut Vec<u8>, s: &str| {
+        payload.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        payload.extend_from_slice(s.as_bytes());
+    };
+    let kind = match node {
+        NodeValue::Leaf(value) => {
+            let mut encoder = Encoder::new();
+            value.encode_graph(&mut encoder)?;
+            let mut nodes = encoder.finish(0).nodes;
+            if nodes.len() != 1 {
+                return Err(AbiError::InvalidEncoding(format!(
+                    "Leaf at node {index} is not a single-node value"
+                )));
+            }
+            return Ok(nodes.remove(0));
+        }
+        NodeValue::List { elem_type, items } => {
+            encode_value_type(elem_type, &mut payload);
+            put_u32(&mut payload, items.len() as u32);
+            items.iter().for_each(|&i| put_u32(&mut payload, i));
+            NodeKind::List
+        }
+        NodeValue::Tuple(items) => {
+            put_u32(&mut payload, items.len() as u32);
+            items.iter().for_each(|&i| put_u32(&mut payload, i));
+            NodeKind::Tuple
+        }
+        NodeValue::Option { inner_type, value } => {
+            encode_value_type(inner_type, &mut payload);
+            match value {
+                Some(i) => {
+                    payload.push(1);
+                    put_u32(&mut payload, *i);
+                }
+                None => payload.push(0),
+            }
+            NodeKind::Option
+        }
+        NodeValue::Result {
+            ok_type,
+            err_type,
+            value,
+        } => {
+            encode_value_type(ok_type, &mut payload);
+            encode_value_type(err_type, &mut payload);
+            let (tag, child) = match value {
+                Ok(i) => (0, *i),
+                Err(i) => (1, *i),
+            };
+            put_u32(&mut payload, tag);
+            payload.push(1);
+            put_u32(&mut payload, child);
+            NodeKind::Result
+        }
+        NodeValue::Record { type_name, fields } => {
+            put_str(&mut payload, type_name);
+            put_u32(&mut payload, fields.len() as u32);
+            fields
+                .iter()
+                .for_each(|(name, _)| put_str(&mut payload, name));
+            fields.iter().for_each(|&(_, i)| put_u32(&mut payload, i));
+            NodeKind::Record
+        }
+        NodeValue::Variant {
+            type_name,
+            case_name,
+            tag,
+            payload: children,
+        } => {
+            put_str(&mut payload, type_name);
+            put_str(&mut payload, case_name);
+            put_u32(&mut payload, *tag as u32);
+            put_u32(&mut payload, children.len() as u32);
+            children.iter().for_each(|&i| put_u32(&mut payload, i));
+            NodeKind::Variant
+        }
+    };
+    Ok(Node { kind, payload })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, encode, encode_shared};
+    use alloc::vec;
+
+    fn point(x: i64, y: i64) -> Value {
+        Value::Record {
+            type_name: String::from("point"),
+            fields: vec![
+                (String::from("x"), Value::S64(x)),
+                (String::from("y"), Value::S64(y)),
+            ],
+        }
+    }
+
+    #[test]
+    fn shared_encoding_emits_repeated_subtrees_once() {
+        let value = Value::List {
+            elem_type: ValueType::Record(String::from("point")),
+            items: vec![point(1, 2), point(3, 4), point(1, 2), point(1, 2)],
+        };
+        let tree = encode(&value).unwrap();
+        let shared = encode_shared(&value).unwrap();
+        assert!(shared.len() < tree.len());
+        assert_eq!(decode(&shared).unwrap(), value);
+
+        // One node per distinct subtree: 4 scalars, 2 points, the list.
+        let buffer = GraphBuffer::from_bytes(&shared).unwrap();
+        assert_eq!(buffer.nodes.len(), 7);
+    }
+
+    #[test]
+    fn decode_graph_keeps_shared_indices() {
+        let value = Value::Tuple(vec![point(5, 6), point(5, 6)]);
+        let graph = decode_graph(&encode_shared(&value).unwrap()).unwrap();
+        let NodeValue::Tuple(items) = &graph.nodes[graph.root as usize] else {
+            panic!("root should be a tuple");
+        };
+        assert_eq!(items[0], items[1]);
+        assert_eq!(graph, ValueGraph::from_value(&value).unwrap());
+        assert_eq!(graph.to_value().unwrap(), value);
+
+        // A plain tree decodes to the same value through either path.
+        let tree = decode_graph(&encode(&value).unwrap()).unwrap();
+        assert_eq!(tree.to_value().unwrap(), value);
+    }
+
+    #[test]
+    fn cycles_survive_a_graph_roundtrip() {
+        // node 0: a list whose only item is an option pointing back at it.
+        let graph = ValueGraph {
+            nodes: vec![
+                NodeValue::List {
+                    elem_type: ValueType::Option(alloc::boxed::Box::new(ValueType::Tuple(vec![]))),
+                    items: vec![1],
+                },
+                NodeValue::Option {
+                    inner_type: ValueType::List(alloc::boxed::Box::new(ValueType::Tuple(vec![]))),
+                    value: Some(0),
+                },
+            ],
+            root: 0,
+        };
+        let bytes = graph.to_bytes().unwrap();
+        assert_eq!(decode_graph(&bytes).unwrap(), graph);
+        assert!(graph.to_value().is_err());
+        assert!(decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn to_bytes_rejects_malformed_graphs() {
+        let dangling = ValueGraph {
+            nodes: vec![NodeValue::Tuple(vec![3])],
+            root: 0,
+        };
+        assert!(dangling.to_bytes().is_err());
+
+        let compound_leaf = ValueGraph {
+            nodes: vec![NodeValue::Leaf(point(0, 0))],
+            root: 0,
+        };
+        assert!(compound_leaf.to_bytes().is_err());
+    }
+}
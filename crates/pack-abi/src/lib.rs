@@ -26,10 +26,13 @@
 
 extern crate alloc;
 
+mod graph;
 mod hash;
 mod parse;
 mod value;
+mod view;
 
+pub use graph::{decode_graph, decode_graph_with_limits, NodeValue, ValueGraph};
 pub use hash::{
     hash_function,
     hash_interface,
@@ -62,6 +65,9 @@ pub use hash::{
 };
 pub use parse::{parse_value, ParseError};
 pub use value::{FromValue, KnownValueType, Rec, Value, ValueType};
+pub use view::{
+    GraphView, ListRef, OptionRef, RecordRef, ResultRef, SeqRef, ValueRef, VariantRef, ViewIter,
+};
 
 // Re-export derive macro when feature is enabled
 #[cfg(feature = "derive")]
@@ -159,6 +165,8 @@ use alloc::format;
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::hash::BuildHasher;
+use hashbrown::hash_map::DefaultHashBuilder;
 use hashbrown::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
@@ -172,6 +180,12 @@ pub enum AbiError {
 const MAGIC: u32 = u32::from_le_bytes(*b"CGRF");
 const VERSION: u16 = 2;
 
+/// Header flag marking the schema-driven compact profile: `Record` nodes carry
+/// only their child indices and `Variant` nodes only their tag (and payload
+/// child), with names restored from the schema by the host's
+/// `decode_with_schema`. The generic decoders here reject such buffers.
+pub const FLAG_COMPACT: u16 = 0x0001;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Limits {
     pub max_buffer_size: usize,
@@ -230,15 +244,46 @@ pub struct GraphBuffer {
 
 pub struct Encoder {
     nodes: Vec<Node>,
+    /// Hash-consing table for [`Encoder::deduplicating`]: node content hash →
+    /// indices of the nodes with that hash.
+    dedup: Option<(DefaultHashBuilder, HashMap<u64, Vec<u32>>)>,
 }
 
 impl Encoder {
     pub fn new() -> Self {
-        Self { nodes: Vec::new() }
+        Self {
+            nodes: Vec::new(),
+            dedup: None,
+        }
+    }
+
+    /// An encoder that emits each distinct node once.
+    ///
+    /// Children are pushed before their parent, so two structurally equal
+    /// subtrees reduce to byte-identical nodes bottom-up; `push_node` returns
+    /// the index of the first copy instead of appending a second. The result
+    /// is a DAG that any decoder reads back as the same value.
+    pub fn deduplicating() -> Self {
+        Self {
+            nodes: Vec::new(),
+            dedup: Some((DefaultHashBuilder::default(), HashMap::new())),
+        }
     }
 
     pub fn push_node(&mut self, node: Node) -> u32 {
         let index = self.nodes.len() as u32;
+        if let Some((hasher, table)) = &mut self.dedup {
+            let hash = hasher.hash_one((node.kind as u8, &node.payload));
+            let bucket = table.entry(hash).or_default();
+            let nodes = &self.nodes;
+            if let Some(&existing) = bucket.iter().find(|&&i| {
+                let seen = &nodes[i as usize];
+                seen.kind == node.kind && seen.payload == node.payload
+            }) {
+                return existing;
+            }
+            bucket.push(index);
+        }
         self.nodes.push(node);
         index
     }
@@ -286,6 +331,16 @@ pub fn encode(value: &Value) -> Result<Vec<u8>, AbiError> {
     Ok(buffer.to_bytes())
 }
 
+/// Encode a value, emitting each repeated subtree once and referring to it by
+/// node index (see [`Encoder::deduplicating`]). Decodes to the same value as
+/// [`encode`]'s output.
+pub fn encode_shared(value: &Value) -> Result<Vec<u8>, AbiError> {
+    let mut encoder = Encoder::deduplicating();
+    let root = value.encode_graph(&mut encoder)?;
+    let buffer = encoder.finish(root);
+    Ok(buffer.to_bytes())
+}
+
 /// Decode bytes to a value (graph-encoded ABI)
 pub fn decode(bytes: &[u8]) -> Result<Value, AbiError> {
     let limits = Limits::default();
@@ -344,7 +399,12 @@ impl GraphBuffer {
             )));
         }
 
-        let _flags = cursor.read_u16()?;
+        let flags = cursor.read_u16()?;
+        if flags & FLAG_COMPACT != 0 {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Compact-profile buffer requires a schema to decode",
+            )));
+        }
         let node_count = cursor.read_u32()? as usize;
         if node_count > limits.max_node_count {
             return Err(AbiError::InvalidEncoding(String::from(
@@ -1340,8 +1400,7 @@ fn decode_value(
 mod shared_node_tests {
     use super::*;
 
-    /// The encoder only ever emits trees, so the shared-node cache path is
-    /// otherwise unexercised. Hand-build a DAG — a Tuple whose two elements are
+    /// `Encoder::new` only ever emits trees. Hand-build a DAG — a Tuple whose two elements are
     /// the SAME child node — and prove (a) `shared_nodes` flags the shared child
     /// (referenced twice) but not the single-referenced root, and (b) decode
     /// still materialises both elements correctly via the cache.
@@ -0,0 +1,1003 @@
+//! Borrowed, lazily decoded views over CGRF graph buffers.
+//!
+//! [`decode`](crate::decode) materialises every node into an owned [`Node`](crate::Node)
+//! and then a fully owned [`Value`] tree. A [`GraphView`] instead indexes the
+//! node headers of the raw bytes once (no payload is copied) and hands out
+//! [`ValueRef`]s that decode a node only when it is visited. Strings come back
+//! as `&str`, `list<u8>` arrays as `&[u8]`, and a host function that reads one
+//! field of a large record never touches the rest of the buffer.
+//!
+//! The same [`Limits`] as the owned decoder apply: buffer size, node count and
+//! payload size are checked when the view is built, sequence lengths when a
+//! node is visited.
+//!
+//! ```
+//! use packr_abi::{encode, GraphView, Value, ValueRef};
+//!
+//! let value = Value::Record {
+//!     type_name: "msg".into(),
+//!     fields: vec![
+//!         ("id".into(), Value::U64(7)),
+//!         ("body".into(), Value::String("hello".into())),
+//!     ],
+//! };
+//! let bytes = encode(&value).unwrap();
+//!
+//! let view = GraphView::from_bytes(&bytes).unwrap();
+//! let ValueRef::Record(record) = view.root().unwrap() else { panic!() };
+//! let body = record.field("body").unwrap().unwrap();
+//! assert_eq!(body.as_str(), Some("hello"));
+//! ```
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use hashbrown::HashSet;
+
+use crate::{
+    decode_value_type, fixed_width, node_kind_from_u8, AbiError, Cursor, Limits, NodeKind, Value,
+    ValueType, FLAG_COMPACT, MAGIC, TYPE_BOOL, TYPE_CHAR, TYPE_F32, TYPE_F64, TYPE_FLAGS,
+    TYPE_LIST, TYPE_OPTION, TYPE_RECORD, TYPE_RESULT, TYPE_S16, TYPE_S32, TYPE_S64, TYPE_S8,
+    TYPE_STRING, TYPE_TUPLE, TYPE_U16, TYPE_U32, TYPE_U64, TYPE_U8, TYPE_VARIANT, VERSION,
+};
+
+/// A borrowed CGRF buffer with an index of its node headers.
+///
+/// Building the view validates the header and the node table, but not the
+/// node payloads: those are checked as each node is visited.
+#[derive(Debug, Clone)]
+pub struct GraphView<'a> {
+    nodes: Vec<(NodeKind, &'a [u8])>,
+    root: u32,
+    limits: Limits,
+}
+
+impl<'a> GraphView<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, AbiError> {
+        Self::from_bytes_with_limits(bytes, &Limits::default())
+    }
+
+    pub fn from_bytes_with_limits(bytes: &'a [u8], limits: &Limits) -> Result<Self, AbiError> {
+        if bytes.len() > limits.max_buffer_size {
+            return Err(AbiError::InvalidEncoding(String::from("Buffer too large")));
+        }
+
+        let mut cursor = Cursor::new(bytes);
+        if cursor.read_u32()? != MAGIC {
+            return Err(AbiError::InvalidEncoding(String::from("Invalid magic")));
+        }
+        if cursor.read_u16()? != VERSION {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Unsupported version",
+            )));
+        }
+        if cursor.read_u16()? & FLAG_COMPACT != 0 {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Compact-profile buffer requires a schema to decode",
+            )));
+        }
+        let node_count = cursor.read_u32()? as usize;
+        if node_count > limits.max_node_count {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Node count exceeds limit",
+            )));
+        }
+        let root = cursor.read_u32()?;
+
+        // Each header is 8 bytes, so a count the buffer can't hold is rejected
+        // before it sizes the index.
+        let mut nodes = Vec::with_capacity(node_count.min(bytes.len() / 8));
+        for _ in 0..node_count {
+            let kind = node_kind_from_u8(cursor.read_u8()?)?;
+            let _node_flags = cursor.read_u8()?;
+            let _reserved = cursor.read_u16()?;
+            let payload_len = cursor.read_u32()? as usize;
+            if payload_len > limits.max_payload_size {
+                return Err(AbiError::InvalidEncoding(String::from("Payload too large")));
+            }
+            nodes.push((kind, cursor.read_bytes(payload_len)?));
+        }
+
+        if (root as usize) >= nodes.len() {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Root index out of range",
+            )));
+        }
+        if !cursor.is_eof() {
+            return Err(AbiError::InvalidEncoding(String::from("Trailing bytes")));
+        }
+
+        Ok(Self {
+            nodes,
+            root,
+            limits: *limits,
+        })
+    }
+
+    /// Number of nodes in the buffer.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Index of the root node.
+    pub fn root_index(&self) -> u32 {
+        self.root
+    }
+
+    /// The root value.
+    pub fn root(&self) -> Result<ValueRef<'_>, AbiError> {
+        self.get(self.root)
+    }
+
+    /// Decode the node at `index` (its children stay undecoded).
+    pub fn get(&self, index: u32) -> Result<ValueRef<'_>, AbiError> {
+        let &(kind, payload) = self
+            .nodes
+            .get(index as usize)
+            .ok_or_else(|| AbiError::InvalidEncoding(format!("Node index {index} out of range")))?;
+        let mut cursor = Cursor::new(payload);
+        let value = match kind {
+            NodeKind::Bool => ValueRef::Bool(cursor.read_u8()? == 1),
+            NodeKind::U8 => ValueRef::U8(cursor.read_u8()?),
+            NodeKind::U16 => ValueRef::U16(cursor.read_u16()?),
+            NodeKind::U32 => ValueRef::U32(cursor.read_u32()?),
+            NodeKind::U64 => ValueRef::U64(cursor.read_u64()?),
+            NodeKind::S8 => ValueRef::S8(cursor.read_u8()? as i8),
+            NodeKind::S16 => ValueRef::S16(cursor.read_u16()? as i16),
+            NodeKind::S32 => ValueRef::S32(cursor.read_u32()? as i32),
+            NodeKind::S64 => ValueRef::S64(cursor.read_u64()? as i64),
+            NodeKind::F32 => ValueRef::F32(f32::from_le_bytes(cursor.read_u32()?.to_le_bytes())),
+            NodeKind::F64 => ValueRef::F64(f64::from_le_bytes(cursor.read_u64()?.to_le_bytes())),
+            NodeKind::Char => {
+                let raw = cursor.read_u32()?;
+                ValueRef::Char(char::from_u32(raw).ok_or_else(|| {
+                    AbiError::InvalidEncoding(String::from("Invalid char scalar"))
+                })?)
+            }
+            NodeKind::Flags => ValueRef::Flags(cursor.read_u64()?),
+            NodeKind::String => {
+                let len = cursor.read_u32()? as usize;
+                ValueRef::String(read_str(&mut cursor, len, "Invalid UTF-8")?)
+            }
+            NodeKind::Array => {
+                // Array: [elem_type:u8, count:u32, data:u8*]
+                let elem_tags = skip_value_type(&mut cursor)?;
+                let width = elem_tags
+                    .first()
+                    .and_then(|&tag| tag_width(tag))
+                    .filter(|_| elem_tags.len() == 1)
+                    .ok_or_else(|| {
+                        AbiError::InvalidEncoding(format!(
+                            "Non-primitive element type in Array at node {index}"
+                        ))
+                    })?;
+                let count = self.sequence_len(cursor.read_u32()?)?;
+                let data = cursor.read_bytes(count * width)?;
+                ValueRef::List(ListRef {
+                    elem_tags,
+                    items: Items::Inline {
+                        tag: elem_tags[0],
+                        width,
+                        data,
+                    },
+                })
+            }
+            NodeKind::List => {
+                // v2 format: [elem_type:type_tag*, count:u32, child_indices:u32*]
+                let elem_tags = skip_value_type(&mut cursor)?;
+                if elem_tags.len() == 1 && tag_width(elem_tags[0]).is_some() {
+                    return Err(AbiError::InvalidEncoding(String::from(
+                        "List node with primitive elem_type; expected Array node",
+                    )));
+                }
+                ValueRef::List(ListRef {
+                    elem_tags,
+                    items: Items::Nodes(self.children(&mut cursor)?),
+                })
+            }
+            NodeKind::Tuple => ValueRef::Tuple(self.children(&mut cursor)?),
+            NodeKind::Record => {
+                // v2 format: [type_name_len:u32, type_name:utf8, field_count:u32, field_names:(len:u32, name:utf8)*, child_indices:u32*]
+                let len = cursor.read_u32()? as usize;
+                let type_name = read_str(&mut cursor, len, "Invalid UTF-8 in type name")?;
+                let count = self.sequence_len(cursor.read_u32()?)?;
+                let names_start = cursor.pos;
+                for _ in 0..count {
+                    let len = cursor.read_u32()? as usize;
+                    read_str(&mut cursor, len, "Invalid UTF-8 in field name")?;
+                }
+                let names = &payload[names_start..cursor.pos];
+                let fields = SeqRef {
+                    view: self,
+                    indices: cursor.read_bytes(count * 4)?,
+                };
+                ValueRef::Record(RecordRef {
+                    type_name,
+                    names,
+                    fields,
+                })
+            }
+            NodeKind::Variant => {
+                // v2 format: [type_name_len:u32, type_name:utf8, case_name_len:u32, case_name:utf8, tag:u32, payload_count:u32, child_indices:u32*]
+                let len = cursor.read_u32()? as usize;
+                let type_name = read_str(&mut cursor, len, "Invalid UTF-8 in type name")?;
+                let len = cursor.read_u32()? as usize;
+                let case_name = read_str(&mut cursor, len, "Invalid UTF-8 in case name")?;
+                let tag = cursor.read_u32()? as usize;
+                ValueRef::Variant(VariantRef {
+                    type_name,
+                    case_name,
+                    tag,
+                    payload: self.children(&mut cursor)?,
+                })
+            }
+            NodeKind::Option => {
+                // v2 format: [inner_type:type_tag*, presence:u8, child_index?:u32]
+                let inner_tags = skip_value_type(&mut cursor)?;
+                let child = if cursor.read_u8()? == 1 {
+                    Some(cursor.read_u32()?)
+                } else {
+                    None
+                };
+                ValueRef::Option(OptionRef {
+                    view: self,
+                    inner_tags,
+                    child,
+                })
+            }
+            NodeKind::Result => {
+                // v2 format: [ok_type:type_tag*, err_type:type_tag*, tag:u32, has_payload:u8, child_index?:u32]
+                let ok_tags = skip_value_type(&mut cursor)?;
+                let err_tags = skip_value_type(&mut cursor)?;
+                let tag = cursor.read_u32()?;
+                if cursor.read_u8()? != 1 {
+                    return Err(AbiError::InvalidEncoding(String::from(
+                        "Result must have payload",
+                    )));
+                }
+                ValueRef::Result(ResultRef {
+                    view: self,
+                    ok_tags,
+                    err_tags,
+                    is_ok: tag == 0,
+                    child: cursor.read_u32()?,
+                })
+            }
+        };
+
+        if !cursor.is_eof() {
+            return Err(AbiError::InvalidEncoding(format!(
+                "Trailing payload bytes at node {index}"
+            )));
+        }
+        Ok(value)
+    }
+
+    /// Materialise the whole graph as an owned [`Value`], like
+    /// [`decode`](crate::decode).
+    pub fn to_value(&self) -> Result<Value, AbiError> {
+        self.value_at(self.root, &mut HashSet::new())
+    }
+
+    fn value_at(&self, index: u32, visiting: &mut HashSet<u32>) -> Result<Value, AbiError> {
+        if !visiting.insert(index) {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Cycle detected in graph buffer",
+            )));
+        }
+        let value = self.get(index)?.materialize(visiting)?;
+        visiting.remove(&index);
+        Ok(value)
+    }
+
+    fn sequence_len(&self, count: u32) -> Result<usize, AbiError> {
+        let count = count as usize;
+        if count > self.limits.max_sequence_len {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Sequence too large",
+            )));
+        }
+        Ok(count)
+    }
+
+    /// Read `[count:u32, child_indices:u32*]`.
+    fn children(&self, cursor: &mut Cursor<'a>) -> Result<SeqRef<'_>, AbiError> {
+        let count = self.sequence_len(cursor.read_u32()?)?;
+        Ok(SeqRef {
+            view: self,
+            indices: cursor.read_bytes(count * 4)?,
+        })
+    }
+}
+
+/// A borrowed value inside a [`GraphView`].
+///
+/// Scalars are decoded eagerly; containers hold their child indices and
+/// decode a child only when it is asked for.
+#[derive(Debug, Clone, Copy)]
+pub enum ValueRef<'a> {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    S8(i8),
+    S16(i16),
+    S32(i32),
+    S64(i64),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Flags(u64),
+    String(&'a str),
+    List(ListRef<'a>),
+    Tuple(SeqRef<'a>),
+    Option(OptionRef<'a>),
+    Result(ResultRef<'a>),
+    Record(RecordRef<'a>),
+    Variant(VariantRef<'a>),
+}
+
+impl<'a> ValueRef<'a> {
+    /// The string slice, if this is a string.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            ValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The raw bytes, if this is a `list<u8>`.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            ValueRef::List(list) => list.as_bytes(),
+            _ => None,
+        }
+    }
+
+    /// Materialise this value (and everything below it) as an owned [`Value`].
+    pub fn to_value(&self) -> Result<Value, AbiError> {
+        self.materialize(&mut HashSet::new())
+    }
+
+    fn materialize(&self, visiting: &mut HashSet<u32>) -> Result<Value, AbiError> {
+        Ok(match *self {
+            ValueRef::Bool(v) => Value::Bool(v),
+            ValueRef::U8(v) => Value::U8(v),
+            ValueRef::U16(v) => Value::U16(v),
+            ValueRef::U32(v) => Value::U32(v),
+            ValueRef::U64(v) => Value::U64(v),
+            ValueRef::S8(v) => Value::S8(v),
+            ValueRef::S16(v) => Value::S16(v),
+            ValueRef::S32(v) => Value::S32(v),
+            ValueRef::S64(v) => Value::S64(v),
+            ValueRef::F32(v) => Value::F32(v),
+            ValueRef::F64(v) => Value::F64(v),
+            ValueRef::Char(v) => Value::Char(v),
+            ValueRef::Flags(v) => Value::Flags(v),
+            ValueRef::String(s) => Value::String(String::from(s)),
+            ValueRef::List(list) => {
+                let mut items = Vec::with_capacity(list.len());
+                match list.items {
+                    Items::Nodes(seq) => {
+                        for i in 0..seq.len() {
+                            items.push(seq.view.value_at(seq.index(i), visiting)?);
+                        }
+                    }
+                    Items::Inline { .. } => {
+                        for item in list.iter() {
+                            items.push(item?.materialize(visiting)?);
+                        }
+                    }
+                }
+                Value::List {
+                    elem_type: list.elem_type()?,
+                    items,
+                }
+            }
+            ValueRef::Tuple(seq) => Value::Tuple(seq.materialize(visiting)?),
+            ValueRef::Option(option) => Value::Option {
+                inner_type: option.inner_type()?,
+                value: match option.child {
+                    Some(child) => Some(Box::new(option.view.value_at(child, visiting)?)),
+                    None => None,
+                },
+            },
+            ValueRef::Result(result) => {
+                let inner = Box::new(result.view.value_at(result.child, visiting)?);
+                Value::Result {
+                    ok_type: result.ok_type()?,
+                    err_type: result.err_type()?,
+                    value: if result.is_ok { Ok(inner) } else { Err(inner) },
+                }
+            }
+            ValueRef::Record(record) => {
+                let mut fields = Vec::with_capacity(record.len());
+                for (i, name) in record.names().enumerate() {
+                    let child = record.fields.index(i);
+                    fields.push((
+                        String::from(name?),
+                        record.fields.view.value_at(child, visiting)?,
+                    ));
+                }
+                Value::Record {
+                    type_name: String::from(record.type_name),
+                    fields,
+                }
+            }
+            ValueRef::Variant(variant) => Value::Variant {
+                type_name: String::from(variant.type_name),
+                case_name: String::from(variant.case_name),
+                tag: variant.tag,
+                payload: variant.payload.materialize(visiting)?,
+            },
+        })
+    }
+}
+
+/// A run of child node indices: a tuple, or a variant's payload.
+#[derive(Debug, Clone, Copy)]
+pub struct SeqRef<'a> {
+    view: &'a GraphView<'a>,
+    indices: &'a [u8],
+}
+
+impl<'a> SeqRef<'a> {
+    pub fn len(&self) -> usize {
+        self.indices.len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Decode the `i`th element, or `None` past the end.
+    pub fn get(&self, i: usize) -> Result<Option<ValueRef<'a>>, AbiError> {
+        if i >= self.len() {
+            return Ok(None);
+        }
+        self.view.get(self.index(i)).map(Some)
+    }
+
+    pub fn iter(&self) -> ViewIter<'a> {
+        ViewIter {
+            items: Items::Nodes(*self),
+            pos: 0,
+        }
+    }
+
+    fn index(&self, i: usize) -> u32 {
+        let b = &self.indices[i * 4..i * 4 + 4];
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+    }
+
+    fn materialize(&self, visiting: &mut HashSet<u32>) -> Result<Vec<Value>, AbiError> {
+        (0..self.len())
+            .map(|i| self.view.value_at(self.index(i), visiting))
+            .collect()
+    }
+}
+
+/// A list: either child nodes, or an `Array` node's inline primitive data.
+#[derive(Debug, Clone, Copy)]
+pub struct ListRef<'a> {
+    elem_tags: &'a [u8],
+    items: Items<'a>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Items<'a> {
+    Nodes(SeqRef<'a>),
+    Inline {
+        tag: u8,
+        width: usize,
+        data: &'a [u8],
+    },
+}
+
+impl<'a> ListRef<'a> {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The declared element type (allocates only for named element types).
+    pub fn elem_type(&self) -> Result<ValueType, AbiError> {
+        decode_value_type(&mut Cursor::new(self.elem_tags))
+    }
+
+    /// The raw bytes of a `list<u8>`.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self.items {
+            Items::Inline {
+                tag: TYPE_U8, data, ..
+            } => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Decode the `i`th element, or `None` past the end.
+    pub fn get(&self, i: usize) -> Result<Option<ValueRef<'a>>, AbiError> {
+        self.items.get(i)
+    }
+
+    pub fn iter(&self) -> ViewIter<'a> {
+        ViewIter {
+            items: self.items,
+            pos: 0,
+        }
+    }
+}
+
+impl<'a> Items<'a> {
+    fn len(&self) -> usize {
+        match self {
+            Items::Nodes(seq) => seq.len(),
+            Items::Inline { width, data, .. } => data.len() / width,
+        }
+    }
+
+    fn get(&self, i: usize) -> Result<Option<ValueRef<'a>>, AbiError> {
+        match *self {
+            Items::Nodes(seq) => seq.get(i),
+            Items::Inline { tag, width, data } => {
+                if i >= data.len() / width {
+                    return Ok(None);
+                }
+                array_element(tag, &data[i * width..(i + 1) * width]).map(Some)
+            }
+        }
+    }
+}
+
+/// Iterator over the elements of a [`ListRef`] or [`SeqRef`].
+#[derive(Debug, Clone)]
+pub struct ViewIter<'a> {
+    items: Items<'a>,
+    pos: usize,
+}
+
+impl<'a> Iterator for ViewIter<'a> {
+    type Item = Result<ValueRef<'a>, AbiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.items.len() {
+            return None;
+        }
+        let i = self.pos;
+        self.pos += 1;
+        Some(match self.items {
+            Items::Nodes(seq) => seq.view.get(seq.index(i)),
+            Items::Inline { tag, width, data } => {
+                array_element(tag, &data[i * width..(i + 1) * width])
+            }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.items.len() - self.pos;
+        (left, Some(left))
+    }
+}
+
+/// A record; field values are decoded on access.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    type_name: &'a str,
+    /// The `(len:u32, name:utf8)*` field-name table, already UTF-8 checked.
+    names: &'a [u8],
+    fields: SeqRef<'a>,
+}
+
+impl<'a> RecordRef<'a> {
+    pub fn type_name(&self) -> &'a str {
+        self.type_name
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Decode the field called `name`, or `None` if the record has no such field.
+    pub fn field(&self, name: &str) -> Result<Option<ValueRef<'a>>, AbiError> {
+        for (i, field_name) in self.names().enumerate() {
+            if field_name? == name {
+                return self.fields.get(i);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Iterate `(name, value)` pairs in wire order.
+    pub fn fields(&self) -> impl Iterator<Item = Result<(&'a str, ValueRef<'a>), AbiError>> + 'a {
+        let fields = self.fields;
+        self.names().enumerate().map(move |(i, name)| {
+            let value = fields.view.get(fields.index(i))?;
+            Ok((name?, value))
+        })
+    }
+
+    fn names(&self) -> impl Iterator<Item = Result<&'a str, AbiError>> + 'a {
+        let mut cursor = Cursor::new(self.names);
+        (0..self.fields.len()).map(move |_| {
+            let len = cursor.read_u32()? as usize;
+            read_str(&mut cursor, len, "Invalid UTF-8 in field name")
+        })
+    }
+}
+
+/// A variant case and its payload.
+#[derive(Debug, Clone, Copy)]
+pub struct VariantRef<'a> {
+    type_name: &'a str,
+    case_name: &'a str,
+    tag: usize,
+    payload: SeqRef<'a>,
+}
+
+impl<'a> VariantRef<'a> {
+    pub fn type_name(&self) -> &'a str {
+        self.type_name
+    }
+
+    pub fn case_name(&self) -> &'a str {
+        self.case_name
+    }
+
+    pub fn tag(&self) -> usize {
+        self.tag
+    }
+
+    pub fn payload(&self) -> SeqRef<'a> {
+        self.payload
+    }
+}
+
+/// An option; the inner value is decoded on access.
+#[derive(Debug, Clone, Copy)]
+pub struct OptionRef<'a> {
+    view: &'a GraphView<'a>,
+    inner_tags: &'a [u8],
+    child: Option<u32>,
+}
+
+impl<'a> OptionRef<'a> {
+    pub fn is_some(&self) -> bool {
+        self.child.is_some()
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.child.is_none()
+    }
+
+    pub fn inner_type(&self) -> Result<ValueType, AbiError> {
+        decode_value_type(&mut Cursor::new(self.inner_tags))
+    }
+
+    pub fn get(&self) -> Result<Option<ValueRef<'a>>, AbiError> {
+        self.child.map(|child| self.view.get(child)).transpose()
+    }
+}
+
+/// A result; the ok/err payload is decoded on access.
+#[derive(Debug, Clone, Copy)]
+pub struct ResultRef<'a> {
+    view: &'a GraphView<'a>,
+    ok_tags: &'a [u8],
+    err_tags: &'a [u8],
+    is_ok: bool,
+    child: u32,
+}
+
+impl<'a> ResultRef<'a> {
+    pub fn is_ok(&self) -> bool {
+        self.is_ok
+    }
+
+    pub fn is_err(&self) -> bool {
+        !self.is_ok
+    }
+
+    pub fn ok_type(&self) -> Result<ValueType, AbiError> {
+        decode_value_type(&mut Cursor::new(self.ok_tags))
+    }
+
+    pub fn err_type(&self) -> Result<ValueType, AbiError> {
+        decode_value_type(&mut Cursor::new(self.err_tags))
+    }
+
+    pub fn get(&self) -> Result<Result<ValueRef<'a>, ValueRef<'a>>, AbiError> {
+        let inner = self.view.get(self.child)?;
+        Ok(if self.is_ok { Ok(inner) } else { Err(inner) })
+    }
+}
+
+fn read_str<'a>(cursor: &mut Cursor<'a>, len: usize, what: &str) -> Result<&'a str, AbiError> {
+    core::str::from_utf8(cursor.read_bytes(len)?)
+        .map_err(|_| AbiError::InvalidEncoding(String::from(what)))
+}
+
+/// Advance past one encoded type (see `decode_value_type`) without building
+/// it, returning the bytes it spans.
+fn skip_value_type<'a>(cursor: &mut Cursor<'a>) -> Result<&'a [u8], AbiError> {
+    let start = cursor.pos;
+    skip_type_tags(cursor)?;
+    Ok(&cursor.bytes[start..cursor.pos])
+}
+
+fn skip_type_tags(cursor: &mut Cursor<'_>) -> Result<(), AbiError> {
+    let tag = cursor.read_u8()?;
+    match tag {
+        TYPE_BOOL | TYPE_U8 | TYPE_U16 | TYPE_U32 | TYPE_U64 | TYPE_S8 | TYPE_S16 | TYPE_S32
+        | TYPE_S64 | TYPE_F32 | TYPE_F64 | TYPE_CHAR | TYPE_STRING | TYPE_FLAGS => {}
+        TYPE_LIST | TYPE_OPTION => skip_type_tags(cursor)?,
+        TYPE_RESULT => {
+            skip_type_tags(cursor)?;
+            skip_type_tags(cursor)?;
+        }
+        TYPE_RECORD | TYPE_VARIANT => {
+            let len = cursor.read_u32()? as usize;
+            read_str(cursor, len, "Invalid UTF-8 in type name")?;
+        }
+        TYPE_TUPLE => {
+            let count = cursor.read_u32()?;
+            for _ in 0..count {
+                skip_type_tags(cursor)?;
+            }
+        }
+        _ => return Err(AbiError::InvalidTag(tag)),
+    }
+    Ok(())
+}
+
+/// Byte width of a fixed-size primitive type tag (mirrors `fixed_width`).
+fn tag_width(tag: u8) -> Option<usize> {
+    let ty = match tag {
+        TYPE_BOOL => ValueType::Bool,
+        TYPE_U8 => ValueType::U8,
+        TYPE_S8 => ValueType::S8,
+        TYPE_U16 => ValueType::U16,
+        TYPE_S16 => ValueType::S16,
+        TYPE_U32 => ValueType::U32,
+        TYPE_S32 => ValueType::S32,
+        TYPE_F32 => ValueType::F32,
+        TYPE_CHAR => ValueType::Char,
+        TYPE_U64 => ValueType::U64,
+        TYPE_S64 => ValueType::S64,
+        TYPE_F64 => ValueType::F64,
+        TYPE_FLAGS => ValueType::Flags,
+        _ => return None,
+    };
+    fixed_width(&ty)
+}
+
+/// Decode one element of an `Array` node's inline data.
+fn array_element(tag: u8, bytes: &[u8]) -> Result<ValueRef<'_>, AbiError> {
+    let mut cursor = Cursor::new(bytes);
+    Ok(match tag {
+        TYPE_BOOL => ValueRef::Bool(cursor.read_u8()? != 0),
+        TYPE_U8 => ValueRef::U8(cursor.read_u8()?),
+        TYPE_S8 => ValueRef::S8(cursor.read_u8()? as i8),
+        TYPE_U16 => ValueRef::U16(cursor.read_u16()?),
+        TYPE_S16 => ValueRef::S16(cursor.read_u16()? as i16),
+        TYPE_U32 => ValueRef::U32(cursor.read_u32()?),
+        TYPE_S32 => ValueRef::S32(cursor.read_u32()? as i32),
+        TYPE_U64 => ValueRef::U64(cursor.read_u64()?),
+        TYPE_S64 => ValueRef::S64(cursor.read_u64()? as i64),
+        TYPE_F32 => ValueRef::F32(f32::from_le_bytes(cursor.read_u32()?.to_le_bytes())),
+        TYPE_F64 => ValueRef::F64(f64::from_le_bytes(cursor.read_u64()?.to_le_bytes())),
+        TYPE_CHAR => ValueRef::Char(
+            char::from_u32(cursor.read_u32()?)
+                .ok_or_else(|| AbiError::InvalidEncoding(String::from("Invalid char in array")))?,
+        ),
+        TYPE_FLAGS => ValueRef::Flags(cursor.read_u64()?),
+        _ => {
+            return Err(AbiError::InvalidEncoding(String::from(
+                "Non-primitive type in array",
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, encode, Encoder, Node};
+    use alloc::vec;
+
+    fn sample() -> Value {
+        Value::Record {
+            type_name: String::from("message"),
+            fields: vec![
+                (String::from("id"), Value::U64(42)),
+                (String::from("from"), Value::String(String::from("a@b.c"))),
+                (
+                    String::from("body"),
+                    Value::List {
+                        elem_type: ValueType::U8,
+                        items: vec![Value::U8(1), Value::U8(2), Value::U8(3)],
+                    },
+                ),
+                (
+                    String::from("tags"),
+                    Value::List {
+                        elem_type: ValueType::String,
+                        items: vec![
+                            Value::String(String::from("x")),
+                            Value::String(String::from("y")),
+                        ],
+                    },
+                ),
+                (
+                    String::from("reply"),
+                    Value::Option {
+                        inner_type: ValueType::Tuple(vec![ValueType::S32, ValueType::Char]),
+                        value: Some(Box::new(Value::Tuple(vec![
+                            Value::S32(-1),
+                            Value::Char('z'),
+                        ]))),
+                    },
+                ),
+                (
+                    String::from("status"),
+                    Value::Result {
+                        ok_type: ValueType::Variant(String::from("state")),
+                        err_type: ValueType::String,
+                        value: Ok(Box::new(Value::Variant {
+                            type_name: String::from("state"),
+                            case_name: String::from("done"),
+                            tag: 1,
+                            payload: vec![Value::F64(0.5)],
+                        })),
+                    },
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn view_matches_owned_decode() {
+        let value = sample();
+        let bytes = encode(&value).unwrap();
+        let view = GraphView::from_bytes(&bytes).unwrap();
+        assert_eq!(view.to_value().unwrap(), decode(&bytes).unwrap());
+        assert_eq!(view.root().unwrap().to_value().unwrap(), value);
+    }
+
+    #[test]
+    fn fields_borrow_from_the_buffer() {
+        let bytes = encode(&sample()).unwrap();
+        let view = GraphView::from_bytes(&bytes).unwrap();
+        let ValueRef::Record(record) = view.root().unwrap() else {
+            panic!("expected record");
+        };
+        assert_eq!(record.type_name(), "message");
+        assert_eq!(record.len(), 6);
+
+        let from = record.field("from").unwrap().unwrap().as_str().unwrap();
+        assert_eq!(from, "a@b.c");
+        let range = bytes.as_ptr_range();
+        assert!(
+            range.contains(&from.as_ptr()),
+            "string must point into the buffer"
+        );
+
+        let body = record.field("body").unwrap().unwrap().as_bytes().unwrap();
+        assert_eq!(body, &[1, 2, 3]);
+        assert!(range.contains(&body.as_ptr()));
+
+        assert!(record.field("missing").unwrap().is_none());
+
+        let names: Vec<&str> = record.fields().map(|f| f.unwrap().0).collect();
+        assert_eq!(names, ["id", "from", "body", "tags", "reply", "status"]);
+    }
+
+    #[test]
+    fn containers_decode_children_on_access() {
+        let bytes = encode(&sample()).unwrap();
+        let view = GraphView::from_bytes(&bytes).unwrap();
+        let ValueRef::Record(record) = view.root().unwrap() else {
+            panic!("expected record");
+        };
+
+        let ValueRef::List(tags) = record.field("tags").unwrap().unwrap() else {
+            panic!("expected list");
+        };
+        assert_eq!(tags.elem_type().unwrap(), ValueType::String);
+        let tags: Vec<&str> = tags.iter().map(|t| t.unwrap().as_str().unwrap()).collect();
+        assert_eq!(tags, ["x", "y"]);
+
+        let ValueRef::Option(reply) = record.field("reply").unwrap().unwrap() else {
+            panic!("expected option");
+        };
+        let ValueRef::Tuple(pair) = reply.get().unwrap().unwrap() else {
+            panic!("expected tuple");
+        };
+        assert!(matches!(pair.get(0).unwrap(), Some(ValueRef::S32(-1))));
+        assert!(matches!(pair.get(1).unwrap(), Some(ValueRef::Char('z'))));
+        assert!(pair.get(2).unwrap().is_none());
+
+        let ValueRef::Result(status) = record.field("status").unwrap().unwrap() else {
+            panic!("expected result");
+        };
+        let Ok(ValueRef::Variant(state)) = status.get().unwrap() else {
+            panic!("expected ok variant");
+        };
+        assert_eq!((state.case_name(), state.tag()), ("done", 1));
+        assert!(matches!(state.payload().get(0).unwrap(), Some(ValueRef::F64(v)) if v == 0.5));
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        let bytes = encode(&sample()).unwrap();
+
+        let small = Limits {
+            max_buffer_size: 8,
+            ..Limits::default()
+        };
+        assert!(GraphView::from_bytes_with_limits(&bytes, &small).is_err());
+
+        let few_nodes = Limits {
+            max_node_count: 2,
+            ..Limits::default()
+        };
+        assert!(GraphView::from_bytes_with_limits(&bytes, &few_nodes).is_err());
+
+        // Sequence limits apply lazily, when the oversized node is visited.
+        let short = Limits {
+            max_sequence_len: 2,
+            ..Limits::default()
+        };
+        let view = GraphView::from_bytes_with_limits(&bytes, &short).unwrap();
+        assert!(view.root().is_err());
+    }
+
+    #[test]
+    fn cycles_are_rejected_when_materialised() {
+        // Tuple node 0 whose only child is itself.
+        let mut enc = Encoder::new();
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        let t = enc.push_node(Node {
+            kind: NodeKind::Tuple,
+            payload,
+        });
+        let bytes = enc.finish(t).to_bytes();
+
+        let view = GraphView::from_bytes(&bytes).unwrap();
+        // A single step is fine — only a full walk loops.
+        let ValueRef::Tuple(seq) = view.root().unwrap() else {
+            panic!("expected tuple");
+        };
+        assert!(matches!(seq.get(0).unwrap(), Some(ValueRef::Tuple(_))));
+        assert!(view.to_value().is_err());
+    }
+
+    #[test]
+    fn bad_child_index_is_an_error_not_a_panic() {
+        let mut enc = Encoder::new();
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&9u32.to_le_bytes());
+        let t = enc.push_node(Node {
+            kind: NodeKind::Tuple,
+            payload,
+        });
+        let bytes = enc.finish(t).to_bytes();
+
+        let view = GraphView::from_bytes(&bytes).unwrap();
+        let ValueRef::Tuple(seq) = view.root().unwrap() else {
+            panic!("expected tuple");
+        };
+        assert!(seq.get(0).is_err());
+    }
+}
@@ -672,11 +672,9 @@ fn try_auto_discover_export(fn_name: &str) -> Option<String> {
                 pact_parser::WorldItem::InlineInterface {
                     name: iface_name,
                     functions,
-                } => {
-                    if functions.iter().any(|f| f.name == fn_name) {
-                        // Found in inline interface - use interface.function format
-                        return Some(format!("{}.{}", iface_name, fn_name));
-                    }
+                } if functions.iter().any(|f| f.name == fn_name) => {
+                    // Found in inline interface - use interface.function format
+                    return Some(format!("{}.{}", iface_name, fn_name));
                 }
                 pact_parser::WorldItem::InterfacePath {
                     namespace,
@@ -1117,15 +1115,13 @@ fn expand_pact(input: TokenStream) -> TokenStream {
                     .into();
             }
         };
+        let name = abs_path.display().to_string();
         let mut world = match pact_parser::parse_world(&content) {
             Ok(w) => w,
             Err(e) => {
-                return syn::Error::new(
-                    file_ref.path.span(),
-                    format!("Failed to parse Pact from {}: {}", abs_path.display(), e),
-                )
-                .to_compile_error()
-                .into();
+                return syn::Error::new(file_ref.path.span(), e.render(&name, &content))
+                    .to_compile_error()
+                    .into();
             }
         };
         // Resolve `use "path".{names}` imports relative to THIS file's directory.
@@ -1141,6 +1137,11 @@ fn expand_pact(input: TokenStream) -> TokenStream {
                     .into();
             }
         };
+        if let Err(e) = pact_parser::check_world_types(&world) {
+            return syn::Error::new(file_ref.path.span(), e.render(&name, &content))
+                .to_compile_error()
+                .into();
+        }
         let generated = codegen::generate_world_types(&world);
         // Register the source file + every `use`d file as build dependencies so
         // editing any of them triggers recompilation of this crate.
@@ -1157,10 +1158,22 @@ fn expand_pact(input: TokenStream) -> TokenStream {
     // Check if we have inline content or should read from files
     let input_str = input.to_string();
 
+    // `(name, offset)` of each file concatenated into the content, for
+    // locating errors.
+    let mut files = Vec::new();
+    let mut inline = None;
     let pact_content = if input_str.trim().is_empty() {
         // Read from pact/ directory
-        match read_pact_files() {
-            Ok(content) => content,
+        match read_pact_sources() {
+            Ok(sources) => {
+                let mut content = String::new();
+                for (path, src) in sources {
+                    files.push((path.display().to_string(), content.len()));
+                    content.push_str(&src);
+                    content.push('\n');
+                }
+                content
+            }
             Err(e) => {
                 return syn::Error::new(
                     proc_macro2::Span::call_site(),
@@ -1173,6 +1186,8 @@ fn expand_pact(input: TokenStream) -> TokenStream {
     } else {
         // Use inline content - parse the token stream as a raw string
         // The input is the raw Pact content between the braces
+        files.push(("pact! input".to_string(), 0));
+        inline = Some(proc_macro2::TokenStream::from(input));
         input_str
     };
 
@@ -1180,12 +1195,9 @@ fn expand_pact(input: TokenStream) -> TokenStream {
     let mut world = match pact_parser::parse_world(&pact_content) {
         Ok(w) => w,
         Err(e) => {
-            return syn::Error::new(
-                proc_macro2::Span::call_site(),
-                format!("Failed to parse Pact: {}", e),
-            )
-            .to_compile_error()
-            .into();
+            return pact_error(&e, &pact_content, &files, inline.as_ref())
+                .to_compile_error()
+                .into();
         }
     };
 
@@ -1202,6 +1214,11 @@ fn expand_pact(input: TokenStream) -> TokenStream {
                 .into();
         }
     };
+    if let Err(e) = pact_parser::check_world_types(&world) {
+        return pact_error(&e, &pact_content, &files, inline.as_ref())
+            .to_compile_error()
+            .into();
+    }
 
     // Generate the types
     let generated = codegen::generate_world_types(&world);
@@ -1214,6 +1231,99 @@ fn expand_pact(input: TokenStream) -> TokenStream {
     .into()
 }
 
+/// The compile error for `error` in the content `pact!` parsed. Inline content
+/// is the stringified `inline` tokens, so the error points at the input token
+/// itself; file content is quoted with a caret instead.
+fn pact_error(
+    error: &pact_parser::ParseError,
+    src: &str,
+    files: &[(String, usize)],
+    inline: Option<&proc_macro2::TokenStream>,
+) -> syn::Error {
+    let span = inline
+        .zip(error.range.as_ref())
+        .and_then(|(tokens, range)| token_span_at(tokens, src, range.start));
+    match span {
+        Some(span) => {
+            let mut message = error.message.clone();
+            if let Some(help) = &error.help {
+                message.push_str(&format!("\nhelp: {help}"));
+            }
+            syn::Error::new(span, message)
+        }
+        None => syn::Error::new(
+            proc_macro2::Span::call_site(),
+            render_pact_error(error, src, files),
+        ),
+    }
+}
+
+/// The span of the token of `tokens` at byte `offset` of `src`, their
+/// stringified form.
+fn token_span_at(
+    tokens: &proc_macro2::TokenStream,
+    src: &str,
+    offset: usize,
+) -> Option<proc_macro2::Span> {
+    // Walk the leaf tokens in order, finding each one's text in `src`, until
+    // one starts past `offset`. Returns false once it has.
+    fn walk(
+        tokens: proc_macro2::TokenStream,
+        src: &str,
+        offset: usize,
+        cursor: &mut usize,
+        found: &mut Option<proc_macro2::Span>,
+    ) -> bool {
+        for tree in tokens {
+            if let proc_macro2::TokenTree::Group(group) = tree {
+                if !walk(group.stream(), src, offset, cursor, found) {
+                    return false;
+                }
+                continue;
+            }
+            let text = tree.to_string();
+            let Some(at) = src[*cursor..].find(&text) else {
+                return false;
+            };
+            let start = *cursor + at;
+            if start > offset {
+                return false;
+            }
+            *found = Some(tree.span());
+            *cursor = start + text.len();
+        }
+        true
+    }
+    let mut found = None;
+    walk(tokens.clone(), src, offset, &mut 0, &mut found);
+    found
+}
+
+/// Render `error` against the file of `files` — `(name, offset)` of each file
+/// concatenated into `src` — that its range falls in.
+fn render_pact_error(
+    error: &pact_parser::ParseError,
+    src: &str,
+    files: &[(String, usize)],
+) -> String {
+    let start = error.range.as_ref().map_or(0, |r| r.start);
+    let Some(index) = files.iter().rposition(|(_, offset)| *offset <= start) else {
+        return error.to_string();
+    };
+    let (name, offset) = &files[index];
+    let end = files.get(index + 1).map_or(src.len(), |(_, next)| *next);
+    let located = pact_parser::ParseError {
+        message: error.message.clone(),
+        span: error.span,
+        range: error
+            .range
+            .as_ref()
+            .map(|r| r.start - offset..r.end - offset),
+        help: error.help.clone(),
+    };
+    located.render(name, &src[*offset..end])
+}
+
 /// Resolve a world's `use "path".{names}` imports: for each, read the file
 /// (relative to `base_dir`), parse it, pull the named type defs plus their
 /// transitive same-file dependencies, and append them to the world's types
@@ -1228,8 +1338,13 @@ fn resolve_world_uses(
         let path = base_dir.join(&u.path);
         let content = std::fs::read_to_string(&path)
             .map_err(|e| format!("Failed to read `use` file {}: {}", path.display(), e))?;
-        let registry = pact_parser::parse_pact(&content)
-            .map_err(|e| format!("Failed to parse `use` file {}: {}", path.display(), e))?;
+        let registry = pact_parser::parse_pact(&content).map_err(|e| {
+            format!(
+                "Failed to parse `use` file {}: {}",
+                path.display(),
+                e.render(&path.display().to_string(), &content)
+            )
+        })?;
         // Types available in the used file: top-level defs + any world's defs.
         let mut avail = registry.types.clone();
         for w in &registry.worlds {
@@ -1429,6 +1544,17 @@ pub fn world(input: TokenStream) -> TokenStream {
 
 /// Read all Pact files from the pact/ directory and pact/deps/ subdirectories
 fn read_pact_files() -> Result<String, String> {
+    let mut content = String::new();
+    for (_, src) in read_pact_sources()? {
+        content.push_str(&src);
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// Read every `.pact` file under the crate's `pact/` directory (recursively,
+/// including `pact/deps/`), with its path.
+fn read_pact_sources() -> Result<Vec<(std::path::PathBuf, String)>, String> {
     // Get the manifest directory (crate root)
     let manifest_dir =
         std::env::var("CARGO_MANIFEST_DIR").map_err(|_| "CARGO_MANIFEST_DIR not set")?;
@@ -1439,20 +1565,23 @@ fn read_pact_files() -> Result<String, String> {
         return Err(format!("pact/ directory not found at {:?}", pact_dir));
     }
 
-    let mut content = String::new();
+    let mut sources = Vec::new();
 
     // Read Pact files recursively (includes pact/deps/)
-    read_pact_files_recursive(&pact_dir, &mut content)?;
+    read_pact_files_recursive(&pact_dir, &mut sources)?;
 
-    if content.is_empty() {
+    if sources.is_empty() {
         return Err("No .pact files found in pact/ directory".to_string());
     }
 
-    Ok(content)
+    Ok(sources)
 }
 
 /// Recursively read Pact files from a directory
-fn read_pact_files_recursive(dir: &std::path::Path, content: &mut String) -> Result<(), String> {
+fn read_pact_files_recursive(
+    dir: &std::path::Path,
+    sources: &mut Vec<(std::path::PathBuf, String)>,
+) -> Result<(), String> {
     let entries =
         std::fs::read_dir(dir).map_err(|e| format!("Failed to read directory {:?}: {}", dir, e))?;
 
@@ -1462,13 +1591,12 @@ fn read_pact_files_recursive(dir: &std::path::Path, content: &mut String) -> Res
 
         if path.is_dir() {
             // Recurse into subdirectories (including deps/)
-            read_pact_files_recursive(&path, content)?;
+            read_pact_files_recursive(&path, sources)?;
         } else if let Some(ext) = path.extension() {
             if ext == "pact" {
                 let file_content = std::fs::read_to_string(&path)
                     .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
-                content.push_str(&file_content);
-                content.push('\n');
+                sources.push((path, file_content));
             }
         }
     }
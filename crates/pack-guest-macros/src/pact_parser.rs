@@ -5,6 +5,7 @@
 
 use proc_macro2::Span;
 use std::collections::HashMap;
+use std::ops::Range;
 
 /// A parsed interface path like "namespace:package/interface"
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -109,13 +110,13 @@ impl PactRegistry {
             for export in &world.exports {
                 match export {
                     WorldItem::Function(f) if f.name == path.function => return Some(f),
-                    WorldItem::InlineInterface { name, functions } => {
-                        // Check if this matches the interface name
-                        if *name == path.interface.interface || path.interface.to_string() == *name
-                        {
-                            if let Some(f) = functions.iter().find(|f| f.name == path.function) {
-                                return Some(f);
-                            }
+                    // Check if this matches the interface name
+                    WorldItem::InlineInterface { name, functions }
+                        if *name == path.interface.interface
+                            || path.interface.to_string() == *name =>
+                    {
+                        if let Some(f) = functions.iter().find(|f| f.name == path.function) {
+                            return Some(f);
                         }
                     }
                     _ => {}
@@ -133,10 +134,10 @@ impl PactRegistry {
             for export in &world.exports {
                 match export {
                     WorldItem::Function(f) if f.name == func_name => return true,
-                    WorldItem::InlineInterface { functions, .. } => {
-                        if functions.iter().any(|f| f.name == func_name) {
-                            return true;
-                        }
+                    WorldItem::InlineInterface { functions, .. }
+                        if functions.iter().any(|f| f.name == func_name) =>
+                    {
+                        return true;
                     }
                     _ => {}
                 }
@@ -252,12 +253,12 @@ impl PactRegistry {
             for import in &world.imports {
                 match import {
                     WorldItem::Function(f) if f.name == path.function => return Some(f),
-                    WorldItem::InlineInterface { name, functions } => {
-                        if *name == path.interface.interface || path.interface.to_string() == *name
-                        {
-                            if let Some(f) = functions.iter().find(|f| f.name == path.function) {
-                                return Some(f);
-                            }
+                    WorldItem::InlineInterface { name, functions }
+                        if *name == path.interface.interface
+                            || path.interface.to_string() == *name =>
+                    {
+                        if let Some(f) = functions.iter().find(|f| f.name == path.function) {
+                            return Some(f);
                         }
                     }
                     WorldItem::InterfacePath {
@@ -606,6 +607,9 @@ pub struct World {
     /// Per-type codegen annotations (`@forward-compatible`, `@default`), keyed
     /// by type name.
     pub type_attrs: HashMap<String, TypeAttrs>,
+    /// Every named type reference in the source, with its byte range — generic
+    /// parameters of the enclosing definition excluded.
+    pub references: Vec<(String, Range<usize>)>,
 }
 
 /// Codegen annotations attached to a type definition via `@`-prefixed lines,
@@ -635,6 +639,10 @@ pub struct PactUse {
 pub struct ParseError {
     pub message: String,
     pub span: Span,
+    /// Byte range of the offending source, when known.
+    pub range: Option<Range<usize>>,
+    /// A trailing `help:` line, such as a did-you-mean suggestion.
+    pub help: Option<String>,
 }
 
 impl ParseError {
@@ -642,7 +650,65 @@ impl ParseError {
         Self {
             message: message.into(),
             span: Span::call_site(),
+            range: None,
+            help: None,
+        }
+    }
+
+    fn at(mut self, range: Range<usize>) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Render the message with the offending line of `src` (registered as
+    /// `name`) and a caret under the range, rustc style. Very long lines — an
+    /// inline `pact!` block arrives as one — are cut to a window around the
+    /// caret.
+    pub fn render(&self, name: &str, src: &str) -> String {
+        let mut out = format!("{}\n", self.message);
+        if let Some(range) = &self.range {
+            let start = range.start.min(src.len());
+            let end = range.end.clamp(start, src.len());
+            let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+            let line = src[..start].matches('\n').count() + 1;
+            let mut text: Vec<char> = src[line_start..line_end].chars().collect();
+            let mut col = src[line_start..start].chars().count();
+            let width = src[start..end.min(line_end)].chars().count().max(1);
+            let column = col + 1;
+            const WINDOW: usize = 40;
+            if text.len() > 2 * WINDOW + width {
+                let from = col.saturating_sub(WINDOW);
+                let to = (col + width + WINDOW).min(text.len());
+                let mut window: Vec<char> = Vec::new();
+                if from > 0 {
+                    window.extend("...".chars());
+                }
+                window.extend(&text[from..to]);
+                if to < text.len() {
+                    window.extend("...".chars());
+                }
+                col = col - from + if from > 0 { 3 } else { 0 };
+                text = window;
+            }
+            let text: String = text.into_iter().collect();
+            let gutter = " ".repeat(line.to_string().len());
+            out.push_str(&format!("{gutter}--> {name}:{line}:{column}\n"));
+            out.push_str(&format!("{gutter} |\n"));
+            out.push_str(&format!("{line} | {}\n", text.trim_end()));
+            out.push_str(&format!(
+                "{gutter} | {}{}\n",
+                " ".repeat(col),
+                "^".repeat(width)
+            ));
+            if let Some(help) = &self.help {
+                out.push_str(&format!("{gutter} |\n{gutter} = help: {help}\n"));
+            }
+        } else if let Some(help) = &self.help {
+            out.push_str(&format!("  = help: {help}\n"));
         }
+        out.truncate(out.trim_end().len());
+        out
     }
 }
 
@@ -666,20 +732,36 @@ pub(crate) enum Token {
 }
 
 struct Lexer<'a> {
-    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    src: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    /// Byte range of each token pushed so far.
+    ranges: Vec<Range<usize>>,
 }
 
 impl<'a> Lexer<'a> {
     fn new(src: &'a str) -> Self {
         Self {
-            chars: src.chars().peekable(),
+            src,
+            chars: src.char_indices().peekable(),
+            ranges: Vec::new(),
         }
     }
 
+    /// The byte offset of the next character.
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.src.len(), |&(i, _)| i)
+    }
+
+    fn push(&mut self, tokens: &mut Vec<Token>, token: Token, start: usize) {
+        let end = self.offset();
+        tokens.push(token);
+        self.ranges.push(start..end);
+    }
+
     fn tokenize(&mut self) -> Result<Vec<Token>, ParseError> {
         let mut tokens = Vec::new();
 
-        while let Some(&ch) = self.chars.peek() {
+        while let Some(&(start, ch)) = self.chars.peek() {
             if ch.is_whitespace() {
                 self.chars.next();
                 continue;
@@ -688,34 +770,34 @@ impl<'a> Lexer<'a> {
             // Comments
             if ch == '/' {
                 self.chars.next();
-                if matches!(self.chars.peek(), Some('/')) {
+                if matches!(self.chars.peek(), Some((_, '/'))) {
                     // Line comment
-                    for c in self.chars.by_ref() {
+                    for (_, c) in self.chars.by_ref() {
                         if c == '\n' {
                             break;
                         }
                     }
                     continue;
                 }
-                if matches!(self.chars.peek(), Some('*')) {
+                if matches!(self.chars.peek(), Some((_, '*'))) {
                     // Block comment
                     self.chars.next();
-                    while let Some(c) = self.chars.next() {
-                        if c == '*' && matches!(self.chars.peek(), Some('/')) {
+                    while let Some((_, c)) = self.chars.next() {
+                        if c == '*' && matches!(self.chars.peek(), Some((_, '/'))) {
                             self.chars.next();
                             break;
                         }
                     }
                     continue;
                 }
-                tokens.push(Token::Symbol('/'));
+                self.push(&mut tokens, Token::Symbol('/'), start);
                 continue;
             }
 
             // Identifiers
             if ch.is_ascii_alphabetic() || ch == '_' {
                 let mut ident = String::new();
-                while let Some(&c) = self.chars.peek() {
+                while let Some(&(_, c)) = self.chars.peek() {
                     if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
                         ident.push(c);
                         self.chars.next();
@@ -723,7 +805,7 @@ impl<'a> Lexer<'a> {
                         break;
                     }
                 }
-                tokens.push(Token::Ident(ident));
+                self.push(&mut tokens, Token::Ident(ident), start);
                 continue;
             }
 
@@ -732,7 +814,7 @@ impl<'a> Lexer<'a> {
                 self.chars.next(); // opening quote
                 let mut s = String::new();
                 let mut closed = false;
-                for c in self.chars.by_ref() {
+                for (_, c) in self.chars.by_ref() {
                     if c == '"' {
                         closed = true;
                         break;
@@ -740,9 +822,11 @@ impl<'a> Lexer<'a> {
                     s.push(c);
                 }
                 if !closed {
-                    return Err(ParseError::new("unterminated string literal"));
+                    return Err(
+                        ParseError::new("unterminated string literal").at(start..self.src.len())
+                    );
                 }
-                tokens.push(Token::Str(s));
+                self.push(&mut tokens, Token::Str(s), start);
                 continue;
             }
 
@@ -751,15 +835,17 @@ impl<'a> Lexer<'a> {
                 ch,
                 '{' | '}' | '(' | ')' | '<' | '>' | ':' | ',' | '=' | ';' | '-' | '.' | '@' | '*'
             ) {
-                tokens.push(Token::Symbol(ch));
                 self.chars.next();
+                self.push(&mut tokens, Token::Symbol(ch), start);
                 continue;
             }
 
-            return Err(ParseError::new(format!("unexpected character: {}", ch)));
+            return Err(ParseError::new(format!("unexpected character: {}", ch))
+                .at(start..start + ch.len_utf8()));
         }
 
-        tokens.push(Token::Eof);
+        let end = self.src.len();
+        self.push(&mut tokens, Token::Eof, end);
         Ok(tokens)
     }
 }
@@ -771,11 +857,41 @@ impl<'a> Lexer<'a> {
 pub(crate) struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    /// Byte range of each token; empty when the tokens came without them.
+    ranges: Vec<Range<usize>>,
+    /// Index of the token most recently consumed.
+    last: usize,
+    /// Generic parameters of the type definition being parsed.
+    generics: Vec<String>,
+    references: Vec<(String, Range<usize>)>,
 }
 
 impl Parser {
     pub(crate) fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+        Self::spanned(tokens, Vec::new())
+    }
+
+    fn spanned(tokens: Vec<Token>, ranges: Vec<Range<usize>>) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            ranges,
+            last: 0,
+            generics: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+
+    /// Point `error` at the token most recently consumed, unless it already
+    /// has a location.
+    fn locate(&self, error: ParseError) -> ParseError {
+        match (&error.range, self.ranges.get(self.last)) {
+            (None, Some(range)) => {
+                let range = range.clone();
+                error.at(range)
+            }
+            _ => error,
+        }
     }
 
     fn peek(&self) -> &Token {
@@ -788,6 +904,7 @@ impl Parser {
 
     fn next(&mut self) -> Token {
         let tok = self.peek().clone();
+        self.last = self.pos.min(self.tokens.len().saturating_sub(1));
         if !matches!(tok, Token::Eof) {
             self.pos += 1;
         }
@@ -796,6 +913,7 @@ impl Parser {
 
     pub(crate) fn accept_symbol(&mut self, expected: char) -> bool {
         if matches!(self.peek(), Token::Symbol(c) if *c == expected) {
+            self.last = self.pos;
             self.pos += 1;
             true
         } else {
@@ -815,6 +933,7 @@ impl Parser {
 
     pub(crate) fn accept_ident(&mut self, expected: &str) -> bool {
         if matches!(self.peek(), Token::Ident(s) if s == expected) {
+            self.last = self.pos;
             self.pos += 1;
             true
         } else {
@@ -871,8 +990,12 @@ pub(crate) fn make_parser(tokens: Vec<Token>) -> Parser {
 pub fn parse_world(src: &str) -> Result<World, ParseError> {
     let mut lexer = Lexer::new(src);
     let tokens = lexer.tokenize()?;
-    let mut parser = Parser::new(tokens);
+    let mut parser = Parser::spanned(tokens, lexer.ranges);
+    let world = parse_world_items(&mut parser);
+    world.map_err(|e| parser.locate(e))
+}
 
+fn parse_world_items(parser: &mut Parser) -> Result<World, ParseError> {
     // Parse optional `use` imports and type definitions before the world.
     let mut types = Vec::new();
     let mut uses = Vec::new();
@@ -882,11 +1005,11 @@ pub fn parse_world(src: &str) -> Result<World, ParseError> {
             break;
         }
         if matches!(parser.peek(), Token::Ident(s) if s == "use") {
-            uses.push(parse_use(&mut parser)?);
+            uses.push(parse_use(parser)?);
             continue;
         }
-        let attrs = parse_type_annotations(&mut parser)?;
-        if let Some(typedef) = try_parse_typedef(&mut parser)? {
+        let attrs = parse_type_annotations(parser)?;
+        if let Some(typedef) = try_parse_typedef(parser)? {
             if !attrs.is_empty() {
                 type_attrs.insert(typedef.name().to_string(), attrs);
             }
@@ -918,8 +1041,8 @@ pub fn parse_world(src: &str) -> Result<World, ParseError> {
         }
 
         // Check for type definitions inside world (optionally annotated).
-        let attrs = parse_type_annotations(&mut parser)?;
-        if let Some(typedef) = try_parse_typedef(&mut parser)? {
+        let attrs = parse_type_annotations(parser)?;
+        if let Some(typedef) = try_parse_typedef(parser)? {
             if !attrs.is_empty() {
                 type_attrs.insert(typedef.name().to_string(), attrs);
             }
@@ -934,8 +1057,8 @@ pub fn parse_world(src: &str) -> Result<World, ParseError> {
 
         let keyword = parser.expect_ident()?;
         match keyword.as_str() {
-            "import" => imports.push(parse_world_item(&mut parser)?),
-            "export" => exports.push(parse_world_item(&mut parser)?),
+            "import" => imports.push(parse_world_item(parser)?),
+            "export" => exports.push(parse_world_item(parser)?),
             _ => {
                 return Err(ParseError::new(format!(
                     "expected 'import' or 'export', got '{}'",
@@ -954,6 +1077,7 @@ pub fn parse_world(src: &str) -> Result<World, ParseError> {
         exports,
         uses,
         type_attrs,
+        references: std::mem::take(&mut parser.references),
     })
 }
 
@@ -1018,8 +1142,12 @@ impl TypeAttrs {
 pub fn parse_pact(src: &str) -> Result<PactRegistry, ParseError> {
     let mut lexer = Lexer::new(src);
     let tokens = lexer.tokenize()?;
-    let mut parser = Parser::new(tokens);
+    let mut parser = Parser::spanned(tokens, lexer.ranges);
+    let registry = parse_registry(&mut parser);
+    registry.map_err(|e| parser.locate(e))
+}
 
+fn parse_registry(parser: &mut Parser) -> Result<PactRegistry, ParseError> {
     let mut registry = PactRegistry::default();
 
     while !parser.is_eof() {
@@ -1031,7 +1159,11 @@ pub fn parse_pact(src: &str) -> Result<PactRegistry, ParseError> {
         let keyword = match parser.peek() {
             Token::Ident(s) => s.clone(),
             Token::Eof => break,
-            other => return Err(ParseError::new(format!("unexpected token: {:?}", other))),
+            other => {
+                let error = ParseError::new(format!("unexpected token: {:?}", other));
+                parser.next();
+                return Err(error);
+            }
         };
 
         match keyword.as_str() {
@@ -1050,7 +1182,7 @@ pub fn parse_pact(src: &str) -> Result<PactRegistry, ParseError> {
             }
             "interface" => {
                 parser.next();
-                let iface = parse_interface(&mut parser)?;
+                let iface = parse_interface(parser)?;
 
                 // Build the full interface path
                 let path = if let Some((ns, pkg)) = &registry.current_package {
@@ -1067,12 +1199,12 @@ pub fn parse_pact(src: &str) -> Result<PactRegistry, ParseError> {
             }
             "world" => {
                 parser.next();
-                let world = parse_world_body(&mut parser)?;
+                let world = parse_world_body(parser)?;
                 registry.worlds.push(world);
             }
             // Type definitions at top level
             "type" | "record" | "variant" | "enum" | "flags" => {
-                if let Some(typedef) = try_parse_typedef(&mut parser)? {
+                if let Some(typedef) = try_parse_typedef(parser)? {
                     registry.types.push(typedef);
                 }
             }
@@ -1202,6 +1334,7 @@ fn parse_world_body(parser: &mut Parser) -> Result<World, ParseError> {
         exports,
         uses: Vec::new(),
         type_attrs: HashMap::new(),
+        references: Vec::new(),
     })
 }
 
@@ -1249,6 +1382,13 @@ fn parse_type_arg_list(parser: &mut Parser) -> Result<Vec<Type>, ParseError> {
 }
 
 fn try_parse_typedef(parser: &mut Parser) -> Result<Option<TypeDef>, ParseError> {
+    let outer = std::mem::take(&mut parser.generics);
+    let typedef = parse_typedef_body(parser);
+    parser.generics = outer;
+    typedef
+}
+
+fn parse_typedef_body(parser: &mut Parser) -> Result<Option<TypeDef>, ParseError> {
     let keyword = match parser.peek() {
         Token::Ident(s) => s.clone(),
         _ => return Ok(None),
@@ -1259,6 +1399,7 @@ fn try_parse_typedef(parser: &mut Parser) -> Result<Option<TypeDef>, ParseError>
             parser.next();
             let name = parser.expect_ident()?;
             let type_params = parse_type_param_list(parser)?;
+            parser.generics = type_params.clone();
             parser.expect_symbol('=')?;
             let ty = parse_type(parser)?;
             Ok(Some(TypeDef::Alias {
@@ -1271,6 +1412,7 @@ fn try_parse_typedef(parser: &mut Parser) -> Result<Option<TypeDef>, ParseError>
             parser.next();
             let name = parser.expect_ident()?;
             let type_params = parse_type_param_list(parser)?;
+            parser.generics = type_params.clone();
             parser.expect_symbol('{')?;
             let mut fields = Vec::new();
             while !parser.accept_symbol('}') {
@@ -1290,6 +1432,7 @@ fn try_parse_typedef(parser: &mut Parser) -> Result<Option<TypeDef>, ParseError>
             parser.next();
             let name = parser.expect_ident()?;
             let type_params = parse_type_param_list(parser)?;
+            parser.generics = type_params.clone();
             parser.expect_symbol('{')?;
             let mut cases = Vec::new();
             while !parser.accept_symbol('}') {
@@ -1570,6 +1713,11 @@ pub(crate) fn parse_type(parser: &mut Parser) -> Result<Type, ParseError> {
             Ok(Type::Set(Box::new(elem)))
         }
         _ => {
+            if !parser.generics.contains(&ident) {
+                if let Some(range) = parser.ranges.get(parser.last) {
+                    parser.references.push((ident.clone(), range.clone()));
+                }
+            }
             // Generic type application `name<...>`, or a bare named reference
             // (which also covers in-scope type-parameter references).
             if matches!(parser.peek(), Token::Symbol('<')) {
@@ -1590,6 +1738,61 @@ fn parse_optional_type(parser: &mut Parser) -> Result<Option<Type>, ParseError>
     }
 }
 
+/// Type names `parse_type` knows without a definition.
+const BUILTIN_TYPES: &[&str] = &[
+    "bool", "u8", "u16", "u32", "u64", "s8", "s16", "s32", "s64", "f32", "f64", "char", "string",
+    "value", "list", "option", "tuple", "result", "map", "set",
+];
+
+/// Check that every named type `world` references is defined — in the world,
+/// at top level, or pulled in by a resolved `use` — and suggest the closest
+/// name when one is not. Run after the world's `use`s are resolved.
+pub fn check_world_types(world: &World) -> Result<(), ParseError> {
+    let defined: Vec<&str> = world.types.iter().map(|t| t.name()).collect();
+    for (name, range) in &world.references {
+        if defined.contains(&name.as_str()) || BUILTIN_TYPES.contains(&name.as_str()) {
+            continue;
+        }
+        let candidates = defined.iter().chain(BUILTIN_TYPES).copied();
+        let mut error = ParseError::new(format!("undefined type `{}`", name)).at(range.clone());
+        if let Some(near) = suggest(name, candidates) {
+            error.help = Some(format!("did you mean `{}`?", near));
+        }
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// The candidate closest to `name`, if it is close enough to be a likely typo.
+fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|c| *c != name)
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= limit)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance between `a` and `b`, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1622,7 +1825,7 @@ mod tests {
         assert!(attrs.forward_compatible);
         assert!(attrs.derive_default);
         // A record with no annotation isn't in the map.
-        assert!(world.type_attrs.get("other").is_none());
+        assert!(!world.type_attrs.contains_key("other"));
     }
 
     #[test]
@@ -1729,6 +1932,41 @@ mod tests {
             _ => panic!("expected record"),
         }
     }
+
+    #[test]
+    fn parse_error_renders_the_offending_line() {
+        let src = "world app {\n    export run: func(x: u32) -> \n}\n";
+        let err = parse_world(src).expect_err("missing result type");
+        assert_eq!(&src[err.range.clone().unwrap()], "}");
+        assert_eq!(
+            err.render("app.pact", src),
+            "expected identifier, got Symbol('}')\n \
+             --> app.pact:3:1\n  \
+             |\n\
+             3 | }\n  \
+             | ^"
+        );
+    }
+
+    #[test]
+    fn undefined_types_are_reported_with_a_suggestion() {
+        let src = r#"
+            record user { id: u64 }
+            record page<t> { items: list<t> }
+            world app {
+                export get: func(id: u64) -> usr
+                export all: func() -> page<user>
+            }
+        "#;
+        let world = parse_world(src).expect("parse");
+        let err = check_world_types(&world).expect_err("`usr` is undefined");
+        assert_eq!(err.message, "undefined type `usr`");
+        assert_eq!(err.help.as_deref(), Some("did you mean `user`?"));
+        assert_eq!(&src[err.range.unwrap()], "usr");
+
+        let ok = parse_world("record user { id: u64 } world app { export get: func() -> user }");
+        assert!(check_world_types(&ok.expect("parse")).is_ok());
+    }
 }
 
 #[cfg(test)]
@@ -1,8 +1,9 @@
-use packr::{generate_rust, parse_pact_dir_with_registry, PactExport, TransformRegistry};
+use packr::{generate_rust, parse_pact_dir_with_registry, PactExport};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (root, type_registry) = parse_pact_dir_with_registry("examples/transforms")?;
-    let transform_registry = TransformRegistry::with_builtins();
+    // The built-in transforms plus those declared with `transform`
+    let transform_registry = type_registry.transforms();
 
     println!("=== Parsed Interfaces ===\n");
     for child in &root.children {
@@ -43,7 +44,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("=== Transformed rpc(calculator) ===\n");
     let rpc_calc =
-        type_registry.get_transformed_interface("rpc", "calculator", &transform_registry)?;
+        type_registry.get_transformed_interface("rpc", "calculator", transform_registry)?;
 
     println!("Name: {}", rpc_calc.name);
     println!("Types added:");
@@ -62,9 +63,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let rust_code = generate_rust(&rpc_calc);
     println!("{}", rust_code);
 
+    println!("=== Declared traced(rpc(calculator)) ===\n");
+    let aliases = type_registry.get_interface("aliases").unwrap();
+    let scope = type_registry.resolve_scope_with_transforms(aliases, transform_registry)?;
+    let traced = &scope.aliased_interfaces["traced-client"];
+    println!("Name: {}", traced.name);
+    for export in &traced.exports {
+        if let PactExport::Function(f) = export {
+            println!("  {}: {:?} -> {:?}", f.name, f.params, f.results);
+        }
+    }
+    println!();
+
     println!("=== Resolved Scope for 'caller' ===\n");
     let caller = type_registry.get_interface("caller").unwrap();
-    let scope = type_registry.resolve_scope_with_transforms(caller, &transform_registry)?;
+    let scope = type_registry.resolve_scope_with_transforms(caller, transform_registry)?;
 
     println!("Types in scope:");
     for name in scope.types.keys() {
@@ -0,0 +1,36 @@
+//! A middle component for the multi-interface provider test.
+//!
+//! `comp-cached` imports `cache` (from `comp-store`) and exports
+//! `lookup { find: func(n: s64) -> s64 }` with `find(n) = cache.get(n)`.
+
+#![no_std]
+
+extern crate alloc;
+
+use packr_guest::{export, import_from};
+
+packr_guest::setup_guest!();
+
+packr_guest::pack_types! {
+    imports {
+        cache {
+            get: func(k: s64) -> s64,
+            peek: func(k: s64) -> s64,
+        }
+    }
+    exports {
+        lookup {
+            find: func(n: s64) -> s64,
+        }
+    }
+}
+
+/// Wired to `comp-store`'s `cache.get` at compose time.
+#[import_from("cache")]
+fn get(k: i64) -> i64;
+
+/// find(n) = cache.get(n).
+#[export(name = "lookup.find")]
+fn find(n: i64) -> i64 {
+    get(n)
+}
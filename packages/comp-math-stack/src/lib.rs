@@ -0,0 +1,46 @@
+//! The nested-composition fixture: one half of a reusable "math stack".
+//!
+//! `comp-math-stack` exports `math.double`, implemented on top of another
+//! component's `util.inc` import and the host's `host.tick`. Composed with
+//! `comp-util` it forms a self-contained sub-system that exports `math` and
+//! still needs `host.tick` from whoever runs it — which is then composed, as a
+//! single component, into an outer composite (`tests/compose_nested.rs`).
+
+#![no_std]
+
+extern crate alloc;
+
+use packr_guest::{export, import_from};
+
+packr_guest::setup_guest!();
+
+packr_guest::pack_types! {
+    imports {
+        host {
+            tick: func(),
+        }
+        util {
+            inc: func(n: s64) -> s64,
+        }
+    }
+    exports {
+        math {
+            double: func(n: s64) -> s64,
+        }
+    }
+}
+
+/// The residual host call; survives both levels of composition.
+#[import_from("host")]
+fn tick();
+
+/// Satisfied inside the stack by `comp-util`.
+#[import_from("util")]
+fn inc(n: i64) -> i64;
+
+/// double(n) = inc(n) * 2, after a host `tick()`.
+#[export]
+fn double(n: i64) -> i64 {
+    tick();
+    inc(n) * 2
+}
@@ -0,0 +1,42 @@
+//! The entry component for the multi-interface provider test.
+//!
+//! `comp-store-app` imports `kv { get }` and `lookup { find }` and exports
+//! `run(n) = kv.get(n) + find(n)`. `lookup` comes from `comp-cached` by hash;
+//! `kv` is `comp-store`'s `db` interface under another name, so it needs an
+//! interface link. With `comp-store`: `run(1)` = `10 + 101` = `111`.
+
+#![no_std]
+
+extern crate alloc;
+
+use packr_guest::{export, import_from};
+
+packr_guest::setup_guest!();
+
+packr_guest::pack_types! {
+    imports {
+        kv {
+            get: func(k: s64) -> s64,
+        }
+        lookup {
+            find: func(n: s64) -> s64,
+        }
+    }
+    exports {
+        run: func(n: s64) -> s64,
+    }
+}
+
+/// Wired to `comp-store`'s `db.get` by an interface link.
+#[import_from("kv")]
+fn get(k: i64) -> i64;
+
+/// Wired to `comp-cached`'s `lookup.find` by hash.
+#[import_from("lookup")]
+fn find(n: i64) -> i64;
+
+/// run(n) = kv.get(n) + find(n).
+#[export]
+fn run(n: i64) -> i64 {
+    get(n) + find(n)
+}
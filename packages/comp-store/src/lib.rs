@@ -0,0 +1,50 @@
+//! A multi-interface provider fixture: ONE component exporting two interfaces.
+//!
+//! `comp-store` exports `db { get }` (`get(k) = k * 10`) and
+//! `cache { get, peek }` (`get(k) = k + 100`, `peek(k) = k`). Both interfaces
+//! have a `get`, so the exports are interface-qualified (`db.get`,
+//! `cache.get`). Composed, each interface is linked to a different consumer.
+
+#![no_std]
+
+extern crate alloc;
+
+use packr_guest::{export, Value};
+
+packr_guest::setup_guest!();
+
+packr_guest::pack_types! {
+    exports {
+        db {
+            get: func(k: s64) -> s64,
+        }
+        cache {
+            get: func(k: s64) -> s64,
+            peek: func(k: s64) -> s64,
+        }
+    }
+}
+
+/// db.get(k) = k * 10.
+#[export(name = "db.get")]
+fn db_get(input: Value) -> Value {
+    match input {
+        Value::S64(k) => Value::S64(k * 10),
+        other => other,
+    }
+}
+
+/// cache.get(k) = k + 100.
+#[export(name = "cache.get")]
+fn cache_get(input: Value) -> Value {
+    match input {
+        Value::S64(k) => Value::S64(k + 100),
+        other => other,
+    }
+}
+
+/// cache.peek(k) = k.
+#[export(name = "cache.peek")]
+fn cache_peek(input: Value) -> Value {
+    input
+}
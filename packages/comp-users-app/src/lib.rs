@@ -0,0 +1,70 @@
+//! A consumer fixture for link adapters: imports `users` at its first schema
+//! version.
+//!
+//! `run(n)` reads user `n`, saves a copy as user `n + 1`, and suspends user
+//! `n`: `run(n) = save({ id: n + 1, name }) + set-status(n, suspended("spam"))`.
+//! Linked to `comp-users-v2`, every one of those calls crosses the adapter.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use packr_guest::{export, import_from};
+
+packr_guest::setup_guest!();
+
+// The Rust types (`User`, `Status`); `pack_types!` below carries the metadata.
+packr_guest::pact! {
+    record user {
+        id: s64,
+        name: string,
+    }
+
+    variant status {
+        active,
+        suspended(string),
+    }
+
+    world app {}
+}
+
+packr_guest::pack_types! {
+    imports {
+        users {
+            record user {
+                id: s64,
+                name: string,
+            }
+            variant status {
+                active,
+                suspended(string),
+            }
+            get: func(id: s64) -> user,
+            save: func(u: user) -> s64,
+            set-status: func(id: s64, s: status) -> s64,
+        }
+    }
+    exports {
+        run: func(n: s64) -> s64,
+    }
+}
+
+#[import_from("users")]
+fn get(id: i64) -> User;
+
+#[import_from("users")]
+fn save(u: User) -> i64;
+
+#[import_from("users", name = "set-status")]
+fn set_status(id: i64, s: Status) -> i64;
+
+#[export]
+fn run(n: i64) -> i64 {
+    let user = get(n);
+    let saved = save(User {
+        id: user.id + 1,
+        name: user.name,
+    });
+    saved + set_status(n, Status::Suspended(String::from("spam")))
+}
@@ -0,0 +1,82 @@
+//! A provider fixture for link adapters: `users` at a later schema version.
+//!
+//! Against `comp-users-app`'s `users` import, this export differs structurally
+//! but compatibly:
+//!   - `user.name` is renamed `full-name`, and `user` gains `email: option<string>`;
+//!   - `status` gains an `archived` case, declared first, so the other cases'
+//!     tags move.
+//!
+//! `get(id)` = `{ id, full-name: "user-<id>", email: some("<id>@example.com") }`,
+//! `save(u)` = `u.id * 100 + len(u.full-name)` (it fails on an email it was
+//! not sent as `none`), and `set-status(id, s)` = the case's position in v2
+//! (`archived` 0, `active` 1, `suspended` 2).
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::format;
+use packr_guest::export;
+
+packr_guest::setup_guest!();
+
+// The Rust types (`User`, `Status`); `pack_types!` below carries the metadata.
+packr_guest::pact! {
+    record user {
+        id: s64,
+        full-name: string,
+        email: option<string>,
+    }
+
+    variant status {
+        archived,
+        active,
+        suspended(string),
+    }
+
+    world users-v2 {}
+}
+
+packr_guest::pack_types! {
+    exports {
+        users {
+            record user {
+                id: s64,
+                full-name: string,
+                email: option<string>,
+            }
+            variant status {
+                archived,
+                active,
+                suspended(string),
+            }
+            get: func(id: s64) -> user,
+            save: func(u: user) -> s64,
+            set-status: func(id: s64, s: status) -> s64,
+        }
+    }
+}
+
+#[export(name = "users.get")]
+fn get(id: i64) -> User {
+    User {
+        id,
+        full_name: format!("user-{id}"),
+        email: Some(format!("{id}@example.com")),
+    }
+}
+
+#[export(name = "users.save")]
+fn save(u: User) -> i64 {
+    assert!(u.email.is_none(), "save: unexpected email");
+    u.id * 100 + u.full_name.len() as i64
+}
+
+#[export(name = "users.set-status")]
+fn set_status(_id: i64, s: Status) -> i64 {
+    match s {
+        Status::Archived => 0,
+        Status::Active => 1,
+        Status::Suspended(_) => 2,
+    }
+}
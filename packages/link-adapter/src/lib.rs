@@ -0,0 +1,188 @@
+//! The link adapter: rewrites a CGRF value from one schema version to another.
+//!
+//! `packr compose` merges this module into a composite that links two
+//! structurally compatible interfaces whose hashes differ (see
+//! `src/compose/adapt.rs`). Each adapted call passes its value through
+//! `__pack_adapt` with a compose-time **plan**, itself a pack value:
+//!
+//! ```text
+//! same                                 the value is already in reader form
+//! record(list<field>)                  one entry per READER field:
+//!   take(reader, writer, plan)           the writer's field, adapted
+//!   fill(reader, value)                  a default the writer does not send
+//! variant(list<tuple<u32, u32, plan>>) writer tag -> reader tag, payload plan
+//! list(plan) | option(plan) | result(plan, plan) | tuple(list<plan>)
+//! ```
+//!
+//! Writer fields the plan does not take are dropped. The adapter knows nothing
+//! of the interfaces; the checked-in `assets/link_adapter.wasm` is this crate
+//! built for `wasm32-unknown-unknown --release`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use packr_guest::{decode, encode, Value};
+
+packr_guest::setup_guest!();
+
+/// Adapt the value at `in_ptr` by the plan at `plan_ptr`.
+///
+/// The result is written like an export's: a fresh buffer whose pointer and
+/// length go to `out_ptr_ptr` / `out_len_ptr`, freed with `__pack_free`. The
+/// status is 0, or -1 with a UTF-8 error message as the result.
+#[no_mangle]
+pub extern "C" fn __pack_adapt(
+    plan_ptr: i32,
+    plan_len: i32,
+    in_ptr: i32,
+    in_len: i32,
+    out_ptr_ptr: i32,
+    out_len_ptr: i32,
+) -> i32 {
+    let (plan, input) = unsafe {
+        (
+            core::slice::from_raw_parts(plan_ptr as *const u8, plan_len as usize),
+            core::slice::from_raw_parts(in_ptr as *const u8, in_len as usize),
+        )
+    };
+    let (status, mut bytes) = match run(plan, input) {
+        Ok(bytes) => (0, bytes),
+        Err(e) => (-1, Vec::from(format!("link adapter: {e}").as_bytes())),
+    };
+    bytes.shrink_to_fit();
+    unsafe {
+        core::ptr::write(out_ptr_ptr as *mut i32, bytes.as_ptr() as i32);
+        core::ptr::write(out_len_ptr as *mut i32, bytes.len() as i32);
+    }
+    core::mem::forget(bytes);
+    status
+}
+
+fn run(plan: &[u8], input: &[u8]) -> Result<Vec<u8>, String> {
+    let plan = decode(plan).map_err(|e| format!("bad plan: {e:?}"))?;
+    let value = decode(input).map_err(|e| format!("decode error: {e:?}"))?;
+    let adapted = apply(&plan, value)?;
+    encode(&adapted).map_err(|e| format!("encode error: {e:?}"))
+}
+
+fn apply(plan: &Value, value: Value) -> Result<Value, String> {
+    let Value::Variant { tag, payload, .. } = plan else {
+        return Err(String::from("bad plan: expected a variant"));
+    };
+    match (*tag, payload.as_slice(), value) {
+        (0, [], value) => Ok(value),
+        (1, [Value::List { items: plan, .. }], Value::Record { type_name, fields }) => {
+            adapt_record(plan, type_name, fields)
+        }
+        (
+            2,
+            [Value::List { items: plan, .. }],
+            Value::Variant {
+                type_name,
+                case_name,
+                tag,
+                payload,
+            },
+        ) => {
+            let (reader_tag, case_plan) = plan
+                .iter()
+                .find_map(|case| match case {
+                    Value::Tuple(case) => match case.as_slice() {
+                        [Value::U32(w), Value::U32(r), p] if *w as usize == tag => {
+                            Some((*r as usize, p))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .ok_or_else(|| format!("case `{case_name}` (tag {tag}) has no reader case"))?;
+            Ok(Value::Variant {
+                type_name,
+                case_name,
+                tag: reader_tag,
+                payload: payload
+                    .into_iter()
+                    .map(|v| apply(case_plan, v))
+                    .collect::<Result<_, _>>()?,
+            })
+        }
+        (3, [plan], Value::List { elem_type, items }) => Ok(Value::List {
+            elem_type,
+            items: items
+                .into_iter()
+                .map(|v| apply(plan, v))
+                .collect::<Result<_, _>>()?,
+        }),
+        (4, [plan], Value::Option { inner_type, value }) => Ok(Value::Option {
+            inner_type,
+            value: match value {
+                Some(v) => Some(Box::new(apply(plan, *v)?)),
+                None => None,
+            },
+        }),
+        (
+            5,
+            [ok, err],
+            Value::Result {
+                ok_type,
+                err_type,
+                value,
+            },
+        ) => Ok(Value::Result {
+            ok_type,
+            err_type,
+            value: match value {
+                Ok(v) => Ok(Box::new(apply(ok, *v)?)),
+                Err(v) => Err(Box::new(apply(err, *v)?)),
+            },
+        }),
+        (6, [Value::List { items: plans, .. }], Value::Tuple(items))
+            if plans.len() == items.len() =>
+        {
+            Ok(Value::Tuple(
+                plans
+                    .iter()
+                    .zip(items)
+                    .map(|(p, v)| apply(p, v))
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+        (tag, _, _) => Err(format!("plan step {tag} does not apply to the value")),
+    }
+}
+
+fn adapt_record(
+    plan: &[Value],
+    type_name: String,
+    mut fields: Vec<(String, Value)>,
+) -> Result<Value, String> {
+    let mut adapted = Vec::with_capacity(plan.len());
+    for field in plan {
+        let Value::Variant { tag, payload, .. } = field else {
+            return Err(String::from("bad plan: expected a field"));
+        };
+        match (*tag, payload.as_slice()) {
+            (0, [Value::String(reader), Value::String(writer), plan]) => {
+                let at = fields
+                    .iter()
+                    .position(|(name, _)| name == writer)
+                    .ok_or_else(|| format!("record `{type_name}` has no field `{writer}`"))?;
+                let (_, value) = fields.swap_remove(at);
+                adapted.push((reader.clone(), apply(plan, value)?));
+            }
+            (1, [Value::String(reader), default]) => {
+                adapted.push((reader.clone(), default.clone()));
+            }
+            _ => return Err(String::from("bad plan: malformed field")),
+        }
+    }
+    Ok(Value::Record {
+        type_name,
+        fields: adapted,
+    })
+}
@@ -4,14 +4,26 @@
 //!
 //! - All types use a graph-encoded ABI (schema-aware)
 
+mod stream;
 mod value;
 
 // Re-export PackType from local module (maps Rust types to Pack types)
 pub use value::PackType;
 
+pub use stream::{encode_into, encoded_len};
+
 // Re-export Value types from packr_abi for unified type system
 pub use packr_abi::{ConversionError, FromValue, Value, ValueType};
 
+// Borrowed views decode straight out of guest memory; they live in packr_abi so
+// guests can use them too.
+pub use packr_abi::{
+    GraphView, ListRef, OptionRef, RecordRef, ResultRef, SeqRef, ValueRef, VariantRef, ViewIter,
+};
+
+// DAG-preserving node graph (shared subtrees, cycles).
+pub use packr_abi::{NodeValue, ValueGraph};
+
 use std::collections::{HashMap, HashSet};
 
 use thiserror::Error;
@@ -31,9 +43,30 @@ pub enum AbiError {
     InvalidTag(u8),
 }
 
+impl From<packr_abi::AbiError> for AbiError {
+    fn from(err: packr_abi::AbiError) -> Self {
+        match err {
+            packr_abi::AbiError::TypeMismatch { expected, got } => {
+                AbiError::TypeMismatch { expected, got }
+            }
+            packr_abi::AbiError::InvalidEncoding(msg) => AbiError::InvalidEncoding(msg),
+            packr_abi::AbiError::BufferTooSmall { need, have } => {
+                AbiError::BufferTooSmall { need, have }
+            }
+            packr_abi::AbiError::InvalidTag(tag) => AbiError::InvalidTag(tag),
+        }
+    }
+}
+
 const MAGIC: u32 = u32::from_le_bytes(*b"CGRF");
 const VERSION: u16 = 2;
 
+/// Header flag for the schema-driven compact profile (see
+/// [`encode_compact_with_schema`](crate::parser::encode_compact_with_schema)).
+/// Plain [`decode`] rejects such buffers: their records and variants carry no
+/// names, so only a decoder holding the schema can rebuild them.
+pub use packr_abi::FLAG_COMPACT;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Limits {
     pub max_buffer_size: usize,
@@ -152,6 +185,18 @@ pub fn decode(bytes: &[u8]) -> Result<Value, AbiError> {
     Value::decode_graph(&decoder, buffer.root)
 }
 
+/// Encode a value with repeated subtrees emitted once and shared by node index.
+/// Any decoder reads the result back as the same value as [`encode`]'s.
+pub fn encode_shared(value: &Value) -> Result<Vec<u8>, AbiError> {
+    Ok(packr_abi::encode_shared(value)?)
+}
+
+/// Decode bytes into a [`ValueGraph`], keeping shared nodes shared and
+/// accepting cycles (which [`decode`] rejects).
+pub fn decode_graph(bytes: &[u8]) -> Result<ValueGraph, AbiError> {
+    Ok(packr_abi::decode_graph(bytes)?)
+}
+
 /// Decode a value from the *prefix* of `bytes`, returning the value and the
 /// number of bytes consumed.
 ///
@@ -169,10 +214,15 @@ pub fn decode_prefix(bytes: &[u8]) -> Result<(Value, usize), AbiError> {
 
 impl GraphBuffer {
     pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with_flags(0)
+    }
+
+    /// Serialize with the given header flags (e.g. [`FLAG_COMPACT`]).
+    pub fn to_bytes_with_flags(&self, flags: u16) -> Vec<u8> {
         let mut out = Vec::new();
         out.extend_from_slice(&MAGIC.to_le_bytes());
         out.extend_from_slice(&VERSION.to_le_bytes());
-        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&flags.to_le_bytes());
         out.extend_from_slice(&(self.nodes.len() as u32).to_le_bytes());
         out.extend_from_slice(&self.root.to_le_bytes());
 
@@ -207,6 +257,31 @@ impl GraphBuffer {
         bytes: &[u8],
         limits: &Limits,
     ) -> Result<(Self, usize), AbiError> {
+        let (buffer, consumed, flags) = Self::parse_prefix(bytes, limits)?;
+        if flags & FLAG_COMPACT != 0 {
+            return Err(AbiError::InvalidEncoding(
+                "Compact-profile buffer requires a schema to decode".to_string(),
+            ));
+        }
+        Ok((buffer, consumed))
+    }
+
+    /// Parse a buffer written in the compact profile. Its `Record` / `Variant`
+    /// payloads are left as-is; the schema-aware decoder rebuilds the names.
+    pub fn from_compact_bytes_with_limits(bytes: &[u8], limits: &Limits) -> Result<Self, AbiError> {
+        let (buffer, consumed, flags) = Self::parse_prefix(bytes, limits)?;
+        if flags & FLAG_COMPACT == 0 {
+            return Err(AbiError::InvalidEncoding(
+                "Expected a compact-profile buffer".to_string(),
+            ));
+        }
+        if consumed != bytes.len() {
+            return Err(AbiError::InvalidEncoding("Trailing bytes".to_string()));
+        }
+        Ok(buffer)
+    }
+
+    fn parse_prefix(bytes: &[u8], limits: &Limits) -> Result<(Self, usize, u16), AbiError> {
         if bytes.len() > limits.max_buffer_size {
             return Err(AbiError::InvalidEncoding("Buffer too large".to_string()));
         }
@@ -222,7 +297,7 @@ impl GraphBuffer {
             return Err(AbiError::InvalidEncoding("Unsupported version".to_string()));
         }
 
-        let _flags = cursor.read_u16()?;
+        let flags = cursor.read_u16()?;
         let node_count = cursor.read_u32()? as usize;
         if node_count > limits.max_node_count {
             return Err(AbiError::InvalidEncoding(
@@ -250,7 +325,7 @@ impl GraphBuffer {
             ));
         }
 
-        Ok((Self { nodes, root }, cursor.pos))
+        Ok((Self { nodes, root }, cursor.pos, flags))
     }
 
     pub fn validate_basic(&self) -> Result<(), AbiError> {
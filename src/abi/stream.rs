@@ -0,0 +1,282 @@
+//! Exact-size encoding straight into a caller-provided buffer.
+//!
+//! [`encode`](super::encode) builds an owned [`Node`](super::Node) per value and
+//! then concatenates them into a fresh `Vec<u8>`, which the runtime copies into
+//! linear memory — a large result is held twice on the host. Here the same
+//! bytes are produced in two passes over the `Value` instead: [`encoded_len`]
+//! walks it once to size the buffer, and [`encode_into`] writes headers and
+//! payloads directly into a slice (typically a `__pack_alloc`'d region of
+//! guest memory). Strings and primitive arrays are copied once, from the
+//! `Value` to their final location.
+
+use super::{encode_array_element, encode_value_type, fixed_width, AbiError, NodeKind};
+use super::{MAGIC, VERSION};
+use crate::abi::{Value, ValueType};
+
+/// Buffer header: magic, version, flags, node count, root.
+const HEADER_LEN: usize = 16;
+
+/// The exact number of bytes [`encode`](super::encode) would produce for
+/// `value`, computed without building the buffer.
+pub fn encoded_len(value: &Value) -> Result<usize, AbiError> {
+    let mut writer = Writer::counting();
+    writer.value(value)?;
+    Ok(HEADER_LEN + writer.pos)
+}
+
+/// Encode `value` into the front of `out`, returning the number of bytes
+/// written. The bytes are identical to [`encode`](super::encode)'s.
+///
+/// `out` must be at least [`encoded_len`] bytes; a shorter buffer is reported
+/// as [`AbiError::BufferTooSmall`] (with whatever was written left in place).
+pub fn encode_into(value: &Value, out: &mut [u8]) -> Result<usize, AbiError> {
+    if out.len() < HEADER_LEN {
+        return Err(AbiError::BufferTooSmall {
+            need: HEADER_LEN,
+            have: out.len(),
+        });
+    }
+    let (header, body) = out.split_at_mut(HEADER_LEN);
+    let mut writer = Writer::new(body);
+    let root = writer.value(value)?;
+
+    header[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+    header[4..6].copy_from_slice(&VERSION.to_le_bytes());
+    header[6..8].copy_from_slice(&0u16.to_le_bytes());
+    header[8..12].copy_from_slice(&writer.nodes.to_le_bytes());
+    header[12..16].copy_from_slice(&root.to_le_bytes());
+    Ok(HEADER_LEN + writer.pos)
+}
+
+/// Emits nodes in the same post-order as the `Encoder` (children before their
+/// parent), either into a slice or — with no slice — only counting bytes.
+struct Writer<'a> {
+    out: Option<&'a mut [u8]>,
+    pos: usize,
+    nodes: u32,
+    /// Reused for type tags, which are tiny.
+    scratch: Vec<u8>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(out: &'a mut [u8]) -> Self {
+        Self {
+            out: Some(out),
+            pos: 0,
+            nodes: 0,
+            scratch: Vec::new(),
+        }
+    }
+
+    fn counting() -> Self {
+        Self {
+            out: None,
+            pos: 0,
+            nodes: 0,
+            scratch: Vec::new(),
+        }
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> Result<(), AbiError> {
+        if let Some(out) = self.out.as_deref_mut() {
+            let end = self.pos + bytes.len();
+            if end > out.len() {
+                return Err(AbiError::BufferTooSmall {
+                    need: HEADER_LEN + end,
+                    have: HEADER_LEN + out.len(),
+                });
+            }
+            out[self.pos..end].copy_from_slice(bytes);
+        }
+        self.pos += bytes.len();
+        Ok(())
+    }
+
+    fn put_u32(&mut self, value: u32) -> Result<(), AbiError> {
+        self.put(&value.to_le_bytes())
+    }
+
+    fn put_str(&mut self, s: &str) -> Result<(), AbiError> {
+        self.put_u32(s.len() as u32)?;
+        self.put(s.as_bytes())
+    }
+
+    /// Write a node header and return the new node's index.
+    fn begin_node(&mut self, kind: NodeKind, payload_len: usize) -> Result<u32, AbiError> {
+        self.put(&[kind as u8, 0, 0, 0])?;
+        self.put_u32(payload_len as u32)?;
+        let index = self.nodes;
+        self.nodes += 1;
+        Ok(index)
+    }
+
+    /// The type-tag bytes for `ty`, built in the reused scratch buffer (hand it
+    /// back with [`Writer::put_tags`]).
+    fn type_tags(&mut self, ty: &ValueType) -> Vec<u8> {
+        let mut tags = std::mem::take(&mut self.scratch);
+        tags.clear();
+        encode_value_type(ty, &mut tags);
+        tags
+    }
+
+    fn put_tags(&mut self, tags: Vec<u8>) -> Result<(), AbiError> {
+        let result = self.put(&tags);
+        self.scratch = tags;
+        result
+    }
+
+    fn scalar(&mut self, kind: NodeKind, bytes: &[u8]) -> Result<u32, AbiError> {
+        let index = self.begin_node(kind, bytes.len())?;
+        self.put(bytes)?;
+        Ok(index)
+    }
+
+    fn children(&mut self, items: &[Value]) -> Result<Vec<u32>, AbiError> {
+        items.iter().map(|item| self.value(item)).collect()
+    }
+
+    fn put_indices(&mut self, indices: &[u32]) -> Result<(), AbiError> {
+        for index in indices {
+            self.put_u32(*index)?;
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, value: &Value) -> Result<u32, AbiError> {
+        match value {
+            Value::Bool(v) => self.scalar(NodeKind::Bool, &[u8::from(*v)]),
+            Value::U8(v) => self.scalar(NodeKind::U8, &[*v]),
+            Value::U16(v) => self.scalar(NodeKind::U16, &v.to_le_bytes()),
+            Value::U32(v) => self.scalar(NodeKind::U32, &v.to_le_bytes()),
+            Value::U64(v) => self.scalar(NodeKind::U64, &v.to_le_bytes()),
+            Value::S8(v) => self.scalar(NodeKind::S8, &v.to_le_bytes()),
+            Value::S16(v) => self.scalar(NodeKind::S16, &v.to_le_bytes()),
+            Value::S32(v) => self.scalar(NodeKind::S32, &v.to_le_bytes()),
+            Value::S64(v) => self.scalar(NodeKind::S64, &v.to_le_bytes()),
+            Value::F32(v) => self.scalar(NodeKind::F32, &v.to_le_bytes()),
+            Value::F64(v) => self.scalar(NodeKind::F64, &v.to_le_bytes()),
+            Value::Char(v) => self.scalar(NodeKind::Char, &(*v as u32).to_le_bytes()),
+            Value::Flags(v) => self.scalar(NodeKind::Flags, &v.to_le_bytes()),
+            Value::String(s) => {
+                let index = self.begin_node(NodeKind::String, 4 + s.len())?;
+                self.put_str(s)?;
+                Ok(index)
+            }
+            Value::List { elem_type, items }
+                if fixed_width(elem_type).is_some()
+                    && items.iter().all(|v| v.infer_type() == *elem_type) =>
+            {
+                // Array: [elem_type:u8, count:u32, data:u8*]
+                let width = fixed_width(elem_type).unwrap();
+                let tags = self.type_tags(elem_type);
+                let index =
+                    self.begin_node(NodeKind::Array, tags.len() + 4 + items.len() * width)?;
+                self.put_tags(tags)?;
+                self.put_u32(items.len() as u32)?;
+                let mut element = Vec::with_capacity(8);
+                for item in items {
+                    element.clear();
+                    encode_array_element(item, elem_type, &mut element)?;
+                    self.put(&element)?;
+                }
+                Ok(index)
+            }
+            Value::List { elem_type, items } => {
+                if fixed_width(elem_type).is_some() {
+                    return Err(AbiError::InvalidEncoding(
+                        "List with primitive elem_type contains non-matching items; \
+                         fix the elem_type or the items"
+                            .to_string(),
+                    ));
+                }
+                let children = self.children(items)?;
+                // v2 format: [elem_type:type_tag*, count:u32, child_indices:u32*]
+                let tags = self.type_tags(elem_type);
+                let index = self.begin_node(NodeKind::List, tags.len() + 4 + 4 * children.len())?;
+                self.put_tags(tags)?;
+                self.put_u32(children.len() as u32)?;
+                self.put_indices(&children)?;
+                Ok(index)
+            }
+            Value::Tuple(items) => {
+                let children = self.children(items)?;
+                let index = self.begin_node(NodeKind::Tuple, 4 + 4 * children.len())?;
+                self.put_u32(children.len() as u32)?;
+                self.put_indices(&children)?;
+                Ok(index)
+            }
+            Value::Option { inner_type, value } => {
+                // v2 format: [inner_type:type_tag*, presence:u8, child_index?:u32]
+                let child = match value {
+                    Some(inner) => Some(self.value(inner)?),
+                    None => None,
+                };
+                let tags = self.type_tags(inner_type);
+                let len = tags.len() + 1 + if child.is_some() { 4 } else { 0 };
+                let index = self.begin_node(NodeKind::Option, len)?;
+                self.put_tags(tags)?;
+                match child {
+                    Some(child) => {
+                        self.put(&[1])?;
+                        self.put_u32(child)?;
+                    }
+                    None => self.put(&[0])?,
+                }
+                Ok(index)
+            }
+            Value::Result {
+                ok_type,
+                err_type,
+                value,
+            } => {
+                // v2 format: [ok_type:type_tag*, err_type:type_tag*, tag:u32, has_payload:u8, child_index?:u32]
+                let (tag, child) = match value {
+                    Ok(inner) => (0u32, self.value(inner)?),
+                    Err(inner) => (1u32, self.value(inner)?),
+                };
+                let mut tags = self.type_tags(ok_type);
+                encode_value_type(err_type, &mut tags);
+                let index = self.begin_node(NodeKind::Result, tags.len() + 4 + 1 + 4)?;
+                self.put_tags(tags)?;
+                self.put_u32(tag)?;
+                self.put(&[1])?;
+                self.put_u32(child)?;
+                Ok(index)
+            }
+            Value::Record { type_name, fields } => {
+                let mut children = Vec::with_capacity(fields.len());
+                for (_, value) in fields {
+                    children.push(self.value(value)?);
+                }
+                // v2 format: [type_name_len:u32, type_name:utf8, field_count:u32, field_names:(len:u32, name:utf8)*, child_indices:u32*]
+                let names: usize = fields.iter().map(|(name, _)| 4 + name.len()).sum();
+                let len = 4 + type_name.len() + 4 + names + 4 * children.len();
+                let index = self.begin_node(NodeKind::Record, len)?;
+                self.put_str(type_name)?;
+                self.put_u32(fields.len() as u32)?;
+                for (name, _) in fields {
+                    self.put_str(name)?;
+                }
+                self.put_indices(&children)?;
+                Ok(index)
+            }
+            Value::Variant {
+                type_name,
+                case_name,
+                tag,
+                payload,
+            } => {
+                let children = self.children(payload)?;
+                // v2 format: [type_name_len:u32, type_name:utf8, case_name_len:u32, case_name:utf8, tag:u32, payload_count:u32, child_indices:u32*]
+                let len = 4 + type_name.len() + 4 + case_name.len() + 4 + 4 + 4 * children.len();
+                let index = self.begin_node(NodeKind::Variant, len)?;
+                self.put_str(type_name)?;
+                self.put_str(case_name)?;
+                self.put_u32(*tag as u32)?;
+                self.put_u32(children.len() as u32)?;
+                self.put_indices(&children)?;
+                Ok(index)
+            }
+        }
+    }
+}
@@ -0,0 +1,13 @@
+//! Pact language server - serves the Language Server Protocol over stdio
+//!
+//! Point an editor's LSP client at `pact-lsp` for a directory of .pact files;
+//! see `packr::lsp` for what it offers.
+
+fn main() {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = packr::lsp::serve(stdin.lock(), stdout.lock()) {
+        eprintln!("pact-lsp: {e}");
+        std::process::exit(1);
+    }
+}
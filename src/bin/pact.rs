@@ -4,13 +4,14 @@
 //!   pact check <file.pact>    - Parse and validate a pact file
 //!   pact check-dir <dir>      - Parse all pact files and validate cross-file references
 //!   pact codegen <file>       - Generate Rust code from a pact file or directory
+//!   pact fmt [--check] <file> - Format pact files (or directories) in place, or check them
+//!   pact diff <old> <new>     - Classify the changes between two versions of an interface
 
 use clap::{Parser, Subcommand};
-use packr::{
-    codegen, parse_pact_dir_with_registry, parse_pact_file, PactInterface, Type, TypeDef,
-    TypeRegistry,
-};
-use std::path::PathBuf;
+use packr::evolution::classify_evolution;
+use packr::parser::{format_pact, parse_pact_dir_in, parse_pact_file_in, Diagnostic, SourceMap};
+use packr::{codegen, PactFileError, PactInterface, PactUse, ResolvedUse, TypeDef, TypeRegistry};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "pact")]
@@ -43,6 +44,27 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
+
+    /// Format pact files in place
+    Fmt {
+        /// .pact files, or directories to format recursively
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Change nothing; fail if any file is not formatted
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Compare two versions of an interface, printing the changes as JSON;
+    /// fails on a breaking change without a major @version bump
+    Diff {
+        /// The old version's .pact file
+        old: PathBuf,
+
+        /// The new version's .pact file
+        new: PathBuf,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
@@ -52,70 +74,98 @@ fn main() -> anyhow::Result<()> {
         Commands::Check { file } => check_command(&file),
         Commands::CheckDir { dir } => check_dir_command(&dir),
         Commands::Codegen { path, output } => codegen_command(&path, output.as_deref()),
+        Commands::Fmt { paths, check } => fmt_command(&paths, check),
+        Commands::Diff { old, new } => diff_command(&old, &new),
     }
 }
 
 fn check_command(file: &PathBuf) -> anyhow::Result<()> {
-    let interface = parse_pact_file(file).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let mut sources = SourceMap::new();
+    let interface =
+        parse_pact_file_in(&mut sources, file).map_err(|e| parse_failed(&sources, e))?;
+    report(&sources, &interface.check_types(None))?;
 
     print_interface_summary(&interface, 0);
     println!("\n✓ {} parsed successfully", file.display());
     Ok(())
 }
 
+/// Print a parse error against its source, with the offending line marked.
+fn parse_failed(sources: &SourceMap, error: PactFileError) -> anyhow::Error {
+    match error {
+        PactFileError::Parse(path, error) => {
+            eprint!("{}", Diagnostic::from(&error).render(sources));
+            anyhow::anyhow!("could not parse {}", path.display())
+        }
+        other => anyhow::anyhow!("{}", other),
+    }
+}
+
+/// Print `diagnostics` against their sources; an error if there are any.
+fn report(sources: &SourceMap, diagnostics: &[Diagnostic]) -> anyhow::Result<()> {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(sources));
+    }
+    match diagnostics.len() {
+        0 => Ok(()),
+        n => Err(anyhow::anyhow!("{} error(s)", n)),
+    }
+}
+
 fn check_dir_command(dir: &PathBuf) -> anyhow::Result<()> {
-    let (root, registry) =
-        parse_pact_dir_with_registry(dir).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let mut sources = SourceMap::new();
+    let root = parse_pact_dir_in(&mut sources, dir).map_err(|e| parse_failed(&sources, e))?;
+    let registry = TypeRegistry::from_interface(&root);
 
     println!("Parsed {} interfaces:", root.children.len());
     for child in &root.children {
         print_interface_summary(child, 1);
     }
 
-    // Validate all use declarations
+    // Validate all use declarations and type references
     println!("\nValidating cross-file references...");
     let mut errors = Vec::new();
 
     for child in &root.children {
         validate_uses(child, &registry, &mut errors);
+        errors.extend(child.check_types(Some(&registry)));
     }
 
     if errors.is_empty() {
         println!("✓ All references valid");
     } else {
-        println!("\nErrors:");
-        for error in &errors {
-            println!("  ✗ {}", error);
-        }
-        return Err(anyhow::anyhow!("{} validation error(s)", errors.len()));
+        println!();
+        report(&sources, &errors)?;
     }
 
     Ok(())
 }
 
-fn validate_uses(interface: &PactInterface, registry: &TypeRegistry, errors: &mut Vec<String>) {
+fn validate_uses(interface: &PactInterface, registry: &TypeRegistry, errors: &mut Vec<Diagnostic>) {
     for use_decl in &interface.uses {
-        match registry.resolve_use(use_decl) {
-            Ok(types) => {
+        match registry.resolve_use_with_transforms(use_decl, registry.transforms()) {
+            Ok(resolved) => {
                 if use_decl.items.is_empty() {
+                    let count = match resolved {
+                        ResolvedUse::Types(types) => types.len(),
+                        ResolvedUse::TransformedInterface(iface) => iface.types.len(),
+                    };
                     println!(
                         "  ✓ {}: use {} (all {} types)",
                         interface.name,
-                        use_decl.interface,
-                        types.len()
+                        use_source(use_decl),
+                        count
                     );
                 } else {
                     println!(
                         "  ✓ {}: use {}.{{{}}}",
                         interface.name,
-                        use_decl.interface,
+                        use_source(use_decl),
                         use_decl.items.join(", ")
                     );
                 }
             }
-            Err(e) => {
-                errors.push(format!("{}: {}", interface.name, e));
-            }
+            Err(_) => errors.extend(registry.diagnose_use(use_decl)),
         }
     }
 
@@ -125,14 +175,27 @@ fn validate_uses(interface: &PactInterface, registry: &TypeRegistry, errors: &mu
     }
 }
 
+/// What a `use` names: `types`, or `traced(rpc(calculator))`.
+fn use_source(use_decl: &PactUse) -> String {
+    if use_decl.transform_args.is_empty() {
+        use_decl.interface.clone()
+    } else {
+        format!(
+            "{}({})",
+            use_decl.interface,
+            use_decl.transform_args.join(", ")
+        )
+    }
+}
+
 fn codegen_command(path: &PathBuf, output: Option<&std::path::Path>) -> anyhow::Result<()> {
+    let mut sources = SourceMap::new();
     let interface = if path.is_dir() {
-        let (root, _registry) =
-            parse_pact_dir_with_registry(path).map_err(|e| anyhow::anyhow!("{}", e))?;
-        root
+        parse_pact_dir_in(&mut sources, path)
     } else {
-        parse_pact_file(path).map_err(|e| anyhow::anyhow!("{}", e))?
-    };
+        parse_pact_file_in(&mut sources, path)
+    }
+    .map_err(|e| parse_failed(&sources, e))?;
 
     let code = codegen::generate_rust(&interface);
 
@@ -149,6 +212,78 @@ fn codegen_command(path: &PathBuf, output: Option<&std::path::Path>) -> anyhow::
     Ok(())
 }
 
+fn fmt_command(paths: &[PathBuf], check: bool) -> anyhow::Result<()> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_pact_files(path, &mut files)?;
+    }
+
+    let mut unformatted = 0;
+    for file in &files {
+        let mut sources = SourceMap::new();
+        let id = sources.load(file)?;
+        let src = &sources.get(id).expect("just loaded").src;
+        let formatted = match format_pact(src) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprint!("{}", Diagnostic::from(&e).render(&sources));
+                anyhow::bail!("could not format {}", file.display());
+            }
+        };
+        if formatted == *src {
+            continue;
+        }
+        unformatted += 1;
+        if check {
+            println!("would reformat {}", file.display());
+        } else {
+            std::fs::write(file, formatted)?;
+            println!("formatted {}", file.display());
+        }
+    }
+
+    if check && unformatted > 0 {
+        anyhow::bail!("{} file(s) would be reformatted", unformatted);
+    }
+    Ok(())
+}
+
+/// `path` if it is a file, or every .pact file under it if it is a directory.
+fn collect_pact_files(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|e| e == "pact") {
+            collect_pact_files(&entry, files)?;
+        }
+    }
+    Ok(())
+}
+
+fn diff_command(old: &Path, new: &Path) -> anyhow::Result<()> {
+    let mut sources = SourceMap::new();
+    let old = parse_pact_file_in(&mut sources, old).map_err(|e| parse_failed(&sources, e))?;
+    let new = parse_pact_file_in(&mut sources, new).map_err(|e| parse_failed(&sources, e))?;
+
+    let diff = classify_evolution(&old, &new);
+    println!("{}", serde_json::to_string_pretty(&diff)?);
+
+    if !diff.is_versioned() {
+        anyhow::bail!(
+            "breaking change without a major @version bump ({} -> {})",
+            diff.old_version.as_deref().unwrap_or("none"),
+            diff.new_version.as_deref().unwrap_or("none")
+        );
+    }
+    Ok(())
+}
+
 fn print_interface_summary(interface: &PactInterface, indent: usize) {
     let prefix = "  ".repeat(indent);
 
@@ -162,12 +297,12 @@ fn print_interface_summary(interface: &PactInterface, indent: usize) {
     // Print uses
     for use_decl in &interface.uses {
         if use_decl.items.is_empty() {
-            println!("{}  use {}", prefix, use_decl.interface);
+            println!("{}  use {}", prefix, use_source(use_decl));
         } else {
             println!(
                 "{}  use {}.{{{}}}",
                 prefix,
-                use_decl.interface,
+                use_source(use_decl),
                 use_decl.items.join(", ")
             );
         }
@@ -219,12 +354,7 @@ fn format_typedef(typedef: &TypeDef) -> String {
             name,
             type_params,
             ty,
-        } => format!(
-            "type {}{} = {}",
-            name,
-            format_type_params(type_params),
-            format_type(ty)
-        ),
+        } => format!("type {}{} = {}", name, format_type_params(type_params), ty),
         TypeDef::Record {
             name,
             type_params,
@@ -253,42 +383,3 @@ fn format_typedef(typedef: &TypeDef) -> String {
         }
     }
 }
-
-fn format_type(ty: &Type) -> String {
-    match ty {
-        Type::Unit => "unit".to_string(),
-        Type::Bool => "bool".to_string(),
-        Type::U8 => "u8".to_string(),
-        Type::U16 => "u16".to_string(),
-        Type::U32 => "u32".to_string(),
-        Type::U64 => "u64".to_string(),
-        Type::S8 => "s8".to_string(),
-        Type::S16 => "s16".to_string(),
-        Type::S32 => "s32".to_string(),
-        Type::S64 => "s64".to_string(),
-        Type::F32 => "f32".to_string(),
-        Type::F64 => "f64".to_string(),
-        Type::Char => "char".to_string(),
-        Type::String => "string".to_string(),
-        Type::List(inner) => format!("list<{}>", format_type(inner)),
-        Type::Option(inner) => format!("option<{}>", format_type(inner)),
-        Type::Result { ok, err } => format!("result<{}, {}>", format_type(ok), format_type(err)),
-        Type::Tuple(types) => {
-            format!(
-                "tuple<{}>",
-                types.iter().map(format_type).collect::<Vec<_>>().join(", ")
-            )
-        }
-        Type::Map { key, value } => {
-            format!("map<{}, {}>", format_type(key), format_type(value))
-        }
-        Type::Set(elem) => format!("set<{}>", format_type(elem)),
-        Type::Ref(path) => path.to_string(),
-        Type::App { path, args } => format!(
-            "{}<{}>",
-            path,
-            args.iter().map(format_type).collect::<Vec<_>>().join(", ")
-        ),
-        Type::Value => "value".to_string(),
-    }
-}
@@ -14,8 +14,8 @@
 //!
 //! # How it works
 //!
-//! `wasm-merge --enable-multimemory` does the module merge: with the entry module
-//! placed FIRST it becomes memory 0 (its exports stay canonical: `memory`,
+//! A native multi-memory merge (see [`merge`]) does the module merge: with the
+//! entry module placed FIRST it becomes memory 0 (its exports stay canonical: `memory`,
 //! `__pack_alloc`, `__pack_free`, its pact functions — that is what theater
 //! loads). Each subsequent component is placed under a unique merge name and gets
 //! memories 1, 2, …; its exports are pre-renamed to a component-scoped prefix
@@ -25,13 +25,21 @@
 //! This module is the walrus post-pass that:
 //!
 //! 1. Pre-renames every non-entry component's exports to `__c_<name>_<export>`.
-//! 2. Shells out to `wasm-merge` to produce the multi-memory module (entry first).
+//! 2. Merges them into one multi-memory module (entry first), remapping every
+//!    component's function, global, table, data and memory indices.
 //! 3. For each link, builds a shim (alloc in the provider's memory → copy in →
 //!    call the provider's renamed export → copy result out into the consumer's
 //!    memory → free) and rewrites every call to the consumer's imported function
 //!    to call the shim instead, then deletes the now-dead import.
 //! 4. Removes the `__c_*` scaffolding exports so the composite presents only the
-//!    entry component's surface (`memory`, `__pack_alloc`/`__pack_free`, pacts).
+//!    entry component's surface (`memory`, `__pack_alloc`/`__pack_free`, pacts),
+//!    plus any re-exports ([`compose_with_exports`]), each behind a shim from
+//!    memory 0 into its component's memory.
+//! 5. Replaces `__pack_types` with metadata regenerated from every component:
+//!    the residual imports of all of them, the entry's exports, fresh hashes.
+//! 6. Deletes everything the composite's exports no longer reach — unlinked
+//!    provider exports, the components' old `__pack_types`, a memory only dead
+//!    code used — and measures what each component kept ([`compose_with_report`]).
 //!
 //! Each shim is pure byte-shuffling over the actor ABI
 //! (`fn(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`), copying between
@@ -47,10 +55,53 @@
 //! [`crate::metadata::find_cgrf_metadata`] + [`crate::metadata::decode_metadata_with_hashes`],
 //! no instantiation) and rejects any link whose two sides disagree — surfacing a
 //! signature drift at compose time instead of as a runtime mis-marshal. A link
-//! whose components carry no embedded hashes (or a name-remapped link) is left
-//! name-wired; the check is an added safety net, not a prerequisite for the
-//! transform.
-
+//! whose components carry no embedded hashes is left name-wired; the check is
+//! an added safety net, not a prerequisite for the transform.
+//!
+//! Differing hashes are not the end of it: [`diff_interfaces`] compares the two
+//! interfaces structurally, and a difference that values can be rewritten
+//! across (a dropped field, a missing `option` field, a renamed field, a case
+//! the reader also has) is bridged by the link adapter (`compose/adapt.rs`),
+//! merged in as a hidden component the link's calls pass through. Only a link
+//! no adapter can bridge is rejected.
+//!
+//! The same hashes drive [`resolve_links`]: an import interface no explicit
+//! link wires is connected to the one other component exporting an equal hash,
+//! so explicit links are only needed to choose between several providers.
+//! Both work per interface, so a provider may export many interfaces to many
+//! consumers; an [`InterfaceLink`] routes one under a different name.
+//!
+//! # Nesting
+//!
+//! A composite is itself a valid component: its memories are carried into the
+//! outer composite as distinct memories, its residual host imports are bridged
+//! through the outer entry's memory like any non-entry component's, and it is
+//! linked through its regenerated `__pack_types` hashes.
+//!
+//! # Shared memory
+//!
+//! [`MemoryMode::Shared`] (`memory = "shared"` in a manifest) is the level-2
+//! layout of `docs/pic-composition.md`: every component is a PIC side module,
+//! placed at its own base in ONE memory (see [`shared`]), and each link is a
+//! direct call that passes the consumer's pointers through unchanged. Each
+//! component still frees with its own allocator, so a provider's result is
+//! freed by the shim on the next call through it.
+
+mod adapt;
+mod dce;
+mod merge;
+#[cfg(feature = "wasmtime")]
+mod route;
+mod shared;
+
+pub use adapt::{diff_interfaces, FieldRename, InterfaceAdapter, LinkCompatibility};
+pub use dce::{ComponentSize, Footprint, SizeReport, GENERATED};
+#[cfg(feature = "wasmtime")]
+pub use route::{AsyncCompositionRuntime, CompositionRuntime};
+
+use crate::metadata::{
+    compute_interface_hash, decode_metadata_with_hashes, find_cgrf_metadata, MetadataWithHashes,
+};
 use anyhow::{anyhow, Context, Result};
 use std::collections::HashMap;
 use walrus::ir::{
@@ -98,6 +149,67 @@ pub struct GraphLink {
     pub export_name: String,
 }
 
+/// A whole-interface link: every function `consumer` imports from interface
+/// `import` is satisfied by the same-named function of `provider`'s export
+/// interface `export`, which may be named differently.
+///
+/// This is how one component's several export interfaces are routed to
+/// different consumers under the consumers' own names; [`interface_links`]
+/// expands it into per-function [`GraphLink`]s.
+#[derive(Debug, Clone)]
+pub struct InterfaceLink {
+    /// The name of the component that declares the import.
+    pub consumer: String,
+    /// The interface the consumer imports (e.g. `"kv"`).
+    pub import: String,
+    /// The name of the component that provides the export.
+    pub provider: String,
+    /// The provider's export interface that satisfies it (e.g. `"db"`).
+    pub export: String,
+}
+
+/// A non-entry component's export kept on the composite's surface.
+///
+/// Only the entry's exports survive composition on their own; a re-export
+/// exposes `component`'s `export_name` as the composite export `as_name`,
+/// through a shim that marshals between the caller's memory 0 and the
+/// component's memory.
+#[derive(Debug, Clone)]
+pub struct Reexport {
+    /// The name of the (non-entry) component that provides the export.
+    pub component: String,
+    /// The component's export (e.g. `"format"` or `"util.format"`).
+    pub export_name: String,
+    /// The composite's export name for it.
+    pub as_name: String,
+}
+
+/// How a composite lays out its components' memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryMode {
+    /// One memory per component; every cross-component call copies its input
+    /// and result between the two memories.
+    #[default]
+    Isolated,
+    /// One memory for all components, which must be PIC side modules; each is
+    /// placed at its own base and cross-component calls pass pointers
+    /// directly (see `docs/pic-composition.md`).
+    Shared,
+}
+
+/// Everything about a composition beyond its components and links.
+#[derive(Debug, Clone, Default)]
+pub struct ComposeOptions {
+    /// Non-entry exports kept on the composite's surface.
+    pub exports: Vec<Reexport>,
+    /// The memory layout.
+    pub memory: MemoryMode,
+    /// Record fields named differently by a link's consumer and provider,
+    /// mapped by the link adapter (see [`diff_interfaces`]).
+    pub renames: Vec<FieldRename>,
+}
+
 /// A residual host import declared by a NON-ENTRY component that must be
 /// bridged through the entry's memory (see [`compose`] and [`emit_host_shim`]).
 ///
@@ -160,109 +272,462 @@ fn scoped_name(component: &str, export: &str) -> String {
     format!("__c_{component}_{export}")
 }
 
-/// Compose N components + a link graph into one multi-memory composite wasm.
+/// Decode each component's embedded `__pack_types` surface once (`None` if the
+/// component carries no CGRF metadata or it fails to decode).
+fn component_surfaces(components: &[Component]) -> HashMap<&str, Option<MetadataWithHashes>> {
+    components
+        .iter()
+        .map(|c| (c.name.as_str(), read_component_metadata(&c.wasm)))
+        .collect()
+}
+
+/// Complete `links` with every link implied by the components' interface hashes.
 ///
-/// Exactly one component must be the entry; it becomes memory 0 and keeps its
-/// canonical exports (`memory`, `__pack_alloc`, `__pack_free`, its pact
-/// functions) — the surface theater loads. Every other component is merged with a
-/// unique memory (1, 2, …) and its exports pre-renamed `__c_<name>_<export>`.
+/// Each component's `__pack_types` lists the interfaces it imports and exports,
+/// each with a Merkle hash. An imported interface that `links` leaves (wholly or
+/// partly) unwired is matched against every OTHER component's export hashes:
 ///
-/// For each link, a bridging shim is generated that copies between the consumer
-/// component's memory and the provider component's memory (whichever two of the N
-/// they are), allocating with the provider's `__pack_alloc`, calling the
-/// provider's export, then copying the result back into the consumer's memory.
-/// Verify each link connects interfaces whose Merkle hashes agree.
+/// - exactly one component exports a matching hash (or a generic interface that
+///   reconciles with it, or a same-named interface the link adapter bridges) —
+///   each unwired function import `iface.f` is linked to that provider's
+///   `<export-iface>.f` export, or its bare `f` export;
+/// - several do — an ambiguity error naming them. An explicit link for any
+///   function of the interface pins its provider and settles the tie;
+/// - none does, but components export an interface of the same NAME with a
+///   different hash — those are the candidates instead, by the same rules. The
+///   link is made, and [`verify_link_hashes`] reports how the signatures
+///   drifted;
+/// - nobody exports it at all — it stays a residual (host) import.
 ///
-/// For a link `consumer.import(module.name) <- provider.export`, both components
-/// must declare an interface named `module` in their `__pack_types` metadata, and
-/// the consumer's IMPORT hash for it must equal the provider's EXPORT hash. Import
-/// and export hashes for the same interface are equal by construction (the guest
-/// macro hashes the same structure both ways), so a mismatch means the signatures
-/// have genuinely drifted — the shim would then marshal bytes the two sides decode
-/// differently. That is a hard error, raised at compose time instead of surfacing
-/// as a runtime "failed to convert parameter".
+/// A provider that exports the interface under several names counts once, as
+/// the export named like the import if it has one.
 ///
-/// If either side lacks an embedded hash for the interface — an older/non-packr
-/// component with no metadata, or a name-remapped link where the provider exports
-/// the interface under a different name than the consumer imports — the link is
-/// left name-wired and the check is skipped for it. Hash-checking is an additional
-/// safety net, not a prerequisite for the transform.
-fn verify_link_hashes(components: &[Component], links: &[GraphLink]) -> Result<()> {
-    use crate::metadata::{decode_metadata_with_hashes, find_cgrf_metadata, MetadataWithHashes};
-    use std::collections::HashMap;
-
-    // Decode each component's embedded interface metadata once (None if the
-    // component carries no CGRF metadata or it fails to decode).
-    let mut metas: HashMap<&str, Option<MetadataWithHashes>> = HashMap::new();
-    for c in components {
-        let meta = find_cgrf_metadata(&c.wasm)
-            .ok()
-            .flatten()
-            .and_then(|bytes| decode_metadata_with_hashes(&bytes).ok());
-        metas.insert(c.name.as_str(), meta);
-    }
-
-    for link in links {
-        // Both components must have decodable metadata to compare.
-        let (Some(Some(consumer_meta)), Some(Some(provider_meta))) = (
-            metas.get(link.consumer.as_str()),
-            metas.get(link.provider.as_str()),
-        ) else {
+/// The explicit `links` come first in the result, unchanged. Components without
+/// embedded metadata neither import nor export anything here.
+pub fn resolve_links(components: &[Component], links: &[GraphLink]) -> Result<Vec<GraphLink>> {
+    let surfaces = component_surfaces(components);
+    let mut resolved = links.to_vec();
+
+    for consumer in components {
+        let Some(Some(consumer_meta)) = surfaces.get(consumer.name.as_str()) else {
             continue;
         };
+        let func_imports = component_func_imports(&consumer.wasm)
+            .with_context(|| format!("reading imports for component `{}`", consumer.name))?;
+
+        for import in &consumer_meta.import_hashes {
+            let explicit: Vec<&GraphLink> = links
+                .iter()
+                .filter(|l| l.consumer == consumer.name && l.import_module == import.name)
+                .collect();
+            let unwired: Vec<&str> = func_imports
+                .iter()
+                .filter(|(module, field)| {
+                    module == &import.name && !explicit.iter().any(|l| &l.import_name == field)
+                })
+                .map(|(_, field)| field.as_str())
+                .collect();
+            if unwired.is_empty() {
+                continue;
+            }
 
-        let consumer_hash = consumer_meta
-            .import_hashes
-            .iter()
-            .find(|h| h.name == link.import_module);
-        let provider_hash = provider_meta
-            .export_hashes
-            .iter()
-            .find(|h| h.name == link.import_module);
-
-        if let (Some(ci), Some(pi)) = (consumer_hash, provider_hash) {
-            if ci.hash != pi.hash {
-                // Hashes differ. If one side is a generic interface, the mismatch
-                // is expected (a generic `state<s>` never hashes equal to a
-                // concrete `state<chat-state>`); try to reconcile by binding the
-                // generic parameters from the concrete side before rejecting.
-                match reconcile_generic_link(consumer_meta, provider_meta, &link.import_module) {
-                    // Reconciled: the generic side, with its parameters bound,
-                    // presents exactly the concrete interface. Accept.
-                    Some(Ok(())) => {}
-                    Some(Err(reason)) => {
-                        return Err(anyhow!(
-                            "hash-checked link rejected: consumer `{}` imports generic \
-                             interface `{}` from provider `{}`, but the parameters could \
-                             not be reconciled: {}",
-                            link.consumer,
-                            link.import_module,
-                            link.provider,
-                            reason,
-                        ));
+            // Every other component exporting this interface, by hash (or by a
+            // reconcilable generic binding), and the same-named near misses.
+            let mut candidates: Vec<(&str, &str)> = Vec::new();
+            let mut near_misses: Vec<(&str, &str)> = Vec::new();
+            for provider in components.iter().filter(|p| p.name != consumer.name) {
+                let Some(Some(provider_meta)) = surfaces.get(provider.name.as_str()) else {
+                    continue;
+                };
+                for export in &provider_meta.export_hashes {
+                    if export.hash == import.hash
+                        || (export.name == import.name
+                            && (matches!(
+                                reconcile_generic_link(consumer_meta, provider_meta, &import.name),
+                                Some(Ok(()))
+                            ) || adaptable(consumer_meta, provider_meta, &import.name)))
+                    {
+                        add_candidate(&mut candidates, &provider.name, &export.name, &import.name);
+                    } else if export.name == import.name {
+                        add_candidate(&mut near_misses, &provider.name, &export.name, &import.name);
                     }
-                    // Neither side is generic — a genuine signature drift.
-                    None => {
+                }
+            }
+            let candidates = if candidates.is_empty() {
+                near_misses
+            } else {
+                candidates
+            };
+
+            let (provider, export_iface) = if let Some(pin) = explicit.first() {
+                let iface = candidates
+                    .iter()
+                    .find(|(p, _)| *p == pin.provider)
+                    .map_or(import.name.as_str(), |(_, iface)| *iface);
+                (pin.provider.as_str(), iface)
+            } else {
+                match candidates.as_slice() {
+                    [one] => *one,
+                    [] => continue,
+                    many => {
+                        let listed: Vec<String> = many
+                            .iter()
+                            .map(|(p, iface)| format!("`{p}` (as `{iface}`)"))
+                            .collect();
                         return Err(anyhow!(
-                            "hash-checked link rejected: consumer `{}` imports interface `{}` \
-                             (hash {}), but provider `{}` exports it with a different hash ({}). \
-                             The interface signatures disagree — rebuild both against the same \
-                             pact, or fix the link.",
-                            link.consumer,
-                            link.import_module,
-                            ci.hash,
-                            link.provider,
-                            pi.hash,
+                            "ambiguous import: component `{}` imports interface `{}` \
+                             (hash {}), which is exported by {}; add a link for one of \
+                             its functions to choose the provider",
+                            consumer.name,
+                            import.name,
+                            import.hash,
+                            listed.join(", "),
                         ));
                     }
                 }
+            };
+
+            let provider_module = component_module(components, provider)?;
+            let provider_meta = surfaces.get(provider).and_then(Option::as_ref);
+            for field in unwired {
+                let export_name =
+                    provider_export(&provider_module, provider_meta, export_iface, field)
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "component `{}` imports `{}.{field}`, but provider \
+                                 `{provider}` does not export `{export_iface}.{field}`",
+                                consumer.name,
+                                import.name,
+                            )
+                        })?;
+                resolved.push(GraphLink {
+                    consumer: consumer.name.clone(),
+                    import_module: import.name.clone(),
+                    import_name: field.to_string(),
+                    provider: provider.to_string(),
+                    export_name,
+                });
             }
         }
-        // Either side missing the interface hash => can't verify; leave the link
-        // name-wired (the transform still produces a working composite).
     }
 
-    Ok(())
+    Ok(resolved)
+}
+
+/// Add `provider`'s export interface `export` to `candidates`, once per
+/// provider: a later export replaces an earlier one only if it is named like
+/// the `import`.
+fn add_candidate<'a>(
+    candidates: &mut Vec<(&'a str, &'a str)>,
+    provider: &'a str,
+    export: &'a str,
+    import: &str,
+) {
+    match candidates.iter_mut().find(|(p, _)| *p == provider) {
+        Some(entry) if export == import => entry.1 = export,
+        Some(_) => {}
+        None => candidates.push((provider, export)),
+    }
+}
+
+/// Expand whole-interface links into one [`GraphLink`] per function the
+/// consumer imports from the linked interface.
+///
+/// Each import `import.f` is linked to the provider's `export.f`. A consumer
+/// that imports nothing from `import`, or a provider missing one of its
+/// functions, is an error. The links are hash-checked like any other when
+/// composed, with the provider's interface compared under the consumer's name.
+pub fn interface_links(
+    components: &[Component],
+    links: &[InterfaceLink],
+) -> Result<Vec<GraphLink>> {
+    let surfaces = component_surfaces(components);
+    let mut expanded = Vec::new();
+    for link in links {
+        let consumer = components
+            .iter()
+            .find(|c| c.name == link.consumer)
+            .ok_or_else(|| anyhow!("link references unknown consumer `{}`", link.consumer))?;
+        let provider_module = component_module(components, &link.provider)?;
+        let provider_meta = surfaces
+            .get(link.provider.as_str())
+            .and_then(Option::as_ref);
+
+        let fields: Vec<String> = component_func_imports(&consumer.wasm)
+            .with_context(|| format!("reading imports for component `{}`", consumer.name))?
+            .into_iter()
+            .filter(|(module, _)| *module == link.import)
+            .map(|(_, field)| field)
+            .collect();
+        if fields.is_empty() {
+            return Err(anyhow!(
+                "component `{}` imports nothing from interface `{}`",
+                link.consumer,
+                link.import
+            ));
+        }
+        for field in fields {
+            let export_name =
+                provider_export(&provider_module, provider_meta, &link.export, &field).ok_or_else(
+                    || {
+                        anyhow!(
+                            "component `{}` imports `{}.{field}`, but provider `{}` does not \
+                             export `{}.{field}`",
+                            link.consumer,
+                            link.import,
+                            link.provider,
+                            link.export,
+                        )
+                    },
+                )?;
+            expanded.push(GraphLink {
+                consumer: link.consumer.clone(),
+                import_module: link.import.clone(),
+                import_name: field,
+                provider: link.provider.clone(),
+                export_name,
+            });
+        }
+    }
+    Ok(expanded)
+}
+
+/// Parse the named component's module.
+fn component_module(components: &[Component], name: &str) -> Result<Module> {
+    let component = components
+        .iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| anyhow!("link references unknown provider `{name}`"))?;
+    Module::from_buffer(&component.wasm).with_context(|| format!("parsing component `{name}`"))
+}
+
+/// The provider's export implementing function `field` of its export interface
+/// `iface`: `iface.field` if exported under that name, else a bare `field`.
+///
+/// A bare export carries no interface, so it is only taken when no OTHER
+/// export interface of the provider declares a `field` too; otherwise a
+/// multi-interface provider could answer one interface with another's function.
+fn provider_export(
+    module: &Module,
+    meta: Option<&MetadataWithHashes>,
+    iface: &str,
+    field: &str,
+) -> Option<String> {
+    let qualified = format!("{iface}.{field}");
+    if export_func(module, &qualified).is_some() {
+        return Some(qualified);
+    }
+    export_func(module, field)?;
+    let declared_elsewhere = meta.is_some_and(|meta| {
+        meta.export_hashes
+            .iter()
+            .filter(|h| h.name != iface)
+            .filter_map(|h| interface_arena(&meta.arena, "exports", &h.name))
+            .any(|arena| arena.functions.iter().any(|f| f.name == field))
+    });
+    (!declared_elsewhere).then(|| field.to_string())
+}
+
+/// The provider export interface a link draws from: the interface qualifying
+/// its export name (`db.get` -> `db`) if the provider declares one by that
+/// name, else the consumer's import interface (a bare export).
+fn link_export_interface<'a>(link: &'a GraphLink, provider_meta: &MetadataWithHashes) -> &'a str {
+    link.export_name
+        .rsplit_once('.')
+        .map(|(iface, _)| iface)
+        .filter(|iface| provider_meta.export_hashes.iter().any(|h| h.name == *iface))
+        .unwrap_or(&link.import_module)
+}
+
+/// Verify each link connects interfaces whose Merkle hashes agree, or that the
+/// link adapter can bridge, and return each link's adapter (`None` for a link
+/// whose values cross unchanged).
+///
+/// For a link `consumer.import(module.name) <- provider.export`, both components
+/// must declare an interface named `module` in their `__pack_types` metadata, and
+/// the consumer's IMPORT hash for it should equal the provider's EXPORT hash. Import
+/// and export hashes for the same interface are equal by construction (the guest
+/// macro hashes the same structure both ways), so a mismatch means the signatures
+/// have drifted. The two interfaces are then diffed ([`diff_interfaces`], with
+/// `renames`): a compatible drift — a field added or renamed, a case added — is
+/// bridged by an adapter rewriting the values in flight. Any other is a hard
+/// error, raised at compose time instead of surfacing as a runtime "failed to
+/// convert parameter".
+///
+/// The check is per interface: a provider exporting several interfaces is
+/// checked against the one the link's export name belongs to (`db.get` -> `db`).
+/// When that interface is named differently from the consumer's import (an
+/// [`InterfaceLink`] routing `db` to a `kv` import), the provider's interface is
+/// rehashed under the consumer's name, so only the names may differ.
+///
+/// If either side lacks an embedded hash for the interface — an older/non-packr
+/// component with no metadata, or a bare export name the provider declares under
+/// no interface of the consumer's name — the link is left name-wired and the
+/// check is skipped for it. Hash-checking is an additional safety net, not a
+/// prerequisite for the transform.
+fn verify_link_hashes(
+    components: &[Component],
+    links: &[GraphLink],
+    renames: &[FieldRename],
+) -> Result<Vec<Option<adapt::FunctionAdapter>>> {
+    let metas = component_surfaces(components);
+    links
+        .iter()
+        .map(|link| verify_link_hash(link, &metas, renames))
+        .collect()
+}
+
+/// [`verify_link_hashes`] for one link.
+fn verify_link_hash(
+    link: &GraphLink,
+    metas: &HashMap<&str, Option<MetadataWithHashes>>,
+    renames: &[FieldRename],
+) -> Result<Option<adapt::FunctionAdapter>> {
+    // Both components must have decodable metadata to compare.
+    let (Some(Some(consumer_meta)), Some(Some(provider_meta))) = (
+        metas.get(link.consumer.as_str()),
+        metas.get(link.provider.as_str()),
+    ) else {
+        return Ok(None);
+    };
+
+    let export_iface = link_export_interface(link, provider_meta);
+    if export_iface != link.import_module {
+        return verify_renamed_link(link, export_iface, consumer_meta, provider_meta, renames);
+    }
+
+    let consumer_hash = consumer_meta
+        .import_hashes
+        .iter()
+        .find(|h| h.name == link.import_module);
+    let provider_hash = provider_meta
+        .export_hashes
+        .iter()
+        .find(|h| h.name == link.import_module);
+
+    // Either side missing the interface hash => can't verify; leave the link
+    // name-wired (the transform still produces a working composite).
+    let (Some(ci), Some(pi)) = (consumer_hash, provider_hash) else {
+        return Ok(None);
+    };
+    if ci.hash == pi.hash {
+        return Ok(None);
+    }
+
+    // Hashes differ. If one side is a generic interface, the mismatch is
+    // expected (a generic `state<s>` never hashes equal to a concrete
+    // `state<chat-state>`); try to reconcile by binding the generic parameters
+    // from the concrete side before rejecting.
+    match reconcile_generic_link(consumer_meta, provider_meta, &link.import_module) {
+        // Reconciled: the generic side, with its parameters bound, presents
+        // exactly the concrete interface. Accept.
+        Some(Ok(())) => Ok(None),
+        Some(Err(reason)) => Err(anyhow!(
+            "hash-checked link rejected: consumer `{}` imports generic interface `{}` from \
+             provider `{}`, but the parameters could not be reconciled: {}",
+            link.consumer,
+            link.import_module,
+            link.provider,
+            reason,
+        )),
+        // Neither side is generic — a drift, which an adapter may bridge.
+        None => adapt_link(link, export_iface, consumer_meta, provider_meta, renames).map_err(
+            |reason| {
+                anyhow!(
+                    "hash-checked link rejected: consumer `{}` imports interface `{}` (hash {}), \
+                     but provider `{}` exports it with a different hash ({}), and no adapter \
+                     can bridge the two: {reason}. Rebuild both against the same pact, or fix \
+                     the link.",
+                    link.consumer,
+                    link.import_module,
+                    ci.hash,
+                    link.provider,
+                    pi.hash,
+                )
+            },
+        ),
+    }
+}
+
+/// Hash-check a link from the consumer's import interface to a provider export
+/// interface of another name: the two must hash equal once the provider's is
+/// renamed to the consumer's, or be bridged by an adapter.
+fn verify_renamed_link(
+    link: &GraphLink,
+    export_iface: &str,
+    consumer_meta: &MetadataWithHashes,
+    provider_meta: &MetadataWithHashes,
+    renames: &[FieldRename],
+) -> Result<Option<adapt::FunctionAdapter>> {
+    let (Some(imported), Some(exported)) = (
+        interface_arena(&consumer_meta.arena, "imports", &link.import_module),
+        interface_arena(&provider_meta.arena, "exports", export_iface),
+    ) else {
+        return Ok(None);
+    };
+    let mut renamed = exported.clone();
+    renamed.name = imported.name.clone();
+    let (import_hash, export_hash) = (
+        compute_interface_hash(imported),
+        compute_interface_hash(&renamed),
+    );
+    if import_hash == export_hash {
+        return Ok(None);
+    }
+    adapt_link(link, export_iface, consumer_meta, provider_meta, renames).map_err(|reason| {
+        anyhow!(
+            "hash-checked link rejected: consumer `{}` imports interface `{}` (hash {}), but \
+             provider `{}`'s export interface `{export_iface}` hashes to {} under that name, \
+             and no adapter can bridge the two: {reason}. Link another interface, or rebuild \
+             both against the same pact.",
+            link.consumer,
+            link.import_module,
+            import_hash,
+            link.provider,
+            export_hash,
+        )
+    })
+}
+
+/// Diff a drifted link's two interfaces: the link's adapter if they are
+/// compatible (`None` if its function needs none), else why they are not.
+fn adapt_link(
+    link: &GraphLink,
+    export_iface: &str,
+    consumer_meta: &MetadataWithHashes,
+    provider_meta: &MetadataWithHashes,
+    renames: &[FieldRename],
+) -> Result<Option<adapt::FunctionAdapter>, String> {
+    let (Some(imported), Some(exported)) = (
+        interface_arena(&consumer_meta.arena, "imports", &link.import_module),
+        interface_arena(&provider_meta.arena, "exports", export_iface),
+    ) else {
+        return Err("the interface's structure is missing from the metadata".to_string());
+    };
+    match diff_interfaces(imported, exported, renames) {
+        LinkCompatibility::Identical => Ok(None),
+        LinkCompatibility::Compatible(adapter) => Ok(adapter.function(&link.import_name).cloned()),
+        LinkCompatibility::Incompatible(reason) => Err(reason),
+    }
+}
+
+/// Whether the link adapter can bridge the consumer's import interface `iface`
+/// and the provider's same-named export interface, without field renames.
+fn adaptable(
+    consumer_meta: &MetadataWithHashes,
+    provider_meta: &MetadataWithHashes,
+    iface: &str,
+) -> bool {
+    match (
+        interface_arena(&consumer_meta.arena, "imports", iface),
+        interface_arena(&provider_meta.arena, "exports", iface),
+    ) {
+        (Some(imported), Some(exported)) => !matches!(
+            diff_interfaces(imported, exported, &[]),
+            LinkCompatibility::Incompatible(_)
+        ),
+        _ => false,
+    }
 }
 
 /// Find the arena describing interface `iface` within `section` ("imports" or
@@ -300,8 +765,8 @@ fn interface_arena<'a>(
 /// concrete`, so the two interfaces hash identically once bound — no host-side
 /// re-hash is required, and the structural copy shim marshals correctly.
 fn reconcile_generic_link(
-    consumer_meta: &crate::metadata::MetadataWithHashes,
-    provider_meta: &crate::metadata::MetadataWithHashes,
+    consumer_meta: &MetadataWithHashes,
+    provider_meta: &MetadataWithHashes,
     iface: &str,
 ) -> Option<Result<(), String>> {
     use crate::types::Type;
@@ -400,41 +865,114 @@ fn reconcile_generic_link(
     Some(Ok(()))
 }
 
+/// Compose N components + a link graph into one multi-memory composite wasm.
+///
+/// Exactly one component must be the entry; it becomes memory 0 and keeps its
+/// canonical exports (`memory`, `__pack_alloc`, `__pack_free`, its pact
+/// functions) — the surface theater loads. Every other component is merged with a
+/// unique memory (1, 2, …) and its exports pre-renamed `__c_<name>_<export>`.
+///
+/// For each link, a bridging shim is generated that copies between the consumer
+/// component's memory and the provider component's memory (whichever two of the N
+/// they are), allocating with the provider's `__pack_alloc`, calling the
+/// provider's export, then copying the result back into the consumer's memory.
+///
+/// `links` need only list what the interface hashes cannot decide: every other
+/// import is wired by [`resolve_links`] first.
 pub fn compose(components: Vec<Component>, links: &[GraphLink]) -> Result<Vec<u8>> {
-    // Exactly one entry.
-    let entry_count = components.iter().filter(|c| c.entry).count();
-    if entry_count != 1 {
-        return Err(anyhow!(
-            "exactly one component must have `entry = true`, found {entry_count}"
-        ));
-    }
+    compose_with_exports(components, links, &[])
+}
 
-    // Names must be unique.
-    {
-        let mut seen = std::collections::HashSet::new();
-        for c in &components {
-            if !seen.insert(c.name.as_str()) {
-                return Err(anyhow!("duplicate component name `{}`", c.name));
+/// [`compose`], additionally keeping each of `reexports` on the composite's
+/// surface next to the entry's exports.
+///
+/// Each re-export gets a shim like a link's, with the entry's memory 0 as the
+/// consumer side: the host calls it with pointers into memory 0, exactly as it
+/// calls the entry's own exports. It is also added to the composite's
+/// `__pack_types` exports (see [`composite_metadata`]).
+pub fn compose_with_exports(
+    components: Vec<Component>,
+    links: &[GraphLink],
+    reexports: &[Reexport],
+) -> Result<Vec<u8>> {
+    let options = ComposeOptions {
+        exports: reexports.to_vec(),
+        ..ComposeOptions::default()
+    };
+    compose_with_options(components, links, &options)
+}
+
+/// [`compose`] with every [`ComposeOptions`] knob.
+///
+/// With [`MemoryMode::Shared`] every component must be a PIC side module. They
+/// are linked into one memory ([`shared`]), and each link's shim passes the
+/// consumer's pointers straight to the provider (see [`emit_direct_shim`]).
+pub fn compose_with_options(
+    components: Vec<Component>,
+    links: &[GraphLink],
+    options: &ComposeOptions,
+) -> Result<Vec<u8>> {
+    compose_with_report(components, links, options).map(|(wasm, _)| wasm)
+}
+
+/// [`compose_with_options`], also reporting each component's size in the
+/// composite before and after dead-code elimination (see [`SizeReport`]).
+pub fn compose_with_report(
+    components: Vec<Component>,
+    links: &[GraphLink],
+    options: &ComposeOptions,
+) -> Result<(Vec<u8>, SizeReport)> {
+    let reexports = options.exports.as_slice();
+    check_graph(&components, links)?;
+
+    for r in reexports {
+        match components.iter().find(|c| c.name == r.component) {
+            None => {
+                return Err(anyhow!(
+                    "re-export `{}` references unknown component `{}`",
+                    r.as_name,
+                    r.component
+                ))
+            }
+            Some(c) if c.entry => {
+                return Err(anyhow!(
+                    "re-export `{}` names the entry component `{}`, whose exports are \
+                     already the composite's",
+                    r.as_name,
+                    r.component
+                ))
             }
+            Some(_) => {}
         }
     }
 
-    // Validate links reference known components (consumer + provider).
-    let names: std::collections::HashSet<&str> =
-        components.iter().map(|c| c.name.as_str()).collect();
-    for l in links {
-        if !names.contains(l.consumer.as_str()) {
-            return Err(anyhow!("link references unknown consumer `{}`", l.consumer));
-        }
-        if !names.contains(l.provider.as_str()) {
-            return Err(anyhow!("link references unknown provider `{}`", l.provider));
+    if options.memory == MemoryMode::Shared {
+        for c in &components {
+            if !shared::is_side_module(&c.wasm) {
+                return Err(anyhow!(
+                    "component `{}` is not a PIC side module; `memory = \"shared\"` needs \
+                     every component built with `-C relocation-model=pic` and `-shared`",
+                    c.name
+                ));
+            }
         }
     }
 
+    let links = &resolve_links(&components, links)?;
+
     // Safety net: reject a link whose two sides disagree on the interface's
     // Merkle hash before we wire it (a mismatch would otherwise silently marshal
-    // garbage across the shim at runtime).
-    verify_link_hashes(&components, links)?;
+    // garbage across the shim at runtime), unless an adapter can bridge them.
+    let adapters = verify_link_hashes(&components, links, &options.renames)?;
+    let adapted = links.iter().zip(&adapters).find(|(_, a)| a.is_some());
+    if let (Some((link, _)), MemoryMode::Shared) = (adapted, options.memory) {
+        return Err(anyhow!(
+            "link `{}.{}.{}` needs an adapter, which `memory = \"shared\"` does not support",
+            link.consumer,
+            link.import_module,
+            link.import_name
+        ));
+    }
 
     // Step 1: order entry first, pre-rename non-entry exports to `__c_<name>_*`.
     // The entry component keeps canonical export names untouched.
@@ -444,44 +982,35 @@ pub fn compose(components: Vec<Component>, links: &[GraphLink]) -> Result<Vec<u8
         ordered.push(c);
     }
 
-    // Interfaces internalized on the ENTRY component: for each link whose consumer
-    // is the entry, that whole import interface (`link.import_module`) is satisfied
-    // internally by a bridging shim and its wasm import is deleted. It must NOT
-    // remain in the composite's `__pack_types`, or theater (which resolves handlers
-    // from that metadata) sees it as an unsatisfied required import and fails actor
-    // setup with "No handler provides interface <x> required by actor". We strip it
-    // PRE-MERGE from the entry's `__pack_types` metadata (which may live at its own
-    // data segment or, under LTO, interior to the merged `.rodata`); the merged
-    // composite then inherits the entry's corrected metadata verbatim.
+    // The composite's metadata import surface must equal its RESIDUAL imports:
+    // theater resolves handlers from `__pack_types`, so an internalized interface
+    // left there fails actor setup with "No handler provides interface <x>
+    // required by actor", and a non-entry component's residual host import missing
+    // from it is never provided. Compute it up front from every component's
+    // metadata; it replaces the entry's `__pack_types` once the composite is built.
     let entry_name = &ordered[0].name;
-    let entry_internalized: Vec<String> = {
-        let mut v: Vec<String> = links
-            .iter()
-            .filter(|l| &l.consumer == entry_name)
-            .map(|l| l.import_module.clone())
-            .collect();
-        v.sort();
-        v.dedup();
-        v
-    };
+    let metadata = composite_metadata(&ordered, entry_name, links, reexports)
+        .context("regenerating the composite's `__pack_types` metadata")?;
 
     let mut merge_inputs: Vec<(String, Vec<u8>)> = Vec::with_capacity(ordered.len());
     for c in &ordered {
         let bytes = if c.entry {
-            strip_internalized_imports_from_metadata(&c.wasm, &entry_internalized).with_context(
-                || {
-                    format!(
-                        "stripping internalized imports from entry component `{}` metadata",
-                        c.name
-                    )
-                },
-            )?
+            c.wasm.clone()
         } else {
-            rename_component_exports(&c.wasm, &c.name)
+            rename_component_exports(&c.wasm, &c.name, options.memory)
                 .with_context(|| format!("renaming exports for component `{}`", c.name))?
         };
         merge_inputs.push((c.name.clone(), bytes));
     }
+    // The link adapter, when a link needs one, is merged as one more component.
+    let mut component_names: Vec<String> = ordered.iter().map(|c| c.name.clone()).collect();
+    if adapted.is_some() {
+        let adapter = adapt::ADAPTER_COMPONENT.to_string();
+        let bytes = rename_component_exports(adapt::ADAPTER_WASM, &adapter, options.memory)
+            .context("renaming exports for the link adapter")?;
+        merge_inputs.push((adapter.clone(), bytes));
+        component_names.push(adapter);
+    }
 
     // A residual host import declared by a NON-ENTRY component points into that
     // component's own memory (memory 1, 2, …), but the host resolves the guest
@@ -512,187 +1041,420 @@ pub fn compose(components: Vec<Component>, links: &[GraphLink]) -> Result<Vec<u8
         }
     }
 
-    // Step 2: merge with wasm-merge (multi-memory), entry first (→ memory 0).
-    let merged = merge_multimemory(&merge_inputs)
-        .context("merging components into a multi-memory module")?;
+    // Step 2: merge into one multi-memory module, entry first (→ memory 0), or
+    // link the side modules into one shared memory.
+    let (merged, contributions) = match options.memory {
+        MemoryMode::Isolated => merge::merge_multimemory(&merge_inputs)
+            .context("merging components into a multi-memory module")?,
+        MemoryMode::Shared => shared::link_shared(&merge_inputs)
+            .context("linking components into one shared memory")?,
+    };
 
     // Step 3 + 4: locate every component's memory/funcs by name, emit one shim
     // per link, rewire the consumer's import, bridge non-entry residual host
     // imports through memory 0, and strip scaffolding exports.
-    let component_names: Vec<String> = ordered.iter().map(|c| c.name.clone()).collect();
-    let composite = shim_and_rewire(&merged, entry_name, &component_names, links, &host_bridges)
-        .context("emitting shims and rewiring imports")?;
+    let mut composite = shim_and_rewire(
+        merged,
+        entry_name,
+        &component_names,
+        links,
+        &adapters,
+        &host_bridges,
+        options,
+    )
+    .context("emitting shims and rewiring imports")?;
+
+    // Step 5: give the composite its own `__pack_types`.
+    if let Some(cgrf) = metadata {
+        embed_metadata(&mut composite, &cgrf).context("embedding composite metadata")?;
+    }
 
-    Ok(composite)
+    // Step 6: drop what the composite's surface no longer reaches.
+    let components: Vec<(String, merge::Contribution)> =
+        component_names.into_iter().zip(contributions).collect();
+    dce::emit_live(&mut composite, &components).context("eliminating dead code")
 }
 
-/// Strip the given import INTERFACES from the entry component's `__pack_types`
-/// CGRF metadata, in place, without shifting any data addresses.
-///
-/// When a link internalizes one of the consumer's imports, we delete the dead
-/// wasm import — but theater resolves actor handlers from the `__pack_types`
-/// metadata, not the wasm import section. If the internalized interface stays in
-/// the metadata, theater sees it as a still-required import with no host handler
-/// and fails actor setup ("No handler provides interface <x> required by actor").
-/// So the composite's metadata import surface must equal the RESIDUAL imports —
-/// every internalized interface (the whole interface, all its functions — theater
-/// resolves by interface, and a consumer may declare more functions than it links)
-/// must be removed.
-///
-/// We do this PRE-MERGE on the entry, before the components share one binary:
-///
-/// 1. Parse `__pack_types` for its two baked-in `i32.const`s: the FIRST is the
-///    metadata data address (DATA_ADDR), the SECOND is the metadata byte length
-///    (`old_len`). (`__pack_types` stores DATA_ADDR into `out_ptr_ptr` then
-///    `old_len` into `out_len_ptr`.)
-/// 2. Find the active data segment whose address range CONTAINS DATA_ADDR, and the
-///    offset `rel = DATA_ADDR - base` of the metadata within it. The CGRF may start
-///    the segment (`rel == 0`, a dedicated `.rodata`) or sit interior to a merged
-///    `.rodata` under LTO (`rel > 0`); `seg.value[rel..rel + old_len]` is the CGRF.
-/// 3. Decode those bytes → arena, remove each named interface from the imports,
-///    re-encode with hashes (which recomputes the surviving interfaces' hashes and
-///    drops the removed one). The re-encode is `new_len <= old_len` bytes.
-/// 4. Overwrite `[rel..rel + old_len)` IN PLACE: `[rel..rel + new_len)` = the new
-///    CGRF, `[rel + new_len..rel + old_len)` = zero. The segment length/offset and
-///    every other byte are untouched, so no address shifts.
-/// 5. Patch `__pack_types`'s length constant from `old_len` to `new_len` (the
-///    DATA_ADDR constant is left unchanged).
-///
-/// If `interfaces` is empty, or the component has no CGRF metadata / no
-/// `__pack_types`, the wasm is returned re-emitted but semantically unchanged.
-fn strip_internalized_imports_from_metadata(
-    entry_wasm: &[u8],
-    interfaces: &[String],
-) -> Result<Vec<u8>> {
-    use walrus::ir::Value as IrValue;
-    use walrus::ConstExpr;
+/// Check the shape of a composition: exactly one entry, unique component
+/// names, and links between known components only.
+fn check_graph(components: &[Component], links: &[GraphLink]) -> Result<()> {
+    // Exactly one entry.
+    let entry_count = components.iter().filter(|c| c.entry).count();
+    if entry_count != 1 {
+        return Err(anyhow!(
+            "exactly one component must have `entry = true`, found {entry_count}"
+        ));
+    }
 
-    if interfaces.is_empty() {
-        return Ok(entry_wasm.to_vec());
+    // Names must be unique.
+    let mut seen = std::collections::HashSet::new();
+    for c in components {
+        if !seen.insert(c.name.as_str()) {
+            return Err(anyhow!("duplicate component name `{}`", c.name));
+        }
+        if c.name == adapt::ADAPTER_COMPONENT {
+            return Err(anyhow!("component name `{}` is reserved", c.name));
+        }
     }
 
-    let mut module = Module::from_buffer(entry_wasm)?;
+    // Validate links reference known components (consumer + provider).
+    for l in links {
+        if !seen.contains(l.consumer.as_str()) {
+            return Err(anyhow!("link references unknown consumer `{}`", l.consumer));
+        }
+        if !seen.contains(l.provider.as_str()) {
+            return Err(anyhow!("link references unknown provider `{}`", l.provider));
+        }
+    }
+    Ok(())
+}
 
-    // Locate the exported `__pack_types` function.
-    let Some(pack_types_fn) = export_func(&module, "__pack_types") else {
-        // No metadata accessor — nothing to strip.
-        return Ok(module.emit_wasm());
-    };
+/// Read a component's `__pack_types` metadata without instantiating it.
+///
+/// The static CGRF scan ([`find_cgrf_metadata`]) finds metadata that starts its
+/// own data segment (the usual layout, and the passive segment a composite's
+/// regenerated metadata lives in). Under LTO the metadata is merged INTERIOR to
+/// the module's single `.rodata`, where no segment starts with the magic; there
+/// we follow `__pack_types`'s two baked-in `i32.const`s instead — the FIRST is
+/// the metadata address (DATA_ADDR), the SECOND its byte length — to the active
+/// segment whose address range contains DATA_ADDR. A side module's data is one
+/// segment at the imported `__memory_base`, which `__pack_types` adds at run
+/// time, so there DATA_ADDR is already relative to that segment.
+///
+/// `None` if the component carries no (decodable) metadata.
+fn read_component_metadata(wasm: &[u8]) -> Option<MetadataWithHashes> {
+    if let Some(bytes) = find_cgrf_metadata(wasm).ok().flatten() {
+        return decode_metadata_with_hashes(&bytes).ok();
+    }
 
-    // Read its two baked-in i32 constants in body order: [DATA_ADDR, old_len].
-    let consts: Vec<i32> = {
-        let func = module.funcs.get(pack_types_fn);
-        let local = match &func.kind {
-            walrus::FunctionKind::Local(l) => l,
-            _ => return Ok(module.emit_wasm()),
-        };
-        let mut collector = ConstCollector { values: Vec::new() };
-        let entry = local.entry_block();
-        walrus::ir::dfs_in_order(&mut collector, local, entry);
-        collector.values
+    let module = Module::from_buffer(wasm).ok()?;
+    let walrus::FunctionKind::Local(local) =
+        &module.funcs.get(export_func(&module, "__pack_types")?).kind
+    else {
+        return None;
     };
-    if consts.len() < 2 {
-        return Err(anyhow!(
-            "entry `__pack_types` has {} i32.const(s); expected the [DATA_ADDR, len] pair",
-            consts.len()
-        ));
-    }
-    let data_addr = consts[0];
-    let old_len = consts[1];
-    if old_len < 0 {
-        return Err(anyhow!("entry `__pack_types` metadata length is negative"));
-    }
-    let old_len = old_len as usize;
-
-    // Find the active data segment whose address range CONTAINS DATA_ADDR. Under
-    // LTO the CGRF metadata is merged into the module's single `.rodata` segment, so
-    // DATA_ADDR is an *interior* offset (segment base + `rel`), not the start of a
-    // dedicated segment. `rel` is where the metadata begins within `seg.value`; the
-    // old exact-offset match was just the `rel == 0` special case.
-    let hit = module.data.iter().find_map(|d| match &d.kind {
-        walrus::DataKind::Active {
-            offset: ConstExpr::Value(IrValue::I32(base)),
-            ..
-        } => {
-            let base = *base;
-            let rel = data_addr.checked_sub(base)?;
-            (rel >= 0 && (rel as usize) < d.value.len()).then(|| (d.id(), rel as usize))
+    let mut collector = ConstCollector { values: Vec::new() };
+    walrus::ir::dfs_in_order(&mut collector, local, local.entry_block());
+    let (&data_addr, &len) = (collector.values.first()?, collector.values.get(1)?);
+    let len = usize::try_from(len).ok()?;
+
+    let is_memory_base = |global: walrus::GlobalId| match module.globals.get(global).kind {
+        walrus::GlobalKind::Import(id) => {
+            let import = module.imports.get(id);
+            import.module == "env" && import.name == "__memory_base"
         }
-        _ => None,
+        walrus::GlobalKind::Local(_) => false,
+    };
+    let meta = module.data.iter().find_map(|d| {
+        let base = match &d.kind {
+            walrus::DataKind::Active {
+                offset: walrus::ConstExpr::Value(IrValue::I32(base)),
+                ..
+            } => *base,
+            walrus::DataKind::Active {
+                offset: walrus::ConstExpr::Global(global),
+                ..
+            } if is_memory_base(*global) => 0,
+            _ => return None,
+        };
+        let rel = usize::try_from(data_addr.checked_sub(base)?).ok()?;
+        let bytes = d.value.get(rel..rel.checked_add(len)?)?;
+        decode_metadata_with_hashes(bytes).ok()
     });
-    let Some((data_id, rel)) = hit else {
-        return Err(anyhow!(
-            "no active data segment contains metadata address {data_addr} (for `__pack_types`)"
-        ));
+    meta
+}
+
+/// The composite's own `__pack_types`: the entry component's metadata with its
+/// import surface replaced by the RESIDUAL imports of every component.
+///
+/// Theater resolves actor handlers from this metadata, not the wasm import
+/// section, so it must list exactly what the host still has to provide:
+/// `∪ components.imports − linked`. An interface is linked (internalized) for a
+/// component when any link has that component as consumer and the interface as
+/// `import_module` — the whole interface goes, since theater resolves by
+/// interface and a consumer may declare more functions than it links. Residual
+/// interfaces several components share are merged function by function; two
+/// components declaring the same function with different signatures is an error.
+///
+/// The exported surface, and any other top-level arena content (type
+/// parameters), is the entry's plus one function per re-export. A re-export
+/// named `iface.f` is listed as `f` in interface `iface`; a bare name keeps the
+/// interface the provider declares the function in. Interface hashes are
+/// recomputed on encode.
+///
+/// `Ok(None)` if the entry carries no metadata: there is nothing to describe the
+/// composite's exports with, so the composite is left as the merge produced it.
+fn composite_metadata(
+    components: &[&Component],
+    entry_name: &str,
+    links: &[GraphLink],
+    reexports: &[Reexport],
+) -> Result<Option<Vec<u8>>> {
+    use crate::types::Arena;
+
+    let surfaces: Vec<(&Component, Option<MetadataWithHashes>)> = components
+        .iter()
+        .map(|c| (*c, read_component_metadata(&c.wasm)))
+        .collect();
+    let Some(Some(entry_meta)) = surfaces
+        .iter()
+        .find(|(c, _)| c.name == entry_name)
+        .map(|(_, m)| m)
+    else {
+        return Ok(None);
     };
 
-    // Decode the current metadata, strip the named interfaces from imports, and
-    // re-encode (hashes recomputed from the surviving arena).
-    let new_cgrf = {
-        let seg = module.data.get(data_id);
-        if seg.value.len() < rel + old_len {
+    let mut residual: Vec<Arena> = Vec::new();
+    for (component, meta) in &surfaces {
+        let Some(meta) = meta else { continue };
+        let Some(imports) = meta.arena.children.iter().find(|c| c.name == "imports") else {
+            continue;
+        };
+        for iface in &imports.children {
+            let linked = links
+                .iter()
+                .any(|l| l.consumer == component.name && l.import_module == iface.name);
+            if linked {
+                continue;
+            }
+            let Some(merged) = residual.iter_mut().find(|r| r.name == iface.name) else {
+                residual.push(iface.clone());
+                continue;
+            };
+            for func in &iface.functions {
+                match merged.functions.iter().find(|f| f.name == func.name) {
+                    Some(existing) if existing != func => {
+                        return Err(anyhow!(
+                            "components disagree on residual import `{}.{}`: component `{}` \
+                             declares it with a different signature than an earlier component",
+                            iface.name,
+                            func.name,
+                            component.name,
+                        ));
+                    }
+                    Some(_) => {}
+                    None => merged.functions.push(func.clone()),
+                }
+            }
+            for ty in &iface.types {
+                if !merged.types.contains(ty) {
+                    merged.types.push(ty.clone());
+                }
+            }
+        }
+    }
+
+    let mut arena = entry_meta.arena.clone();
+    arena.children.retain(|c| c.name != "imports");
+    let mut imports = Arena::new("imports");
+    imports.children = residual;
+    arena.children.insert(0, imports);
+
+    for r in reexports {
+        let Some((_, Some(meta))) = surfaces.iter().find(|(c, _)| c.name == r.component) else {
             return Err(anyhow!(
-                "entry metadata segment is {} bytes, shorter than the metadata end {} (rel {rel} + len {old_len})",
-                seg.value.len(),
-                rel + old_len
+                "re-export `{}`: component `{}` carries no `__pack_types` metadata to \
+                 describe it with",
+                r.as_name,
+                r.component
             ));
-        }
-        let meta_bytes = &seg.value[rel..rel + old_len];
-        let mut meta = crate::metadata::decode_metadata_with_hashes(meta_bytes)
-            .map_err(|e| anyhow!("decoding entry `__pack_types` metadata: {e}"))?;
-        remove_import_interfaces(&mut meta.arena, interfaces);
-        crate::metadata::encode_metadata_with_hashes(&meta.arena)
-            .map_err(|e| anyhow!("re-encoding stripped entry metadata: {e}"))?
-    };
+        };
+        let (iface, func) = export_signature(meta, &r.export_name).ok_or_else(|| {
+            anyhow!(
+                "re-export `{}`: component `{}` declares no export `{}` in its metadata",
+                r.as_name,
+                r.component,
+                r.export_name
+            )
+        })?;
+        let (iface_name, func_name) = match r.as_name.rsplit_once('.') {
+            Some((i, f)) => (i, f),
+            None => (iface.name.as_str(), r.as_name.as_str()),
+        };
 
-    let new_len = new_cgrf.len();
-    if new_len > old_len {
-        return Err(anyhow!(
-            "stripped metadata grew ({new_len} > {old_len}); refusing to shift data addresses"
-        ));
+        if !arena.children.iter().any(|c| c.name == "exports") {
+            arena.add_child(Arena::new("exports"));
+        }
+        let exports = arena
+            .children
+            .iter_mut()
+            .find(|c| c.name == "exports")
+            .expect("exports section present");
+        if !exports.children.iter().any(|c| c.name == iface_name) {
+            exports.add_child(Arena::new(iface_name));
+        }
+        let target = exports
+            .children
+            .iter_mut()
+            .find(|c| c.name == iface_name)
+            .expect("interface present");
+        if target.functions.iter().any(|f| f.name == func_name) {
+            return Err(anyhow!(
+                "re-export `{}` collides with the composite's export `{}.{}`",
+                r.as_name,
+                iface_name,
+                func_name
+            ));
+        }
+        let mut func = func.clone();
+        func.name = func_name.to_string();
+        target.functions.push(func);
+        for ty in &iface.types {
+            if !target.types.contains(ty) {
+                target.types.push(ty.clone());
+            }
+        }
     }
+    let bytes = crate::metadata::encode_metadata_with_hashes(&arena)
+        .map_err(|e| anyhow!("encoding composite metadata: {e}"))?;
+    Ok(Some(bytes))
+}
 
-    // Overwrite in place within the segment: [rel..rel+new_len) = new CGRF,
-    // [rel+new_len..rel+old_len) = zero.
-    {
-        let seg = module.data.get_mut(data_id);
-        seg.value[rel..rel + new_len].copy_from_slice(&new_cgrf);
-        for b in &mut seg.value[rel + new_len..rel + old_len] {
-            *b = 0;
+/// Find the metadata interface and function behind a component's export name.
+///
+/// Guest export names are `iface.f` or a bare `f`; a bare name is looked up
+/// across every exported interface and must be unambiguous.
+fn export_signature<'a>(
+    meta: &'a MetadataWithHashes,
+    export_name: &str,
+) -> Option<(&'a crate::types::Arena, &'a crate::types::Function)> {
+    let exports = meta.arena.children.iter().find(|c| c.name == "exports")?;
+    let find = |iface: &'a crate::types::Arena, name: &str| {
+        iface
+            .functions
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| (iface, f))
+    };
+    if let Some((iface, f)) = export_name.rsplit_once('.') {
+        if let Some(found) = exports
+            .children
+            .iter()
+            .find(|c| c.name == iface)
+            .and_then(|i| find(i, f))
+        {
+            return Some(found);
         }
     }
+    let mut matches = exports.children.iter().filter_map(|i| find(i, export_name));
+    match (matches.next(), matches.next()) {
+        (Some(found), None) => Some(found),
+        _ => None,
+    }
+}
 
-    // Patch the `__pack_types` length constant (old_len -> new_len). Leave the
-    // DATA_ADDR constant untouched.
-    {
-        let func = module.funcs.get_mut(pack_types_fn);
-        if let walrus::FunctionKind::Local(local) = &mut func.kind {
-            let entry = local.entry_block();
-            let mut patcher = ConstPatcher {
-                from: old_len as i32,
-                to: new_len as i32,
-                done: false,
-            };
-            walrus::ir::dfs_pre_order_mut(&mut patcher, local, entry);
-            if !patcher.done {
-                return Err(anyhow!(
-                    "could not find the `__pack_types` length constant {old_len} to patch"
-                ));
-            }
-        }
+/// Give the composite a fresh `__pack_types` returning `cgrf`.
+///
+/// The metadata is a NEW passive data segment, so nothing in any component's
+/// memory layout moves. The new `__pack_types` copies it into the entry's
+/// memory on first call (`__pack_alloc` + `memory.init`), caches the pointer in
+/// a global, and hands out the same buffer on every later call — the host
+/// treats `__pack_types` output as static and never frees it.
+///
+/// The components' own metadata segments are dead after this (their
+/// `__pack_types` exports are gone), but a static scan would still find the
+/// first of them; their magic is zeroed so [`find_cgrf_metadata`] sees only the
+/// composite's.
+fn embed_metadata(module: &mut Module, cgrf: &[u8]) -> Result<()> {
+    let memory = export_memory(module, EXPORT_MEMORY)
+        .ok_or_else(|| anyhow!("composite has no `{EXPORT_MEMORY}` export"))?;
+    let alloc = export_func(module, EXPORT_ALLOC)
+        .ok_or_else(|| anyhow!("composite has no `{EXPORT_ALLOC}` export"))?;
+
+    let stale: Vec<_> = module
+        .data
+        .iter()
+        .filter(|d| d.value.starts_with(&crate::metadata::CGRF_MAGIC))
+        .map(|d| d.id())
+        .collect();
+    for id in stale {
+        module.data.get_mut(id).value[..4].fill(0);
     }
 
-    Ok(module.emit_wasm())
-}
+    let len = i32::try_from(cgrf.len()).context("composite metadata exceeds 2 GiB")?;
+    let data = module.data.add(walrus::DataKind::Passive, cgrf.to_vec());
+    let cached = module.globals.add_local(
+        ValType::I32,
+        true,
+        false,
+        walrus::ConstExpr::Value(IrValue::I32(0)),
+    );
 
-/// Remove each named interface (the whole interface, all functions) from the
-/// arena's `imports` child. Interfaces not present are ignored.
-fn remove_import_interfaces(arena: &mut crate::types::Arena, interfaces: &[String]) {
-    if let Some(imports) = arena.children.iter_mut().find(|c| c.name == "imports") {
-        imports
-            .children
-            .retain(|iface| !interfaces.iter().any(|n| n == &iface.name));
+    let i32 = ValType::I32;
+    let mut builder = FunctionBuilder::new(&mut module.types, &[i32, i32], &[i32]);
+    builder.name("__pack_types".to_string());
+    let out_ptr_ptr = module.locals.add(i32);
+    let out_len_ptr = module.locals.add(i32);
+    let mem4 = MemArg {
+        align: 2,
+        offset: 0,
+    };
+
+    // First call: cached = __pack_alloc(len); memory.init cached <- data[0..len].
+    let copy_in = {
+        let mut seq = builder.dangling_instr_seq(InstrSeqType::Simple(None));
+        seq.instr(Const {
+            value: IrValue::I32(len),
+        })
+        .instr(Call { func: alloc })
+        .instr(walrus::ir::GlobalSet { global: cached })
+        .instr(walrus::ir::GlobalGet { global: cached })
+        .instr(Const {
+            value: IrValue::I32(0),
+        })
+        .instr(Const {
+            value: IrValue::I32(len),
+        })
+        .instr(walrus::ir::MemoryInit { memory, data });
+        seq.id()
+    };
+    let cached_already = builder.dangling_instr_seq(InstrSeqType::Simple(None)).id();
+
+    let mut body = builder.func_body();
+    body.instr(walrus::ir::GlobalGet { global: cached })
+        .instr(walrus::ir::Unop {
+            op: walrus::ir::UnaryOp::I32Eqz,
+        })
+        .instr(IfElse {
+            consequent: copy_in,
+            alternative: cached_already,
+        });
+
+    // *out_ptr_ptr = cached; *out_len_ptr = len; return 0
+    body.instr(LocalGet { local: out_ptr_ptr })
+        .instr(walrus::ir::GlobalGet { global: cached })
+        .instr(Store {
+            memory,
+            kind: StoreKind::I32 { atomic: false },
+            arg: mem4,
+        })
+        .instr(LocalGet { local: out_len_ptr })
+        .instr(Const {
+            value: IrValue::I32(len),
+        })
+        .instr(Store {
+            memory,
+            kind: StoreKind::I32 { atomic: false },
+            arg: mem4,
+        })
+        .instr(Const {
+            value: IrValue::I32(0),
+        });
+    let pack_types = builder.finish(vec![out_ptr_ptr, out_len_ptr], &mut module.funcs);
+
+    let existing = module
+        .exports
+        .iter()
+        .find(|e| e.name == "__pack_types")
+        .map(|e| e.id());
+    match existing {
+        Some(id) => module.exports.get_mut(id).item = ExportItem::Function(pack_types),
+        None => {
+            module.exports.add("__pack_types", pack_types);
+        }
     }
+
+    Ok(())
 }
 
 /// Collects `i32.const` values from a function body in traversal order.
@@ -708,42 +1470,28 @@ impl<'instr> walrus::ir::Visitor<'instr> for ConstCollector {
     }
 }
 
-/// Rewrites the FIRST `i32.const from` to `i32.const to`.
-struct ConstPatcher {
-    from: i32,
-    to: i32,
-    done: bool,
-}
-
-impl walrus::ir::VisitorMut for ConstPatcher {
-    fn visit_instr_mut(&mut self, instr: &mut Instr, _loc: &mut walrus::ir::InstrLocId) {
-        if self.done {
-            return;
-        }
-        if let Instr::Const(walrus::ir::Const {
-            value: walrus::ir::Value::I32(v),
-        }) = instr
-        {
-            if *v == self.from {
-                *v = self.to;
-                self.done = true;
-            }
-        }
-    }
-}
-
 /// Rename ALL of a non-entry component's exports to `__c_<name>_<orig>` so none
 /// collides on merge and each is findable by a unique, component-scoped name.
 ///
 /// The load-bearing three (`__pack_alloc`, `__pack_free`, `memory`) plus every
 /// linked export end up as `__c_<name>_<orig>`; the shim looks them up by that
 /// name. Every other export (`__pack_types`, `__heap_base`, …) is renamed too so
-/// it survives the merge harmlessly and is dropped later.
-fn rename_component_exports(component_wasm: &[u8], component: &str) -> Result<Vec<u8>> {
+/// it survives the merge harmlessly and is dropped later. A side module in a
+/// shared-memory composite has no memory of its own to export; the linker
+/// exports the shared one under its scoped name.
+fn rename_component_exports(
+    component_wasm: &[u8],
+    component: &str,
+    memory: MemoryMode,
+) -> Result<Vec<u8>> {
     let mut module = Module::from_buffer(component_wasm)?;
 
-    // Sanity: the three required exports must exist.
-    for required in [EXPORT_ALLOC, EXPORT_FREE, EXPORT_MEMORY] {
+    // Sanity: the required exports must exist.
+    let required: &[&str] = match memory {
+        MemoryMode::Isolated => &[EXPORT_ALLOC, EXPORT_FREE, EXPORT_MEMORY],
+        MemoryMode::Shared => &[EXPORT_ALLOC, EXPORT_FREE],
+    };
+    for &required in required {
         if !module.exports.iter().any(|e| e.name == required) {
             return Err(anyhow!(
                 "component `{component}` is missing required export `{required}`"
@@ -760,116 +1508,6 @@ fn rename_component_exports(component_wasm: &[u8], component: &str) -> Result<Ve
     Ok(module.emit_wasm())
 }
 
-/// Shell out to `wasm-merge --enable-multimemory`, writing inputs to temp files.
-/// `inputs` are `(merge_name, wasm)` in order; the FIRST becomes memory 0.
-fn merge_multimemory(inputs: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
-    let dir = std::env::temp_dir();
-    let pid = std::process::id();
-    // Per-call nonce: two `compose()` calls in the SAME process (e.g. parallel
-    // test threads) share the PID, so PID alone is not unique — they would clobber
-    // each other's temp inputs/output and wasm-merge would read a half-written
-    // file. The monotonic counter makes every call's paths distinct.
-    static COMPOSE_SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
-    let seq = COMPOSE_SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-    let out_path = dir.join(format!("packr-compose-out-{pid}-{seq}.wasm"));
-
-    // Write each input to a temp file, remembering the paths for cleanup.
-    let mut paths: Vec<std::path::PathBuf> = Vec::with_capacity(inputs.len());
-    for (i, (_name, wasm)) in inputs.iter().enumerate() {
-        let p = dir.join(format!("packr-compose-in-{pid}-{seq}-{i}.wasm"));
-        std::fs::write(&p, wasm)?;
-        paths.push(p);
-    }
-
-    let status = run_wasm_merge(inputs, &paths, &out_path);
-
-    let cleanup = || {
-        for p in &paths {
-            let _ = std::fs::remove_file(p);
-        }
-        let _ = std::fs::remove_file(&out_path);
-    };
-
-    match status {
-        Ok(true) => {}
-        Ok(false) => {
-            cleanup();
-            return Err(anyhow!("wasm-merge failed"));
-        }
-        Err(e) => {
-            cleanup();
-            return Err(e);
-        }
-    }
-
-    let merged = std::fs::read(&out_path)?;
-    cleanup();
-    Ok(merged)
-}
-
-/// Invoke `wasm-merge <in0> <name0> <in1> <name1> … -o <out> --enable-multimemory`.
-///
-/// Tries a bare `wasm-merge` first; if that isn't on PATH, retries through
-/// `nix shell nixpkgs#binaryen -c wasm-merge ...` so the transform works in the
-/// dev environment without a global binaryen install.
-fn run_wasm_merge(
-    inputs: &[(String, Vec<u8>)],
-    paths: &[std::path::PathBuf],
-    out: &std::path::Path,
-) -> Result<bool> {
-    use std::process::Command;
-
-    // Build the positional `<file> <merge-name>` pairs plus the output flags.
-    let merge_args: Vec<String> = {
-        let mut v = Vec::new();
-        for (i, (name, _)) in inputs.iter().enumerate() {
-            v.push(paths[i].display().to_string());
-            v.push(name.clone());
-        }
-        v.push("-o".to_string());
-        v.push(out.display().to_string());
-        v.push("--enable-multimemory".to_string());
-        v
-    };
-
-    // First: bare `wasm-merge`.
-    let direct = Command::new("wasm-merge").args(&merge_args).output();
-    match direct {
-        Ok(o) if o.status.success() => return Ok(true),
-        Ok(o) => {
-            return Err(anyhow!(
-                "wasm-merge failed: {}",
-                String::from_utf8_lossy(&o.stderr)
-            ));
-        }
-        Err(_) => { /* not on PATH — fall through to nix */ }
-    }
-
-    // Fallback: through nix.
-    let nix_args = {
-        let mut v = vec![
-            "shell".to_string(),
-            "nixpkgs#binaryen".to_string(),
-            "-c".to_string(),
-            "wasm-merge".to_string(),
-        ];
-        v.extend(merge_args);
-        v
-    };
-    let via_nix = Command::new("nix").args(&nix_args).output().context(
-        "could not run wasm-merge directly or via `nix shell nixpkgs#binaryen`; \
-         install binaryen or make nix available",
-    )?;
-    if via_nix.status.success() {
-        Ok(true)
-    } else {
-        Err(anyhow!(
-            "wasm-merge (via nix) failed: {}",
-            String::from_utf8_lossy(&via_nix.stderr)
-        ))
-    }
-}
-
 /// A component's resolved handles in the merged module.
 struct ResolvedComponent {
     memory: MemoryId,
@@ -880,13 +1518,15 @@ struct ResolvedComponent {
 }
 
 fn shim_and_rewire(
-    merged_wasm: &[u8],
+    mut module: Module,
     entry_name: &str,
     component_names: &[String],
     links: &[GraphLink],
+    adapters: &[Option<adapt::FunctionAdapter>],
     host_bridges: &[HostBridge],
-) -> Result<Vec<u8>> {
-    let mut module = Module::from_buffer(merged_wasm)?;
+    options: &ComposeOptions,
+) -> Result<Module> {
+    let (reexports, memory) = (options.exports.as_slice(), options.memory);
 
     // Resolve each component's memory + alloc/free + exported funcs by the unique
     // names we assigned (entry = canonical, others = `__c_<name>_*`).
@@ -929,15 +1569,30 @@ fn shim_and_rewire(
                 )
             })?;
 
-        let params = HostShimParams {
-            comp_mem,
-            comp_alloc,
-            entry_mem,
-            entry_alloc,
-            entry_free,
-            host_import: import_func,
+        let shim_id = match memory {
+            MemoryMode::Isolated => {
+                let params = HostShimParams {
+                    comp_mem,
+                    comp_alloc,
+                    entry_mem,
+                    entry_alloc,
+                    entry_free,
+                    host_import: import_func,
+                };
+                emit_host_shim(&mut module, &params)
+            }
+            // Same memory, but the host's result comes from the entry's
+            // allocator, which the component must not free with its own.
+            MemoryMode::Shared => {
+                let params = DirectShimParams {
+                    memory: entry_mem,
+                    callee: import_func,
+                    callee_free: entry_free,
+                    host: true,
+                };
+                emit_direct_shim(&mut module, &params)
+            }
         };
-        let shim_id = emit_host_shim(&mut module, &params);
 
         // Rewire the component's calls to the import → the bridge, but leave the
         // bridge's own call to the import intact, and KEEP the import.
@@ -945,8 +1600,9 @@ fn shim_and_rewire(
     }
 
     // For each link, emit a shim bridging the consumer's import to the provider's
-    // export, then rewire the consumer's import calls to the shim.
-    for link in links {
+    // export, then rewire the consumer's import calls to the shim. An adapted
+    // link's shim runs through the link adapter's memory instead.
+    for (link, adapter) in links.iter().zip(adapters) {
         let consumer = resolved
             .get(&link.consumer)
             .ok_or_else(|| anyhow!("link consumer `{}` not resolved", link.consumer))?;
@@ -974,22 +1630,80 @@ fn shim_and_rewire(
                 )
             })?;
 
+        let shim_id = match memory {
+            MemoryMode::Isolated => {
+                let params = ShimParams {
+                    consumer_mem: consumer.memory,
+                    consumer_alloc: consumer.alloc,
+                    provider_mem: provider.memory,
+                    provider_alloc: provider.alloc,
+                    provider_free: provider.free,
+                    provider_export: prov_export,
+                    forward_status: false,
+                };
+                match adapter {
+                    Some(function) => {
+                        let adapter = resolved.get(adapt::ADAPTER_COMPONENT).ok_or_else(|| {
+                            anyhow!("the link adapter was not merged into the composite")
+                        })?;
+                        adapt::emit_adapted_link(&mut module, &params, adapter, function)?
+                    }
+                    None => emit_shim(&mut module, &params),
+                }
+            }
+            MemoryMode::Shared => {
+                let params = DirectShimParams {
+                    memory: provider.memory,
+                    callee: prov_export,
+                    callee_free: provider.free,
+                    host: false,
+                };
+                emit_direct_shim(&mut module, &params)
+            }
+        };
+
+        rewire_import(&mut module, import_func, shim_id);
+    }
+
+    let mut module = strip_scoped_exports(module, component_names, entry_name);
+
+    // Re-exports: the host calls them like any entry export, with pointers into
+    // memory 0, so each is a shim with the entry in the consumer's place. Added
+    // after the strip so an `as` name never reads as scaffolding. In a shared
+    // memory the copy stays: the host frees the result with the entry's
+    // allocator, so it must come from there.
+    for r in reexports {
+        let provider = resolved
+            .get(&r.component)
+            .ok_or_else(|| anyhow!("re-export component `{}` not resolved", r.component))?;
+        let prov_export = *provider.exports.get(&r.export_name).ok_or_else(|| {
+            anyhow!(
+                "component `{}` has no export `{}` to re-export",
+                r.component,
+                r.export_name
+            )
+        })?;
+        if module.exports.iter().any(|e| e.name == r.as_name) {
+            return Err(anyhow!(
+                "re-export `{}` collides with an existing composite export",
+                r.as_name
+            ));
+        }
+
         let params = ShimParams {
-            consumer_mem: consumer.memory,
-            consumer_alloc: consumer.alloc,
+            consumer_mem: entry_mem,
+            consumer_alloc: entry_alloc,
             provider_mem: provider.memory,
             provider_alloc: provider.alloc,
             provider_free: provider.free,
             provider_export: prov_export,
+            forward_status: true,
         };
         let shim_id = emit_shim(&mut module, &params);
-
-        rewire_import(&mut module, import_func, shim_id);
+        module.exports.add(&r.as_name, shim_id);
     }
 
-    let module = strip_scoped_exports(module, component_names, entry_name);
-    let mut module = module;
-    Ok(module.emit_wasm())
+    Ok(module)
 }
 
 /// Resolve a component's memory, alloc, free and all exported functions by name.
@@ -1146,6 +1860,9 @@ struct ShimParams {
     provider_alloc: FunctionId,
     provider_free: FunctionId,
     provider_export: FunctionId,
+    /// Return the provider's status unchanged, as an export does (a re-export
+    /// called by the host), instead of the import-side ownership status.
+    forward_status: bool,
 }
 
 /// Emit a bridging shim function and return its id.
@@ -1298,6 +2015,14 @@ fn emit_shim(module: &mut Module, p: &ShimParams) -> FunctionId {
     // returns 0 on success; passing that 0 through would leak `aptr` in the
     // consumer's memory on every call. On error (status < 0) propagate unchanged.
     //   result = (status < 0) ? status : 1
+    //
+    // A re-export answers the HOST, which (like for any export) expects the
+    // export's own status — 0 on success — and frees the result itself.
+    if p.forward_status {
+        body.instr(LocalGet { local: status });
+        let args: Vec<LocalId> = vec![in_ptr, in_len, out_ptr_ptr, out_len_ptr];
+        return builder.finish(args, &mut module.funcs);
+    }
     body.instr(LocalGet { local: status }) // select val1 (returned when cond true)
         .instr(Const {
             value: IrValue::I32(1),
@@ -1315,6 +2040,124 @@ fn emit_shim(module: &mut Module, p: &ShimParams) -> FunctionId {
     builder.finish(args, &mut module.funcs)
 }
 
+/// The handles a shared-memory shim needs: the one memory, the function it
+/// forwards to, and the allocator that function's results come from.
+struct DirectShimParams {
+    memory: MemoryId,
+    callee: FunctionId,
+    callee_free: FunctionId,
+    /// The callee is a host import (status 1 = the caller owns the result)
+    /// rather than a provider's export (the result is always the provider's).
+    host: bool,
+}
+
+/// Emit a shared-memory shim and return its id.
+///
+/// Signature `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`, like
+/// [`emit_shim`], but with every component in one memory the pointers are
+/// passed straight to the callee: no buffer is allocated or copied.
+///
+/// What remains is ownership. The callee's result comes from ITS allocator,
+/// and the caller's `__import_impl` would free an owned result with its own.
+/// So the shim reports it as callee-owned (status 0) and keeps it until the
+/// next call through the same shim returns — by then the caller has decoded
+/// it — and frees it with the callee's `__pack_free`. At most one result per
+/// shim is outstanding.
+///
+///   status = callee(in_ptr, in_len, out_ptr_ptr, out_len_ptr)
+///   callee.__pack_free(prev_ptr, prev_len)
+///   (prev_ptr, prev_len) = owned ? (*out_ptr_ptr, *out_len_ptr) : (0, 0)
+///   return owned && host ? 0 : status
+///
+/// A provider's export owns every result it writes (errors included); a
+/// host import's result is owned only with status 1.
+fn emit_direct_shim(module: &mut Module, p: &DirectShimParams) -> FunctionId {
+    let i32 = ValType::I32;
+    let zero = walrus::ConstExpr::Value(IrValue::I32(0));
+    let prev_ptr = module.globals.add_local(i32, true, false, zero);
+    let prev_len = module.globals.add_local(i32, true, false, zero);
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &[i32, i32, i32, i32], &[i32]);
+    builder.name("__direct_shim".to_string());
+    let in_ptr = module.locals.add(i32);
+    let in_len = module.locals.add(i32);
+    let out_ptr_ptr = module.locals.add(i32);
+    let out_len_ptr = module.locals.add(i32);
+    let status = module.locals.add(i32);
+    let mem4 = MemArg {
+        align: 2,
+        offset: 0,
+    };
+
+    // prev = the result slots; a host result is then reported as borrowed.
+    let keep = {
+        let mut seq = builder.dangling_instr_seq(InstrSeqType::Simple(None));
+        for (slot, global) in [(out_ptr_ptr, prev_ptr), (out_len_ptr, prev_len)] {
+            seq.instr(LocalGet { local: slot })
+                .instr(walrus::ir::Load {
+                    memory: p.memory,
+                    kind: LoadKind::I32 { atomic: false },
+                    arg: mem4,
+                })
+                .instr(walrus::ir::GlobalSet { global });
+        }
+        if p.host {
+            seq.instr(Const {
+                value: IrValue::I32(0),
+            })
+            .instr(LocalSet { local: status });
+        }
+        seq.id()
+    };
+    let forget = {
+        let mut seq = builder.dangling_instr_seq(InstrSeqType::Simple(None));
+        for global in [prev_ptr, prev_len] {
+            seq.instr(Const {
+                value: IrValue::I32(0),
+            })
+            .instr(walrus::ir::GlobalSet { global });
+        }
+        seq.id()
+    };
+
+    let mut body = builder.func_body();
+    body.instr(LocalGet { local: in_ptr })
+        .instr(LocalGet { local: in_len })
+        .instr(LocalGet { local: out_ptr_ptr })
+        .instr(LocalGet { local: out_len_ptr })
+        .instr(Call { func: p.callee })
+        .instr(LocalSet { local: status });
+
+    body.instr(walrus::ir::GlobalGet { global: prev_ptr })
+        .instr(walrus::ir::GlobalGet { global: prev_len })
+        .instr(Call {
+            func: p.callee_free,
+        });
+
+    // owned = host ? status == 1 : true
+    if p.host {
+        body.instr(LocalGet { local: status })
+            .instr(Const {
+                value: IrValue::I32(1),
+            })
+            .instr(Binop {
+                op: BinaryOp::I32Eq,
+            });
+    } else {
+        body.instr(Const {
+            value: IrValue::I32(1),
+        });
+    }
+    body.instr(IfElse {
+        consequent: keep,
+        alternative: forget,
+    })
+    .instr(LocalGet { local: status });
+
+    let args: Vec<LocalId> = vec![in_ptr, in_len, out_ptr_ptr, out_len_ptr];
+    builder.finish(args, &mut module.funcs)
+}
+
 /// The handles a host-bridge shim needs: the calling COMPONENT's memory + alloc
 /// (where the guest's call args live), the ENTRY's memory + alloc/free (which the
 /// host reads/writes), and the real host import to invoke.
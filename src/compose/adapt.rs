@@ -0,0 +1,934 @@
+//! Link adapters: linking interfaces that differ, but compatibly.
+//!
+//! A link's two interfaces normally hash equal. When they do not, compose diffs
+//! them structurally ([`diff_interfaces`]) and classifies the link as
+//! identical, compatible with an adapter, or incompatible. A compatible link is
+//! one whose every value the WRITER sends (the consumer's arguments, the
+//! provider's results) can be rewritten into the form the READER decodes:
+//!
+//! - a record field the reader declares is taken from the writer's field of the
+//!   same name, or of the name a [`FieldRename`] maps it to;
+//! - a reader field the writer lacks is filled with `none` if it is an
+//!   `option`, and is incompatible otherwise;
+//! - a writer field the reader does not declare is dropped;
+//! - a variant or enum case is matched by name, so cases may be added to the
+//!   reader or reordered; a case the writer may send but the reader lacks is
+//!   incompatible;
+//! - lists, options, results and tuples are adapted element-wise; primitives
+//!   and flags must be equal. So must a recursive type, whose values cannot be
+//!   rewritten by a finite plan.
+//!
+//! Only the functions the consumer imports are compared. Each must exist on
+//! the provider; functions only the provider declares are ignored, since
+//! nothing calls them across the link.
+//!
+//! These rules assume the adapter sits between the two sides. Without one,
+//! more changes break; [`classify_evolution`](crate::evolution::classify_evolution)
+//! classifies a version change by direct decoding.
+//!
+//! The rewrite runs in flight, in the `link-adapter` guest
+//! (`packages/link-adapter`, checked in as `assets/link_adapter.wasm`), merged
+//! into the composite as one more memory. Each adapted function gets a plan
+//! per direction, stored as a passive data segment, and a shim
+//! ([`emit_adapter_shim`]) that runs the value through the adapter on its way
+//! to and from the provider.
+
+use super::{emit_shim, ShimParams};
+use crate::abi::{encode, Value, ValueType};
+use crate::metadata::{hash_type_in, CGRF_MAGIC};
+use crate::types::{Arena, Field, Function, Type, TypeDef};
+use anyhow::{anyhow, Result};
+use walrus::ir::{
+    BinaryOp, Binop, Call, Const, IfElse, InstrSeqType, LoadKind, LocalGet, LocalSet, MemArg,
+    MemoryInit, Return, Store, StoreKind, Value as IrValue,
+};
+use walrus::ValType::I32;
+use walrus::{DataKind, FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, MemoryId, Module};
+
+/// The adapter guest's name in the composite (its exports are scoped under it).
+pub(super) const ADAPTER_COMPONENT: &str = "__adapter";
+/// The adapter guest's entry point.
+const EXPORT_ADAPT: &str = "__pack_adapt";
+/// `packages/link-adapter`, prebuilt.
+pub(super) const ADAPTER_WASM: &[u8] = include_bytes!("../../assets/link_adapter.wasm");
+
+/// A record field known by different names on the two sides of a link.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct FieldRename {
+    /// The record (e.g. `"user"`), as the consumer names it.
+    pub record: String,
+    /// The consumer's name for the field (e.g. `"name"`).
+    pub consumer: String,
+    /// The provider's name for it (e.g. `"full-name"`).
+    pub provider: String,
+}
+
+/// How a consumer's import interface relates to a provider's export interface.
+#[derive(Debug, Clone)]
+pub enum LinkCompatibility {
+    /// Every value crosses the link unchanged.
+    Identical,
+    /// The link works with the adapter rewriting some values in flight.
+    Compatible(InterfaceAdapter),
+    /// No adapter can bridge the two; the reason names the first difference.
+    Incompatible(String),
+}
+
+/// The in-flight rewrites a compatible link needs, per imported function.
+#[derive(Debug, Clone, Default)]
+pub struct InterfaceAdapter {
+    functions: Vec<(String, FunctionAdapter)>,
+}
+
+impl InterfaceAdapter {
+    /// The imported functions whose values are rewritten.
+    pub fn adapted_functions(&self) -> impl Iterator<Item = &str> {
+        self.functions.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub(super) fn function(&self, name: &str) -> Option<&FunctionAdapter> {
+        self.functions
+            .iter()
+            .find(|(f, _)| f == name)
+            .map(|(_, adapter)| adapter)
+    }
+}
+
+/// One function's plans: for its arguments and for its results.
+#[derive(Debug, Clone)]
+pub(super) struct FunctionAdapter {
+    request: Option<Plan>,
+    response: Option<Plan>,
+}
+
+/// How to rewrite one value; the adapter guest's plan format (see
+/// `packages/link-adapter`).
+#[derive(Debug, Clone, PartialEq)]
+enum Plan {
+    Same,
+    Record(Vec<FieldPlan>),
+    /// `(writer tag, reader tag, payload plan)` per case the writer may send.
+    Variant(Vec<(u32, u32, Plan)>),
+    List(Box<Plan>),
+    Option(Box<Plan>),
+    Result(Box<Plan>, Box<Plan>),
+    Tuple(Vec<Plan>),
+}
+
+/// One reader field: taken from a writer field, or filled with a default.
+#[derive(Debug, Clone, PartialEq)]
+enum FieldPlan {
+    Take {
+        reader: String,
+        writer: String,
+        plan: Plan,
+    },
+    Fill {
+        reader: String,
+        value: Value,
+    },
+}
+
+/// Diff the consumer's `import` interface against the provider's `export`
+/// interface (both as decoded from `__pack_types`), with `renames` mapping
+/// record fields between them.
+pub fn diff_interfaces(
+    import: &Arena,
+    export: &Arena,
+    renames: &[FieldRename],
+) -> LinkCompatibility {
+    let mut adapter = InterfaceAdapter::default();
+    for f in &import.functions {
+        let Some(g) = export.functions.iter().find(|g| g.name == f.name) else {
+            return LinkCompatibility::Incompatible(format!(
+                "the provider has no function `{}`",
+                f.name
+            ));
+        };
+        match diff_function(import, f, export, g, renames) {
+            Ok(adapted) if adapted.request.is_none() && adapted.response.is_none() => {}
+            Ok(adapted) => adapter.functions.push((f.name.clone(), adapted)),
+            Err(reason) => {
+                return LinkCompatibility::Incompatible(format!("function `{}`: {reason}", f.name))
+            }
+        }
+    }
+    if adapter.functions.is_empty() {
+        LinkCompatibility::Identical
+    } else {
+        LinkCompatibility::Compatible(adapter)
+    }
+}
+
+fn diff_function(
+    import: &Arena,
+    f: &Function,
+    export: &Arena,
+    g: &Function,
+    renames: &[FieldRename],
+) -> Result<FunctionAdapter, String> {
+    if f.params.len() != g.params.len() || f.results.len() != g.results.len() {
+        return Err(format!(
+            "it takes {} parameter(s) and returns {} result(s), but the provider's takes {} \
+             and returns {}",
+            f.params.len(),
+            f.results.len(),
+            g.params.len(),
+            g.results.len()
+        ));
+    }
+    let consumer: Vec<TypeDef> = f.types.iter().chain(&import.types).cloned().collect();
+    let provider: Vec<TypeDef> = g.types.iter().chain(&export.types).cloned().collect();
+
+    let mut request = Differ::new(&consumer, &provider, renames, true);
+    let params: Vec<(&Type, &Type, String)> = f
+        .params
+        .iter()
+        .zip(&g.params)
+        .map(|(p, q)| (&p.ty, &q.ty, format!("parameter `{}`", p.name)))
+        .collect();
+    let request = request.plan_values(&params)?;
+
+    let mut response = Differ::new(&provider, &consumer, renames, false);
+    let results: Vec<(&Type, &Type, String)> = g
+        .results
+        .iter()
+        .zip(&f.results)
+        .map(|(w, r)| (w, r, "result".to_string()))
+        .collect();
+    let response = response.plan_values(&results)?;
+
+    Ok(FunctionAdapter {
+        request: (request != Plan::Same).then_some(request),
+        response: (response != Plan::Same).then_some(response),
+    })
+}
+
+/// A named type, or a structural one, after aliases and generics are resolved.
+enum Shape {
+    Def(TypeDef),
+    Type(Type),
+}
+
+/// Plans values from the WRITER's types into the READER's, each resolved in
+/// its own scope.
+struct Differ<'a> {
+    writer: &'a [TypeDef],
+    reader: &'a [TypeDef],
+    renames: &'a [FieldRename],
+    /// The consumer writes (a request) rather than reads (a response).
+    consumer_writes: bool,
+    /// The `(writer, reader)` named types being planned, to refuse recursion.
+    stack: Vec<(String, String)>,
+}
+
+impl<'a> Differ<'a> {
+    fn new(
+        writer: &'a [TypeDef],
+        reader: &'a [TypeDef],
+        renames: &'a [FieldRename],
+        consumer_writes: bool,
+    ) -> Self {
+        Self {
+            writer,
+            reader,
+            renames,
+            consumer_writes,
+            stack: Vec::new(),
+        }
+    }
+
+    fn writer_side(&self) -> &'static str {
+        if self.consumer_writes {
+            "consumer"
+        } else {
+            "provider"
+        }
+    }
+
+    fn reader_side(&self) -> &'static str {
+        if self.consumer_writes {
+            "provider"
+        } else {
+            "consumer"
+        }
+    }
+
+    /// The plan for a function's parameters or results, marshalled as the
+    /// guest macros do: none as `()`, one as itself, several as a tuple.
+    fn plan_values(&mut self, values: &[(&Type, &Type, String)]) -> Result<Plan, String> {
+        let mut plans = Vec::with_capacity(values.len());
+        for (writer, reader, what) in values {
+            plans.push(
+                self.plan(writer, reader)
+                    .map_err(|e| format!("{what}: {e}"))?,
+            );
+        }
+        Ok(match plans.len() {
+            1 => plans.remove(0),
+            _ if plans.iter().all(|p| *p == Plan::Same) => Plan::Same,
+            _ => Plan::Tuple(plans),
+        })
+    }
+
+    fn plan(&mut self, writer: &Type, reader: &Type) -> Result<Plan, String> {
+        if hash_type_in(writer, self.writer) == hash_type_in(reader, self.reader) {
+            return Ok(Plan::Same);
+        }
+        let (w, r) = (resolve(writer, self.writer)?, resolve(reader, self.reader)?);
+        match (&w, &r) {
+            (Shape::Def(wd), Shape::Def(rd)) => {
+                let key = (wd.name().to_string(), rd.name().to_string());
+                if self.stack.contains(&key) {
+                    return Err(format!(
+                        "recursive type `{}` differs between the two sides",
+                        rd.name()
+                    ));
+                }
+                self.stack.push(key);
+                let plan = self.plan_def(wd, rd);
+                self.stack.pop();
+                plan.map_err(|e| format!("{} `{}`: {e}", def_kind(rd), rd.name()))
+            }
+            (Shape::Type(w), Shape::Type(r)) => self.plan_structural(w, r),
+            _ => Err(mismatch(&w, &r)),
+        }
+    }
+
+    fn plan_structural(&mut self, writer: &Type, reader: &Type) -> Result<Plan, String> {
+        let boxed = |plan: Plan, wrap: fn(Box<Plan>) -> Plan| match plan {
+            Plan::Same => Plan::Same,
+            plan => wrap(Box::new(plan)),
+        };
+        match (writer, reader) {
+            (Type::List(w), Type::List(r)) => Ok(boxed(self.plan(w, r)?, Plan::List)),
+            (Type::Option(w), Type::Option(r)) => Ok(boxed(self.plan(w, r)?, Plan::Option)),
+            (
+                Type::Result {
+                    ok: w_ok,
+                    err: w_err,
+                },
+                Type::Result {
+                    ok: r_ok,
+                    err: r_err,
+                },
+            ) => match (self.plan(w_ok, r_ok)?, self.plan(w_err, r_err)?) {
+                (Plan::Same, Plan::Same) => Ok(Plan::Same),
+                (ok, err) => Ok(Plan::Result(Box::new(ok), Box::new(err))),
+            },
+            (Type::Tuple(w), Type::Tuple(r)) if w.len() == r.len() => {
+                let plans = w
+                    .iter()
+                    .zip(r)
+                    .map(|(w, r)| self.plan(w, r))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if plans.iter().all(|p| *p == Plan::Same) {
+                    Ok(Plan::Same)
+                } else {
+                    Ok(Plan::Tuple(plans))
+                }
+            }
+            (w, r) if w == r => Ok(Plan::Same),
+            (w, r) => Err(mismatch(&Shape::Type(w.clone()), &Shape::Type(r.clone()))),
+        }
+    }
+
+    fn plan_def(&mut self, writer: &TypeDef, reader: &TypeDef) -> Result<Plan, String> {
+        match (writer, reader) {
+            (TypeDef::Record { fields: w, .. }, TypeDef::Record { fields: r, .. }) => {
+                let record = if self.consumer_writes {
+                    writer.name()
+                } else {
+                    reader.name()
+                };
+                self.plan_record(record, w, r)
+            }
+            (
+                TypeDef::Variant { .. } | TypeDef::Enum { .. },
+                TypeDef::Variant { .. } | TypeDef::Enum { .. },
+            ) => self.plan_variant(&cases(writer), &cases(reader)),
+            (TypeDef::Flags { flags: w, .. }, TypeDef::Flags { flags: r, .. }) if w == r => {
+                Ok(Plan::Same)
+            }
+            (TypeDef::Flags { .. }, TypeDef::Flags { .. }) => {
+                Err("the flags differ between the two sides".to_string())
+            }
+            (w, r) => Err(mismatch(&Shape::Def(w.clone()), &Shape::Def(r.clone()))),
+        }
+    }
+
+    fn plan_record(
+        &mut self,
+        record: &str,
+        writer: &[Field],
+        reader: &[Field],
+    ) -> Result<Plan, String> {
+        let mut same = writer.len() == reader.len();
+        let mut fields = Vec::with_capacity(reader.len());
+        for (i, field) in reader.iter().enumerate() {
+            let wanted = self.writer_field(record, &field.name).to_string();
+            match writer.iter().position(|f| f.name == wanted) {
+                Some(j) => {
+                    let plan = self
+                        .plan(&writer[j].ty, &field.ty)
+                        .map_err(|e| format!("field `{}`: {e}", field.name))?;
+                    same &= i == j && wanted == field.name && plan == Plan::Same;
+                    fields.push(FieldPlan::Take {
+                        reader: wire_name(&field.name),
+                        writer: wire_name(&wanted),
+                        plan,
+                    });
+                }
+                None => match resolve(&field.ty, self.reader)? {
+                    Shape::Type(Type::Option(inner)) => {
+                        same = false;
+                        let inner_type = value_type(&inner, self.reader)
+                            .map_err(|e| format!("field `{}`: {e}", field.name))?;
+                        fields.push(FieldPlan::Fill {
+                            reader: wire_name(&field.name),
+                            value: Value::Option {
+                                inner_type,
+                                value: None,
+                            },
+                        });
+                    }
+                    _ => {
+                        return Err(format!(
+                            "the {} needs field `{}`, which the {} does not send (only an \
+                             `option` field can be filled in)",
+                            self.reader_side(),
+                            field.name,
+                            self.writer_side(),
+                        ))
+                    }
+                },
+            }
+        }
+        Ok(if same {
+            Plan::Same
+        } else {
+            Plan::Record(fields)
+        })
+    }
+
+    fn plan_variant(
+        &mut self,
+        writer: &[(&str, Option<&Type>)],
+        reader: &[(&str, Option<&Type>)],
+    ) -> Result<Plan, String> {
+        let mut same = true;
+        let mut cases = Vec::with_capacity(writer.len());
+        for (i, (name, w_payload)) in writer.iter().enumerate() {
+            let Some(j) = reader.iter().position(|(r, _)| r == name) else {
+                return Err(format!(
+                    "the {} may send case `{name}`, which the {} does not have",
+                    self.writer_side(),
+                    self.reader_side(),
+                ));
+            };
+            let plan = match (w_payload, reader[j].1) {
+                (None, None) => Plan::Same,
+                (Some(w), Some(r)) => self.plan(w, r).map_err(|e| format!("case `{name}`: {e}"))?,
+                _ => return Err(format!("case `{name}` carries a payload on one side only")),
+            };
+            same &= i == j && plan == Plan::Same;
+            cases.push((i as u32, j as u32, plan));
+        }
+        Ok(if same {
+            Plan::Same
+        } else {
+            Plan::Variant(cases)
+        })
+    }
+
+    /// The writer's name for the reader's field `field` of `record`.
+    fn writer_field<'f>(&'f self, record: &str, field: &'f str) -> &'f str {
+        self.renames
+            .iter()
+            .filter(|r| r.record == record)
+            .find_map(|r| match self.consumer_writes {
+                true => (r.provider == field).then_some(r.consumer.as_str()),
+                false => (r.consumer == field).then_some(r.provider.as_str()),
+            })
+            .unwrap_or(field)
+    }
+}
+
+/// Follow aliases, generic applications and map/set sugar to a named
+/// definition or a structural type.
+fn resolve(ty: &Type, scope: &[TypeDef]) -> Result<Shape, String> {
+    let mut ty = ty.clone();
+    // Bounded, so an alias cycle is an error rather than a hang.
+    for _ in 0..=scope.len() {
+        let def = match &ty {
+            Type::Ref(path) if path.is_self_ref() => {
+                return Err("recursive types cannot be adapted".to_string())
+            }
+            Type::Ref(path) => lookup(path, scope)?.clone(),
+            Type::App { path, args } => lookup(path, scope)?.instantiate(args),
+            Type::Map { .. } => {
+                ty = ty.desugar_map();
+                continue;
+            }
+            Type::Set(..) => {
+                ty = ty.desugar_set();
+                continue;
+            }
+            _ => return Ok(Shape::Type(ty)),
+        };
+        match def {
+            TypeDef::Alias { ty: target, .. } => ty = target,
+            def => return Ok(Shape::Def(def)),
+        }
+    }
+    Err(format!("type alias cycle at {ty:?}"))
+}
+
+fn lookup<'s>(path: &crate::types::TypePath, scope: &'s [TypeDef]) -> Result<&'s TypeDef, String> {
+    let name = path.as_simple().or_else(|| path.name()).unwrap_or_default();
+    scope
+        .iter()
+        .find(|t| t.name() == name)
+        .ok_or_else(|| format!("type `{path}` is not defined"))
+}
+
+fn cases(def: &TypeDef) -> Vec<(&str, Option<&Type>)> {
+    match def {
+        TypeDef::Variant { cases, .. } => cases
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    (!c.payload.is_unit()).then_some(&c.payload),
+                )
+            })
+            .collect(),
+        TypeDef::Enum { cases, .. } => cases.iter().map(|c| (c.as_str(), None)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn def_kind(def: &TypeDef) -> &'static str {
+    match def {
+        TypeDef::Alias { .. } => "type",
+        TypeDef::Record { .. } => "record",
+        TypeDef::Variant { .. } => "variant",
+        TypeDef::Enum { .. } => "enum",
+        TypeDef::Flags { .. } => "flags",
+    }
+}
+
+fn describe(shape: &Shape) -> String {
+    match shape {
+        Shape::Def(def) => format!("{} `{}`", def_kind(def), def.name()),
+        Shape::Type(Type::List(_)) => "a list".to_string(),
+        Shape::Type(Type::Option(_)) => "an option".to_string(),
+        Shape::Type(Type::Result { .. }) => "a result".to_string(),
+        Shape::Type(Type::Tuple(items)) => format!("a {}-tuple", items.len()),
+        Shape::Type(ty) => format!("{ty:?}").to_lowercase(),
+    }
+}
+
+fn mismatch(writer: &Shape, reader: &Shape) -> String {
+    format!(
+        "{} cannot be read as {}",
+        describe(writer),
+        describe(reader)
+    )
+}
+
+/// A field's name on the wire: generated Rust types spell `full-name` as
+/// `full_name`.
+fn wire_name(field: &str) -> String {
+    field.replace('-', "_")
+}
+
+/// The value type tag a `none` of `ty` is encoded with.
+fn value_type(ty: &Type, scope: &[TypeDef]) -> Result<ValueType, String> {
+    Ok(match resolve(ty, scope)? {
+        Shape::Def(def @ (TypeDef::Variant { .. } | TypeDef::Enum { .. })) => {
+            ValueType::Variant(def.name().to_string())
+        }
+        Shape::Def(TypeDef::Flags { .. }) => ValueType::Flags,
+        Shape::Def(def) => ValueType::Record(def.name().to_string()),
+        Shape::Type(ty) => match ty {
+            Type::Bool => ValueType::Bool,
+            Type::U8 => ValueType::U8,
+            Type::U16 => ValueType::U16,
+            Type::U32 => ValueType::U32,
+            Type::U64 => ValueType::U64,
+            Type::S8 => ValueType::S8,
+            Type::S16 => ValueType::S16,
+            Type::S32 => ValueType::S32,
+            Type::S64 => ValueType::S64,
+            Type::F32 => ValueType::F32,
+            Type::F64 => ValueType::F64,
+            Type::Char => ValueType::Char,
+            Type::String => ValueType::String,
+            Type::List(inner) => ValueType::List(Box::new(value_type(&inner, scope)?)),
+            Type::Option(inner) => ValueType::Option(Box::new(value_type(&inner, scope)?)),
+            Type::Result { ok, err } => ValueType::Result {
+                ok: Box::new(value_type(&ok, scope)?),
+                err: Box::new(value_type(&err, scope)?),
+            },
+            Type::Tuple(items) => ValueType::Tuple(
+                items
+                    .iter()
+                    .map(|t| value_type(t, scope))
+                    .collect::<Result<_, _>>()?,
+            ),
+            other => return Err(format!("{other:?} has no wire type to fill `none` with")),
+        },
+    })
+}
+
+impl Plan {
+    /// The plan as the pack value the adapter guest decodes.
+    fn to_value(&self) -> Value {
+        let plans = |plans: Vec<Value>| Value::List {
+            elem_type: ValueType::Variant("adapt".to_string()),
+            items: plans,
+        };
+        match self {
+            Plan::Same => step(0, "same", vec![]),
+            Plan::Record(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| match field {
+                        FieldPlan::Take {
+                            reader,
+                            writer,
+                            plan,
+                        } => step(
+                            0,
+                            "take",
+                            vec![
+                                Value::String(reader.clone()),
+                                Value::String(writer.clone()),
+                                plan.to_value(),
+                            ],
+                        ),
+                        FieldPlan::Fill { reader, value } => step(
+                            1,
+                            "fill",
+                            vec![Value::String(reader.clone()), value.clone()],
+                        ),
+                    })
+                    .collect();
+                step(1, "record", vec![plans(fields)])
+            }
+            Plan::Variant(cases) => {
+                let cases = cases
+                    .iter()
+                    .map(|(writer, reader, plan)| {
+                        Value::Tuple(vec![
+                            Value::U32(*writer),
+                            Value::U32(*reader),
+                            plan.to_value(),
+                        ])
+                    })
+                    .collect();
+                step(
+                    2,
+                    "variant",
+                    vec![Value::List {
+                        elem_type: ValueType::Tuple(vec![
+                            ValueType::U32,
+                            ValueType::U32,
+                            ValueType::Variant("adapt".to_string()),
+                        ]),
+                        items: cases,
+                    }],
+                )
+            }
+            Plan::List(plan) => step(3, "list", vec![plan.to_value()]),
+            Plan::Option(plan) => step(4, "option", vec![plan.to_value()]),
+            Plan::Result(ok, err) => step(5, "result", vec![ok.to_value(), err.to_value()]),
+            Plan::Tuple(items) => step(
+                6,
+                "tuple",
+                vec![plans(items.iter().map(Plan::to_value).collect())],
+            ),
+        }
+    }
+}
+
+fn step(tag: usize, case: &str, payload: Vec<Value>) -> Value {
+    Value::Variant {
+        type_name: "adapt".to_string(),
+        case_name: case.to_string(),
+        tag,
+        payload,
+    }
+}
+
+/// The adapter guest's handles in the composite, plus the function a link's
+/// adapted call ends in.
+struct AdapterShimParams<'a> {
+    memory: MemoryId,
+    alloc: FunctionId,
+    free: FunctionId,
+    adapt: FunctionId,
+    /// A shim from the adapter's memory to the provider's export, forwarding
+    /// its status.
+    provider: FunctionId,
+    adapter: &'a FunctionAdapter,
+}
+
+/// Emit the adapting half of an adapted link and return its id.
+///
+/// Signature `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`, like an
+/// export's, with every pointer in the ADAPTER's memory: the caller reaches it
+/// through an ordinary [`emit_shim`] from the consumer's memory, and it reaches
+/// the provider through another ([`AdapterShimParams::provider`]).
+///
+/// ```text
+/// if request plan:  status = adapt(plan, in) -> out; on error return it
+///                   in = out
+/// status = provider(in) -> out;  free an adapted in
+/// if response plan and status >= 0:
+///                   status = adapt(plan, out) -> out;  free the raw out
+/// return status
+/// ```
+///
+/// An adapter error is returned like an export's: -1, with the message as the
+/// result. Each plan is a passive data segment, copied into the adapter's
+/// memory for the call. The segment's CGRF magic is blanked — compose zeroes
+/// the magic of every embedded CGRF value but its own metadata, taking them for
+/// stale component metadata — and written back after the copy.
+fn emit_adapter_shim(module: &mut Module, p: &AdapterShimParams) -> Result<FunctionId> {
+    let mut plans = [None, None];
+    for (slot, plan) in plans
+        .iter_mut()
+        .zip([&p.adapter.request, &p.adapter.response])
+    {
+        if let Some(plan) = plan {
+            let mut bytes =
+                encode(&plan.to_value()).map_err(|e| anyhow!("encoding an adapter plan: {e}"))?;
+            bytes[..CGRF_MAGIC.len()].fill(0);
+            let len = bytes.len() as i32;
+            *slot = Some((module.data.add(DataKind::Passive, bytes), len));
+        }
+    }
+    let [request, response] = plans;
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &[I32, I32, I32, I32], &[I32]);
+    builder.name("__adapter_shim".to_string());
+    let l = Locals {
+        in_ptr: module.locals.add(I32),
+        in_len: module.locals.add(I32),
+        out_ptr_ptr: module.locals.add(I32),
+        out_len_ptr: module.locals.add(I32),
+        plan: module.locals.add(I32),
+        status: module.locals.add(I32),
+        raw_ptr: module.locals.add(I32),
+        raw_len: module.locals.add(I32),
+    };
+
+    // The early return for a request the adapter rejects.
+    let bail = {
+        let mut seq = builder.dangling_instr_seq(InstrSeqType::Simple(None));
+        seq.instr(LocalGet { local: l.status }).instr(Return {});
+        seq.id()
+    };
+    let carry_on = builder.dangling_instr_seq(InstrSeqType::Simple(None)).id();
+    let adapt_response = response.map(|response| {
+        let mut seq = builder.dangling_instr_seq(InstrSeqType::Simple(None));
+        l.take_result(&mut seq, p.memory);
+        l.adapt(&mut seq, p, response, l.raw_ptr, l.raw_len);
+        seq.instr(LocalGet { local: l.raw_ptr })
+            .instr(LocalGet { local: l.raw_len })
+            .instr(Call { func: p.free });
+        seq.id()
+    });
+    let keep_response = builder.dangling_instr_seq(InstrSeqType::Simple(None)).id();
+
+    let mut body = builder.func_body();
+    if let Some(request) = request {
+        l.adapt(&mut body, p, request, l.in_ptr, l.in_len);
+        body.instr(LocalGet { local: l.status })
+            .instr(Const {
+                value: IrValue::I32(0),
+            })
+            .instr(Binop {
+                op: BinaryOp::I32LtS,
+            })
+            .instr(IfElse {
+                consequent: bail,
+                alternative: carry_on,
+            });
+        // The adapted request replaces the caller's for the provider call.
+        l.take_result(&mut body, p.memory);
+    } else {
+        for (from, to) in [(l.in_ptr, l.raw_ptr), (l.in_len, l.raw_len)] {
+            body.instr(LocalGet { local: from })
+                .instr(LocalSet { local: to });
+        }
+    }
+
+    body.instr(LocalGet { local: l.raw_ptr })
+        .instr(LocalGet { local: l.raw_len })
+        .instr(LocalGet {
+            local: l.out_ptr_ptr,
+        })
+        .instr(LocalGet {
+            local: l.out_len_ptr,
+        })
+        .instr(Call { func: p.provider })
+        .instr(LocalSet { local: l.status });
+    if request.is_some() {
+        body.instr(LocalGet { local: l.raw_ptr })
+            .instr(LocalGet { local: l.raw_len })
+            .instr(Call { func: p.free });
+    }
+
+    if let Some(adapt_response) = adapt_response {
+        body.instr(LocalGet { local: l.status })
+            .instr(Const {
+                value: IrValue::I32(0),
+            })
+            .instr(Binop {
+                op: BinaryOp::I32GeS,
+            })
+            .instr(IfElse {
+                consequent: adapt_response,
+                alternative: keep_response,
+            });
+    }
+    body.instr(LocalGet { local: l.status });
+
+    let args = vec![l.in_ptr, l.in_len, l.out_ptr_ptr, l.out_len_ptr];
+    Ok(builder.finish(args, &mut module.funcs))
+}
+
+/// [`emit_adapter_shim`]'s locals.
+struct Locals {
+    in_ptr: LocalId,
+    in_len: LocalId,
+    out_ptr_ptr: LocalId,
+    out_len_ptr: LocalId,
+    plan: LocalId,
+    status: LocalId,
+    /// The buffer being handed on: the request for the provider, then the
+    /// provider's raw result.
+    raw_ptr: LocalId,
+    raw_len: LocalId,
+}
+
+impl Locals {
+    /// `status = adapt(plan, [ptr, len]) -> out`, with the plan copied in from
+    /// its segment (and its magic restored) for the call.
+    fn adapt(
+        &self,
+        seq: &mut InstrSeqBuilder,
+        p: &AdapterShimParams,
+        (data, len): (walrus::DataId, i32),
+        ptr: LocalId,
+        len_local: LocalId,
+    ) {
+        let len_const = || Const {
+            value: IrValue::I32(len),
+        };
+        seq.instr(len_const())
+            .instr(Call { func: p.alloc })
+            .instr(LocalSet { local: self.plan });
+        seq.instr(LocalGet { local: self.plan })
+            .instr(Const {
+                value: IrValue::I32(0),
+            })
+            .instr(len_const())
+            .instr(MemoryInit {
+                memory: p.memory,
+                data,
+            });
+        seq.instr(LocalGet { local: self.plan })
+            .instr(Const {
+                value: IrValue::I32(i32::from_le_bytes(CGRF_MAGIC)),
+            })
+            .instr(Store {
+                memory: p.memory,
+                kind: StoreKind::I32 { atomic: false },
+                arg: MemArg {
+                    align: 0,
+                    offset: 0,
+                },
+            });
+        seq.instr(LocalGet { local: self.plan })
+            .instr(len_const())
+            .instr(LocalGet { local: ptr })
+            .instr(LocalGet { local: len_local })
+            .instr(LocalGet {
+                local: self.out_ptr_ptr,
+            })
+            .instr(LocalGet {
+                local: self.out_len_ptr,
+            })
+            .instr(Call { func: p.adapt })
+            .instr(LocalSet { local: self.status });
+        seq.instr(LocalGet { local: self.plan })
+            .instr(len_const())
+            .instr(Call { func: p.free });
+    }
+
+    /// `(raw_ptr, raw_len)` = the result slots.
+    fn take_result(&self, seq: &mut InstrSeqBuilder, memory: MemoryId) {
+        for (slot, local) in [
+            (self.out_ptr_ptr, self.raw_ptr),
+            (self.out_len_ptr, self.raw_len),
+        ] {
+            seq.instr(LocalGet { local: slot })
+                .instr(walrus::ir::Load {
+                    memory,
+                    kind: LoadKind::I32 { atomic: false },
+                    arg: MemArg {
+                        align: 2,
+                        offset: 0,
+                    },
+                })
+                .instr(LocalSet { local });
+        }
+    }
+}
+
+/// Emit an adapted link's three functions — the consumer-side shim into the
+/// adapter's memory, the adapting function, the shim on to the provider — and
+/// return the first, which replaces the consumer's import.
+pub(super) fn emit_adapted_link(
+    module: &mut Module,
+    link: &ShimParams,
+    adapter: &super::ResolvedComponent,
+    function: &FunctionAdapter,
+) -> Result<FunctionId> {
+    let provider = emit_shim(
+        module,
+        &ShimParams {
+            consumer_mem: adapter.memory,
+            consumer_alloc: adapter.alloc,
+            forward_status: true,
+            ..*link
+        },
+    );
+    let adapting = emit_adapter_shim(
+        module,
+        &AdapterShimParams {
+            memory: adapter.memory,
+            alloc: adapter.alloc,
+            free: adapter.free,
+            adapt: *adapter.exports.get(EXPORT_ADAPT).ok_or_else(|| {
+                anyhow!("link adapter `{EXPORT_ADAPT}` not found in merged module")
+            })?,
+            provider,
+            adapter: function,
+        },
+    )?;
+    Ok(emit_shim(
+        module,
+        &ShimParams {
+            provider_mem: adapter.memory,
+            provider_alloc: adapter.alloc,
+            provider_free: adapter.free,
+            provider_export: adapting,
+            ..*link
+        },
+    ))
+}
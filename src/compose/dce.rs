@@ -0,0 +1,477 @@
+//! Dead-code elimination for composites, and the size report it feeds.
+//!
+//! After linking, a composite still carries every function of every component:
+//! provider exports nothing links to, each non-entry component's own
+//! `__pack_types`, the allocator of a component no link reaches. [`emit_live`]
+//! keeps what the composite's exports and start function can reach and deletes
+//! the rest:
+//!
+//! - a function is live if a live function calls it or takes its reference,
+//!   or a live table's element segment holds it;
+//! - a memory is live if live code or an export touches it, and it keeps its
+//!   active data segments (code reads them by address, not by id); a table
+//!   keeps its active element segments the same way;
+//! - passive segments and globals are live when live code or a live
+//!   initializer names them.
+//!
+//! Imports are never removed: they are the composite's contract with its host,
+//! and its `__pack_types` lists them.
+//!
+//! The [`SizeReport`] attributes every function and data segment to the
+//! component whose [`Contribution`] brought it into the merge; what compose
+//! generated itself (shims, bridges, adapter plans, the composite's metadata)
+//! is reported under [`GENERATED`].
+
+use super::merge::Contribution;
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use walrus::{
+    ConstExpr, DataId, DataKind, ElementId, ElementItems, ElementKind, ExportItem, FunctionId,
+    FunctionKind, GlobalId, GlobalKind, ImportKind, MemoryId, Module, TableId,
+};
+use wasmparser::{KnownCustom, Name, Parser, Payload, TypeRef};
+
+/// The [`ComponentSize::name`] of everything compose generated itself.
+pub const GENERATED: &str = "(compose)";
+
+/// A composite's size before and after dead-code elimination, whole and per
+/// component.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SizeReport {
+    /// The composite's size in bytes before elimination.
+    pub before: usize,
+    /// The composite's size in bytes as emitted.
+    pub after: usize,
+    /// Each component in merge order (the entry first), then [`GENERATED`].
+    pub components: Vec<ComponentSize>,
+}
+
+/// One component's share of a composite.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ComponentSize {
+    /// The component's name, or [`GENERATED`].
+    pub name: String,
+    /// Before dead-code elimination.
+    pub before: Footprint,
+    /// After dead-code elimination.
+    pub after: Footprint,
+}
+
+/// What a component occupies in a composite.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Footprint {
+    /// Defined functions.
+    pub functions: usize,
+    /// Bytes of function bodies in the code section.
+    pub code: usize,
+    /// Bytes of data segment contents.
+    pub data: usize,
+}
+
+impl Footprint {
+    /// Code and data together.
+    pub fn bytes(&self) -> usize {
+        self.code + self.data
+    }
+}
+
+/// Delete everything unreachable from `module`'s exports, emit it, and report
+/// each of `components`' (`(name, contribution)`, in merge order) size before
+/// and after.
+pub(super) fn emit_live(
+    module: &mut Module,
+    components: &[(String, Contribution)],
+) -> Result<(Vec<u8>, SizeReport)> {
+    let before_total = module.emit_wasm().len();
+    let before = measure(module, components).context("measuring the composite")?;
+    eliminate(module);
+    let after = measure(module, components).context("measuring the pruned composite")?;
+    let wasm = module.emit_wasm();
+
+    let names = components
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .chain([GENERATED]);
+    let report = SizeReport {
+        before: before_total,
+        after: wasm.len(),
+        components: names
+            .zip(before.into_iter().zip(after))
+            .map(|(name, (before, after))| ComponentSize {
+                name: name.to_string(),
+                before,
+                after,
+            })
+            .collect(),
+    };
+    Ok((wasm, report))
+}
+
+/// Each component's [`Footprint`], then the generated code's.
+///
+/// Function body sizes are only known once encoded, so every function is
+/// emitted under a name recording its owner's index and the code section is
+/// read back; the real names are restored afterwards.
+fn measure(module: &mut Module, components: &[(String, Contribution)]) -> Result<Vec<Footprint>> {
+    let generated = components.len();
+    let mut footprints = vec![Footprint::default(); generated + 1];
+
+    let func_owner: HashMap<FunctionId, usize> = components
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (_, c))| c.funcs.iter().map(move |f| (*f, i)))
+        .collect();
+    let data_owner: HashMap<DataId, usize> = components
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (_, c))| c.data.iter().map(move |d| (*d, i)))
+        .collect();
+
+    for data in module.data.iter() {
+        let owner = data_owner.get(&data.id()).copied().unwrap_or(generated);
+        footprints[owner].data += data.value.len();
+    }
+
+    let locals: Vec<FunctionId> = module.funcs.iter_local().map(|(id, _)| id).collect();
+    let mut names = Vec::with_capacity(locals.len());
+    for id in locals {
+        let owner = func_owner.get(&id).copied().unwrap_or(generated);
+        let func = module.funcs.get_mut(id);
+        names.push((id, func.name.replace(owner.to_string())));
+    }
+    let wasm = module.emit_wasm();
+    for (id, name) in names {
+        module.funcs.get_mut(id).name = name;
+    }
+
+    let (mut imported, mut bodies) = (0u32, Vec::new());
+    let mut owners = HashMap::new();
+    for payload in Parser::new(0).parse_all(&wasm) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    if let TypeRef::Func(_) = import?.ty {
+                        imported += 1;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => bodies.push(body.range().len()),
+            Payload::CustomSection(section) => {
+                let KnownCustom::Name(reader) = section.as_known() else {
+                    continue;
+                };
+                for name in reader {
+                    if let Name::Function(map) = name? {
+                        for naming in map {
+                            let naming = naming?;
+                            if let Ok(owner) = naming.name.parse::<usize>() {
+                                owners.insert(naming.index, owner);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for (index, size) in (imported..).zip(bodies) {
+        let owner = owners
+            .get(&index)
+            .copied()
+            .filter(|o| *o <= generated)
+            .unwrap_or(generated);
+        footprints[owner].functions += 1;
+        footprints[owner].code += size;
+    }
+    Ok(footprints)
+}
+
+/// Delete every function, data and element segment, memory, table and global
+/// that nothing live reaches.
+fn eliminate(module: &mut Module) {
+    let live = Live::find(module);
+
+    let funcs: Vec<FunctionId> = module
+        .funcs
+        .iter_local()
+        .map(|(id, _)| id)
+        .filter(|id| !live.funcs.contains(id))
+        .collect();
+    for id in funcs {
+        module.funcs.delete(id);
+    }
+    let data: Vec<DataId> = module
+        .data
+        .iter()
+        .map(|d| d.id())
+        .filter(|id| !live.data.contains(id))
+        .collect();
+    for id in data {
+        module.data.delete(id);
+    }
+    let elements: Vec<ElementId> = module
+        .elements
+        .iter()
+        .map(|e| e.id())
+        .filter(|id| !live.elements.contains(id))
+        .collect();
+    for id in elements {
+        module.elements.delete(id);
+    }
+    let memories: Vec<MemoryId> = module
+        .memories
+        .iter()
+        .filter(|m| m.import.is_none() && !live.memories.contains(&m.id()))
+        .map(|m| m.id())
+        .collect();
+    for id in memories {
+        module.memories.delete(id);
+    }
+    let tables: Vec<TableId> = module
+        .tables
+        .iter()
+        .filter(|t| t.import.is_none() && !live.tables.contains(&t.id()))
+        .map(|t| t.id())
+        .collect();
+    for id in tables {
+        module.tables.delete(id);
+    }
+    let globals: Vec<GlobalId> = module
+        .globals
+        .iter()
+        .filter(|g| matches!(g.kind, GlobalKind::Local(_)) && !live.globals.contains(&g.id()))
+        .map(|g| g.id())
+        .collect();
+    for id in globals {
+        module.globals.delete(id);
+    }
+}
+
+/// The entities reachable from a module's exports, start function and imports.
+#[derive(Default)]
+struct Live {
+    funcs: HashSet<FunctionId>,
+    memories: HashSet<MemoryId>,
+    tables: HashSet<TableId>,
+    globals: HashSet<GlobalId>,
+    data: HashSet<DataId>,
+    elements: HashSet<ElementId>,
+    /// Reached, but not yet followed.
+    queue: Vec<Reached>,
+}
+
+/// A live entity that refers to others.
+enum Reached {
+    Func(FunctionId),
+    Global(GlobalId),
+    Element(ElementId),
+}
+
+impl Live {
+    fn find(module: &Module) -> Self {
+        let mut live = Live::default();
+        for export in module.exports.iter() {
+            match export.item {
+                ExportItem::Function(f) => live.func(f),
+                ExportItem::Memory(m) => live.memory(m),
+                ExportItem::Table(t) => live.table(t),
+                ExportItem::Global(g) => live.global(g),
+            }
+        }
+        for import in module.imports.iter() {
+            match import.kind {
+                ImportKind::Function(f) => live.func(f),
+                ImportKind::Memory(m) => live.memory(m),
+                ImportKind::Table(t) => live.table(t),
+                ImportKind::Global(g) => live.global(g),
+            }
+        }
+        if let Some(start) = module.start {
+            live.func(start);
+        }
+        // Declared segments only license `ref.func`; keep them whole.
+        for element in module.elements.iter() {
+            if let ElementKind::Declared = element.kind {
+                live.element(element.id());
+            }
+        }
+
+        loop {
+            while let Some(reached) = live.queue.pop() {
+                match reached {
+                    Reached::Func(f) => {
+                        if let FunctionKind::Local(local) = &module.funcs.get(f).kind {
+                            walrus::ir::dfs_in_order(&mut live, local, local.entry_block());
+                        }
+                    }
+                    Reached::Global(g) => {
+                        if let GlobalKind::Local(init) = &module.globals.get(g).kind {
+                            live.const_expr(init);
+                        }
+                    }
+                    Reached::Element(e) => {
+                        let element = module.elements.get(e);
+                        if let ElementKind::Active { offset, .. } = &element.kind {
+                            live.const_expr(offset);
+                        }
+                        match &element.items {
+                            ElementItems::Functions(funcs) => {
+                                funcs.iter().for_each(|f| live.func(*f))
+                            }
+                            ElementItems::Expressions(_, exprs) => {
+                                exprs.iter().for_each(|e| live.const_expr(e))
+                            }
+                        }
+                    }
+                }
+            }
+
+            // A live memory or table keeps its active segments.
+            for data in module.data.iter() {
+                if let DataKind::Active { memory, offset } = &data.kind {
+                    if live.memories.contains(memory) && live.data.insert(data.id()) {
+                        live.const_expr(offset);
+                    }
+                }
+            }
+            for element in module.elements.iter() {
+                if let ElementKind::Active { table, .. } = element.kind {
+                    if live.tables.contains(&table) {
+                        live.element(element.id());
+                    }
+                }
+            }
+            if live.queue.is_empty() {
+                return live;
+            }
+        }
+    }
+
+    fn func(&mut self, id: FunctionId) {
+        if self.funcs.insert(id) {
+            self.queue.push(Reached::Func(id));
+        }
+    }
+
+    fn memory(&mut self, id: MemoryId) {
+        self.memories.insert(id);
+    }
+
+    fn table(&mut self, id: TableId) {
+        self.tables.insert(id);
+    }
+
+    fn global(&mut self, id: GlobalId) {
+        if self.globals.insert(id) {
+            self.queue.push(Reached::Global(id));
+        }
+    }
+
+    fn element(&mut self, id: ElementId) {
+        if self.elements.insert(id) {
+            self.queue.push(Reached::Element(id));
+        }
+    }
+
+    fn const_expr(&mut self, expr: &ConstExpr) {
+        match *expr {
+            ConstExpr::Global(g) => self.global(g),
+            ConstExpr::RefFunc(f) => self.func(f),
+            _ => {}
+        }
+    }
+}
+
+impl<'instr> walrus::ir::Visitor<'instr> for Live {
+    fn visit_function_id(&mut self, id: &FunctionId) {
+        self.func(*id);
+    }
+
+    fn visit_memory_id(&mut self, id: &MemoryId) {
+        self.memory(*id);
+    }
+
+    fn visit_table_id(&mut self, id: &TableId) {
+        self.table(*id);
+    }
+
+    fn visit_global_id(&mut self, id: &GlobalId) {
+        self.global(*id);
+    }
+
+    fn visit_data_id(&mut self, id: &DataId) {
+        self.data.insert(*id);
+    }
+
+    fn visit_element_id(&mut self, id: &ElementId) {
+        self.element(*id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{emit_live, GENERATED};
+    use crate::compose::merge::Contribution;
+
+    #[test]
+    fn unreachable_code_data_and_memory_are_dropped() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "host" "unused" (func $unused))
+                (memory $kept (export "memory") 1)
+                (memory $dropped 1)
+                (table 1 funcref)
+                (elem (i32.const 0) $indirect)
+                (type $t (func (result i32)))
+                (data (memory $kept) (i32.const 8) "kept")
+                (data (memory $dropped) (i32.const 8) "dropped")
+                (data $passive "init")
+                (global $g (mut i32) (i32.const 1))
+                (global $dead (mut i32) (i32.const 2))
+                (func $indirect (result i32) (global.get $g))
+                (func $orphan (result i32) (i32.load $dropped (i32.const 8)))
+                (func $init (memory.init $kept $passive (i32.const 0) (i32.const 0) (i32.const 4)))
+                (func (export "run") (result i32)
+                    (call $init)
+                    (call_indirect (type $t) (i32.const 0))))"#,
+        )
+        .unwrap();
+        let mut module = walrus::Module::from_buffer(&wasm).unwrap();
+        let whole = Contribution::whole(&module);
+        let (pruned, report) = emit_live(&mut module, &[("m".to_string(), whole)]).unwrap();
+
+        let module = walrus::Module::from_buffer(&pruned).unwrap();
+        assert_eq!(module.memories.iter().count(), 1);
+        assert_eq!(module.data.iter().count(), 2);
+        assert_eq!(module.globals.iter().count(), 1);
+        assert_eq!(module.imports.iter().count(), 1);
+        let names: Vec<_> = module
+            .funcs
+            .iter()
+            .filter_map(|f| f.name.as_deref())
+            .collect();
+        assert!(!names.contains(&"orphan"), "{names:?}");
+        assert!(names.contains(&"indirect"), "{names:?}");
+
+        let [m, generated] = report.components.as_slice() else {
+            panic!("{report:?}");
+        };
+        assert_eq!((m.before.functions, m.after.functions), (4, 3));
+        assert_eq!((m.before.data, m.after.data), (15, 8));
+        assert!(m.after.code < m.before.code);
+        assert_eq!(generated.name, GENERATED);
+        assert_eq!(report.after, pruned.len());
+        assert!(report.after < report.before);
+
+        let engine = wasmtime::Engine::default();
+        let compiled = wasmtime::Module::new(&engine, &pruned).expect("valid pruned module");
+        let mut store = wasmtime::Store::new(&engine, ());
+        let mut linker = wasmtime::Linker::new(&engine);
+        linker.func_wrap("host", "unused", || {}).unwrap();
+        let instance = linker.instantiate(&mut store, &compiled).unwrap();
+        let run = instance
+            .get_typed_func::<(), i32>(&mut store, "run")
+            .unwrap();
+        assert_eq!(run.call(&mut store, ()).unwrap(), 1);
+    }
+}
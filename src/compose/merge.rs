@@ -0,0 +1,562 @@
+//! Native multi-memory module merge.
+//!
+//! Folds N modules into one, in order, the way `wasm-merge --enable-multimemory`
+//! does for self-contained components: the FIRST module is the base and keeps
+//! every index it had (so its memory stays memory 0 and its exports stay
+//! canonical); each following module's types, imports, functions, globals,
+//! memories, tables, data and element segments are appended after it, with
+//! every reference in its code and initializers remapped to the new ids.
+//!
+//! Nothing is fused: a module's imports stay imports (even when another module
+//! exports a matching name) and duplicate exports are an error, which is why
+//! [`super::compose`] pre-renames non-entry exports to `__c_<name>_*`. Start
+//! functions run in module order. Custom sections other than names are kept
+//! from the base module only.
+//!
+//! The shared-memory linker ([`super::shared`]) drives [`merge_into`] with an
+//! import resolver instead, so a PIC module's `env.memory`, table and base
+//! globals land on entities of the target rather than staying imports.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use walrus::ir::{Instr, InstrSeqId, VisitorMut};
+use walrus::{
+    ConstExpr, DataId, DataKind, ElementId, ElementItems, ElementKind, ExportItem, FunctionBuilder,
+    FunctionId, FunctionKind, GlobalId, GlobalKind, ImportKind, LocalFunction, LocalId, MemoryId,
+    Module, ModuleTypes, TableId, TypeId,
+};
+
+/// Merge `inputs` (`(name, wasm)` in order) into one multi-memory module. The
+/// FIRST input is the base: its memory becomes memory 0.
+///
+/// Returns the module with each input's [`Contribution`], in input order.
+pub(super) fn merge_multimemory(
+    inputs: &[(String, Vec<u8>)],
+) -> Result<(Module, Vec<Contribution>)> {
+    let ((_, base), rest) = inputs
+        .split_first()
+        .ok_or_else(|| anyhow!("nothing to merge"))?;
+    let mut target = Module::from_buffer(base)?;
+    let mut contributions = vec![Contribution::whole(&target)];
+    for (name, wasm) in rest {
+        let source = Module::from_buffer(wasm)?;
+        let contribution = merge_into(&mut target, &source, &mut |_, _, _| Ok(None))
+            .with_context(|| format!("merging `{name}`"))?;
+        contributions.push(contribution);
+    }
+    Ok((target, contributions))
+}
+
+/// The target ids of the functions and data segments one module brought into
+/// a merge, so the composite's size can be attributed to its components.
+#[derive(Debug, Default)]
+pub(super) struct Contribution {
+    pub(super) funcs: Vec<FunctionId>,
+    pub(super) data: Vec<DataId>,
+}
+
+impl Contribution {
+    /// Everything `module` defines: the contribution of a merge's base.
+    pub(super) fn whole(module: &Module) -> Self {
+        Contribution {
+            funcs: module.funcs.iter_local().map(|(id, _)| id).collect(),
+            data: module.data.iter().map(|d| d.id()).collect(),
+        }
+    }
+}
+
+/// Where one of the source module's imports goes in the merge target, when it
+/// is not copied as an import.
+pub(super) enum ImportTarget {
+    Memory(MemoryId),
+    Table(TableId),
+    Global(GlobalId),
+    /// An immutable `i32` global with this value; constant initializers that
+    /// read it (data and element offsets) are folded to the value.
+    Const(i32),
+}
+
+/// Decides each source import's [`ImportTarget`]; `None` keeps it an import.
+pub(super) type ImportResolver<'a> =
+    dyn FnMut(&mut Module, &Module, &walrus::Import) -> Result<Option<ImportTarget>> + 'a;
+
+/// Source-module ids → their counterparts in the merge target.
+#[derive(Default)]
+struct IdMap {
+    types: HashMap<TypeId, TypeId>,
+    funcs: HashMap<FunctionId, FunctionId>,
+    globals: HashMap<GlobalId, GlobalId>,
+    /// Source globals resolved to [`ImportTarget::Const`], by value.
+    consts: HashMap<GlobalId, i32>,
+    memories: HashMap<MemoryId, MemoryId>,
+    tables: HashMap<TableId, TableId>,
+    data: HashMap<DataId, DataId>,
+    elements: HashMap<ElementId, ElementId>,
+}
+
+impl IdMap {
+    fn ty(&mut self, source: &ModuleTypes, target: &mut ModuleTypes, id: TypeId) -> TypeId {
+        *self.types.entry(id).or_insert_with(|| {
+            let ty = source.get(id);
+            target.add(ty.params(), ty.results())
+        })
+    }
+
+    fn func(&self, id: FunctionId) -> FunctionId {
+        self.funcs[&id]
+    }
+
+    fn const_expr(&self, expr: &ConstExpr) -> ConstExpr {
+        match *expr {
+            ConstExpr::Global(g) => match self.consts.get(&g) {
+                Some(&value) => ConstExpr::Value(walrus::ir::Value::I32(value)),
+                None => ConstExpr::Global(self.globals[&g]),
+            },
+            ConstExpr::RefFunc(f) => ConstExpr::RefFunc(self.func(f)),
+            other => other,
+        }
+    }
+}
+
+/// Append everything in `source` to `target`, resolving its imports through
+/// `resolve` first.
+pub(super) fn merge_into(
+    target: &mut Module,
+    source: &Module,
+    resolve: &mut ImportResolver<'_>,
+) -> Result<Contribution> {
+    let mut ids = IdMap::default();
+
+    // Imports first: globals' and segments' initializers may refer to them.
+    for import in source.imports.iter() {
+        if let Some(resolved) = resolve(target, source, import)? {
+            match (&import.kind, resolved) {
+                (ImportKind::Memory(m), ImportTarget::Memory(id)) => {
+                    ids.memories.insert(*m, id);
+                }
+                (ImportKind::Table(t), ImportTarget::Table(id)) => {
+                    ids.tables.insert(*t, id);
+                }
+                (ImportKind::Global(g), ImportTarget::Global(id)) => {
+                    ids.globals.insert(*g, id);
+                }
+                (ImportKind::Global(g), ImportTarget::Const(value)) => {
+                    let id = target.globals.add_local(
+                        walrus::ValType::I32,
+                        false,
+                        false,
+                        ConstExpr::Value(walrus::ir::Value::I32(value)),
+                    );
+                    ids.globals.insert(*g, id);
+                    ids.consts.insert(*g, value);
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "import `{}.{}` resolved to an entity of another kind",
+                        import.module,
+                        import.name
+                    ))
+                }
+            }
+            continue;
+        }
+        let (module, name) = (import.module.as_str(), import.name.as_str());
+        match import.kind {
+            ImportKind::Function(f) => {
+                let ty = ids.ty(&source.types, &mut target.types, source.funcs.get(f).ty());
+                let (id, _) = target.add_import_func(module, name, ty);
+                target.funcs.get_mut(id).name = source.funcs.get(f).name.clone();
+                ids.funcs.insert(f, id);
+            }
+            ImportKind::Global(g) => {
+                let g_src = source.globals.get(g);
+                let (id, _) =
+                    target.add_import_global(module, name, g_src.ty, g_src.mutable, g_src.shared);
+                ids.globals.insert(g, id);
+            }
+            ImportKind::Memory(m) => {
+                let m_src = source.memories.get(m);
+                let (id, _) = target.add_import_memory(
+                    module,
+                    name,
+                    m_src.shared,
+                    m_src.memory64,
+                    m_src.initial,
+                    m_src.maximum,
+                    m_src.page_size_log2,
+                );
+                ids.memories.insert(m, id);
+            }
+            ImportKind::Table(t) => {
+                let t_src = source.tables.get(t);
+                let (id, _) = target.add_import_table(
+                    module,
+                    name,
+                    t_src.table64,
+                    t_src.initial,
+                    t_src.maximum,
+                    t_src.element_ty,
+                );
+                ids.tables.insert(t, id);
+            }
+        }
+    }
+
+    // Reserve an id for every defined function so bodies, element segments
+    // and `ref.func` initializers can refer to functions defined later. The
+    // placeholder bodies are replaced below.
+    let locals: Vec<(FunctionId, &LocalFunction)> = source.funcs.iter_local().collect();
+    for (f, _) in &locals {
+        let ty = source.types.get(source.funcs.get(*f).ty());
+        let id = FunctionBuilder::new(&mut target.types, ty.params(), ty.results())
+            .finish(Vec::new(), &mut target.funcs);
+        target.funcs.get_mut(id).name = source.funcs.get(*f).name.clone();
+        ids.funcs.insert(*f, id);
+    }
+
+    for global in source.globals.iter() {
+        if let GlobalKind::Local(init) = &global.kind {
+            let id = target.globals.add_local(
+                global.ty,
+                global.mutable,
+                global.shared,
+                ids.const_expr(init),
+            );
+            target.globals.get_mut(id).name = global.name.clone();
+            ids.globals.insert(global.id(), id);
+        }
+    }
+    for memory in source.memories.iter().filter(|m| m.import.is_none()) {
+        let id = target.memories.add_local(
+            memory.shared,
+            memory.memory64,
+            memory.initial,
+            memory.maximum,
+            memory.page_size_log2,
+        );
+        target.memories.get_mut(id).name = memory.name.clone();
+        ids.memories.insert(memory.id(), id);
+    }
+    for table in source.tables.iter().filter(|t| t.import.is_none()) {
+        let id = target.tables.add_local(
+            table.table64,
+            table.initial,
+            table.maximum,
+            table.element_ty,
+        );
+        target.tables.get_mut(id).name = table.name.clone();
+        ids.tables.insert(table.id(), id);
+    }
+
+    for data in source.data.iter() {
+        let kind = match &data.kind {
+            DataKind::Active { memory, offset } => DataKind::Active {
+                memory: ids.memories[memory],
+                offset: ids.const_expr(offset),
+            },
+            DataKind::Passive => DataKind::Passive,
+        };
+        let memory = match kind {
+            DataKind::Active { memory, .. } => Some(memory),
+            DataKind::Passive => None,
+        };
+        let id = target.data.add(kind, data.value.clone());
+        target.data.get_mut(id).name = data.name.clone();
+        if let Some(memory) = memory {
+            target.memories.get_mut(memory).data_segments.insert(id);
+        }
+        ids.data.insert(data.id(), id);
+    }
+    for element in source.elements.iter() {
+        let kind = match element.kind {
+            ElementKind::Active { table, offset } => ElementKind::Active {
+                table: ids.tables[&table],
+                offset: ids.const_expr(&offset),
+            },
+            other => other,
+        };
+        let items = match &element.items {
+            ElementItems::Functions(funcs) => {
+                ElementItems::Functions(funcs.iter().map(|f| ids.func(*f)).collect())
+            }
+            ElementItems::Expressions(ty, exprs) => {
+                ElementItems::Expressions(*ty, exprs.iter().map(|e| ids.const_expr(e)).collect())
+            }
+        };
+        let id = target.elements.add(kind, items);
+        target.elements.get_mut(id).name = element.name.clone();
+        if let ElementKind::Active { table, .. } = kind {
+            target.tables.get_mut(table).elem_segments.insert(id);
+        }
+        ids.elements.insert(element.id(), id);
+    }
+
+    let mut contribution = Contribution {
+        funcs: Vec::with_capacity(locals.len()),
+        data: source.data.iter().map(|d| ids.data[&d.id()]).collect(),
+    };
+    for (f, local) in locals {
+        let body = copy_function(target, source, &mut ids, f, local);
+        target.funcs.get_mut(ids.func(f)).kind = FunctionKind::Local(body);
+        contribution.funcs.push(ids.func(f));
+    }
+
+    for export in source.exports.iter() {
+        if target.exports.iter().any(|e| e.name == export.name) {
+            return Err(anyhow!("duplicate export `{}`", export.name));
+        }
+        let item = match export.item {
+            ExportItem::Function(f) => ExportItem::Function(ids.func(f)),
+            ExportItem::Global(g) => ExportItem::Global(ids.globals[&g]),
+            ExportItem::Memory(m) => ExportItem::Memory(ids.memories[&m]),
+            ExportItem::Table(t) => ExportItem::Table(ids.tables[&t]),
+        };
+        target.exports.add(&export.name, item);
+    }
+
+    if let Some(start) = source.start.map(|f| ids.func(f)) {
+        target.start = Some(match target.start {
+            None => start,
+            // Both modules have a start function: run them in module order.
+            Some(first) => {
+                let mut builder = FunctionBuilder::new(&mut target.types, &[], &[]);
+                builder.func_body().call(first).call(start);
+                builder.finish(Vec::new(), &mut target.funcs)
+            }
+        });
+    }
+
+    Ok(contribution)
+}
+
+/// Rebuild `source`'s function `f` inside `target`, remapping every id.
+fn copy_function(
+    target: &mut Module,
+    source: &Module,
+    ids: &mut IdMap,
+    f: FunctionId,
+    local: &LocalFunction,
+) -> LocalFunction {
+    let ty = source.types.get(source.funcs.get(f).ty());
+    let mut builder = FunctionBuilder::new(&mut target.types, ty.params(), ty.results());
+
+    // Recreate the instruction-sequence tree. Instructions are copied as-is
+    // here (still holding source ids, including their branch targets) and
+    // remapped in one pass below.
+    let mut seqs: HashMap<InstrSeqId, InstrSeqId> = HashMap::new();
+    seqs.insert(local.entry_block(), builder.func_body_id());
+    let mut stack = vec![local.entry_block()];
+    while let Some(src) = stack.pop() {
+        let instrs: Vec<Instr> = local
+            .block(src)
+            .instrs
+            .iter()
+            .map(|(i, _)| i.clone())
+            .collect();
+        for instr in &instrs {
+            let nested = match instr {
+                Instr::Block(b) => vec![b.seq],
+                Instr::Loop(l) => vec![l.seq],
+                Instr::IfElse(ie) => vec![ie.consequent, ie.alternative],
+                _ => Vec::new(),
+            };
+            for seq in nested {
+                let ty = local.block(seq).ty;
+                seqs.insert(seq, builder.dangling_instr_seq(ty).id());
+                stack.push(seq);
+            }
+        }
+        let mut dst = builder.instr_seq(seqs[&src]);
+        for instr in instrs {
+            dst.instr(instr);
+        }
+    }
+
+    // Map every local and type the body mentions up front, so the remap
+    // pass below is a pure lookup.
+    let mut used = UsedIds::default();
+    walrus::ir::dfs_in_order(&mut used, local, local.entry_block());
+    for ty in used.types {
+        ids.ty(&source.types, &mut target.types, ty);
+    }
+    let mut locals: HashMap<LocalId, LocalId> = HashMap::new();
+    for id in local.args.iter().copied().chain(used.locals) {
+        locals.entry(id).or_insert_with(|| {
+            let src = source.locals.get(id);
+            let new = target.locals.add(src.ty());
+            target.locals.get_mut(new).name = src.name.clone();
+            new
+        });
+    }
+    let args = local.args.iter().map(|arg| locals[arg]).collect();
+    let mut func = builder.local_func(args);
+
+    let entry = func.entry_block();
+    let mut remap = Remap {
+        ids,
+        seqs: &seqs,
+        locals: &locals,
+    };
+    walrus::ir::dfs_pre_order_mut(&mut remap, &mut func, entry);
+    func
+}
+
+/// Collects the locals and types a source function body refers to.
+#[derive(Default)]
+struct UsedIds {
+    locals: Vec<LocalId>,
+    types: Vec<TypeId>,
+}
+
+impl<'instr> walrus::ir::Visitor<'instr> for UsedIds {
+    fn visit_local_id(&mut self, id: &LocalId) {
+        self.locals.push(*id);
+    }
+
+    fn visit_type_id(&mut self, id: &TypeId) {
+        self.types.push(*id);
+    }
+}
+
+/// Rewrites every id in a copied function body from `source`'s to the target's.
+///
+/// walrus's mutable traversal visits the ids of most instructions twice, so
+/// an id that is not a source id (because it was already rewritten, or it is
+/// the body block type the builder created in the target) is left alone.
+/// Source and target ids never compare equal: they come from different arenas.
+struct Remap<'a> {
+    ids: &'a IdMap,
+    seqs: &'a HashMap<InstrSeqId, InstrSeqId>,
+    locals: &'a HashMap<LocalId, LocalId>,
+}
+
+fn remap<T: Copy + Eq + std::hash::Hash>(map: &HashMap<T, T>, id: &mut T) {
+    if let Some(new) = map.get(id) {
+        *id = *new;
+    }
+}
+
+impl VisitorMut for Remap<'_> {
+    // Branch targets are skipped by the generated visitor.
+    fn visit_instr_mut(&mut self, instr: &mut Instr, _: &mut walrus::InstrLocId) {
+        match instr {
+            Instr::Br(br) => remap(self.seqs, &mut br.block),
+            Instr::BrIf(br) => remap(self.seqs, &mut br.block),
+            Instr::BrTable(br) => {
+                br.blocks.iter_mut().for_each(|b| remap(self.seqs, b));
+                remap(self.seqs, &mut br.default);
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_instr_seq_id_mut(&mut self, id: &mut InstrSeqId) {
+        remap(self.seqs, id);
+    }
+
+    fn visit_local_id_mut(&mut self, id: &mut LocalId) {
+        remap(self.locals, id);
+    }
+
+    fn visit_type_id_mut(&mut self, id: &mut TypeId) {
+        remap(&self.ids.types, id);
+    }
+
+    fn visit_function_id_mut(&mut self, id: &mut FunctionId) {
+        remap(&self.ids.funcs, id);
+    }
+
+    fn visit_global_id_mut(&mut self, id: &mut GlobalId) {
+        remap(&self.ids.globals, id);
+    }
+
+    fn visit_memory_id_mut(&mut self, id: &mut MemoryId) {
+        remap(&self.ids.memories, id);
+    }
+
+    fn visit_table_id_mut(&mut self, id: &mut TableId) {
+        remap(&self.ids.tables, id);
+    }
+
+    fn visit_data_id_mut(&mut self, id: &mut DataId) {
+        remap(&self.ids.data, id);
+    }
+
+    fn visit_element_id_mut(&mut self, id: &mut ElementId) {
+        remap(&self.ids.elements, id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_multimemory;
+
+    fn wat(src: &str) -> Vec<u8> {
+        wat::parse_str(src).unwrap()
+    }
+
+    #[test]
+    fn appends_second_module_with_remapped_indices() {
+        let a = wat(r#"(module
+            (import "host" "log" (func $log (param i32)))
+            (memory (export "memory") 1)
+            (global $g (mut i32) (i32.const 7))
+            (data (i32.const 16) "aa")
+            (func (export "a") (result i32) (global.get $g)))"#);
+        let b = wat(r#"(module
+            (import "host" "tick" (func $tick))
+            (memory (export "b_memory") 2)
+            (global $sp (mut i32) (i32.const 1024))
+            (table 2 funcref)
+            (elem (i32.const 0) $inc $get)
+            (data (i32.const 32) "bb")
+            (type $t (func (result i32)))
+            (func $inc (result i32)
+                (global.set $sp (i32.add (global.get $sp) (i32.const 1)))
+                (global.get $sp))
+            (func $get (result i32)
+                (block $out (result i32)
+                    (br $out (i32.load8_u (i32.const 32)))))
+            (func (export "b") (result i32)
+                (call $tick)
+                (drop (call_indirect (type $t) (i32.const 0)))
+                (call_indirect (type $t) (i32.const 1))))"#);
+
+        let (mut module, contributions) =
+            merge_multimemory(&[("a".into(), a), ("b".into(), b)]).unwrap();
+        assert_eq!(contributions[0].funcs.len(), 1);
+        assert_eq!(contributions[1].funcs.len(), 3);
+        assert_eq!(contributions[1].data.len(), 1);
+        let merged = module.emit_wasm();
+        let module = walrus::Module::from_buffer(&merged).unwrap();
+        assert_eq!(module.memories.iter().count(), 2);
+        assert_eq!(module.imports.iter().count(), 2);
+
+        let engine = wasmtime::Engine::default();
+        let compiled = wasmtime::Module::new(&engine, &merged).expect("valid merged module");
+        let mut store = wasmtime::Store::new(&engine, ());
+        let mut linker = wasmtime::Linker::new(&engine);
+        linker.func_wrap("host", "log", |_: i32| {}).unwrap();
+        linker.func_wrap("host", "tick", || {}).unwrap();
+        let instance = linker.instantiate(&mut store, &compiled).unwrap();
+
+        let a = instance.get_typed_func::<(), i32>(&mut store, "a").unwrap();
+        let b = instance.get_typed_func::<(), i32>(&mut store, "b").unwrap();
+        assert_eq!(a.call(&mut store, ()).unwrap(), 7);
+        // `b` bumps its own global, then reads its data from its own memory.
+        assert_eq!(b.call(&mut store, ()).unwrap(), i32::from(b'b'));
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        assert_eq!(&memory.data(&store)[16..18], b"aa");
+        assert_eq!(memory.data(&store)[32], 0);
+    }
+
+    #[test]
+    fn duplicate_exports_are_rejected() {
+        let a = wat(r#"(module (func (export "f")))"#);
+        let err = merge_multimemory(&[("a".into(), a.clone()), ("b".into(), a)]).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("duplicate export `f`"),
+            "{err:#}"
+        );
+    }
+}
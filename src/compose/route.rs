@@ -0,0 +1,568 @@
+//! The routed backend: a composition run as separate live instances.
+//!
+//! [`CompositionRuntime`] takes the same components and links as
+//! [`compose`](super::compose) but fuses nothing. Every component is
+//! instantiated on its own, providers first, and each linked import is
+//! satisfied by a host function that forwards the call's CGRF bytes to the
+//! provider instance's export and copies the result into the consumer's
+//! memory. Links are resolved and hash-checked exactly as compose does it, so
+//! a graph that routes also fuses.
+//!
+//! Because each component keeps its own instance, one can be replaced while the
+//! others keep running ([`CompositionRuntime::reload`]), and any component can
+//! be called directly to see which side of a link misbehaves.
+//!
+//! A provider's error reaches its consumer as it does through the fused shim:
+//! the negative status, with the message as the output.
+//!
+//! Limits: links must be acyclic, a component in a call cannot be re-entered,
+//! and links that need an adapter are rejected — the routed bytes are passed
+//! through untouched.
+
+use super::{check_graph, resolve_links, verify_link_hashes, Component, GraphLink};
+use crate::abi::Value;
+use crate::runtime::{
+    AsyncInstance, AsyncRuntime, HostLinkerBuilder, Instance, LinkerError, Runtime, StoreData,
+};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+use wasmtime::{Caller, Memory, TypedFunc};
+
+/// Registers the host functions for a component's unlinked imports.
+type Configure<T> = dyn Fn(&mut HostLinkerBuilder<'_, T>) -> Result<(), LinkerError> + Send + Sync;
+
+/// A checked link graph, shared by both runtimes.
+struct Plan {
+    components: Vec<Component>,
+    links: Vec<GraphLink>,
+    entry: String,
+    /// Component indices, every provider ahead of its consumers.
+    order: Vec<usize>,
+}
+
+impl Plan {
+    fn new(components: Vec<Component>, links: &[GraphLink]) -> Result<Self> {
+        check_graph(&components, links)?;
+        let links = resolve_links(&components, links)?;
+        check_links(&components, &links)?;
+        let entry = components.iter().find(|c| c.entry).unwrap().name.clone();
+        let order = provider_order(&components, &links)?;
+        Ok(Plan {
+            components,
+            links,
+            entry,
+            order,
+        })
+    }
+
+    fn index(&self, component: &str) -> Result<usize> {
+        self.components
+            .iter()
+            .position(|c| c.name == component)
+            .ok_or_else(|| anyhow!("no component named `{component}`"))
+    }
+
+    /// Swap `component`'s wasm for `wasm` if every link still checks, returning
+    /// its index and the wasm it had.
+    fn replace(&mut self, component: &str, wasm: Vec<u8>) -> Result<(usize, Vec<u8>)> {
+        let index = self.index(component)?;
+        let old = std::mem::replace(&mut self.components[index].wasm, wasm);
+        match check_links(&self.components, &self.links) {
+            Ok(()) => Ok((index, old)),
+            Err(e) => {
+                self.components[index].wasm = old;
+                Err(e.context(format!("reloading component `{component}`")))
+            }
+        }
+    }
+
+    /// The links satisfying `consumer`'s imports.
+    fn routes<'a>(&'a self, consumer: &'a str) -> impl Iterator<Item = &'a GraphLink> + 'a {
+        self.links.iter().filter(move |l| l.consumer == consumer)
+    }
+}
+
+/// Hash-check every link, rejecting the ones only an adapter could bridge.
+fn check_links(components: &[Component], links: &[GraphLink]) -> Result<()> {
+    let adapters = verify_link_hashes(components, links, &[])?;
+    match links.iter().zip(&adapters).find(|(_, a)| a.is_some()) {
+        Some((link, _)) => Err(anyhow!(
+            "link `{}.{}.{}` needs an adapter, which a routed composition does not support",
+            link.consumer,
+            link.import_module,
+            link.import_name
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Order the components so every provider is instantiated before its
+/// consumers; a cycle cannot be routed.
+fn provider_order(components: &[Component], links: &[GraphLink]) -> Result<Vec<usize>> {
+    fn visit(
+        index: usize,
+        components: &[Component],
+        links: &[GraphLink],
+        state: &mut [u8],
+        order: &mut Vec<usize>,
+    ) -> Result<()> {
+        match state[index] {
+            2 => return Ok(()),
+            1 => {
+                return Err(anyhow!(
+                    "component `{}` is part of a link cycle, which a routed composition \
+                     cannot instantiate",
+                    components[index].name
+                ))
+            }
+            _ => {}
+        }
+        state[index] = 1;
+        for link in links
+            .iter()
+            .filter(|l| l.consumer == components[index].name)
+        {
+            let provider = components
+                .iter()
+                .position(|c| c.name == link.provider)
+                .unwrap();
+            visit(provider, components, links, state, order)?;
+        }
+        state[index] = 2;
+        order.push(index);
+        Ok(())
+    }
+
+    let mut state = vec![0u8; components.len()];
+    let mut order = Vec::with_capacity(components.len());
+    for index in 0..components.len() {
+        visit(index, components, links, &mut state, &mut order)?;
+    }
+    Ok(order)
+}
+
+fn link_name(link: &GraphLink) -> String {
+    format!(
+        "`{}.{}.{}` -> `{}.{}`",
+        link.consumer, link.import_module, link.import_name, link.provider, link.export_name
+    )
+}
+
+fn reentered(component: &str) -> anyhow::Error {
+    anyhow!("component `{component}` is already in a call and cannot be re-entered")
+}
+
+/// The consumer's memory, and a copy of the `len` input bytes at `ptr`.
+fn read_input<T>(caller: &mut Caller<'_, T>, ptr: i32, len: i32) -> Result<(Memory, Vec<u8>)> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|e| e.into_memory())
+        .ok_or_else(|| anyhow!("consumer does not export `memory`"))?;
+    let (start, len) = (ptr as u32 as usize, len as u32 as usize);
+    let input = memory
+        .data(&*caller)
+        .get(start..start + len)
+        .ok_or_else(|| anyhow!("input of {len} bytes at {start} is out of bounds"))?
+        .to_vec();
+    Ok((memory, input))
+}
+
+/// The consumer's `__pack_alloc`, which the routed output is copied into.
+fn consumer_alloc<T>(caller: &mut Caller<'_, T>) -> Result<TypedFunc<i32, i32>> {
+    caller
+        .get_export("__pack_alloc")
+        .and_then(|e| e.into_func())
+        .ok_or_else(|| anyhow!("consumer does not export `__pack_alloc`"))?
+        .typed(&*caller)
+}
+
+/// Store the provider's `output` at the consumer-allocated `ptr` and fill in
+/// the result slots. Returns the status the fused shim would: the provider's
+/// if it failed (`output` is then the message), else 1 for a guest-owned
+/// buffer, which the consumer frees.
+fn write_output<T>(
+    caller: &mut Caller<'_, T>,
+    memory: Memory,
+    ptr: i32,
+    (status, output): (i32, &[u8]),
+    out_ptr_ptr: i32,
+    out_len_ptr: i32,
+) -> Result<i32> {
+    memory.write(&mut *caller, ptr as u32 as usize, output)?;
+    memory.write(
+        &mut *caller,
+        out_ptr_ptr as u32 as usize,
+        &ptr.to_le_bytes(),
+    )?;
+    memory.write(
+        &mut *caller,
+        out_len_ptr as u32 as usize,
+        &(output.len() as i32).to_le_bytes(),
+    )?;
+    Ok(if status < 0 { status } else { 1 })
+}
+
+/// Run a composition's components as separate instances, routing every link
+/// through the host. The links must be acyclic and need no adapter, and a
+/// component already in a call cannot be re-entered.
+///
+/// ```ignore
+/// let mut app = CompositionRuntime::new(components, &[])?;
+/// assert_eq!(app.call_with_value("run", &Value::S64(21))?, Value::S64(42));
+/// app.reload("math", std::fs::read("math-v2.wasm")?)?;
+/// ```
+pub struct CompositionRuntime<T = ()> {
+    runtime: Runtime,
+    plan: Plan,
+    instances: HashMap<String, Arc<Mutex<Instance<T>>>>,
+    state: T,
+    configure: Arc<Configure<T>>,
+}
+
+impl CompositionRuntime {
+    /// Route `components` through `links` (plus every link resolved by hash,
+    /// as for [`compose`](super::compose)), with no host functions.
+    pub fn new(components: Vec<Component>, links: &[GraphLink]) -> Result<Self> {
+        Self::with_host(Runtime::new(), components, links, (), |_| Ok(()))
+    }
+}
+
+impl<T: Clone + Send + 'static> CompositionRuntime<T> {
+    /// Route `components` through `links` under `runtime`. Every instance gets
+    /// its own clone of `state`, and `configure` registers the host functions
+    /// for the imports no link satisfies; it runs once per component.
+    pub fn with_host<F>(
+        runtime: Runtime,
+        components: Vec<Component>,
+        links: &[GraphLink],
+        state: T,
+        configure: F,
+    ) -> Result<Self>
+    where
+        F: Fn(&mut HostLinkerBuilder<'_, T>) -> Result<(), LinkerError> + Send + Sync + 'static,
+    {
+        let mut composition = CompositionRuntime {
+            runtime,
+            plan: Plan::new(components, links)?,
+            instances: HashMap::new(),
+            state,
+            configure: Arc::new(configure),
+        };
+        for index in composition.plan.order.clone() {
+            let instance = composition.instantiate(index)?;
+            let name = composition.plan.components[index].name.clone();
+            composition
+                .instances
+                .insert(name, Arc::new(Mutex::new(instance)));
+        }
+        Ok(composition)
+    }
+
+    /// Every link being routed: the explicit ones, then those resolved by hash.
+    pub fn links(&self) -> &[GraphLink] {
+        &self.plan.links
+    }
+
+    /// Call the entry component's export `func`.
+    pub fn call_with_value(&self, func: &str, input: &Value) -> Result<Value> {
+        self.call_component(&self.plan.entry, func, input)
+    }
+
+    /// Call `component`'s export `func` directly, as if from the host.
+    pub fn call_component(&self, component: &str, func: &str, input: &Value) -> Result<Value> {
+        let mut instance = self
+            .slot(component)?
+            .try_lock()
+            .map_err(|_| reentered(component))?;
+        instance
+            .call_with_value(func, input)
+            .with_context(|| format!("calling `{component}.{func}`"))
+    }
+
+    /// Replace `component` with a new build, `wasm`. The replacement must
+    /// still satisfy the component's links both ways — as a consumer and as a
+    /// provider — with matching hashes. Its consumers call the new instance
+    /// from then on; the old instance and its state are dropped.
+    pub fn reload(&mut self, component: &str, wasm: Vec<u8>) -> Result<()> {
+        let (index, old) = self.plan.replace(component, wasm)?;
+        match self.instantiate(index) {
+            Ok(instance) => {
+                *lock(self.slot(component)?) = instance;
+                Ok(())
+            }
+            Err(e) => {
+                self.plan.components[index].wasm = old;
+                Err(e)
+            }
+        }
+    }
+
+    fn slot(&self, component: &str) -> Result<&Arc<Mutex<Instance<T>>>> {
+        self.instances
+            .get(component)
+            .ok_or_else(|| anyhow!("no component named `{component}`"))
+    }
+
+    /// Instantiate component `index`, its linked imports forwarded to its
+    /// providers' instances.
+    fn instantiate(&self, index: usize) -> Result<Instance<T>> {
+        let component = &self.plan.components[index];
+        let routes = self
+            .plan
+            .routes(&component.name)
+            .map(|link| Ok((link.clone(), self.slot(&link.provider)?.clone())))
+            .collect::<Result<Vec<_>>>()?;
+        let configure = self.configure.clone();
+        let module = self.runtime.load_module(&component.wasm)?;
+        module
+            .instantiate_with_host(self.state.clone(), move |builder| {
+                configure(builder)?;
+                let linker = builder.inner();
+                linker.allow_shadowing(true);
+                for (link, provider) in routes {
+                    let (module, name) = (link.import_module.clone(), link.import_name.clone());
+                    linker
+                        .func_wrap(
+                            &module,
+                            &name,
+                            move |mut caller: Caller<'_, StoreData<T>>,
+                                  in_ptr: i32,
+                                  in_len: i32,
+                                  out_ptr_ptr: i32,
+                                  out_len_ptr: i32|
+                                  -> Result<i32> {
+                                let (memory, input) = read_input(&mut caller, in_ptr, in_len)?;
+                                let (status, output) = provider
+                                    .try_lock()
+                                    .map_err(|_| reentered(&link.provider))?
+                                    .call_with_bytes_status(&link.export_name, &input)
+                                    .with_context(|| format!("routing {}", link_name(&link)))?;
+                                let ptr = consumer_alloc(&mut caller)?
+                                    .call(&mut caller, output.len() as i32)?;
+                                write_output(
+                                    &mut caller,
+                                    memory,
+                                    ptr,
+                                    (status, &output),
+                                    out_ptr_ptr,
+                                    out_len_ptr,
+                                )
+                            },
+                        )
+                        .map_err(|e| LinkerError::FunctionRegistration(e.to_string()))?;
+                }
+                Ok(())
+            })
+            .with_context(|| format!("instantiating component `{}`", component.name))
+    }
+}
+
+/// Lock `slot`, whose instance is still usable if a call through it panicked.
+fn lock<I>(slot: &Mutex<I>) -> MutexGuard<'_, I> {
+    slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// An async instance taken out of its slot for one call. Dropping it puts the
+/// instance back, so a call cancelled mid-await does not leave the component
+/// in a call for good.
+struct Checkout<'a, I> {
+    slot: &'a Mutex<Option<I>>,
+    instance: Option<I>,
+}
+
+impl<'a, I> Checkout<'a, I> {
+    /// Take `component`'s instance out of `slot`, unless it is in a call.
+    fn take(slot: &'a Mutex<Option<I>>, component: &str) -> Result<Self> {
+        let instance = lock(slot).take().ok_or_else(|| reentered(component))?;
+        Ok(Checkout {
+            slot,
+            instance: Some(instance),
+        })
+    }
+}
+
+impl<I> Deref for Checkout<'_, I> {
+    type Target = I;
+
+    fn deref(&self) -> &I {
+        self.instance.as_ref().unwrap()
+    }
+}
+
+impl<I> DerefMut for Checkout<'_, I> {
+    fn deref_mut(&mut self) -> &mut I {
+        self.instance.as_mut().unwrap()
+    }
+}
+
+impl<I> Drop for Checkout<'_, I> {
+    fn drop(&mut self) {
+        *lock(self.slot) = self.instance.take();
+    }
+}
+
+/// The async [`CompositionRuntime`]: every component is an [`AsyncInstance`],
+/// so host functions registered with `func_async` can suspend mid-call.
+///
+/// An instance is taken out of its slot for the length of a call, so no lock
+/// is held across an await; a call reaching a component that is already in one
+/// fails rather than waiting. The instance goes back when the call ends, even
+/// if its future is dropped part way.
+pub struct AsyncCompositionRuntime<T = ()> {
+    runtime: AsyncRuntime,
+    plan: Plan,
+    instances: HashMap<String, Arc<Mutex<Option<AsyncInstance<T>>>>>,
+    state: T,
+    configure: Arc<Configure<T>>,
+}
+
+impl AsyncCompositionRuntime {
+    /// Route `components` through `links`, with no host functions.
+    pub async fn new(components: Vec<Component>, links: &[GraphLink]) -> Result<Self> {
+        Self::with_host(AsyncRuntime::new(), components, links, (), |_| Ok(())).await
+    }
+}
+
+impl<T: Clone + Send + 'static> AsyncCompositionRuntime<T> {
+    /// [`CompositionRuntime::with_host`] under an [`AsyncRuntime`].
+    pub async fn with_host<F>(
+        runtime: AsyncRuntime,
+        components: Vec<Component>,
+        links: &[GraphLink],
+        state: T,
+        configure: F,
+    ) -> Result<Self>
+    where
+        F: Fn(&mut HostLinkerBuilder<'_, T>) -> Result<(), LinkerError> + Send + Sync + 'static,
+    {
+        let mut composition = AsyncCompositionRuntime {
+            runtime,
+            plan: Plan::new(components, links)?,
+            instances: HashMap::new(),
+            state,
+            configure: Arc::new(configure),
+        };
+        for index in composition.plan.order.clone() {
+            let instance = composition.instantiate(index).await?;
+            let name = composition.plan.components[index].name.clone();
+            composition
+                .instances
+                .insert(name, Arc::new(Mutex::new(Some(instance))));
+        }
+        Ok(composition)
+    }
+
+    /// Every link being routed: the explicit ones, then those resolved by hash.
+    pub fn links(&self) -> &[GraphLink] {
+        &self.plan.links
+    }
+
+    /// Call the entry component's export `func`.
+    pub async fn call_with_value(&self, func: &str, input: &Value) -> Result<Value> {
+        self.call_component(&self.plan.entry, func, input).await
+    }
+
+    /// Call `component`'s export `func` directly, as if from the host.
+    pub async fn call_component(
+        &self,
+        component: &str,
+        func: &str,
+        input: &Value,
+    ) -> Result<Value> {
+        let mut instance = Checkout::take(self.slot(component)?, component)?;
+        instance
+            .call_with_value_async(func, input)
+            .await
+            .with_context(|| format!("calling `{component}.{func}`"))
+    }
+
+    /// [`CompositionRuntime::reload`]. Fails if `component` is in a call.
+    pub async fn reload(&mut self, component: &str, wasm: Vec<u8>) -> Result<()> {
+        let (index, old) = self.plan.replace(component, wasm)?;
+        match self.instantiate(index).await {
+            Ok(instance) => {
+                let mut slot = lock(self.slot(component)?);
+                if slot.is_none() {
+                    drop(slot);
+                    self.plan.components[index].wasm = old;
+                    return Err(reentered(component));
+                }
+                *slot = Some(instance);
+                Ok(())
+            }
+            Err(e) => {
+                self.plan.components[index].wasm = old;
+                Err(e)
+            }
+        }
+    }
+
+    fn slot(&self, component: &str) -> Result<&Arc<Mutex<Option<AsyncInstance<T>>>>> {
+        self.instances
+            .get(component)
+            .ok_or_else(|| anyhow!("no component named `{component}`"))
+    }
+
+    /// Instantiate component `index`, its linked imports forwarded to its
+    /// providers' instances.
+    async fn instantiate(&self, index: usize) -> Result<AsyncInstance<T>> {
+        let component = &self.plan.components[index];
+        let routes = self
+            .plan
+            .routes(&component.name)
+            .map(|link| Ok((link.clone(), self.slot(&link.provider)?.clone())))
+            .collect::<Result<Vec<_>>>()?;
+        let configure = self.configure.clone();
+        let module = self.runtime.load_module(&component.wasm)?;
+        module
+            .instantiate_with_host_async(self.state.clone(), move |builder| {
+                configure(builder)?;
+                let linker = builder.inner();
+                linker.allow_shadowing(true);
+                for (link, provider) in routes {
+                    let (module, name) = (link.import_module.clone(), link.import_name.clone());
+                    linker
+                        .func_wrap_async(
+                            &module,
+                            &name,
+                            move |mut caller: Caller<'_, StoreData<T>>,
+                                  (in_ptr, in_len, out_ptr_ptr, out_len_ptr): (
+                                i32,
+                                i32,
+                                i32,
+                                i32,
+                            )| {
+                                let (link, provider) = (link.clone(), provider.clone());
+                                Box::new(async move {
+                                    let (memory, input) = read_input(&mut caller, in_ptr, in_len)?;
+                                    let (status, output) =
+                                        Checkout::take(&provider, &link.provider)?
+                                            .call_with_bytes_status_async(&link.export_name, &input)
+                                            .await
+                                            .with_context(|| {
+                                                format!("routing {}", link_name(&link))
+                                            })?;
+                                    let ptr = consumer_alloc(&mut caller)?
+                                        .call_async(&mut caller, output.len() as i32)
+                                        .await?;
+                                    write_output(
+                                        &mut caller,
+                                        memory,
+                                        ptr,
+                                        (status, &output),
+                                        out_ptr_ptr,
+                                        out_len_ptr,
+                                    )
+                                })
+                            },
+                        )
+                        .map_err(|e| LinkerError::FunctionRegistration(e.to_string()))?;
+                }
+                Ok(())
+            })
+            .await
+            .with_context(|| format!("instantiating component `{}`", component.name))
+    }
+}
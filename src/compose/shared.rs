@@ -0,0 +1,242 @@
+//! Shared-memory linking: the `memory = "shared"` composition mode.
+//!
+//! Every component is a position-independent side module (built with
+//! `relocation-model=pic`, `-shared` and `--import-memory`, the
+//! `docs/pic-composition.md` recipe): instead of owning a memory it imports
+//! `env.memory`, the function table, its `__memory_base` / `__table_base` /
+//! `__stack_pointer` and GOT entries, and leaves placing its data to a loader.
+//! This module is that loader, run ahead of time: it places every component in
+//! ONE memory and ONE table and merges them into a single module.
+//!
+//! The memory is laid out in component order (entry first):
+//!
+//! ```text
+//! [0, LOW_GUARD)              never used, so address 0 stays null
+//! [base_i, base_i + size_i)   component i's static data (`dylink.0` size)
+//! [.., stack_top_i)           component i's own stack, STACK_SIZE bytes
+//! ...                         one data + stack region per component
+//! [end, ..)                   heap
+//! ```
+//!
+//! Each component keeps its linked-in allocator. The GOT's `__heap_end` is
+//! resolved to 0, so no allocator claims a linker-provided heap region; each
+//! grows the shared memory for its own segments instead, and their heaps never
+//! overlap. Data relocations and constructors run from the composite's start
+//! function, in component order.
+
+use super::merge::{merge_into, Contribution, ImportTarget};
+use super::{scoped_name, EXPORT_MEMORY};
+use anyhow::{anyhow, Context, Result};
+use walrus::{ConstExpr, ExportItem, FunctionBuilder, ImportKind, Module, RefType, ValType};
+use wasmparser::{Dylink0Subsection, KnownCustom, Parser, Payload};
+
+/// Bytes left unused at the bottom of the memory.
+const LOW_GUARD: u32 = 1024;
+/// Each component's stack (the size rustc links a wasm binary's stack with).
+const STACK_SIZE: u32 = 1 << 20;
+const PAGE_SIZE: u32 = 1 << 16;
+
+/// Linker scaffolding every side module exports; run by the start function.
+const APPLY_DATA_RELOCS: &str = "__wasm_apply_data_relocs";
+const CALL_CTORS: &str = "__wasm_call_ctors";
+
+/// The `dylink.0` memory info: what a side module needs reserved for it.
+struct MemInfo {
+    memory_size: u32,
+    memory_alignment: u32,
+    table_size: u32,
+}
+
+/// Where one component sits in the shared memory and table.
+struct Placement {
+    memory_base: i32,
+    table_base: i32,
+    stack_top: i32,
+}
+
+/// Whether `wasm` is a PIC side module (it carries a `dylink.0` section).
+pub(super) fn is_side_module(wasm: &[u8]) -> bool {
+    matches!(mem_info(wasm), Ok(Some(_)))
+}
+
+/// Read a side module's `dylink.0` memory info; `None` if it has none.
+fn mem_info(wasm: &[u8]) -> Result<Option<MemInfo>> {
+    for payload in Parser::new(0).parse_all(wasm) {
+        let Payload::CustomSection(section) = payload? else {
+            continue;
+        };
+        let KnownCustom::Dylink0(reader) = section.as_known() else {
+            continue;
+        };
+        for subsection in reader {
+            if let Dylink0Subsection::MemInfo(info) = subsection? {
+                return Ok(Some(MemInfo {
+                    memory_size: info.memory_size,
+                    memory_alignment: info.memory_alignment,
+                    table_size: info.table_size,
+                }));
+            }
+        }
+        // A `dylink.0` section without memory info reserves nothing.
+        return Ok(Some(MemInfo {
+            memory_size: 0,
+            memory_alignment: 0,
+            table_size: 0,
+        }));
+    }
+    Ok(None)
+}
+
+fn align_up(value: u32, align: u32) -> Result<u32> {
+    value
+        .checked_next_multiple_of(align)
+        .ok_or_else(|| anyhow!("shared memory layout exceeds 4 GiB"))
+}
+
+/// Link `inputs` (`(name, wasm)`, entry first, non-entry exports already
+/// scoped) into one module with one memory and one table.
+///
+/// The memory is exported as `memory` and, for each non-entry component, under
+/// its scoped memory name, so every component resolves to it by name. Returns
+/// the module with each input's [`Contribution`], in input order.
+pub(super) fn link_shared(inputs: &[(String, Vec<u8>)]) -> Result<(Module, Vec<Contribution>)> {
+    let mut placements = Vec::with_capacity(inputs.len());
+    let (mut cursor, mut table_cursor) = (LOW_GUARD, 1u32);
+    for (name, wasm) in inputs {
+        let info = mem_info(wasm)?
+            .ok_or_else(|| anyhow!("component `{name}` is not a PIC side module"))?;
+        let base = align_up(cursor, 1 << info.memory_alignment.min(16))?;
+        let stack_bottom = align_up(
+            base.checked_add(info.memory_size)
+                .ok_or_else(|| anyhow!("shared memory layout exceeds 4 GiB"))?,
+            16,
+        )?;
+        cursor = stack_bottom
+            .checked_add(STACK_SIZE)
+            .ok_or_else(|| anyhow!("shared memory layout exceeds 4 GiB"))?;
+        placements.push(Placement {
+            memory_base: base as i32,
+            table_base: table_cursor as i32,
+            stack_top: cursor as i32,
+        });
+        table_cursor += info.table_size;
+    }
+
+    let mut target = Module::default();
+    let pages = u64::from(align_up(cursor, PAGE_SIZE)? / PAGE_SIZE);
+    let memory = target.memories.add_local(false, false, pages, None, None);
+    let table = target
+        .tables
+        .add_local(false, u64::from(table_cursor), None, RefType::Funcref);
+
+    let mut contributions = Vec::with_capacity(inputs.len());
+    for (i, ((name, wasm), place)) in inputs.iter().zip(&placements).enumerate() {
+        let source = Module::from_buffer(wasm)?;
+        // Non-entry exports, the data symbols among them, are already scoped.
+        let exported = |symbol: &str| match i {
+            0 => symbol.to_string(),
+            _ => scoped_name(name, symbol),
+        };
+        let mut resolve = |target: &mut Module, source: &Module, import: &walrus::Import| {
+            let global = |target: &mut Module, value: i32| {
+                let mutable = match import.kind {
+                    ImportKind::Global(g) => source.globals.get(g).mutable,
+                    _ => false,
+                };
+                target.globals.add_local(
+                    ValType::I32,
+                    mutable,
+                    false,
+                    ConstExpr::Value(walrus::ir::Value::I32(value)),
+                )
+            };
+            let resolved = match (import.module.as_str(), import.name.as_str()) {
+                ("env", "memory") => ImportTarget::Memory(memory),
+                ("env", "__indirect_function_table") => ImportTarget::Table(table),
+                ("env", "__memory_base") => ImportTarget::Const(place.memory_base),
+                ("env", "__table_base") => ImportTarget::Const(place.table_base),
+                ("env", "__stack_pointer") => ImportTarget::Global(global(target, place.stack_top)),
+                ("GOT.mem", symbol) => {
+                    let address =
+                        data_symbol(source, symbol, &exported(symbol), place.memory_base)?;
+                    ImportTarget::Global(global(target, address))
+                }
+                ("GOT.func", symbol) => {
+                    return Err(anyhow!(
+                        "function-pointer import `GOT.func.{symbol}` is not supported"
+                    ))
+                }
+                ("env", field) if !matches!(import.kind, ImportKind::Function(_)) => {
+                    return Err(anyhow!("unsupported side-module import `env.{field}`"))
+                }
+                _ => return Ok(None),
+            };
+            Ok(Some(resolved))
+        };
+        let contribution = merge_into(&mut target, &source, &mut resolve)
+            .with_context(|| format!("linking `{name}` into the shared memory"))?;
+        contributions.push(contribution);
+    }
+
+    // Relocate every component's data, then run its constructors.
+    let mut builder = FunctionBuilder::new(&mut target.types, &[], &[]);
+    builder.name("__pack_shared_init".to_string());
+    let mut scaffolding = Vec::new();
+    for (i, (name, _)) in inputs.iter().enumerate() {
+        for export in [APPLY_DATA_RELOCS, CALL_CTORS] {
+            let export = if i == 0 {
+                export.to_string()
+            } else {
+                scoped_name(name, export)
+            };
+            let Some(e) = target.exports.iter().find(|e| e.name == export) else {
+                continue;
+            };
+            if let ExportItem::Function(f) = e.item {
+                builder.func_body().call(f);
+            }
+            if i == 0 {
+                scaffolding.push(e.id());
+            }
+        }
+    }
+    let init = builder.finish(Vec::new(), &mut target.funcs);
+    target.start = Some(init);
+    // Running the entry's again from outside would re-apply its relocations.
+    for id in scaffolding {
+        target.exports.delete(id);
+    }
+
+    target.exports.add(EXPORT_MEMORY, memory);
+    for (name, _) in &inputs[1..] {
+        target
+            .exports
+            .add(&scoped_name(name, EXPORT_MEMORY), memory);
+    }
+    Ok((target, contributions))
+}
+
+/// The absolute address of data symbol `symbol` for a component placed at
+/// `memory_base`: the module exports each symbol it defines (as `export`) as a
+/// global holding its offset from the base.
+///
+/// `__heap_end` is 0 (see the module docs); any other symbol the component
+/// does not define itself cannot be resolved, since components share no data.
+fn data_symbol(source: &Module, symbol: &str, export: &str, memory_base: i32) -> Result<i32> {
+    let offset = source.exports.iter().find_map(|e| match e.item {
+        ExportItem::Global(g) if e.name == export => match source.globals.get(g).kind {
+            walrus::GlobalKind::Local(ConstExpr::Value(walrus::ir::Value::I32(offset))) => {
+                Some(offset)
+            }
+            _ => None,
+        },
+        _ => None,
+    });
+    match offset {
+        Some(offset) => Ok(memory_base.wrapping_add(offset)),
+        None if symbol == "__heap_end" => Ok(0),
+        None => Err(anyhow!(
+            "data symbol `{symbol}` is not defined by the component"
+        )),
+    }
+}
@@ -0,0 +1,629 @@
+//! Engine abstraction
+//!
+//! [`WasmEngine`] is the small surface packr needs from a WebAssembly
+//! backend: compile a module, instantiate it against a set of
+//! [`HostFunctions`], read and write linear memory, and call exports with
+//! core values. Everything above that — the Pack calling convention,
+//! `call_with_value`, typed host functions — is written once against the
+//! [`Guest`] trait, so it runs unchanged on every backend.
+//!
+//! Backends are selected with cargo features:
+//!
+//! - `wasmtime` (default): [`WasmtimeEngine`], a JIT.
+//! - `wasmi`: [`WasmiEngine`], an interpreter for environments where JIT
+//!   is not allowed.
+//!
+//! ```ignore
+//! use packr::engine::{Guest, HostFunctions, WasmEngine, WasmiEngine};
+//!
+//! let engine = WasmiEngine::new();
+//! let module = engine.compile(&wasm_bytes)?;
+//!
+//! let mut host = HostFunctions::new();
+//! host.func_typed("theater:simple/runtime", "double", |n: i64| n * 2);
+//!
+//! let mut instance = engine.instantiate(&module, &host)?;
+//! let output = instance.call_with_value("process", &Value::S64(21))?;
+//! ```
+//!
+//! The wasmtime-only [`crate::runtime`] API (async host functions, call
+//! interceptors, epoch deadlines) is built directly on wasmtime and is not
+//! routed through this trait.
+
+#[cfg(feature = "wasmi")]
+mod wasmi;
+#[cfg(feature = "wasmtime")]
+mod wasmtime;
+
+#[cfg(feature = "wasmi")]
+pub use self::wasmi::{WasmiCaller, WasmiEngine, WasmiInstance};
+#[cfg(feature = "wasmtime")]
+pub use self::wasmtime::{WasmtimeCaller, WasmtimeEngine, WasmtimeInstance};
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+use thiserror::Error;
+
+use crate::abi::{decode, encode_into, encoded_len, Value};
+
+// ============================================================================
+// Calling Convention Constants
+// ============================================================================
+
+/// Host scratch input buffer (0-16KB), used only for guests that do not
+/// export `__pack_alloc`
+pub const INPUT_BUFFER_OFFSET: usize = 0;
+
+/// Host scratch result pointer slot (4 bytes at 16KB), used only for guests
+/// that do not export `__pack_alloc`
+pub const RESULT_PTR_OFFSET: usize = 16 * 1024;
+
+/// Host scratch result length slot (4 bytes at 16KB + 4), used only for
+/// guests that do not export `__pack_alloc`
+pub const RESULT_LEN_OFFSET: usize = 16 * 1024 + 4;
+
+/// Size of the `(out_ptr, out_len)` slot pair a Pack ABI export writes its
+/// result location into. Guests that export `__pack_alloc` get the input
+/// buffer and this pair from their own allocator, so no call depends on a
+/// fixed address and nested calls cannot clobber each other's buffers.
+pub const RESULT_SLOTS_SIZE: usize = 8;
+
+// Legacy constants - kept for backward compatibility during transition
+/// Default output buffer offset (16KB) - DEPRECATED: use RESULT_PTR_OFFSET
+pub const OUTPUT_BUFFER_OFFSET: usize = 16 * 1024;
+
+/// Max size of a host-function return written to the fixed, host-owned scratch
+/// buffer (`OUTPUT_BUFFER_OFFSET+8`). Only the SYNC host-fn path uses that buffer
+/// and it lives in low memory below the PIC package base, so this stays a
+/// conservative fail-safe. The ASYNC path guest-allocates on the heap and is
+/// UNBOUNDED — that is where theater's large returns ride.
+pub const OUTPUT_BUFFER_CAPACITY: usize = 32 * 1024;
+
+// ============================================================================
+// Errors and core values
+// ============================================================================
+
+#[derive(Error, Debug)]
+pub enum EngineError {
+    #[error("Compilation failed: {0}")]
+    Compile(String),
+
+    #[error("Instantiation failed: {0}")]
+    Instantiate(String),
+
+    #[error("Function not found: {0}")]
+    FunctionNotFound(String),
+
+    #[error("Type mismatch: {0}")]
+    TypeMismatch(String),
+
+    #[error("WASM execution error: {0}")]
+    Wasm(String),
+
+    #[error("Memory error: {0}")]
+    Memory(String),
+
+    #[error("ABI error: {0}")]
+    Abi(String),
+}
+
+/// A core WebAssembly value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Val {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+/// A core WebAssembly value type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+/// A Rust scalar that maps onto one core value.
+pub trait WasmVal: Sized {
+    const TYPE: ValType;
+    fn into_val(self) -> Val;
+    fn from_val(val: Val) -> Option<Self>;
+}
+
+macro_rules! wasm_val {
+    ($($ty:ty => $variant:ident),*) => {$(
+        impl WasmVal for $ty {
+            const TYPE: ValType = ValType::$variant;
+            fn into_val(self) -> Val {
+                Val::$variant(self)
+            }
+            fn from_val(val: Val) -> Option<Self> {
+                match val {
+                    Val::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+wasm_val!(i32 => I32, i64 => I64, f32 => F32, f64 => F64);
+
+/// Parameters of a typed call: a scalar or a tuple of scalars.
+pub trait WasmParams {
+    fn into_vals(self) -> Vec<Val>;
+}
+
+/// Results of a typed call: `()`, a scalar, or a tuple of scalars.
+pub trait WasmResults: Sized {
+    fn from_vals(vals: &[Val]) -> Option<Self>;
+}
+
+impl WasmParams for () {
+    fn into_vals(self) -> Vec<Val> {
+        Vec::new()
+    }
+}
+
+impl WasmResults for () {
+    fn from_vals(vals: &[Val]) -> Option<Self> {
+        vals.is_empty().then_some(())
+    }
+}
+
+impl<V: WasmVal> WasmParams for V {
+    fn into_vals(self) -> Vec<Val> {
+        vec![self.into_val()]
+    }
+}
+
+impl<V: WasmVal> WasmResults for V {
+    fn from_vals(vals: &[Val]) -> Option<Self> {
+        match vals {
+            [val] => V::from_val(*val),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! wasm_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: WasmVal),+> WasmParams for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn into_vals(self) -> Vec<Val> {
+                let ($($name,)+) = self;
+                vec![$($name.into_val()),+]
+            }
+        }
+
+        impl<$($name: WasmVal),+> WasmResults for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn from_vals(vals: &[Val]) -> Option<Self> {
+                match vals {
+                    [$($name),+] => Some(($($name::from_val(*$name)?,)+)),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+wasm_tuple!(A);
+wasm_tuple!(A, B);
+wasm_tuple!(A, B, C);
+wasm_tuple!(A, B, C, D);
+
+// ============================================================================
+// Backend traits
+// ============================================================================
+
+/// A WebAssembly backend.
+pub trait WasmEngine: Send + Sync {
+    type Module;
+    type Instance: Guest;
+
+    /// Compile (or, for an interpreter, validate and translate) a module.
+    fn compile(&self, wasm_bytes: &[u8]) -> Result<Self::Module, EngineError>;
+
+    /// Instantiate `module`, resolving its imports from `host`, and run its
+    /// start function.
+    fn instantiate(
+        &self,
+        module: &Self::Module,
+        host: &HostFunctions,
+    ) -> Result<Self::Instance, EngineError>;
+}
+
+/// Access to a running guest: an instance from the host's side, or the
+/// calling instance from inside a host function.
+///
+/// Backends implement the three required methods; the Pack ABI helpers are
+/// provided on top of them.
+pub trait Guest {
+    /// The guest's exported `memory`.
+    fn memory(&mut self) -> Result<&mut [u8], EngineError>;
+
+    /// Call an exported function with core values.
+    fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, EngineError>;
+
+    /// Whether the guest exports `name`.
+    fn has_export(&mut self, name: &str) -> bool;
+
+    /// Read bytes from the guest's memory
+    fn read_memory(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, EngineError> {
+        Ok(memory_range(self.memory()?, offset, len)?.to_vec())
+    }
+
+    /// Write bytes to the guest's memory at the given offset
+    fn write_memory(&mut self, offset: usize, data: &[u8]) -> Result<(), EngineError> {
+        memory_range(self.memory()?, offset, data.len())?.copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Call an export with typed parameters and results.
+    ///
+    /// ```ignore
+    /// let sum: i32 = instance.call_typed("add", (2, 3))?;
+    /// ```
+    fn call_typed<P: WasmParams, R: WasmResults>(
+        &mut self,
+        name: &str,
+        params: P,
+    ) -> Result<R, EngineError>
+    where
+        Self: Sized,
+    {
+        let results = self.call(name, &params.into_vals())?;
+        R::from_vals(&results).ok_or_else(|| {
+            EngineError::TypeMismatch(format!("unexpected results from '{name}': {results:?}"))
+        })
+    }
+
+    /// Encode a Value and write it to memory at the given offset.
+    /// Returns the number of bytes written.
+    fn write_value(&mut self, offset: usize, value: &Value) -> Result<usize, EngineError> {
+        let len = encoded_len(value).map_err(|e| EngineError::Abi(e.to_string()))?;
+        let dst = memory_range(self.memory()?, offset, len)?;
+        encode_into(value, dst).map_err(|e| EngineError::Abi(e.to_string()))
+    }
+
+    /// Read bytes from memory and decode them as a Value.
+    fn read_value(&mut self, offset: usize, len: usize) -> Result<Value, EngineError> {
+        let bytes = memory_range(self.memory()?, offset, len)?;
+        decode(bytes).map_err(|e| EngineError::Abi(e.to_string()))
+    }
+
+    /// Call a function using the Pack ABI.
+    ///
+    /// If the guest exports `__pack_alloc`, the input buffer and the result
+    /// slots are allocated by the guest. Otherwise they use the host scratch
+    /// region at `INPUT_BUFFER_OFFSET` / `RESULT_PTR_OFFSET`.
+    ///
+    /// The WASM function signature is `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`:
+    /// - Returns: 0 on success, -1 on error (error message in ptr/len)
+    fn call_with_value(&mut self, name: &str, input: &Value) -> Result<Value, EngineError>
+    where
+        Self: Sized,
+    {
+        let input_len = encoded_len(input).map_err(|e| EngineError::Abi(e.to_string()))?;
+        let (out_ptr, out_len) = now(call_pack_abi(self, name, input_len, |data, in_ptr| {
+            let dst = memory_range(data, in_ptr, input_len)?;
+            encode_into(input, dst).map_err(|e| EngineError::Abi(e.to_string()))?;
+            Ok(())
+        }))?;
+
+        let result = self.read_value(out_ptr, out_len)?;
+        self.free(out_ptr, out_len)?;
+        Ok(result)
+    }
+
+    /// Allocate `size` bytes through the guest's `__pack_alloc`.
+    fn alloc(&mut self, size: usize) -> Result<usize, EngineError>
+    where
+        Self: Sized,
+    {
+        let ptr: i32 = self.call_typed("__pack_alloc", size as i32)?;
+        if ptr == 0 {
+            return Err(EngineError::Memory("Guest allocation failed".into()));
+        }
+        Ok(ptr as u32 as usize)
+    }
+
+    /// Hand a guest-allocated buffer back through `__pack_free`, if exported.
+    fn free(&mut self, ptr: usize, len: usize) -> Result<(), EngineError>
+    where
+        Self: Sized,
+    {
+        if self.has_export("__pack_free") {
+            self.call_typed::<_, ()>("__pack_free", (ptr as i32, len as i32))?;
+        }
+        Ok(())
+    }
+}
+
+fn memory_range(data: &mut [u8], offset: usize, len: usize) -> Result<&mut [u8], EngineError> {
+    let size = data.len();
+    data.get_mut(offset..offset.saturating_add(len))
+        .ok_or_else(|| {
+            EngineError::Memory(format!(
+                "out of bounds access: {len} bytes at {offset} (memory is {size} bytes)"
+            ))
+        })
+}
+
+// ============================================================================
+// The Pack ABI call sequence
+// ============================================================================
+
+/// The guest operations one Pack ABI call is made of. Every [`Guest`] and the
+/// wasmtime runtime's instances implement it, and all of them make the call
+/// through [`call_pack_abi`]. The calls into the guest are `async` so the
+/// async runtime can await wasmtime; the sync implementations never suspend
+/// and are driven by [`now`].
+pub(crate) trait PackAbiCalls {
+    type Error;
+
+    /// Whether the guest exports `name`.
+    fn has_export(&mut self, name: &str) -> bool;
+
+    /// The guest's linear memory.
+    fn memory(&mut self) -> Result<&mut [u8], Self::Error>;
+
+    /// Copy `len` bytes at `offset` out of the guest's memory.
+    fn read(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, Self::Error>;
+
+    /// Call a Pack ABI export `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`.
+    async fn call_export(
+        &mut self,
+        name: &str,
+        args: (i32, i32, i32, i32),
+    ) -> Result<i32, Self::Error>;
+
+    /// Allocate `size` bytes through the guest's `__pack_alloc`.
+    async fn alloc(&mut self, size: usize) -> Result<usize, Self::Error>;
+
+    /// Hand a buffer back through `__pack_free`, if the guest exports it.
+    async fn free(&mut self, ptr: usize, len: usize) -> Result<(), Self::Error>;
+
+    /// The error for an export that returned a non-zero status with `message`.
+    fn returned_error(name: &str, message: &str) -> Self::Error;
+}
+
+/// One Pack ABI call of `name`: [`call_pack_abi_status`], then check the
+/// status. Returns the output's `(ptr, len)`, which the caller reads and frees.
+pub(crate) async fn call_pack_abi<G: PackAbiCalls>(
+    guest: &mut G,
+    name: &str,
+    input_len: usize,
+    write_input: impl FnOnce(&mut [u8], usize) -> Result<(), G::Error>,
+) -> Result<(usize, usize), G::Error> {
+    let (status, out_ptr, out_len) =
+        call_pack_abi_status(guest, name, input_len, write_input).await?;
+
+    // On error the output is the message
+    if status != 0 {
+        let message = guest.read(out_ptr, out_len)?;
+        guest.free(out_ptr, out_len).await.ok();
+        return Err(G::returned_error(name, &String::from_utf8_lossy(&message)));
+    }
+
+    Ok((out_ptr, out_len))
+}
+
+/// One Pack ABI call of `name`: negotiate its buffers, let `write_input` fill
+/// the `input_len`-byte input at the offset it is given, and call. Returns the
+/// export's status and its output's `(ptr, len)` — on error, the message —
+/// which the caller reads and frees.
+///
+/// A guest that exports `__pack_alloc` provides the input buffer and the
+/// result slots itself, so nothing sits at a fixed address and a nested call
+/// cannot clobber them; a guest without one gets the host scratch region.
+/// Freeing the call's own buffers is best effort.
+pub(crate) async fn call_pack_abi_status<G: PackAbiCalls>(
+    guest: &mut G,
+    name: &str,
+    input_len: usize,
+    write_input: impl FnOnce(&mut [u8], usize) -> Result<(), G::Error>,
+) -> Result<(i32, usize, usize), G::Error> {
+    let guest_allocated = guest.has_export("__pack_alloc");
+    let (in_ptr, slots) = if guest_allocated {
+        (
+            guest.alloc(input_len).await?,
+            guest.alloc(RESULT_SLOTS_SIZE).await?,
+        )
+    } else {
+        (INPUT_BUFFER_OFFSET, RESULT_PTR_OFFSET)
+    };
+
+    write_input(guest.memory()?, in_ptr)?;
+
+    let status = guest
+        .call_export(
+            name,
+            (
+                in_ptr as i32,
+                input_len as i32,
+                slots as i32,
+                (slots + 4) as i32,
+            ),
+        )
+        .await?;
+
+    // Read result ptr/len from the slots, then release the call's buffers
+    let slot_bytes = guest.read(slots, RESULT_SLOTS_SIZE)?;
+    let word = |at: usize| {
+        u32::from_le_bytes([
+            slot_bytes[at],
+            slot_bytes[at + 1],
+            slot_bytes[at + 2],
+            slot_bytes[at + 3],
+        ]) as usize
+    };
+    let (out_ptr, out_len) = (word(0), word(4));
+    if guest_allocated {
+        guest.free(slots, RESULT_SLOTS_SIZE).await.ok();
+        guest.free(in_ptr, input_len).await.ok();
+    }
+
+    Ok((status, out_ptr, out_len))
+}
+
+/// Run a future that never suspends to completion, as the futures of the sync
+/// [`PackAbiCalls`] implementations are.
+pub(crate) fn now<F: Future>(future: F) -> F::Output {
+    let mut cx = Context::from_waker(Waker::noop());
+    match pin!(future).poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("sync Pack ABI call suspended"),
+    }
+}
+
+impl<G: Guest> PackAbiCalls for G {
+    type Error = EngineError;
+
+    fn has_export(&mut self, name: &str) -> bool {
+        Guest::has_export(self, name)
+    }
+
+    fn memory(&mut self) -> Result<&mut [u8], EngineError> {
+        Guest::memory(self)
+    }
+
+    fn read(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, EngineError> {
+        self.read_memory(offset, len)
+    }
+
+    async fn call_export(
+        &mut self,
+        name: &str,
+        args: (i32, i32, i32, i32),
+    ) -> Result<i32, EngineError> {
+        self.call_typed(name, args)
+    }
+
+    async fn alloc(&mut self, size: usize) -> Result<usize, EngineError> {
+        Guest::alloc(self, size)
+    }
+
+    async fn free(&mut self, ptr: usize, len: usize) -> Result<(), EngineError> {
+        Guest::free(self, ptr, len)
+    }
+
+    fn returned_error(name: &str, message: &str) -> EngineError {
+        EngineError::Wasm(format!("function '{name}' returned error: {message}"))
+    }
+}
+
+// ============================================================================
+// Host functions
+// ============================================================================
+
+/// Callback behind a [`HostFunction`]. It receives the calling guest and the
+/// core arguments, and returns the core results.
+pub type HostCallback =
+    Arc<dyn Fn(&mut dyn Guest, &[Val]) -> Result<Vec<Val>, EngineError> + Send + Sync>;
+
+/// One host import: `module.name` with its core signature.
+#[derive(Clone)]
+pub struct HostFunction {
+    pub module: String,
+    pub name: String,
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+    pub callback: HostCallback,
+}
+
+/// Backend-neutral host imports, handed to [`WasmEngine::instantiate`].
+///
+/// State shared with the host functions lives in their closures (e.g. an
+/// `Arc<Mutex<_>>`), so one set can be instantiated any number of times.
+#[derive(Clone, Default)]
+pub struct HostFunctions {
+    funcs: Vec<HostFunction>,
+}
+
+impl HostFunctions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a host function with a raw core signature.
+    pub fn func_raw<F>(
+        &mut self,
+        module: &str,
+        name: &str,
+        params: &[ValType],
+        results: &[ValType],
+        func: F,
+    ) -> &mut Self
+    where
+        F: Fn(&mut dyn Guest, &[Val]) -> Result<Vec<Val>, EngineError> + Send + Sync + 'static,
+    {
+        self.funcs.push(HostFunction {
+            module: module.to_string(),
+            name: name.to_string(),
+            params: params.to_vec(),
+            results: results.to_vec(),
+            callback: Arc::new(func),
+        });
+        self
+    }
+
+    /// Register a typed host function with automatic Graph ABI encode/decode.
+    ///
+    /// Same calling convention as `InterfaceBuilder::func_typed`:
+    /// `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`, with the
+    /// encoded return written to the host-owned scratch buffer at
+    /// `OUTPUT_BUFFER_OFFSET + 8`. A decode or conversion failure traps the
+    /// call with the error message.
+    pub fn func_typed<P, R, F>(&mut self, module: &str, name: &str, func: F) -> &mut Self
+    where
+        P: TryFrom<Value> + 'static,
+        <P as TryFrom<Value>>::Error: std::fmt::Debug,
+        R: Into<Value> + 'static,
+        F: Fn(P) -> R + Send + Sync + 'static,
+    {
+        let func_name = format!("{module}.{name}");
+        self.func_raw(
+            module,
+            name,
+            &[ValType::I32; 4],
+            &[ValType::I32],
+            move |guest, args| {
+                let [Val::I32(in_ptr), Val::I32(in_len), Val::I32(out_ptr_ptr), Val::I32(out_len_ptr)] =
+                    *args
+                else {
+                    return Err(EngineError::TypeMismatch(format!(
+                        "{func_name}: expected four i32 arguments"
+                    )));
+                };
+                let input = guest.read_value(in_ptr as u32 as usize, in_len as u32 as usize)?;
+                let input = P::try_from(input).map_err(|e| {
+                    EngineError::TypeMismatch(format!("{func_name}: {e:?}"))
+                })?;
+                let output: Value = func(input).into();
+
+                let len = encoded_len(&output).map_err(|e| EngineError::Abi(e.to_string()))?;
+                if len > OUTPUT_BUFFER_CAPACITY {
+                    return Err(EngineError::Memory(format!(
+                        "host return {len} bytes exceeds capacity {OUTPUT_BUFFER_CAPACITY}"
+                    )));
+                }
+                let data_ptr = OUTPUT_BUFFER_OFFSET + 8;
+                guest.write_value(data_ptr, &output)?;
+                guest.write_memory(out_ptr_ptr as u32 as usize, &(data_ptr as i32).to_le_bytes())?;
+                guest.write_memory(out_len_ptr as u32 as usize, &(len as i32).to_le_bytes())?;
+                Ok(vec![Val::I32(0)])
+            },
+        )
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HostFunction> {
+        self.funcs.iter()
+    }
+}
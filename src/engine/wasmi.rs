@@ -0,0 +1,197 @@
+//! wasmi backend for [`WasmEngine`].
+//!
+//! wasmi is a pure interpreter: no JIT, no executable memory, so it runs
+//! where wasmtime cannot (iOS, locked-down sandboxes, some embedded targets).
+
+use wasmi::core::{ValType as WasmiValType, F32, F64};
+use wasmi::{Caller, Config, Engine, Extern, FuncType, Instance, Linker, Module, Store};
+
+use super::{EngineError, Guest, HostFunctions, Val, ValType, WasmEngine};
+
+/// Interpreter backend built on wasmi. wasmi does not implement
+/// multi-memory, so merged composites that keep one memory per component
+/// still need the wasmtime backend.
+#[derive(Clone)]
+pub struct WasmiEngine {
+    engine: Engine,
+}
+
+impl WasmiEngine {
+    pub fn new() -> Self {
+        Self {
+            engine: Engine::new(&Config::default()),
+        }
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+}
+
+impl Default for WasmiEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WasmEngine for WasmiEngine {
+    type Module = Module;
+    type Instance = WasmiInstance;
+
+    fn compile(&self, wasm_bytes: &[u8]) -> Result<Module, EngineError> {
+        Module::new(&self.engine, wasm_bytes).map_err(|e| EngineError::Compile(e.to_string()))
+    }
+
+    fn instantiate(
+        &self,
+        module: &Module,
+        host: &HostFunctions,
+    ) -> Result<WasmiInstance, EngineError> {
+        let mut store = Store::new(&self.engine, ());
+        let mut linker = Linker::new(&self.engine);
+
+        for func in host.iter() {
+            let ty = FuncType::new(
+                func.params.iter().map(|t| to_val_type(*t)),
+                func.results.iter().map(|t| to_val_type(*t)),
+            );
+            let callback = func.callback.clone();
+            linker
+                .func_new(
+                    &func.module,
+                    &func.name,
+                    ty,
+                    move |caller, params, results| {
+                        let args: Vec<Val> =
+                            params.iter().map(from_val).collect::<Result<_, _>>()?;
+                        let mut guest = WasmiCaller { caller };
+                        let values = callback(&mut guest, &args)?;
+                        write_results(&values, results)?;
+                        Ok(())
+                    },
+                )
+                .map_err(|e| EngineError::Instantiate(e.to_string()))?;
+        }
+
+        let instance = linker
+            .instantiate(&mut store, module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| EngineError::Instantiate(e.to_string()))?;
+        Ok(WasmiInstance { store, instance })
+    }
+}
+
+impl From<EngineError> for wasmi::Error {
+    fn from(e: EngineError) -> Self {
+        wasmi::Error::new(e.to_string())
+    }
+}
+
+/// A module instantiated by [`WasmiEngine`].
+pub struct WasmiInstance {
+    store: Store<()>,
+    instance: Instance,
+}
+
+impl Guest for WasmiInstance {
+    fn memory(&mut self) -> Result<&mut [u8], EngineError> {
+        let memory = self
+            .instance
+            .get_memory(&self.store, "memory")
+            .ok_or_else(|| EngineError::Memory("No memory export".into()))?;
+        Ok(memory.data_mut(&mut self.store))
+    }
+
+    fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, EngineError> {
+        let func = self
+            .instance
+            .get_func(&self.store, name)
+            .ok_or_else(|| EngineError::FunctionNotFound(name.to_string()))?;
+        let params: Vec<wasmi::Val> = args.iter().map(|v| to_val(*v)).collect();
+        let mut results = vec![wasmi::Val::I32(0); func.ty(&self.store).results().len()];
+        func.call(&mut self.store, &params, &mut results)
+            .map_err(|e| EngineError::Wasm(e.to_string()))?;
+        results.iter().map(from_val).collect()
+    }
+
+    fn has_export(&mut self, name: &str) -> bool {
+        self.instance.get_export(&self.store, name).is_some()
+    }
+}
+
+/// The calling instance, as seen from inside a host function.
+pub struct WasmiCaller<'a> {
+    caller: Caller<'a, ()>,
+}
+
+impl Guest for WasmiCaller<'_> {
+    fn memory(&mut self) -> Result<&mut [u8], EngineError> {
+        let memory = self
+            .caller
+            .get_export("memory")
+            .and_then(Extern::into_memory)
+            .ok_or_else(|| EngineError::Memory("No memory export".into()))?;
+        Ok(memory.data_mut(&mut self.caller))
+    }
+
+    fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, EngineError> {
+        let func = self
+            .caller
+            .get_export(name)
+            .and_then(Extern::into_func)
+            .ok_or_else(|| EngineError::FunctionNotFound(name.to_string()))?;
+        let params: Vec<wasmi::Val> = args.iter().map(|v| to_val(*v)).collect();
+        let mut results = vec![wasmi::Val::I32(0); func.ty(&self.caller).results().len()];
+        func.call(&mut self.caller, &params, &mut results)
+            .map_err(|e| EngineError::Wasm(e.to_string()))?;
+        results.iter().map(from_val).collect()
+    }
+
+    fn has_export(&mut self, name: &str) -> bool {
+        self.caller.get_export(name).is_some()
+    }
+}
+
+fn to_val_type(ty: ValType) -> WasmiValType {
+    match ty {
+        ValType::I32 => WasmiValType::I32,
+        ValType::I64 => WasmiValType::I64,
+        ValType::F32 => WasmiValType::F32,
+        ValType::F64 => WasmiValType::F64,
+    }
+}
+
+fn to_val(val: Val) -> wasmi::Val {
+    match val {
+        Val::I32(v) => wasmi::Val::I32(v),
+        Val::I64(v) => wasmi::Val::I64(v),
+        Val::F32(v) => wasmi::Val::F32(F32::from_float(v)),
+        Val::F64(v) => wasmi::Val::F64(F64::from_float(v)),
+    }
+}
+
+fn from_val(val: &wasmi::Val) -> Result<Val, EngineError> {
+    match val {
+        wasmi::Val::I32(v) => Ok(Val::I32(*v)),
+        wasmi::Val::I64(v) => Ok(Val::I64(*v)),
+        wasmi::Val::F32(v) => Ok(Val::F32(v.to_float())),
+        wasmi::Val::F64(v) => Ok(Val::F64(v.to_float())),
+        other => Err(EngineError::TypeMismatch(format!(
+            "unsupported value: {other:?}"
+        ))),
+    }
+}
+
+fn write_results(values: &[Val], results: &mut [wasmi::Val]) -> Result<(), EngineError> {
+    if values.len() != results.len() {
+        return Err(EngineError::TypeMismatch(format!(
+            "host function returned {} values, expected {}",
+            values.len(),
+            results.len()
+        )));
+    }
+    for (slot, value) in results.iter_mut().zip(values) {
+        *slot = to_val(*value);
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! wasmtime backend for [`WasmEngine`].
+
+use wasmtime::{Caller, Config, Engine, Extern, FuncType, Instance, Linker, Module, Store};
+
+use super::{EngineError, Guest, HostFunctions, Val, ValType, WasmEngine};
+
+/// JIT backend built on wasmtime, with multi-memory enabled like
+/// [`crate::Runtime`].
+#[derive(Clone)]
+pub struct WasmtimeEngine {
+    engine: Engine,
+}
+
+impl WasmtimeEngine {
+    pub fn new() -> Self {
+        let mut config = Config::new();
+        config.wasm_multi_memory(true);
+        let engine = Engine::new(&config).expect("Failed to create engine");
+        Self { engine }
+    }
+
+    /// Use an existing wasmtime engine (e.g. one shared with a [`crate::Runtime`]).
+    pub fn from_engine(engine: Engine) -> Self {
+        Self { engine }
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+}
+
+impl Default for WasmtimeEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WasmEngine for WasmtimeEngine {
+    type Module = Module;
+    type Instance = WasmtimeInstance;
+
+    fn compile(&self, wasm_bytes: &[u8]) -> Result<Module, EngineError> {
+        Module::new(&self.engine, wasm_bytes).map_err(|e| EngineError::Compile(e.to_string()))
+    }
+
+    fn instantiate(
+        &self,
+        module: &Module,
+        host: &HostFunctions,
+    ) -> Result<WasmtimeInstance, EngineError> {
+        let mut store = Store::new(&self.engine, ());
+        let mut linker = Linker::new(&self.engine);
+
+        for func in host.iter() {
+            let ty = FuncType::new(
+                &self.engine,
+                func.params.iter().map(|t| to_val_type(*t)),
+                func.results.iter().map(|t| to_val_type(*t)),
+            );
+            let callback = func.callback.clone();
+            linker
+                .func_new(
+                    &func.module,
+                    &func.name,
+                    ty,
+                    move |caller, params, results| {
+                        let args: Vec<Val> =
+                            params.iter().map(from_val).collect::<Result<_, _>>()?;
+                        let mut guest = WasmtimeCaller { caller };
+                        let values = callback(&mut guest, &args)?;
+                        write_results(&values, results)?;
+                        Ok(())
+                    },
+                )
+                .map_err(|e| EngineError::Instantiate(e.to_string()))?;
+        }
+
+        let instance = linker
+            .instantiate(&mut store, module)
+            .map_err(|e| EngineError::Instantiate(e.to_string()))?;
+        Ok(WasmtimeInstance { store, instance })
+    }
+}
+
+/// A module instantiated by [`WasmtimeEngine`].
+pub struct WasmtimeInstance {
+    store: Store<()>,
+    instance: Instance,
+}
+
+impl Guest for WasmtimeInstance {
+    fn memory(&mut self) -> Result<&mut [u8], EngineError> {
+        let memory = self
+            .instance
+            .get_memory(&mut self.store, "memory")
+            .ok_or_else(|| EngineError::Memory("No memory export".into()))?;
+        Ok(memory.data_mut(&mut self.store))
+    }
+
+    fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, EngineError> {
+        let func = self
+            .instance
+            .get_func(&mut self.store, name)
+            .ok_or_else(|| EngineError::FunctionNotFound(name.to_string()))?;
+        let params: Vec<wasmtime::Val> = args.iter().map(|v| to_val(*v)).collect();
+        let mut results = vec![wasmtime::Val::I32(0); func.ty(&self.store).results().len()];
+        func.call(&mut self.store, &params, &mut results)
+            .map_err(|e| EngineError::Wasm(format!("{e:?}")))?;
+        results.iter().map(from_val).collect()
+    }
+
+    fn has_export(&mut self, name: &str) -> bool {
+        self.instance.get_export(&mut self.store, name).is_some()
+    }
+}
+
+/// The calling instance, as seen from inside a host function.
+pub struct WasmtimeCaller<'a> {
+    caller: Caller<'a, ()>,
+}
+
+impl Guest for WasmtimeCaller<'_> {
+    fn memory(&mut self) -> Result<&mut [u8], EngineError> {
+        let memory = self
+            .caller
+            .get_export("memory")
+            .and_then(Extern::into_memory)
+            .ok_or_else(|| EngineError::Memory("No memory export".into()))?;
+        Ok(memory.data_mut(&mut self.caller))
+    }
+
+    fn call(&mut self, name: &str, args: &[Val]) -> Result<Vec<Val>, EngineError> {
+        let func = self
+            .caller
+            .get_export(name)
+            .and_then(Extern::into_func)
+            .ok_or_else(|| EngineError::FunctionNotFound(name.to_string()))?;
+        let params: Vec<wasmtime::Val> = args.iter().map(|v| to_val(*v)).collect();
+        let mut results = vec![wasmtime::Val::I32(0); func.ty(&self.caller).results().len()];
+        func.call(&mut self.caller, &params, &mut results)
+            .map_err(|e| EngineError::Wasm(format!("{e:?}")))?;
+        results.iter().map(from_val).collect()
+    }
+
+    fn has_export(&mut self, name: &str) -> bool {
+        self.caller.get_export(name).is_some()
+    }
+}
+
+fn to_val_type(ty: ValType) -> wasmtime::ValType {
+    match ty {
+        ValType::I32 => wasmtime::ValType::I32,
+        ValType::I64 => wasmtime::ValType::I64,
+        ValType::F32 => wasmtime::ValType::F32,
+        ValType::F64 => wasmtime::ValType::F64,
+    }
+}
+
+fn to_val(val: Val) -> wasmtime::Val {
+    match val {
+        Val::I32(v) => wasmtime::Val::I32(v),
+        Val::I64(v) => wasmtime::Val::I64(v),
+        Val::F32(v) => wasmtime::Val::F32(v.to_bits()),
+        Val::F64(v) => wasmtime::Val::F64(v.to_bits()),
+    }
+}
+
+fn from_val(val: &wasmtime::Val) -> Result<Val, EngineError> {
+    match val {
+        wasmtime::Val::I32(v) => Ok(Val::I32(*v)),
+        wasmtime::Val::I64(v) => Ok(Val::I64(*v)),
+        wasmtime::Val::F32(bits) => Ok(Val::F32(f32::from_bits(*bits))),
+        wasmtime::Val::F64(bits) => Ok(Val::F64(f64::from_bits(*bits))),
+        other => Err(EngineError::TypeMismatch(format!(
+            "unsupported value: {other:?}"
+        ))),
+    }
+}
+
+fn write_results(values: &[Val], results: &mut [wasmtime::Val]) -> Result<(), EngineError> {
+    if values.len() != results.len() {
+        return Err(EngineError::TypeMismatch(format!(
+            "host function returned {} values, expected {}",
+            values.len(),
+            results.len()
+        )));
+    }
+    for (slot, value) in results.iter_mut().zip(values) {
+        *slot = to_val(*value);
+    }
+    Ok(())
+}
@@ -0,0 +1,812 @@
+//! Interface evolution: what changed between two versions of a Pact interface,
+//! and whom the change breaks.
+//!
+//! [`classify_evolution`] compares two [`PactInterface`]s structurally —
+//! exported functions and their parameters, type definitions, record fields,
+//! variant, enum and flags cases, nested interfaces — and classifies every
+//! change for two audiences:
+//!
+//! - **callers**: code built against the old version, calling an
+//!   implementation of the new one;
+//! - **implementors**: an implementation of the old version, called by code
+//!   built against the new one.
+//!
+//! A function added breaks implementors (they lack it) and a function removed
+//! breaks callers; any change to a signature breaks both. Whether a change to
+//! a type breaks either depends on which way its values flow and on the
+//! decode rules of whoever reads them. Parameters flow to the implementor and
+//! results back to the caller, through every type they contain; a type that
+//! no function uses is taken to flow both ways. A reader rejects a variant,
+//! enum or flags case it does not know. A record's reader expects exactly its
+//! own fields, unless the reader's version marks it `@forward-compatible` —
+//! decoded like `#[graph(forward_compatible)]`, where a missing field defaults
+//! and an extra one is ignored. Any other change to a type breaks both.
+//!
+//! The `@version` metadata says whether a breaking change was announced: see
+//! [`InterfaceDiff::is_versioned`].
+//!
+//! This is not the question [`compose::diff_interfaces`] answers. Evolution
+//! asks what happens when the two versions talk to each other directly, so a
+//! reader decodes exactly what the writer sent. Compose asks whether a link
+//! adapter can rewrite every value in flight, and an adapter fills a missing
+//! `option` field with `none` and drops an extra field. Adding an `option`
+//! field to a record is therefore breaking here, unless the reader is
+//! `@forward-compatible`, and compatible with an adapter there.
+//!
+//! [`compose::diff_interfaces`]: crate::compose::diff_interfaces
+
+use crate::parser::{PactExport, PactInterface};
+use crate::types::{Function, Type, TypeDef};
+use serde::Serialize;
+use std::collections::HashMap;
+use Compatibility::{Breaking, Compatible};
+
+/// Whether a change keeps working for an audience.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compatibility {
+    Compatible,
+    Breaking,
+}
+
+/// A version bump, from one `major.minor.patch` to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bump {
+    None,
+    Patch,
+    Minor,
+    Major,
+}
+
+/// What a [`Change`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    FunctionAdded,
+    FunctionRemoved,
+    ParamAdded,
+    ParamRemoved,
+    ParamTypeChanged,
+    ResultChanged,
+    TypeAdded,
+    TypeRemoved,
+    /// The kind of definition, its generic parameters or an alias's target
+    TypeChanged,
+    FieldAdded,
+    FieldRemoved,
+    FieldTypeChanged,
+    /// A variant, enum or flags case
+    CaseAdded,
+    CaseRemoved,
+    CasePayloadChanged,
+}
+
+/// One structural difference between two versions of an interface.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    /// What changed, e.g. `get`, `get.id`, `user.name` or `inner.shape.circle`
+    pub path: String,
+    pub kind: ChangeKind,
+    /// The old signature or type, where there was one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    /// The new signature or type, where there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+    pub callers: Compatibility,
+    pub implementors: Compatibility,
+}
+
+/// Every change between two versions of an interface, and their verdict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InterfaceDiff {
+    pub interface: String,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+    /// The worst of the changes for callers
+    pub callers: Compatibility,
+    /// The worst of the changes for implementors
+    pub implementors: Compatibility,
+    /// What the changes call for: major if any breaks, minor if there are any
+    pub required_bump: Bump,
+    /// What the `@version` metadata did
+    pub bump: Bump,
+    pub changes: Vec<Change>,
+}
+
+impl InterfaceDiff {
+    /// Whether any change breaks callers or implementors.
+    pub fn is_breaking(&self) -> bool {
+        self.callers == Compatibility::Breaking || self.implementors == Compatibility::Breaking
+    }
+
+    /// Whether `@version` was bumped for a breaking change: a major bump, or
+    /// a minor one while the major version is 0. Trivially true if nothing
+    /// breaks.
+    pub fn is_versioned(&self) -> bool {
+        let zero_major = self
+            .old_version
+            .as_deref()
+            .and_then(semver)
+            .is_some_and(|(major, _, _)| major == 0);
+        !self.is_breaking() || self.bump == Bump::Major || (self.bump == Bump::Minor && zero_major)
+    }
+}
+
+/// Compare `old` and `new` and classify every change; see the module docs.
+pub fn classify_evolution(old: &PactInterface, new: &PactInterface) -> InterfaceDiff {
+    let mut changes = Vec::new();
+    diff_into(old, new, "", &mut changes);
+
+    let worst = |audience: fn(&Change) -> Compatibility| {
+        changes
+            .iter()
+            .map(audience)
+            .max()
+            .unwrap_or(Compatibility::Compatible)
+    };
+    let callers = worst(|c| c.callers);
+    let implementors = worst(|c| c.implementors);
+    let required_bump = if callers.max(implementors) == Compatibility::Breaking {
+        Bump::Major
+    } else if !changes.is_empty() {
+        Bump::Minor
+    } else {
+        Bump::None
+    };
+    InterfaceDiff {
+        interface: new.name.clone(),
+        old_version: old.version().map(str::to_string),
+        new_version: new.version().map(str::to_string),
+        callers,
+        implementors,
+        required_bump,
+        bump: bump(old.version(), new.version()),
+        changes,
+    }
+}
+
+/// Which way a type's values travel.
+#[derive(Debug, Clone, Copy, Default)]
+struct Flow {
+    /// In a parameter, from caller to implementor
+    inbound: bool,
+    /// In a result, from implementor to caller
+    outbound: bool,
+}
+
+/// Which version of the interface a piece of code was built against.
+#[derive(Clone, Copy, PartialEq)]
+enum Version {
+    Old,
+    New,
+}
+
+/// The two versions of one interface being compared, and how their types
+/// flow.
+struct Pair<'a> {
+    old: &'a PactInterface,
+    new: &'a PactInterface,
+    flows: HashMap<String, Flow>,
+    prefix: &'a str,
+}
+
+impl Pair<'_> {
+    fn path(&self, name: &str) -> String {
+        format!("{}{name}", self.prefix)
+    }
+
+    /// The versions that decode values of type `name`, for callers and for
+    /// implementors. For callers, the new implementation reads what flows in
+    /// and the old caller what flows out; for implementors it is the other
+    /// way round.
+    fn readers(&self, name: &str) -> [Vec<Version>; 2] {
+        let flow = self.flows.get(name).copied().unwrap_or_default();
+        let flow = if flow.inbound || flow.outbound {
+            flow
+        } else {
+            Flow {
+                inbound: true,
+                outbound: true,
+            }
+        };
+        let mut callers = Vec::new();
+        let mut implementors = Vec::new();
+        if flow.inbound {
+            callers.push(Version::New);
+            implementors.push(Version::Old);
+        }
+        if flow.outbound {
+            callers.push(Version::Old);
+            implementors.push(Version::New);
+        }
+        [callers, implementors]
+    }
+
+    /// Classify a change to type `name` that breaks an audience when one of
+    /// its readers `rejects` it.
+    fn classify(
+        &self,
+        name: &str,
+        rejects: impl Fn(Version) -> bool,
+    ) -> (Compatibility, Compatibility) {
+        let [callers, implementors] = self.readers(name).map(|readers| {
+            if readers.into_iter().any(&rejects) {
+                Breaking
+            } else {
+                Compatible
+            }
+        });
+        (callers, implementors)
+    }
+
+    fn forward_compatible(&self, version: Version, name: &str) -> bool {
+        match version {
+            Version::Old => self.old.forward_compatible.contains(name),
+            Version::New => self.new.forward_compatible.contains(name),
+        }
+    }
+}
+
+fn diff_into(old: &PactInterface, new: &PactInterface, prefix: &str, changes: &mut Vec<Change>) {
+    let mut flows = type_flows(old);
+    for (name, flow) in type_flows(new) {
+        let merged = flows.entry(name).or_default();
+        merged.inbound |= flow.inbound;
+        merged.outbound |= flow.outbound;
+    }
+    let pair = Pair {
+        old,
+        new,
+        flows,
+        prefix,
+    };
+    let mut push = |path: String,
+                    kind: ChangeKind,
+                    old: Option<String>,
+                    new: Option<String>,
+                    (callers, implementors): (Compatibility, Compatibility)| {
+        changes.push(Change {
+            path,
+            kind,
+            old,
+            new,
+            callers,
+            implementors,
+        })
+    };
+
+    let (old_funcs, new_funcs) = (functions(old), functions(new));
+    for func in &old_funcs {
+        match new_funcs.iter().find(|f| f.name == func.name) {
+            None => push(
+                pair.path(&func.name),
+                ChangeKind::FunctionRemoved,
+                Some(signature(func)),
+                None,
+                (Breaking, Compatible),
+            ),
+            Some(new_func) => {
+                for (path, kind, old, new) in diff_function(func, new_func) {
+                    push(pair.path(&path), kind, old, new, (Breaking, Breaking));
+                }
+            }
+        }
+    }
+    for func in new_funcs
+        .iter()
+        .filter(|f| !old_funcs.iter().any(|o| o.name == f.name))
+    {
+        push(
+            pair.path(&func.name),
+            ChangeKind::FunctionAdded,
+            None,
+            Some(signature(func)),
+            (Compatible, Breaking),
+        );
+    }
+
+    let (old_types, new_types) = (types(old), types(new));
+    for typedef in &old_types {
+        let name = typedef.name();
+        match new_types.iter().find(|t| t.name() == name) {
+            None => push(
+                pair.path(name),
+                ChangeKind::TypeRemoved,
+                Some(shape(typedef)),
+                None,
+                (Breaking, Breaking),
+            ),
+            Some(new_typedef) => diff_type(&pair, typedef, new_typedef, &mut push),
+        }
+    }
+    for typedef in new_types
+        .iter()
+        .filter(|t| !old_types.iter().any(|o| o.name() == t.name()))
+    {
+        push(
+            pair.path(typedef.name()),
+            ChangeKind::TypeAdded,
+            None,
+            Some(shape(typedef)),
+            (Compatible, Compatible),
+        );
+    }
+
+    for child in &old.children {
+        let prefix = format!("{prefix}{}.", child.name);
+        match new.children.iter().find(|c| c.name == child.name) {
+            Some(new_child) => diff_into(child, new_child, &prefix, changes),
+            None => diff_into(child, &PactInterface::new(&child.name), &prefix, changes),
+        }
+    }
+    for child in &new.children {
+        if !old.children.iter().any(|c| c.name == child.name) {
+            let prefix = format!("{prefix}{}.", child.name);
+            diff_into(&PactInterface::new(&child.name), child, &prefix, changes);
+        }
+    }
+}
+
+/// The changes to a function that kept its name, as (path, kind, old, new).
+fn diff_function(
+    old: &Function,
+    new: &Function,
+) -> Vec<(String, ChangeKind, Option<String>, Option<String>)> {
+    let mut changes = Vec::new();
+    let path = |param: &str| format!("{}.{param}", old.name);
+    for param in &old.params {
+        match new.params.iter().find(|p| p.name == param.name) {
+            None => changes.push((
+                path(&param.name),
+                ChangeKind::ParamRemoved,
+                Some(param.ty.to_string()),
+                None,
+            )),
+            Some(new_param) if new_param.ty != param.ty => changes.push((
+                path(&param.name),
+                ChangeKind::ParamTypeChanged,
+                Some(param.ty.to_string()),
+                Some(new_param.ty.to_string()),
+            )),
+            Some(_) => {}
+        }
+    }
+    for param in &new.params {
+        if !old.params.iter().any(|p| p.name == param.name) {
+            changes.push((
+                path(&param.name),
+                ChangeKind::ParamAdded,
+                None,
+                Some(param.ty.to_string()),
+            ));
+        }
+    }
+    if old.results != new.results {
+        changes.push((
+            old.name.clone(),
+            ChangeKind::ResultChanged,
+            Some(results(old)),
+            Some(results(new)),
+        ));
+    }
+    changes
+}
+
+fn diff_type(
+    pair: &Pair<'_>,
+    old: &TypeDef,
+    new: &TypeDef,
+    push: &mut impl FnMut(
+        String,
+        ChangeKind,
+        Option<String>,
+        Option<String>,
+        (Compatibility, Compatibility),
+    ),
+) {
+    let name = old.name();
+    if shape(old) != shape(new) {
+        push(
+            pair.path(name),
+            ChangeKind::TypeChanged,
+            Some(shape(old)),
+            Some(shape(new)),
+            (Breaking, Breaking),
+        );
+        return;
+    }
+    let path = |member: &str| pair.path(&format!("{name}.{member}"));
+
+    // A reader missing a field, or seeing an extra one, needs to be
+    // forward-compatible.
+    let strict = |version: Version| !pair.forward_compatible(version, name);
+    // A reader that does not know a case rejects it.
+    let knows_only_old = |version: Version| version == Version::Old;
+    let knows_only_new = |version: Version| version == Version::New;
+
+    match (old, new) {
+        (TypeDef::Record { fields: old, .. }, TypeDef::Record { fields: new, .. }) => {
+            for field in old {
+                match new.iter().find(|f| f.name == field.name) {
+                    None => push(
+                        path(&field.name),
+                        ChangeKind::FieldRemoved,
+                        Some(field.ty.to_string()),
+                        None,
+                        pair.classify(name, strict),
+                    ),
+                    Some(new_field) if new_field.ty != field.ty => push(
+                        path(&field.name),
+                        ChangeKind::FieldTypeChanged,
+                        Some(field.ty.to_string()),
+                        Some(new_field.ty.to_string()),
+                        (Breaking, Breaking),
+                    ),
+                    Some(_) => {}
+                }
+            }
+            for field in new.iter().filter(|f| !old.iter().any(|o| o.name == f.name)) {
+                push(
+                    path(&field.name),
+                    ChangeKind::FieldAdded,
+                    None,
+                    Some(field.ty.to_string()),
+                    pair.classify(name, strict),
+                );
+            }
+        }
+        (TypeDef::Variant { cases: old, .. }, TypeDef::Variant { cases: new, .. }) => {
+            let payload = |ty: &Type| (*ty != Type::Unit).then(|| ty.to_string());
+            for case in old {
+                match new.iter().find(|c| c.name == case.name) {
+                    None => push(
+                        path(&case.name),
+                        ChangeKind::CaseRemoved,
+                        payload(&case.payload),
+                        None,
+                        pair.classify(name, knows_only_new),
+                    ),
+                    Some(new_case) if new_case.payload != case.payload => push(
+                        path(&case.name),
+                        ChangeKind::CasePayloadChanged,
+                        payload(&case.payload),
+                        payload(&new_case.payload),
+                        (Breaking, Breaking),
+                    ),
+                    Some(_) => {}
+                }
+            }
+            for case in new.iter().filter(|c| !old.iter().any(|o| o.name == c.name)) {
+                push(
+                    path(&case.name),
+                    ChangeKind::CaseAdded,
+                    None,
+                    payload(&case.payload),
+                    pair.classify(name, knows_only_old),
+                );
+            }
+        }
+        (TypeDef::Enum { cases: old, .. }, TypeDef::Enum { cases: new, .. })
+        | (TypeDef::Flags { flags: old, .. }, TypeDef::Flags { flags: new, .. }) => {
+            for case in old.iter().filter(|c| !new.contains(c)) {
+                push(
+                    path(case),
+                    ChangeKind::CaseRemoved,
+                    None,
+                    None,
+                    pair.classify(name, knows_only_new),
+                );
+            }
+            for case in new.iter().filter(|c| !old.contains(c)) {
+                push(
+                    path(case),
+                    ChangeKind::CaseAdded,
+                    None,
+                    None,
+                    pair.classify(name, knows_only_old),
+                );
+            }
+        }
+        // Aliases with the same shape have the same target.
+        _ => {}
+    }
+}
+
+/// The interface's exported functions.
+fn functions(interface: &PactInterface) -> Vec<&Function> {
+    interface
+        .exports
+        .iter()
+        .filter_map(|e| match e {
+            PactExport::Function(f) => Some(f),
+            PactExport::Type(_) => None,
+        })
+        .collect()
+}
+
+/// The interface's type definitions, exported ones included.
+fn types(interface: &PactInterface) -> Vec<&TypeDef> {
+    let exported = interface.exports.iter().filter_map(|e| match e {
+        PactExport::Type(t) => Some(t),
+        PactExport::Function(_) => None,
+    });
+    interface.types.iter().chain(exported).collect()
+}
+
+/// How each named type of `interface` flows: into the implementor through a
+/// parameter, or back to the caller through a result, directly or inside
+/// another type.
+fn type_flows(interface: &PactInterface) -> HashMap<String, Flow> {
+    let defs: HashMap<&str, &TypeDef> = types(interface)
+        .into_iter()
+        .map(|t| (t.name(), t))
+        .collect();
+    let mut flows = HashMap::new();
+    for func in functions(interface) {
+        for param in &func.params {
+            mark(&param.ty, true, &defs, &mut flows);
+        }
+        for result in &func.results {
+            mark(result, false, &defs, &mut flows);
+        }
+    }
+    flows
+}
+
+fn mark(
+    ty: &Type,
+    inbound: bool,
+    defs: &HashMap<&str, &TypeDef>,
+    flows: &mut HashMap<String, Flow>,
+) {
+    let mut names = Vec::new();
+    named_in(ty, &mut names);
+    for name in names {
+        let flow = flows.entry(name.to_string()).or_default();
+        let seen = if inbound {
+            std::mem::replace(&mut flow.inbound, true)
+        } else {
+            std::mem::replace(&mut flow.outbound, true)
+        };
+        if seen {
+            continue;
+        }
+        let inner: Vec<&Type> = match defs.get(name) {
+            Some(TypeDef::Alias { ty, .. }) => vec![ty],
+            Some(TypeDef::Record { fields, .. }) => fields.iter().map(|f| &f.ty).collect(),
+            Some(TypeDef::Variant { cases, .. }) => cases.iter().map(|c| &c.payload).collect(),
+            _ => Vec::new(),
+        };
+        for ty in inner {
+            mark(ty, inbound, defs, flows);
+        }
+    }
+}
+
+/// The names of the types `ty` refers to.
+fn named_in<'a>(ty: &'a Type, names: &mut Vec<&'a str>) {
+    match ty {
+        Type::List(inner) | Type::Option(inner) | Type::Set(inner) => named_in(inner, names),
+        Type::Result { ok, err } => {
+            named_in(ok, names);
+            named_in(err, names);
+        }
+        Type::Map { key, value } => {
+            named_in(key, names);
+            named_in(value, names);
+        }
+        Type::Tuple(types) => types.iter().for_each(|t| named_in(t, names)),
+        Type::Ref(path) => names.extend(path.name()),
+        Type::App { path, args } => {
+            names.extend(path.name());
+            args.iter().for_each(|t| named_in(t, names));
+        }
+        _ => {}
+    }
+}
+
+/// A function's signature in Pact syntax.
+fn signature(func: &Function) -> String {
+    let params: Vec<String> = func
+        .params
+        .iter()
+        .map(|p| format!("{}: {}", p.name, p.ty))
+        .collect();
+    if func.results.is_empty() {
+        format!("func({})", params.join(", "))
+    } else {
+        format!("func({}) -> {}", params.join(", "), results(func))
+    }
+}
+
+fn results(func: &Function) -> String {
+    match func.results.as_slice() {
+        [] => "()".to_string(),
+        [single] => single.to_string(),
+        many => {
+            let many: Vec<String> = many.iter().map(Type::to_string).collect();
+            format!("({})", many.join(", "))
+        }
+    }
+}
+
+/// What kind of definition `typedef` is, with its generic parameters, and
+/// for an alias its target: what must stay the same for its members to be
+/// compared.
+fn shape(typedef: &TypeDef) -> String {
+    let kind = match typedef {
+        TypeDef::Alias { ty, .. } => return format!("type{} = {ty}", generics(typedef)),
+        TypeDef::Record { .. } => "record",
+        TypeDef::Variant { .. } => "variant",
+        TypeDef::Enum { .. } => "enum",
+        TypeDef::Flags { .. } => "flags",
+    };
+    format!("{kind}{}", generics(typedef))
+}
+
+fn generics(typedef: &TypeDef) -> String {
+    match typedef.type_params() {
+        [] => String::new(),
+        params => format!("<{}>", params.join(", ")),
+    }
+}
+
+/// `major.minor.patch`, ignoring a leading `v` and any pre-release or build
+/// suffix; missing parts are 0.
+fn semver(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(str::parse::<u64>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// The bump from `old` to `new`; [`Bump::None`] if either is missing or
+/// unreadable, or the version did not go up.
+fn bump(old: Option<&str>, new: Option<&str>) -> Bump {
+    let (Some(old), Some(new)) = (old.and_then(semver), new.and_then(semver)) else {
+        return Bump::None;
+    };
+    if new.0 != old.0 {
+        return if new.0 > old.0 {
+            Bump::Major
+        } else {
+            Bump::None
+        };
+    }
+    if new.1 != old.1 {
+        return if new.1 > old.1 {
+            Bump::Minor
+        } else {
+            Bump::None
+        };
+    }
+    if new.2 > old.2 {
+        Bump::Patch
+    } else {
+        Bump::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_pact;
+
+    fn diff(old: &str, new: &str) -> InterfaceDiff {
+        classify_evolution(
+            &parse_pact(old).expect("old"),
+            &parse_pact(new).expect("new"),
+        )
+    }
+
+    fn verdicts(diff: &InterfaceDiff) -> Vec<(&str, ChangeKind, Compatibility, Compatibility)> {
+        diff.changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.kind, c.callers, c.implementors))
+            .collect()
+    }
+
+    #[test]
+    fn functions_added_removed_and_changed() {
+        let old = "exports {\n    get: func(id: u64) -> string\n    drop: func(id: u64)\n}\n";
+        let new = "exports {\n    get: func(id: string, limit: u32) -> string\n    \
+                   put: func(id: u64, body: string)\n}\n";
+        let diff = diff(old, new);
+        assert_eq!(
+            verdicts(&diff),
+            [
+                ("get.id", ChangeKind::ParamTypeChanged, Breaking, Breaking),
+                ("get.limit", ChangeKind::ParamAdded, Breaking, Breaking),
+                ("drop", ChangeKind::FunctionRemoved, Breaking, Compatible),
+                ("put", ChangeKind::FunctionAdded, Compatible, Breaking),
+            ]
+        );
+        assert_eq!(diff.changes[0].old.as_deref(), Some("u64"));
+        assert_eq!(diff.changes[0].new.as_deref(), Some("string"));
+        assert_eq!(
+            diff.changes[3].new.as_deref(),
+            Some("func(id: u64, body: string)")
+        );
+    }
+
+    #[test]
+    fn added_fields_follow_the_readers_decode_rules() {
+        let old = "record user { id: u64 }\nexports { get: func() -> user }\n";
+        let new =
+            "record user { id: u64, nick: option<string> }\nexports { get: func() -> user }\n";
+        let strict = diff(old, new);
+        assert_eq!(
+            verdicts(&strict),
+            [("user.nick", ChangeKind::FieldAdded, Breaking, Breaking)]
+        );
+        assert_eq!(strict.changes[0].new.as_deref(), Some("option<string>"));
+
+        // Only the new version tolerates the field. `user` is a result, so an
+        // old caller still reads it strictly; a new caller of an old
+        // implementation reads it with the new, tolerant decode.
+        let tolerant = format!("@forward-compatible\n{new}");
+        assert_eq!(
+            verdicts(&diff(old, &tolerant)),
+            [("user.nick", ChangeKind::FieldAdded, Breaking, Compatible)]
+        );
+        let both = format!("@forward-compatible\n{old}");
+        assert_eq!(
+            verdicts(&diff(&both, &tolerant)),
+            [("user.nick", ChangeKind::FieldAdded, Compatible, Compatible)]
+        );
+    }
+
+    #[test]
+    fn new_cases_break_whoever_reads_them() {
+        let old =
+            |position: &str| format!("variant shape {{ circle(f32) }}\nexports {{ {position} }}\n");
+        let new = |position: &str| {
+            format!("variant shape {{ circle(f32), square(f32) }}\nexports {{ {position} }}\n")
+        };
+        let result = "area: func() -> shape";
+        assert_eq!(
+            verdicts(&diff(&old(result), &new(result))),
+            [("shape.square", ChangeKind::CaseAdded, Breaking, Compatible)]
+        );
+        let param = "draw: func(s: shape)";
+        assert_eq!(
+            verdicts(&diff(&old(param), &new(param))),
+            [("shape.square", ChangeKind::CaseAdded, Compatible, Breaking)]
+        );
+        // Removing the case is the mirror image.
+        assert_eq!(
+            verdicts(&diff(&new(param), &old(param))),
+            [(
+                "shape.square",
+                ChangeKind::CaseRemoved,
+                Breaking,
+                Compatible
+            )]
+        );
+    }
+
+    #[test]
+    fn breaking_changes_need_a_major_bump() {
+        let src = |version: &str, func: &str| {
+            format!("@version: string = \"{version}\"\nexports {{ {func}: func() }}\n")
+        };
+        let breaking = diff(&src("1.2.0", "get"), &src("1.3.0", "fetch"));
+        assert!(breaking.is_breaking());
+        assert_eq!(
+            (breaking.required_bump, breaking.bump),
+            (Bump::Major, Bump::Minor)
+        );
+        assert!(!breaking.is_versioned());
+        assert!(diff(&src("1.2.0", "get"), &src("2.0.0", "fetch")).is_versioned());
+        assert!(diff(&src("0.4.1", "get"), &src("0.5.0", "fetch")).is_versioned());
+
+        let same = diff(&src("1.2.0", "get"), &src("1.2.0", "get"));
+        assert_eq!((same.required_bump, same.changes.len()), (Bump::None, 0));
+        assert!(same.is_versioned());
+    }
+}
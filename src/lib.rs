@@ -14,12 +14,24 @@
 //! │  parser    - Extended Pact parsing       │
 //! │  abi       - Type encoding/decoding     │
 //! │  runtime   - Package instantiation      │
+//! │  engine    - Backend abstraction        │
 //! │                                         │
 //! ├─────────────────────────────────────────┤
-//! │       WASM Execution (wasmtime)         │
+//! │   WASM Execution (wasmtime | wasmi)     │
 //! └─────────────────────────────────────────┘
 //! ```
 //!
+//! ## Backends
+//!
+//! Only [`engine`] is backend-neutral: its [`engine::Guest`] trait carries
+//! the Pack calling convention and typed host functions on wasmtime and on
+//! wasmi alike. `runtime` — `Runtime`, `Instance`, `HostLinkerBuilder`,
+//! `Ctx` and their async forms — is built on wasmtime directly and exists
+//! only with the `wasmtime` feature. A `--no-default-features --features
+//! wasmi` build has `engine::WasmiEngine` and nothing from `runtime`, so
+//! code written against `Runtime` has to move to the engine API to run on
+//! wasmi until the runtime is ported onto [`engine::WasmEngine`].
+//!
 //! ## Extended Pact Types
 //!
 //! Pact allows recursive types by default:
@@ -52,9 +64,13 @@
 pub mod abi;
 pub mod codegen;
 pub mod compose;
+pub mod engine;
+pub mod evolution;
 pub mod interface_impl;
+pub mod lsp;
 pub mod metadata;
 pub mod parser;
+#[cfg(feature = "wasmtime")]
 pub mod runtime;
 pub mod transform;
 pub mod types;
@@ -78,13 +94,16 @@ pub use parser::{
     PactFileError, PactImport, PactInterface, PactUse, ResolvedScope, ResolvedUse, TypeDef,
     TypeParam, TypeRegistry, World, WorldItem,
 };
+#[cfg(feature = "wasmtime")]
 pub use runtime::{
     validate_instance_implements_interface, AsyncCompiledModule, AsyncCtx, AsyncInstance,
     AsyncRuntime, CallInterceptor, CompiledModule, Ctx, DefaultHostProvider, Engine, ErrorHandler,
-    HostFunctionError, HostFunctionErrorKind, HostFunctionProvider, HostLinkerBuilder, Instance,
-    InterfaceBuilder, InterfaceError, LinkerError, Module, Runtime,
+    FilteredInterceptor, HostFunctionError, HostFunctionErrorKind, HostFunctionProvider,
+    HostLinkerBuilder, Instance, InstanceSnapshot, InterceptorChain, InterfaceBuilder,
+    InterfaceError, LinkerError, Module, RecordingInterceptor, ReplayInterceptor, ResourceKind,
+    ResourceLimits, Runtime, StoreData,
 };
-pub use transform::{InterfaceTransform, RpcTransform, TransformRegistry};
+pub use transform::{DeclaredTransform, InterfaceTransform, RpcTransform, TransformRegistry};
 pub use types::{Arena, Case, Field, Function, Param, Type, TypePath};
 
 pub use codegen::generate_rust;
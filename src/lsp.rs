@@ -0,0 +1,737 @@
+//! A language server for Pact, run by the `pact-lsp` binary.
+//!
+//! The server speaks the Language Server Protocol over any reader/writer pair
+//! (the binary uses stdio). It analyzes the workspace directory the way
+//! `pact check-dir` does — [`parse_pact_dir_lenient_in`] builds the interface
+//! tree and its [`TypeRegistry`], with a file that fails to parse reported
+//! rather than fatal — and analyzes it again whenever a file is opened or
+//! saved. From that analysis it serves:
+//!
+//! - diagnostics: parse errors, undefined type references and unresolved
+//!   `use` declarations, published for every file in the workspace;
+//! - go-to-definition of a type or interface name, following `use`
+//!   declarations across files;
+//! - hover, with a type's definition and its Merkle hash ([`hash_type_in`]);
+//! - completion of the type names in scope, of interface names after `use`,
+//!   and of an interface's types in `use name.{...}`;
+//! - rename of a type, at its definition and every reference to it.
+//!
+//! The analysis is of the files on disk, so in a buffer edited since its last
+//! save, positions may be off until it is saved again.
+
+use crate::metadata::hash_type_in;
+use crate::parser::{
+    parse_pact_dir_lenient_in, Cst, CstToken, Diagnostic, FileId, PactExport, PactFileError,
+    PactInterface, SourceMap, Span, TokenKind, TypeRegistry, BUILTIN_TYPES,
+};
+use crate::types::{Type, TypeDef};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// JSON-RPC's code for a method the server does not implement.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// LSP's code for a well-formed request the server cannot carry out.
+const REQUEST_FAILED: i64 = -32803;
+
+/// `CompletionItemKind`s.
+const KIND_MODULE: u32 = 9;
+const KIND_KEYWORD: u32 = 14;
+const KIND_STRUCT: u32 = 22;
+
+/// Serve one client: read its messages from `input` until it sends `exit` (or
+/// closes the stream), writing responses and notifications to `output`.
+///
+/// An `exit` not preceded by `shutdown` is an error, since the protocol asks
+/// the server to exit with a failure code then.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::default();
+    while let Some(message) = read_message(&mut input)? {
+        if message["method"] == "exit" {
+            return match server.shut_down {
+                true => Ok(()),
+                false => Err(io::Error::other("exit before shutdown")),
+            };
+        }
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read one `Content-Length`-framed message; `None` at the end of the stream.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "message without Content-Length")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+#[derive(Default)]
+struct Server {
+    /// The workspace directory, from `initialize` or else the first file opened
+    root: Option<PathBuf>,
+    analysis: Option<Analysis>,
+    /// The text of open documents, by URI, for completing what is being typed
+    documents: HashMap<String, String>,
+    /// Files last published with diagnostics, to clear if they go away
+    published: HashSet<String>,
+    shut_down: bool,
+}
+
+impl Server {
+    /// Handle one message, returning what to send back.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id").cloned() else {
+            return self.notify(method, params);
+        };
+        if method.is_empty() {
+            // A response to a request of ours; we send none.
+            return Vec::new();
+        }
+
+        let result = match method {
+            "initialize" => Ok(self.initialize(params)),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => Ok(self.definition(params)),
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/completion" => Ok(self.completion(params)),
+            "textDocument/rename" => self.rename(params),
+            _ => Err((METHOD_NOT_FOUND, format!("unhandled method `{method}`"))),
+        };
+        let reply = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        vec![reply]
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let document = &params["textDocument"];
+        let uri = document["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "initialized" => self.analyze(),
+            "textDocument/didOpen" => {
+                let text = document["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                if self.root.is_none() {
+                    self.root = uri_to_path(&uri).and_then(|p| Some(p.parent()?.to_path_buf()));
+                }
+                self.analyze()
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change is the whole text.
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|c| c.last()?["text"].as_str()) {
+                    self.documents.insert(uri, text.to_string());
+                }
+                Vec::new()
+            }
+            "textDocument/didSave" => self.analyze(),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        self.root = params["rootUri"]
+            .as_str()
+            .and_then(uri_to_path)
+            .or_else(|| {
+                params["workspaceFolders"][0]["uri"]
+                    .as_str()
+                    .and_then(uri_to_path)
+            })
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+        json!({
+            "capabilities": {
+                "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+                "definitionProvider": true,
+                "hoverProvider": true,
+                "completionProvider": { "triggerCharacters": [".", "{"] },
+                "renameProvider": true,
+            },
+            "serverInfo": { "name": "pact-lsp", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    /// Analyze the workspace again and publish its diagnostics.
+    fn analyze(&mut self) -> Vec<Value> {
+        let Some(root) = &self.root else {
+            return Vec::new();
+        };
+        let mut sources = SourceMap::new();
+        match parse_pact_dir_lenient_in(&mut sources, root) {
+            Ok((tree, registry, skipped)) => {
+                self.analysis = Some(Analysis {
+                    sources,
+                    tree,
+                    registry,
+                    skipped,
+                });
+                self.publish_diagnostics()
+            }
+            Err(e) => {
+                self.analysis = None;
+                let params = json!({ "type": 1, "message": e.to_string() });
+                vec![notification("window/logMessage", params)]
+            }
+        }
+    }
+
+    /// `textDocument/publishDiagnostics` for every file in the workspace — an
+    /// empty list clears a file's old ones — and for any file that had
+    /// diagnostics last time but is gone now.
+    fn publish_diagnostics(&mut self) -> Vec<Value> {
+        let Some(analysis) = &self.analysis else {
+            return Vec::new();
+        };
+        let mut by_uri: BTreeMap<String, Vec<Value>> = self
+            .published
+            .drain()
+            .map(|uri| (uri, Vec::new()))
+            .collect();
+        for (_, file) in analysis.sources.iter() {
+            by_uri
+                .entry(path_to_uri(Path::new(&file.name)))
+                .or_default();
+        }
+        for (uri, diagnostic) in analysis.diagnostics() {
+            by_uri.entry(uri).or_default().push(diagnostic);
+        }
+
+        self.published = by_uri
+            .iter()
+            .filter(|(_, diagnostics)| !diagnostics.is_empty())
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        by_uri
+            .into_iter()
+            .map(|(uri, diagnostics)| {
+                let params = json!({ "uri": uri, "diagnostics": diagnostics });
+                notification("textDocument/publishDiagnostics", params)
+            })
+            .collect()
+    }
+
+    /// The analysis, and the file and byte offset a `textDocument/...` request
+    /// points at.
+    fn locate(&self, params: &Value) -> Option<(&Analysis, FileId, usize)> {
+        let analysis = self.analysis.as_ref()?;
+        let path = uri_to_path(params["textDocument"]["uri"].as_str()?)?;
+        let (file, source) = analysis
+            .sources
+            .iter()
+            .find(|(_, source)| Path::new(&source.name) == path)?;
+        Some((analysis, file, offset(&source.src, &params["position"])))
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let target = self.locate(params).and_then(|(analysis, file, offset)| {
+            let (_, target) = analysis.target_at(file, offset)?;
+            analysis.location(analysis.name_span(&target)?)
+        });
+        target.unwrap_or(Value::Null)
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let hover = self.locate(params).and_then(|(analysis, file, offset)| {
+            let (token, target) = analysis.target_at(file, offset)?;
+            let value = match target {
+                Target::Type {
+                    name,
+                    interface,
+                    span,
+                } => {
+                    let src = &analysis.sources.get(span.file)?.src;
+                    let hash = hash_type_in(&Type::named(&name), &analysis.scope_of(interface));
+                    format!(
+                        "```pact\n{}\n```\n\n`{name}` in `{}` · hash `{}`",
+                        &src[span.start..span.end],
+                        interface.name,
+                        hash.to_hex()
+                    )
+                }
+                Target::Interface(interface) => format!(
+                    "interface `{}`: {} type(s), {} export(s)",
+                    interface.name,
+                    interface.types.len(),
+                    interface.exports.len()
+                ),
+            };
+            let src = &analysis.sources.get(file)?.src;
+            Some(json!({
+                "contents": { "kind": "markdown", "value": value },
+                "range": range(src, token.span),
+            }))
+        });
+        hover.unwrap_or(Value::Null)
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let Some(analysis) = &self.analysis else {
+            return json!([]);
+        };
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let position = &params["position"];
+
+        // What is being typed comes from the buffer, which may be unsaved.
+        let located = self.locate(params);
+        let text = match (self.documents.get(uri), located) {
+            (Some(text), _) => text.as_str(),
+            (None, Some((_, file, _))) => &analysis.sources.get(file).expect("located").src,
+            (None, None) => "",
+        };
+        let cursor = offset(text, position);
+        let line = &text[text[..cursor].rfind('\n').map_or(0, |i| i + 1)..cursor];
+
+        let mut items = BTreeMap::new();
+        if let Some(used) = use_list(line) {
+            for name in analysis.registry.types_in(used).into_iter().flatten() {
+                items.insert(name.to_string(), (KIND_STRUCT, Some(used)));
+            }
+        } else if is_use_target(line) {
+            for name in analysis.registry.interfaces() {
+                items.insert(name.to_string(), (KIND_MODULE, None));
+            }
+        } else {
+            for name in BUILTIN_TYPES {
+                items.insert(name.to_string(), (KIND_KEYWORD, None));
+            }
+            if let Some((analysis, file, offset)) = located {
+                for name in analysis.types_in_scope(&analysis.enclosing(file, offset)) {
+                    items.insert(name, (KIND_STRUCT, None));
+                }
+            }
+        }
+
+        let items: Vec<Value> = items
+            .into_iter()
+            .map(|(label, (kind, from))| match from {
+                Some(interface) => json!({ "label": label, "kind": kind, "detail": interface }),
+                None => json!({ "label": label, "kind": kind }),
+            })
+            .collect();
+        Value::Array(items)
+    }
+
+    fn rename(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let new_name = params["newName"].as_str().unwrap_or_default();
+        if !is_ident(new_name) {
+            return Err((REQUEST_FAILED, format!("`{new_name}` is not a type name")));
+        }
+        let Some((analysis, file, offset)) = self.locate(params) else {
+            return Ok(Value::Null);
+        };
+        let Some((_, Target::Type { name, span, .. })) = analysis.target_at(file, offset) else {
+            return Err((REQUEST_FAILED, "only types can be renamed".to_string()));
+        };
+
+        let mut changes: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+        for span in analysis.occurrences(&name, span) {
+            let Some(source) = analysis.sources.get(span.file) else {
+                continue;
+            };
+            let edit = json!({ "range": range(&source.src, span), "newText": new_name });
+            changes
+                .entry(path_to_uri(Path::new(&source.name)))
+                .or_default()
+                .push(edit);
+        }
+        Ok(json!({ "changes": changes }))
+    }
+}
+
+/// The workspace as of the last open or save.
+struct Analysis {
+    sources: SourceMap,
+    tree: PactInterface,
+    registry: TypeRegistry,
+    /// The files that failed to parse
+    skipped: Vec<PactFileError>,
+}
+
+/// What a name in the source refers to.
+enum Target<'a> {
+    /// A type, with the interface defining it and the definition's span
+    Type {
+        name: String,
+        interface: &'a PactInterface,
+        span: Span,
+    },
+    Interface(&'a PactInterface),
+}
+
+impl Analysis {
+    /// Every diagnostic in the workspace, as LSP diagnostics keyed by URI.
+    fn diagnostics(&self) -> Vec<(String, Value)> {
+        let mut out = Vec::new();
+        for error in &self.skipped {
+            if let PactFileError::Parse(path, error) = error {
+                let diagnostic = Diagnostic::from(error);
+                out.push((path_to_uri(path), self.lsp_diagnostic(&diagnostic)));
+            }
+        }
+
+        let mut diagnostics = self.tree.check_types(Some(&self.registry));
+        self.visit(&mut |chain| {
+            let uses = &chain.last().expect("visited").uses;
+            diagnostics.extend(uses.iter().filter_map(|u| self.registry.diagnose_use(u)));
+        });
+        for diagnostic in &diagnostics {
+            let Some(file) = diagnostic.span.and_then(|s| self.sources.get(s.file)) else {
+                continue;
+            };
+            let uri = path_to_uri(Path::new(&file.name));
+            out.push((uri, self.lsp_diagnostic(diagnostic)));
+        }
+        out
+    }
+
+    fn lsp_diagnostic(&self, diagnostic: &Diagnostic) -> Value {
+        let range = match diagnostic
+            .span
+            .and_then(|s| Some((s, self.sources.get(s.file)?)))
+        {
+            Some((span, file)) => range(&file.src, span),
+            None => range("", Span::default()),
+        };
+        let message = match &diagnostic.help {
+            Some(help) => format!("{}\nhelp: {help}", diagnostic.message),
+            None => diagnostic.message.clone(),
+        };
+        json!({ "range": range, "severity": 1, "source": "pact", "message": message })
+    }
+
+    /// Call `f` with every interface in the tree, as the chain of interfaces
+    /// from the outermost down to it.
+    fn visit<'a>(&'a self, f: &mut impl FnMut(&[&'a PactInterface])) {
+        fn walk<'a>(
+            interface: &'a PactInterface,
+            chain: &mut Vec<&'a PactInterface>,
+            f: &mut impl FnMut(&[&'a PactInterface]),
+        ) {
+            chain.push(interface);
+            f(chain);
+            for child in &interface.children {
+                walk(child, chain, f);
+            }
+            chain.pop();
+        }
+        walk(&self.tree, &mut Vec::new(), f);
+    }
+
+    /// The interfaces whose source holds byte `offset` of `file`, outermost
+    /// first.
+    fn enclosing(&self, file: FileId, offset: usize) -> Vec<&PactInterface> {
+        let mut chain = Vec::new();
+        self.visit(&mut |visited| {
+            let span = visited.last().expect("visited").span;
+            if span.file == file
+                && span.start < span.end
+                && (span.start..=span.end).contains(&offset)
+            {
+                chain = visited.to_vec();
+            }
+        });
+        chain
+    }
+
+    /// Where the type `name` is defined, as seen from the innermost interface
+    /// of `chain`: in it or an interface it is nested in, or in an interface
+    /// one of those `use`s.
+    fn resolve<'a>(&'a self, chain: &[&'a PactInterface], name: &str) -> Option<Target<'a>> {
+        let found = |interface: &'a PactInterface| {
+            let span = *interface.spans.types.get(name)?;
+            Some(Target::Type {
+                name: name.to_string(),
+                interface,
+                span,
+            })
+        };
+        for interface in chain.iter().rev() {
+            if let Some(target) = found(interface) {
+                return Some(target);
+            }
+            for use_decl in &interface.uses {
+                let brings = use_decl.items.is_empty() || use_decl.items.iter().any(|i| i == name);
+                if !use_decl.transform_args.is_empty() || !brings {
+                    continue;
+                }
+                let used = self.registry.get_interface(&use_decl.interface);
+                if let Some(target) = used.and_then(found) {
+                    return Some(target);
+                }
+            }
+        }
+        None
+    }
+
+    /// The identifier at byte `offset` of `file` and what it refers to.
+    fn target_at(&self, file: FileId, offset: usize) -> Option<(CstToken, Target<'_>)> {
+        let src = &self.sources.get(file)?.src;
+        let token = Cst::parse(src, file)
+            .ok()?
+            .tokens()
+            .into_iter()
+            .find(|t| t.kind == TokenKind::Ident && (t.span.start..=t.span.end).contains(&offset))?
+            .clone();
+        let target = match self.resolve(&self.enclosing(file, offset), &token.text) {
+            Some(target) => target,
+            None => Target::Interface(self.registry.get_interface(&token.text)?),
+        };
+        Some((token, target))
+    }
+
+    /// The name token of a target's definition.
+    fn name_span(&self, target: &Target<'_>) -> Option<Span> {
+        let (name, span) = match target {
+            Target::Type { name, span, .. } => (name, *span),
+            Target::Interface(interface) => (&interface.name, interface.span),
+        };
+        if span.start == span.end {
+            // A directory's interface has no source.
+            return None;
+        }
+        // A file's anonymous interface is named after the file instead.
+        let named = self.ident_in(span, name);
+        named.or(Some(Span::new(span.file, span.start, span.start)))
+    }
+
+    /// The tokens within `span`.
+    fn tokens_in(&self, span: Span) -> impl Iterator<Item = CstToken> {
+        let src = self.sources.get(span.file).map_or("", |f| f.src.as_str());
+        let tokens = Cst::parse(src, span.file)
+            .map(|cst| cst.tokens().into_iter().cloned().collect())
+            .unwrap_or_else(|_| Vec::new());
+        tokens
+            .into_iter()
+            .filter(move |t| t.span.start >= span.start && t.span.end <= span.end)
+    }
+
+    /// The identifier `name`'s first token within `span`.
+    fn ident_in(&self, span: Span, name: &str) -> Option<Span> {
+        self.tokens_in(span)
+            .find(|t| t.kind == TokenKind::Ident && t.text == name)
+            .map(|t| t.span)
+    }
+
+    fn location(&self, span: Span) -> Option<Value> {
+        let file = self.sources.get(span.file)?;
+        Some(json!({
+            "uri": path_to_uri(Path::new(&file.name)),
+            "range": range(&file.src, span),
+        }))
+    }
+
+    /// Every place the type `name` defined at `definition` is named: the
+    /// definition itself, references that resolve to it, and `use` lists
+    /// that import it.
+    fn occurrences(&self, name: &str, definition: Span) -> Vec<Span> {
+        let defines = |target: Option<Target<'_>>| matches!(target, Some(Target::Type { span, .. }) if span == definition);
+        let mut spans = Vec::new();
+        spans.extend(self.ident_in(definition, name));
+        self.visit(&mut |chain| {
+            let interface = chain.last().expect("visited");
+            for (reference, span) in &interface.spans.references {
+                if reference == name && defines(self.resolve(chain, name)) {
+                    spans.push(*span);
+                }
+            }
+            for use_decl in &interface.uses {
+                if !use_decl.items.iter().any(|i| i == name) {
+                    continue;
+                }
+                let used = self.registry.get_interface(&use_decl.interface);
+                if !defines(used.and_then(|used| self.resolve(&[used], name))) {
+                    continue;
+                }
+                // The items follow the `{`; the interface name comes before.
+                let items = self
+                    .tokens_in(use_decl.span)
+                    .skip_while(|t| !t.is_symbol('{'));
+                spans.extend(
+                    items
+                        .filter(|t| t.kind == TokenKind::Ident && t.text == name)
+                        .map(|t| t.span),
+                );
+            }
+        });
+        spans
+    }
+
+    /// The type names in scope in the innermost interface of `chain`.
+    fn types_in_scope(&self, chain: &[&PactInterface]) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        for interface in chain {
+            names.extend(interface.spans.types.keys().cloned());
+            names.extend(interface.type_params.iter().map(|p| p.name.clone()));
+            for use_decl in interface
+                .uses
+                .iter()
+                .filter(|u| u.transform_args.is_empty())
+            {
+                if !use_decl.items.is_empty() {
+                    names.extend(use_decl.items.iter().cloned());
+                } else if let Some(types) = self.registry.types_in(&use_decl.interface) {
+                    names.extend(types.map(str::to_string));
+                }
+            }
+        }
+        names
+    }
+
+    /// The type definitions a type of `interface` is hashed against: its own,
+    /// exported ones included, and those its `use`s bring in.
+    fn scope_of(&self, interface: &PactInterface) -> Vec<TypeDef> {
+        let mut scope: Vec<TypeDef> = match self.registry.resolve_scope(interface) {
+            Ok(scope) => scope.into_values().collect(),
+            Err(_) => interface.types.clone(),
+        };
+        scope.extend(interface.exports.iter().filter_map(|e| match e {
+            PactExport::Type(t) => Some(t.clone()),
+            PactExport::Function(_) => None,
+        }));
+        scope
+    }
+}
+
+/// The interface named in an unclosed `use name.{` that `line` ends in.
+fn use_list(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix("use ")?;
+    let (name, items) = rest.split_once(".{")?;
+    (!items.contains('}')).then_some(name.trim())
+}
+
+/// Whether `line` ends in the interface name of a `use`.
+fn is_use_target(line: &str) -> bool {
+    let rest = line.trim_start().strip_prefix("use ");
+    rest.is_some_and(|rest| rest.trim_start().chars().all(is_ident_char))
+}
+
+fn is_ident(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(is_ident_char)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// The LSP position — line, and column in UTF-16 code units — of byte
+/// `offset` of `src`.
+fn position(src: &str, offset: usize) -> Value {
+    let before = &src[..offset.min(src.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+/// The byte offset of LSP `position` in `src`, clamped to its line.
+fn offset(src: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    let line_start = match line {
+        0 => 0,
+        n => src
+            .match_indices('\n')
+            .nth(n - 1)
+            .map_or(src.len(), |(i, _)| i + 1),
+    };
+    let mut units = 0;
+    for (i, ch) in src[line_start..].char_indices() {
+        if units >= character || ch == '\n' {
+            return line_start + i;
+        }
+        units += ch.len_utf16();
+    }
+    src.len()
+}
+
+fn range(src: &str, span: Span) -> Value {
+    json!({ "start": position(src, span.start), "end": position(src, span.end) })
+}
+
+/// The path of a `file://` URI.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = encoded
+            .get(i + 1..i + 3)
+            .filter(|_| encoded[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// The `file://` URI of an absolute path.
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
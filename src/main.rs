@@ -2,12 +2,16 @@
 //!
 //! Commands:
 //!   packr inspect <wasm>       - Display a package's metadata
+//!   packr replay <wasm> <log>  - Re-execute a recorded call log headlessly
 
 use clap::{Parser, Subcommand};
-use packr::compose::{compose, Component, GraphLink};
+use packr::compose::{
+    compose_with_report, interface_links, resolve_links, Component, ComposeOptions, FieldRename,
+    GraphLink, InterfaceLink, MemoryMode, Reexport, SizeReport,
+};
 use packr::{decode_metadata_with_hashes, Arena, Function, Param, Type};
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "pack")]
@@ -42,6 +46,10 @@ enum Commands {
         /// Output path for the composite wasm
         #[arg(long, short = 'o')]
         output: PathBuf,
+
+        /// Print the per-component size report as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Verify a composite / self-contained actor wasm meets a property
@@ -56,36 +64,89 @@ enum Commands {
         #[arg(long)]
         host_only: bool,
     },
+
+    /// Re-execute a call log recorded by `RecordingInterceptor` against an actor
+    #[cfg(feature = "wasmtime")]
+    Replay {
+        /// Path to the self-contained actor wasm
+        wasm_file: PathBuf,
+
+        /// Path to the call log
+        log_file: PathBuf,
+    },
 }
 
-/// The compose manifest: `[[component]]` entries + `[[link]]` entries.
+/// The compose manifest: `[[component]]` entries + `[[link]]` entries, and
+/// `[[export]]` entries for non-entry exports the composite keeps.
+///
+/// Links are optional: any import whose interface hash matches exactly one
+/// other component's export is wired automatically, so `[[link]]` is only
+/// needed to pick between providers.
+///
+/// `memory = "shared"` links PIC side-module components into one memory;
+/// the default, `"isolated"`, gives each component its own.
+///
+/// `[[rename]]` entries (`record`, `consumer`, `provider`) map a record field
+/// a link's two sides name differently, for the link adapter.
 #[derive(Debug, Deserialize)]
 struct ComposeManifest {
+    #[serde(default)]
+    memory: MemoryMode,
     #[serde(default, rename = "component")]
     components: Vec<ManifestComponent>,
     #[serde(default, rename = "link")]
     links: Vec<ManifestLink>,
+    #[serde(default, rename = "export")]
+    exports: Vec<ManifestExport>,
+    #[serde(default, rename = "rename")]
+    renames: Vec<FieldRename>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ManifestComponent {
     name: String,
-    /// Path to a prebuilt `.wasm`, relative to the manifest file.
-    wasm: String,
+    /// Path to a prebuilt `.wasm` (a plain component or an earlier composite),
+    /// relative to the manifest file.
+    #[serde(default)]
+    wasm: Option<String>,
+    /// Path to a sub-manifest, relative to the manifest file. It is composed
+    /// first and its composite used as this component.
+    #[serde(default)]
+    manifest: Option<String>,
     #[serde(default)]
     entry: bool,
 }
 
+/// A function link (`import = "module.name"`, `export = "name"`) or a
+/// whole-interface link (`interface = "kv"`, `export = "db"`, where `export`
+/// defaults to the consumer's interface name).
 #[derive(Debug, Deserialize)]
 struct ManifestLink {
     /// Name of the consumer component.
     consumer: String,
     /// The consumer's import, formatted `"module.name"`.
-    import: String,
+    #[serde(default)]
+    import: Option<String>,
+    /// The consumer's import interface, linked as a whole.
+    #[serde(default)]
+    interface: Option<String>,
     /// Name of the provider component.
     provider: String,
-    /// The provider's export name.
+    /// The provider's export name, or its export interface for an
+    /// `interface` link.
+    #[serde(default)]
+    export: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestExport {
+    /// Name of the (non-entry) component that provides the export.
+    component: String,
+    /// The component's export name.
     export: String,
+    /// The composite's export name; defaults to `export`.
+    #[serde(default, rename = "as")]
+    as_name: Option<String>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -97,11 +158,101 @@ fn main() -> anyhow::Result<()> {
             hashes,
             json,
         } => inspect_command(&wasm_file, hashes, json),
-        Commands::Compose { manifest, output } => compose_command(&manifest, &output),
+        Commands::Compose {
+            manifest,
+            output,
+            json,
+        } => compose_command(&manifest, &output, json),
         Commands::Verify {
             wasm_file,
             host_only,
         } => verify_command(&wasm_file, host_only),
+        #[cfg(feature = "wasmtime")]
+        Commands::Replay {
+            wasm_file,
+            log_file,
+        } => replay_command(&wasm_file, &log_file),
+    }
+}
+
+/// `packr replay <wasm> <log>`: drive every recorded export call through the
+/// actor, answering its imports from the log. Exits non-zero on the first
+/// divergence.
+#[cfg(feature = "wasmtime")]
+fn replay_command(wasm_file: &PathBuf, log_file: &PathBuf) -> anyhow::Result<()> {
+    use packr::abi::Value;
+    use packr::runtime::{CallInterceptor, CallKind, ReplayInterceptor};
+    use packr::{AsyncCtx, AsyncRuntime};
+    use std::sync::Arc;
+
+    let wasm = std::fs::read(wasm_file)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", wasm_file.display(), e))?;
+    let log = std::fs::read(log_file)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", log_file.display(), e))?;
+    let replay = Arc::new(ReplayInterceptor::from_log(&log)?);
+    let exports: Vec<_> = replay
+        .remaining()
+        .into_iter()
+        .filter(|event| event.kind == CallKind::Export)
+        .collect();
+
+    let tokio = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    tokio.block_on(async {
+        let runtime = AsyncRuntime::new();
+        let module = runtime.load_module(&wasm)?;
+
+        // Every Pack ABI import gets a stub: the replay interceptor answers it
+        // from the log, so the stub only runs once the replay has diverged.
+        let imports: Vec<(String, String)> = module
+            .module()
+            .imports()
+            .filter(|imp| {
+                imp.ty().func().is_some_and(|f| {
+                    f.params().len() == 4
+                        && f.params().all(|p| matches!(p, wasmtime::ValType::I32))
+                        && matches!(
+                            f.results().collect::<Vec<_>>()[..],
+                            [wasmtime::ValType::I32]
+                        )
+                })
+            })
+            .map(|imp| (imp.module().to_string(), imp.name().to_string()))
+            .collect();
+
+        let interceptor: Arc<dyn CallInterceptor> = replay.clone();
+        let mut instance = module
+            .instantiate_with_host_and_interceptor_async((), Some(interceptor), |builder| {
+                for (interface, name) in &imports {
+                    builder
+                        .interface(interface)?
+                        .func_async(name, |_ctx: AsyncCtx<()>, _input: Value| async {
+                            Value::Tuple(vec![])
+                        })?;
+                }
+                Ok(())
+            })
+            .await?;
+
+        for event in &exports {
+            if replay.divergence().is_some() {
+                break;
+            }
+            instance
+                .call_with_value_async(&event.function, &event.input)
+                .await
+                .map_err(|e| anyhow::anyhow!("export {} failed: {}", event.function, e))?;
+        }
+        anyhow::Ok(())
+    })?;
+
+    match replay.finish() {
+        Ok(()) => {
+            println!("replayed {} events, no divergence", replay.replayed());
+            Ok(())
+        }
+        Err(divergence) => anyhow::bail!("{divergence}"),
     }
 }
 
@@ -139,66 +290,195 @@ fn verify_command(wasm_file: &PathBuf, host_only: bool) -> anyhow::Result<()> {
 
 /// `packr compose <manifest> -o <out>`: parse the manifest, read each
 /// component's wasm (relative to the manifest), compose, and write the composite.
-fn compose_command(manifest_path: &PathBuf, output: &PathBuf) -> anyhow::Result<()> {
+/// Prints each component's size before and after dead-code elimination; with
+/// `--json`, only that report, for CI size budgets.
+fn compose_command(manifest_path: &Path, output: &PathBuf, json: bool) -> anyhow::Result<()> {
+    let composed = compose_manifest(manifest_path, &mut Vec::new())
+        .map_err(|e| anyhow::anyhow!("Composition failed: {:#}", e))?;
+
+    std::fs::write(output, &composed.wasm)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", output.display(), e))?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&composed.sizes)?);
+        return Ok(());
+    }
+    println!(
+        "Composed {} component(s), {} link(s) ({} by interface hash) -> {}",
+        composed.components,
+        composed.links,
+        composed.resolved,
+        output.display()
+    );
+    print_sizes(&composed.sizes);
+    Ok(())
+}
+
+/// The size report as a table: each component's functions, code and data
+/// bytes, before -> after dead-code elimination.
+fn print_sizes(report: &SizeReport) {
+    let change = |before: usize, after: usize| format!("{before} -> {after}");
+    println!("\nSize: {} bytes", change(report.before, report.after));
+    println!(
+        "  {:<16} {:>16} {:>20} {:>20}",
+        "component", "functions", "code bytes", "data bytes"
+    );
+    for c in &report.components {
+        println!(
+            "  {:<16} {:>16} {:>20} {:>20}",
+            c.name,
+            change(c.before.functions, c.after.functions),
+            change(c.before.code, c.after.code),
+            change(c.before.data, c.after.data),
+        );
+    }
+}
+
+/// Compose the manifest at `manifest_path`, composing any `manifest = ...`
+/// component first. `including` is the chain of manifests currently being
+/// composed, to reject one that includes itself.
+fn compose_manifest(
+    manifest_path: &Path,
+    including: &mut Vec<PathBuf>,
+) -> anyhow::Result<Composed> {
     let text = std::fs::read_to_string(manifest_path).map_err(|e| {
         anyhow::anyhow!("Failed to read manifest {}: {}", manifest_path.display(), e)
     })?;
-    let manifest: ComposeManifest =
-        toml::from_str(&text).map_err(|e| anyhow::anyhow!("Failed to parse manifest: {}", e))?;
+    let manifest: ComposeManifest = toml::from_str(&text).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to parse manifest {}: {}",
+            manifest_path.display(),
+            e
+        )
+    })?;
+
+    let canonical = manifest_path
+        .canonicalize()
+        .unwrap_or_else(|_| manifest_path.to_path_buf());
+    if including.contains(&canonical) {
+        anyhow::bail!(
+            "manifest {} includes itself (via {})",
+            manifest_path.display(),
+            including
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        );
+    }
+    including.push(canonical);
 
-    // Component wasm paths are relative to the manifest's directory.
-    let base = manifest_path
-        .parent()
-        .unwrap_or_else(|| std::path::Path::new("."));
+    // Component paths are relative to the manifest's directory.
+    let base = manifest_path.parent().unwrap_or_else(|| Path::new("."));
 
     let mut components = Vec::with_capacity(manifest.components.len());
     for c in &manifest.components {
-        let wasm_path = base.join(&c.wasm);
-        let wasm = std::fs::read(&wasm_path).map_err(|e| {
-            anyhow::anyhow!(
-                "Failed to read component `{}` wasm {}: {} \
-                 (component `wasm` paths are resolved relative to the manifest \
-                 directory `{}`, not the cwd)",
-                c.name,
-                wasm_path.display(),
-                e,
-                base.display(),
-            )
-        })?;
+        let wasm = match (&c.wasm, &c.manifest) {
+            (Some(wasm), None) => {
+                let wasm_path = base.join(wasm);
+                std::fs::read(&wasm_path).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to read component `{}` wasm {}: {} \
+                         (component `wasm` paths are resolved relative to the manifest \
+                         directory `{}`, not the cwd)",
+                        c.name,
+                        wasm_path.display(),
+                        e,
+                        base.display(),
+                    )
+                })?
+            }
+            (None, Some(sub)) => {
+                compose_manifest(&base.join(sub), including)
+                    .map_err(|e| anyhow::anyhow!("component `{}`: {:#}", c.name, e))?
+                    .wasm
+            }
+            _ => anyhow::bail!(
+                "component `{}` must set exactly one of `wasm` or `manifest`",
+                c.name
+            ),
+        };
         components.push(Component {
             name: c.name.clone(),
             wasm,
             entry: c.entry,
         });
     }
+    including.pop();
 
     let mut links = Vec::with_capacity(manifest.links.len());
+    let mut whole = Vec::new();
     for l in &manifest.links {
-        let (import_module, import_name) = l.import.split_once('.').ok_or_else(|| {
-            anyhow::anyhow!("link import `{}` must be formatted `module.name`", l.import)
-        })?;
-        links.push(GraphLink {
-            consumer: l.consumer.clone(),
-            import_module: import_module.to_string(),
-            import_name: import_name.to_string(),
-            provider: l.provider.clone(),
-            export_name: l.export.clone(),
-        });
+        match (&l.import, &l.interface) {
+            (Some(import), None) => {
+                let (import_module, import_name) = import.split_once('.').ok_or_else(|| {
+                    anyhow::anyhow!("link import `{import}` must be formatted `module.name`")
+                })?;
+                let export = l.export.clone().ok_or_else(|| {
+                    anyhow::anyhow!("link for import `{import}` must name its `export`")
+                })?;
+                links.push(GraphLink {
+                    consumer: l.consumer.clone(),
+                    import_module: import_module.to_string(),
+                    import_name: import_name.to_string(),
+                    provider: l.provider.clone(),
+                    export_name: export,
+                });
+            }
+            (None, Some(interface)) => whole.push(InterfaceLink {
+                consumer: l.consumer.clone(),
+                import: interface.clone(),
+                provider: l.provider.clone(),
+                export: l.export.clone().unwrap_or_else(|| interface.clone()),
+            }),
+            _ => anyhow::bail!(
+                "a link from component `{}` must set exactly one of `import` or `interface`",
+                l.consumer
+            ),
+        }
     }
+    links.extend(interface_links(&components, &whole)?);
+    let explicit = links.len();
 
-    let composite =
-        compose(components, &links).map_err(|e| anyhow::anyhow!("Composition failed: {:#}", e))?;
-
-    std::fs::write(output, &composite)
-        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", output.display(), e))?;
+    let reexports: Vec<Reexport> = manifest
+        .exports
+        .iter()
+        .map(|e| Reexport {
+            component: e.component.clone(),
+            export_name: e.export.clone(),
+            as_name: e.as_name.clone().unwrap_or_else(|| e.export.clone()),
+        })
+        .collect();
+
+    // Imports the manifest leaves unlinked are wired by interface hash.
+    let links = resolve_links(&components, &links)?;
+    let (count, resolved) = (components.len(), links.len() - explicit);
+    let (wasm, sizes) = compose_with_report(
+        components,
+        &links,
+        &ComposeOptions {
+            exports: reexports,
+            memory: manifest.memory,
+            renames: manifest.renames,
+        },
+    )?;
+    Ok(Composed {
+        wasm,
+        sizes,
+        components: count,
+        links: links.len(),
+        resolved,
+    })
+}
 
-    println!(
-        "Composed {} component(s), {} link(s) -> {}",
-        manifest.components.len(),
-        manifest.links.len(),
-        output.display()
-    );
-    Ok(())
+/// A composed manifest and what went into it.
+struct Composed {
+    wasm: Vec<u8>,
+    sizes: SizeReport,
+    components: usize,
+    links: usize,
+    /// How many of `links` were resolved by interface hash.
+    resolved: usize,
 }
 
 fn inspect_command(wasm_file: &PathBuf, show_hashes: bool, json: bool) -> anyhow::Result<()> {
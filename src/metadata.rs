@@ -860,27 +860,25 @@ fn decode_interface_hash(value: Value) -> Result<InterfaceHash, MetadataError> {
                                     hash = TypeHash::from_bytes(arr);
                                 }
                             }
-                            Value::Tuple(parts) => {
+                            Value::Tuple(parts) if parts.len() == 4 => {
                                 // Legacy: tuple of 4 u64s
-                                if parts.len() == 4 {
-                                    let a = match &parts[0] {
-                                        Value::U64(v) => *v,
-                                        _ => 0,
-                                    };
-                                    let b = match &parts[1] {
-                                        Value::U64(v) => *v,
-                                        _ => 0,
-                                    };
-                                    let c = match &parts[2] {
-                                        Value::U64(v) => *v,
-                                        _ => 0,
-                                    };
-                                    let d = match &parts[3] {
-                                        Value::U64(v) => *v,
-                                        _ => 0,
-                                    };
-                                    hash = TypeHash::from_u64s(a, b, c, d);
-                                }
+                                let a = match &parts[0] {
+                                    Value::U64(v) => *v,
+                                    _ => 0,
+                                };
+                                let b = match &parts[1] {
+                                    Value::U64(v) => *v,
+                                    _ => 0,
+                                };
+                                let c = match &parts[2] {
+                                    Value::U64(v) => *v,
+                                    _ => 0,
+                                };
+                                let d = match &parts[3] {
+                                    Value::U64(v) => *v,
+                                    _ => 0,
+                                };
+                                hash = TypeHash::from_u64s(a, b, c, d);
                             }
                             _ => {}
                         }
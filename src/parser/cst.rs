@@ -0,0 +1,251 @@
+//! A lossless concrete syntax tree for Pact source.
+//!
+//! Unlike the parser in `pact.rs`, which keeps only what the AST needs, the
+//! CST keeps every byte: whitespace and comments are tokens too, and printing
+//! the tree gives back the source exactly. The tree knows only tokens and
+//! bracket nesting — `{}`, `()` and `<>` — so it covers every Pact dialect
+//! (interfaces, worlds, metadata, `use` transforms) without knowing their
+//! grammar. The formatter in `fmt.rs` is built on it.
+
+use super::span::{FileId, Span};
+use super::ParseError;
+use std::fmt;
+
+/// What a [`CstToken`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// An identifier or keyword; Pact identifiers may contain `-`
+    Ident,
+    /// A string literal, quotes and escapes included
+    String,
+    Number,
+    /// `->`
+    Arrow,
+    /// A single punctuation character
+    Symbol,
+    Whitespace,
+    /// `// ...`, without its newline
+    LineComment,
+    /// `/* ... */`
+    BlockComment,
+}
+
+impl TokenKind {
+    /// Whitespace and comments.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+        )
+    }
+}
+
+/// A token and its exact source text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl CstToken {
+    /// Whether this is the symbol `ch`.
+    pub fn is_symbol(&self, ch: char) -> bool {
+        self.kind == TokenKind::Symbol && self.text.len() == 1 && self.text.starts_with(ch)
+    }
+}
+
+/// A bracketed group: its delimiters and everything between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstGroup {
+    pub open: CstToken,
+    pub children: Vec<CstElement>,
+    pub close: CstToken,
+}
+
+impl CstGroup {
+    /// The opening delimiter: `{`, `(` or `<`.
+    pub fn delimiter(&self) -> char {
+        self.open.text.chars().next().unwrap_or('{')
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Token(CstToken),
+    Group(CstGroup),
+}
+
+/// A whole source file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst {
+    pub children: Vec<CstElement>,
+}
+
+impl Cst {
+    /// Build the tree for `src`. Fails only on an unterminated string or block
+    /// comment, or on unbalanced brackets.
+    pub fn parse(src: &str, file: FileId) -> Result<Cst, ParseError> {
+        let mut stack: Vec<(CstToken, Vec<CstElement>)> = Vec::new();
+        let mut children = Vec::new();
+        for token in lex(src, file)? {
+            let ch = token.text.chars().next();
+            match (token.kind, ch) {
+                (TokenKind::Symbol, Some('{' | '(' | '<')) => {
+                    stack.push((token, std::mem::take(&mut children)));
+                }
+                (TokenKind::Symbol, Some(close @ ('}' | ')' | '>'))) => {
+                    let Some((open, outer)) = stack.pop() else {
+                        return Err(unexpected(&token));
+                    };
+                    if closing(&open) != close {
+                        return Err(unexpected(&token));
+                    }
+                    let inner = std::mem::replace(&mut children, outer);
+                    children.push(CstElement::Group(CstGroup {
+                        open,
+                        children: inner,
+                        close: token,
+                    }));
+                }
+                _ => children.push(CstElement::Token(token)),
+            }
+        }
+        match stack.pop() {
+            Some((open, _)) => Err(ParseError::UnexpectedEof.at(open.span)),
+            None => Ok(Cst { children }),
+        }
+    }
+
+    /// Every token in source order, trivia included.
+    pub fn tokens(&self) -> Vec<&CstToken> {
+        let mut out = Vec::new();
+        collect_tokens(&self.children, &mut out);
+        out
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tokens().iter().try_for_each(|t| f.write_str(&t.text))
+    }
+}
+
+fn collect_tokens<'a>(elements: &'a [CstElement], out: &mut Vec<&'a CstToken>) {
+    for element in elements {
+        match element {
+            CstElement::Token(token) => out.push(token),
+            CstElement::Group(group) => {
+                out.push(&group.open);
+                collect_tokens(&group.children, out);
+                out.push(&group.close);
+            }
+        }
+    }
+}
+
+fn closing(open: &CstToken) -> char {
+    match open.text.as_str() {
+        "(" => ')',
+        "<" => '>',
+        _ => '}',
+    }
+}
+
+fn unexpected(token: &CstToken) -> ParseError {
+    ParseError::UnexpectedToken(token.text.clone()).at(token.span)
+}
+
+/// Split `src` into tokens, trivia included. The token boundaries match the
+/// Pact tokenizer's, so the CST and the parser agree on what a token is.
+fn lex(src: &str, file: FileId) -> Result<Vec<CstToken>, ParseError> {
+    let mut tokens = Vec::new();
+    let bytes = src.as_bytes();
+    let mut start = 0;
+    while let Some(ch) = src[start..].chars().next() {
+        let rest = &src[start..];
+        let (kind, len) = if ch.is_whitespace() {
+            let len = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            (TokenKind::Whitespace, len)
+        } else if rest.starts_with("//") {
+            (
+                TokenKind::LineComment,
+                rest.find('\n').unwrap_or(rest.len()),
+            )
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            let Some(end) = body.find("*/") else {
+                return Err(ParseError::UnexpectedEof.at(Span::new(file, start, src.len())));
+            };
+            (TokenKind::BlockComment, end + 4)
+        } else if ch == '"' {
+            let mut escaped = false;
+            let end = rest[1..].find(|c: char| {
+                let close = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                close
+            });
+            let Some(end) = end else {
+                return Err(ParseError::UnexpectedEof.at(Span::new(file, start, src.len())));
+            };
+            (TokenKind::String, end + 2)
+        } else if ch.is_ascii_digit()
+            || (ch == '-' && bytes.get(start + 1).is_some_and(u8::is_ascii_digit))
+        {
+            let len = rest[1..]
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .map_or(rest.len(), |i| i + 1);
+            (TokenKind::Number, len)
+        } else if ch.is_ascii_alphabetic() || ch == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(rest.len());
+            (TokenKind::Ident, len)
+        } else if rest.starts_with("->") {
+            (TokenKind::Arrow, 2)
+        } else {
+            (TokenKind::Symbol, ch.len_utf8())
+        };
+        tokens.push(CstToken {
+            kind,
+            text: rest[..len].to_string(),
+            span: Span::new(file, start, start + len),
+        });
+        start += len;
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_source_exactly() {
+        let src = "// api\ninterface api {\n    @version: string = \"1.0\"  /* pinned */\n\n    \
+                   record page<t> { items: list<t>, next: option<u64> }\n    exports {\n        \
+                   get: func(id: s64) -> result<page<user>, string> // fetch\n    }\n}\n";
+        let cst = Cst::parse(src, FileId::default()).expect("parse");
+        assert_eq!(cst.to_string(), src);
+
+        let kinds: Vec<_> = cst.tokens().iter().take(4).map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::LineComment,
+                TokenKind::Whitespace,
+                TokenKind::Ident,
+                TokenKind::Whitespace
+            ]
+        );
+    }
+
+    #[test]
+    fn unbalanced_brackets_are_located() {
+        let src = "interface api {\n    record user { id: u64 )\n}\n";
+        let err = Cst::parse(src, FileId::default()).expect_err("mismatched");
+        let span = err.span().expect("located");
+        assert_eq!(&src[span.start..span.end], ")");
+    }
+}
@@ -0,0 +1,376 @@
+//! The canonical layout for Pact source, as printed by `pact fmt`.
+//!
+//! Formatting works on the lossless [`Cst`], so comments survive and the
+//! token stream is never changed — only the whitespace between tokens is:
+//!
+//! - a `{ ... }` body (an interface, world, record, `exports`, ...) puts each
+//!   item on its own line, indented four spaces per level; an empty one is
+//!   `{}`. Items are split after `,` and `;` and at the source's line breaks,
+//!   except where a line continues the one before (`->`, `=`, `.`, ...);
+//! - everything else in an item — generics, parameter lists, `use` lists and
+//!   transforms, metadata values — is laid out on one line, with a space after
+//!   `,` and `:`, around `=` and `->`, and none inside brackets;
+//! - a comment stays on its own line or at the end of the item it followed;
+//!   runs of blank lines collapse to one, and the file ends with a newline.
+//!
+//! A bracketed group holding a line comment is laid out like a body, since
+//! joining it onto one line would comment out the rest.
+
+use super::cst::{Cst, CstElement, CstGroup, CstToken, TokenKind};
+use super::span::FileId;
+use super::ParseError;
+
+const INDENT: &str = "    ";
+
+/// Format Pact source. Fails only where [`Cst::parse`] does.
+pub fn format_pact(src: &str) -> Result<String, ParseError> {
+    let cst = Cst::parse(src, FileId::default())?;
+    let mut out = String::new();
+    write_lines(&split_lines(&cst.children), 0, &mut out);
+    Ok(out)
+}
+
+/// One line of a body.
+enum Line<'a> {
+    Item {
+        elements: Vec<&'a CstElement>,
+        comment: Option<&'a CstToken>,
+        blank_before: bool,
+    },
+    Comment {
+        comment: &'a CstToken,
+        blank_before: bool,
+    },
+}
+
+/// Split a body's elements into lines.
+fn split_lines(elements: &[CstElement]) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut current: Vec<&CstElement> = Vec::new();
+    // Line breaks seen since the last line ended.
+    let mut newlines = 0;
+    let mut blank_before = false;
+
+    for element in elements {
+        if let CstElement::Token(token) = element {
+            match token.kind {
+                TokenKind::Whitespace => {
+                    newlines += token.text.matches('\n').count();
+                    continue;
+                }
+                TokenKind::LineComment => {
+                    if !current.is_empty() && newlines == 0 {
+                        lines.push(Line::Item {
+                            elements: std::mem::take(&mut current),
+                            comment: Some(token),
+                            blank_before,
+                        });
+                    } else if let (true, 0, Some(Line::Item { comment, .. })) =
+                        (current.is_empty(), newlines, lines.last_mut())
+                    {
+                        if comment.is_none() {
+                            *comment = Some(token);
+                        } else {
+                            lines.push(Line::Comment {
+                                comment: token,
+                                blank_before: false,
+                            });
+                        }
+                    } else {
+                        if !current.is_empty() {
+                            lines.push(Line::Item {
+                                elements: std::mem::take(&mut current),
+                                comment: None,
+                                blank_before,
+                            });
+                        }
+                        lines.push(Line::Comment {
+                            comment: token,
+                            blank_before: newlines >= 2 && !lines.is_empty(),
+                        });
+                    }
+                    newlines = 0;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if newlines > 0 && !current.is_empty() && !continues(&current, element) {
+            lines.push(Line::Item {
+                elements: std::mem::take(&mut current),
+                comment: None,
+                blank_before,
+            });
+        }
+        if current.is_empty() {
+            blank_before = newlines >= 2 && !lines.is_empty();
+        }
+        newlines = 0;
+        let ends_item = match element {
+            CstElement::Token(token) => token.is_symbol(',') || token.is_symbol(';'),
+            CstElement::Group(group) => is_block(current.last().copied(), group),
+        };
+        current.push(element);
+        if ends_item {
+            lines.push(Line::Item {
+                elements: std::mem::take(&mut current),
+                comment: None,
+                blank_before,
+            });
+        }
+    }
+    if !current.is_empty() {
+        lines.push(Line::Item {
+            elements: current,
+            comment: None,
+            blank_before,
+        });
+    }
+    lines
+}
+
+/// Whether `next`, which follows a line break, continues the item `current`
+/// rather than starting a new one.
+fn continues(current: &[&CstElement], next: &CstElement) -> bool {
+    let token = match next {
+        CstElement::Group(_) => return true,
+        CstElement::Token(token) => token,
+    };
+    let last = match current.last() {
+        Some(CstElement::Token(last)) => last,
+        _ => return token.kind == TokenKind::Arrow || token.is_symbol('='),
+    };
+    token.kind == TokenKind::Arrow
+        || [':', '=', '.'].iter().any(|&c| token.is_symbol(c))
+        || last.kind == TokenKind::Arrow
+        || [':', '=', '.', '@', '/'].iter().any(|&c| last.is_symbol(c))
+}
+
+/// Whether `group`, following `prev` in its item, is a body laid out one item
+/// per line.
+fn is_body(prev: Option<&CstElement>, group: &CstGroup) -> bool {
+    let has_line_comment = group
+        .children
+        .iter()
+        .any(|e| matches!(e, CstElement::Token(t) if t.kind == TokenKind::LineComment));
+    has_line_comment || is_block(prev, group)
+}
+
+/// Whether `group`, following `prev`, is the `{ ... }` body of a definition,
+/// which ends its item.
+fn is_block(prev: Option<&CstElement>, group: &CstGroup) -> bool {
+    group.delimiter() == '{'
+        && match prev {
+            Some(CstElement::Token(token)) => token.kind == TokenKind::Ident,
+            Some(CstElement::Group(generics)) => generics.delimiter() == '<',
+            None => false,
+        }
+}
+
+fn write_lines(lines: &[Line<'_>], depth: usize, out: &mut String) {
+    for line in lines {
+        let (Line::Item { blank_before, .. } | Line::Comment { blank_before, .. }) = line;
+        if *blank_before {
+            out.push('\n');
+        }
+        out.push_str(&INDENT.repeat(depth));
+        match line {
+            Line::Item {
+                elements, comment, ..
+            } => {
+                write_item(elements, depth, out);
+                if let Some(comment) = comment {
+                    out.push(' ');
+                    out.push_str(comment.text.trim_end());
+                }
+            }
+            Line::Comment { comment, .. } => out.push_str(comment.text.trim_end()),
+        }
+        out.push('\n');
+    }
+}
+
+/// A piece of an item's one-line layout.
+enum Piece<'a> {
+    Token(&'a CstToken),
+    Body(&'a CstGroup),
+}
+
+fn flatten<'a>(elements: &[&'a CstElement], pieces: &mut Vec<Piece<'a>>) {
+    let mut prev = None;
+    for &element in elements {
+        match element {
+            CstElement::Token(token) if token.kind == TokenKind::Whitespace => continue,
+            CstElement::Token(token) => pieces.push(Piece::Token(token)),
+            CstElement::Group(group) if is_body(prev, group) => pieces.push(Piece::Body(group)),
+            CstElement::Group(group) => {
+                pieces.push(Piece::Token(&group.open));
+                flatten(&group.children.iter().collect::<Vec<_>>(), pieces);
+                pieces.push(Piece::Token(&group.close));
+            }
+        }
+        prev = Some(element);
+    }
+}
+
+fn write_item(elements: &[&CstElement], depth: usize, out: &mut String) {
+    let mut pieces = Vec::new();
+    flatten(elements, &mut pieces);
+
+    let mut prev: Option<&CstToken> = None;
+    for (i, piece) in pieces.iter().enumerate() {
+        let token = match piece {
+            Piece::Token(token) => token,
+            Piece::Body(group) => &&group.open,
+        };
+        let after = match pieces.get(i + 1) {
+            Some(Piece::Token(next)) => Some(*next),
+            _ => None,
+        };
+        if prev.is_some_and(|prev| space_between(prev, token, after)) {
+            out.push(' ');
+        }
+        match piece {
+            Piece::Token(token) => {
+                out.push_str(&token.text);
+                prev = Some(token);
+            }
+            Piece::Body(group) => {
+                out.push_str(&group.open.text);
+                let lines = split_lines(&group.children);
+                if !lines.is_empty() {
+                    out.push('\n');
+                    write_lines(&lines, depth + 1, out);
+                    out.push_str(&INDENT.repeat(depth));
+                }
+                out.push_str(&group.close.text);
+                prev = Some(&group.close);
+            }
+        }
+    }
+}
+
+/// Whether a space goes between `prev` and `next`, with `after` the token
+/// after `next`.
+fn space_between(prev: &CstToken, next: &CstToken, after: Option<&CstToken>) -> bool {
+    let closes = [',', ';', ':', ')', '>', '}', '.', '/'];
+    if closes.iter().any(|&c| next.is_symbol(c)) {
+        return false;
+    }
+    let opens = ['(', '<', '{', '.', '@', '/'];
+    if opens.iter().any(|&c| prev.is_symbol(c)) {
+        return false;
+    }
+    // `namespace:package/interface` stays packed.
+    if prev.is_symbol(':') {
+        return !after.is_some_and(|t| t.is_symbol('/'));
+    }
+    if next.is_symbol('(') || next.is_symbol('<') {
+        return prev.kind != TokenKind::Ident;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The non-whitespace tokens of `src`.
+    fn significant(src: &str) -> Vec<String> {
+        let cst = Cst::parse(src, FileId::default()).expect("parse");
+        cst.tokens()
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| t.text.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lays_out_an_interface() {
+        let src = r#"// The api.
+interface   api{
+  @version : string="1.2.0"
+  @limits: tuple<u32,u32> = {max:10, burst : 2}
+  use types.{ user,status }
+  use rpc( calculator )   // transformed
+
+
+  record page < t > { items : list<t> , next:option<u64> }
+  variant shape{circle(f32),rect(tuple<f32,f32>)}
+  interface remote = rpc (calc)
+  exports {
+    get : func ( id : u64 ) -> result < page<user> , string >
+    reset: func()
+  }
+  interface inner {}
+}
+"#;
+        let want = r#"// The api.
+interface api {
+    @version: string = "1.2.0"
+    @limits: tuple<u32, u32> = {max: 10, burst: 2}
+    use types.{user, status}
+    use rpc(calculator) // transformed
+
+    record page<t> {
+        items: list<t>,
+        next: option<u64>
+    }
+    variant shape {
+        circle(f32),
+        rect(tuple<f32, f32>)
+    }
+    interface remote = rpc(calc)
+    exports {
+        get: func(id: u64) -> result<page<user>, string>
+        reset: func()
+    }
+    interface inner {}
+}
+"#;
+        let formatted = format_pact(src).expect("format");
+        assert_eq!(formatted, want);
+        assert_eq!(significant(&formatted), significant(src));
+        assert_eq!(format_pact(&formatted).expect("format"), formatted);
+    }
+
+    #[test]
+    fn lays_out_a_world() {
+        let src =
+            "use \"../shared.pact\".{entry};\n@forward-compatible\nrecord entry { id: u64 }\n\
+                   world app {\n  import wasi:cli/env\n  import log { info: func(msg: string) }\n  \
+                   export run: func(\n    // the input\n    x: u32) -> u32\n}\n";
+        let want = "use \"../shared.pact\".{entry};\n@forward-compatible\nrecord entry {\n    id: u64\n}\n\
+                    world app {\n    import wasi:cli/env\n    import log {\n        \
+                    info: func(msg: string)\n    }\n    export run: func(\n        // the input\n        \
+                    x: u32\n    ) -> u32\n}\n";
+        let formatted = format_pact(src).expect("format");
+        assert_eq!(formatted, want);
+        assert_eq!(significant(&formatted), significant(src));
+        assert_eq!(format_pact(&formatted).expect("format"), formatted);
+    }
+
+    #[test]
+    fn repository_pact_files_keep_their_tokens_and_are_stable() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut dirs = vec![root.join("packages"), root.join("examples")];
+        let mut seen = 0;
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir).expect("read dir") {
+                let path = entry.expect("entry").path();
+                if path.is_dir() && !path.ends_with("target") {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|e| e == "pact") {
+                    let src = std::fs::read_to_string(&path).expect("read");
+                    let formatted = format_pact(&src).expect("format");
+                    assert_eq!(significant(&formatted), significant(&src), "{path:?}");
+                    let again = format_pact(&formatted).expect("format");
+                    assert_eq!(again, formatted, "{path:?}");
+                    seen += 1;
+                }
+            }
+        }
+        assert!(seen > 0);
+    }
+}
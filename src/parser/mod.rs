@@ -3,20 +3,29 @@
 //! Parses Pact interface definitions.
 //! The module focuses on parsing; types are defined in `crate::types`.
 
+mod cst;
+mod fmt;
 mod pact;
+mod span;
 mod validation;
 mod world;
 
+pub use cst::{Cst, CstElement, CstGroup, CstToken, TokenKind};
+pub use fmt::format_pact;
+pub(crate) use pact::BUILTIN_TYPES;
 pub use pact::{
-    parse_pact, parse_pact_dir, parse_pact_dir_with_registry, parse_pact_file, InterfaceAlias,
-    InterfaceTypes, Metadata, MetadataValue, PactExport, PactFileError, PactImport, PactInterface,
-    PactUse, ResolvedScope, ResolvedUse, TypeParam, TypeRegistry,
+    parse_pact, parse_pact_dir, parse_pact_dir_in, parse_pact_dir_lenient_in,
+    parse_pact_dir_with_registry, parse_pact_file, parse_pact_file_in, parse_pact_source,
+    InterfaceAlias, InterfaceTypes, Metadata, MetadataValue, PactExport, PactFileError, PactImport,
+    PactInterface, PactSpans, PactUse, ResolvedScope, ResolvedUse, TypeParam, TypeRegistry,
 };
+pub use span::{suggest, Diagnostic, FileId, SourceFile, SourceMap, Span};
 
 // The legacy world/interface parser is deprecated - use parse_pact() instead.
 // Kept for internal tests only.
 pub use validation::{
-    decode_with_schema, encode_with_schema, validate_graph_against_type, ValidationError,
+    decode_with_schema, encode_compact_with_schema, encode_with_schema,
+    validate_graph_against_type, ValidationError,
 };
 #[doc(hidden)]
 #[allow(deprecated)]
@@ -41,6 +50,48 @@ pub enum ParseError {
     UndefinedType(String),
     #[error("Self reference used outside of a type definition")]
     SelfRefOutsideType,
+    /// Any of the above, at a known place in the source.
+    #[error("{error}")]
+    At { span: Span, error: Box<ParseError> },
+}
+
+impl ParseError {
+    /// Where in the source the error is, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::At { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// The error without its location.
+    pub fn kind(&self) -> &ParseError {
+        match self {
+            ParseError::At { error, .. } => error.kind(),
+            other => other,
+        }
+    }
+
+    /// Locate the error at `span`, unless it already has a location.
+    pub fn at(self, span: Span) -> ParseError {
+        match self {
+            ParseError::At { .. } => self,
+            error => ParseError::At {
+                span,
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        let label = match error.kind() {
+            ParseError::UnexpectedEof => "input ends here",
+            _ => "here",
+        };
+        Diagnostic::new(error.kind().to_string(), error.span()).with_label(label)
+    }
 }
 
 // ============================================================================
@@ -5,12 +5,17 @@
 //! Pact features:
 //! - First-class interfaces with imports/exports
 //! - Metadata annotations (@name: Type = value)
+//! - `@forward-compatible` records, which tolerate added and removed fields
 //! - Generic type parameters with interface constraints
 //! - Nested interfaces for namespacing
+//! - Interface transforms, declared with `transform` and applied by `use`
 
+use super::span::{suggest, Diagnostic, FileId, SourceMap, Span};
 use super::{Arena, Case, Field, Function, Param, ParseError, Type, TypeDef};
-use std::collections::HashMap;
+use crate::transform::{fill, DeclaredTransform, TransformRegistry, HOLE};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::Arc;
 
 // ============================================================================
 // Pact AST Types
@@ -37,8 +42,39 @@ pub struct PactInterface {
     pub children: Vec<PactInterface>,
     /// Interface aliases (transformed interfaces)
     pub aliases: Vec<InterfaceAlias>,
+    /// Transforms declared with `transform`
+    pub transforms: Vec<DeclaredTransform>,
+    /// Types annotated `@forward-compatible`: their decode, like
+    /// `#[graph(forward_compatible)]`, defaults a missing field and ignores
+    /// an extra one
+    pub forward_compatible: HashSet<String>,
+    /// Where the interface is in its source (the whole file for a root one)
+    pub span: Span,
+    /// Where its items and type references are
+    pub spans: PactSpans,
 }
 
+/// Source locations for a [`PactInterface`]'s items.
+///
+/// `TypeDef` and `Function` are shared with the hashed type model, so their
+/// spans are kept here, keyed by name, rather than on the items themselves.
+#[derive(Debug, Clone, Default)]
+pub struct PactSpans {
+    /// Type definitions, by name
+    pub types: HashMap<String, Span>,
+    /// Exported functions, by name
+    pub functions: HashMap<String, Span>,
+    /// Every named type reference, in source order — generic parameters of
+    /// the enclosing definition excluded
+    pub references: Vec<(String, Span)>,
+}
+
+/// Type names the parser knows without a definition, for suggestions.
+pub(crate) const BUILTIN_TYPES: &[&str] = &[
+    "bool", "u8", "u16", "u32", "u64", "s8", "s16", "s32", "s64", "f32", "f64", "char", "string",
+    "value", "list", "option", "tuple", "result", "map", "set",
+];
+
 impl PactInterface {
     pub fn new(name: impl Into<String>) -> Self {
         Self {
@@ -51,6 +87,74 @@ impl PactInterface {
             exports: Vec::new(),
             children: Vec::new(),
             aliases: Vec::new(),
+            transforms: Vec::new(),
+            forward_compatible: HashSet::new(),
+            span: Span::default(),
+            spans: PactSpans::default(),
+        }
+    }
+
+    /// Find every named type reference that nothing in scope defines, in this
+    /// interface and its nested ones.
+    ///
+    /// In scope are the interface's own types and type parameters, those of
+    /// the interfaces it is nested in, and whatever its `use` declarations
+    /// bring in. A `use` of a whole interface needs `registry` to know what
+    /// that is; without it (or for a transformed `use`), the interface's
+    /// references are not checked. Each diagnostic suggests the closest
+    /// defined name when there is one.
+    pub fn check_types(&self, registry: Option<&TypeRegistry>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.check_types_in(&[], registry, &mut diagnostics);
+        diagnostics
+    }
+
+    fn check_types_in<'a>(
+        &'a self,
+        outer: &[&'a str],
+        registry: Option<&'a TypeRegistry>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let mut scope = outer.to_vec();
+        scope.extend(self.types.iter().map(|t| t.name()));
+        scope.extend(self.type_params.iter().map(|p| p.name.as_str()));
+        scope.extend(self.exports.iter().filter_map(|e| match e {
+            PactExport::Type(t) => Some(t.name()),
+            PactExport::Function(_) => None,
+        }));
+
+        let mut open = false;
+        for use_decl in &self.uses {
+            if !use_decl.transform_args.is_empty() {
+                open = true;
+            } else if !use_decl.items.is_empty() {
+                scope.extend(use_decl.items.iter().map(String::as_str));
+            } else {
+                match registry.and_then(|r| r.interfaces.get(&use_decl.interface)) {
+                    Some(used) => scope.extend(used.types.keys().map(String::as_str)),
+                    None => open = true,
+                }
+            }
+        }
+
+        if !open {
+            for (name, span) in &self.spans.references {
+                if scope.contains(&name.as_str()) {
+                    continue;
+                }
+                let mut diagnostic =
+                    Diagnostic::new(format!("undefined type `{name}`"), Some(*span))
+                        .with_label("not defined in scope");
+                let candidates = scope.iter().copied().chain(BUILTIN_TYPES.iter().copied());
+                if let Some(close) = suggest(name, candidates) {
+                    diagnostic = diagnostic.with_help(format!("did you mean `{close}`?"));
+                }
+                diagnostics.push(diagnostic);
+            }
+        }
+
+        for child in &self.children {
+            child.check_types_in(&scope, registry, diagnostics);
         }
     }
 
@@ -194,15 +298,19 @@ pub struct TypeParam {
 /// - `use types.{chain, actor-id}` - import specific items from interface
 /// - `use logger` - import all items from interface
 /// - `use rpc(calculator)` - import transformed interface
+/// - `use traced(rpc(calculator))` - import an interface transformed twice
 #[derive(Debug, Clone)]
 pub struct PactUse {
     /// Source interface name, or transform name if transform_args is non-empty
     pub interface: String,
     /// Transform arguments (e.g., ["calculator"] for rpc(calculator))
-    /// When non-empty, `interface` is the transform name
+    /// When non-empty, `interface` is the transform name. A nested
+    /// application is kept in source form: ["rpc(calculator)"].
     pub transform_args: Vec<String>,
     /// Items to bring into scope (empty means all)
     pub items: Vec<String>,
+    /// Where the declaration is, `use` keyword included
+    pub span: Span,
 }
 
 /// An import declaration.
@@ -234,7 +342,8 @@ pub struct InterfaceAlias {
     pub name: String,
     /// The transform to apply (e.g., "rpc")
     pub transform: String,
-    /// Arguments to the transform (e.g., ["calculator"])
+    /// Arguments to the transform (e.g., ["calculator"]), nested
+    /// applications in source form as in [`PactUse::transform_args`]
     pub args: Vec<String>,
 }
 
@@ -252,11 +361,15 @@ enum Token {
     Eof,
 }
 
-fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+fn tokenize(src: &str, file: FileId) -> Result<Vec<(Token, Span)>, ParseError> {
     let mut tokens = Vec::new();
-    let mut chars = src.chars().peekable();
+    let mut chars = src.char_indices().peekable();
+    // The byte offset of the next character, or the end of the source
+    let offset = |chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>| {
+        chars.peek().map_or(src.len(), |&(i, _)| i)
+    };
 
-    while let Some(&ch) = chars.peek() {
+    while let Some(&(start, ch)) = chars.peek() {
         // Skip whitespace
         if ch.is_whitespace() {
             chars.next();
@@ -266,9 +379,9 @@ fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
         // Comments
         if ch == '/' {
             chars.next();
-            if matches!(chars.peek(), Some('/')) {
+            if matches!(chars.peek(), Some((_, '/'))) {
                 // Line comment
-                for next in chars.by_ref() {
+                for (_, next) in chars.by_ref() {
                     if next == '\n' {
                         break;
                     }
@@ -276,14 +389,14 @@ fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
                 continue;
             }
             // Standalone / is a symbol
-            tokens.push(Token::Symbol('/'));
+            tokens.push((Token::Symbol('/'), Span::new(file, start, start + 1)));
             continue;
         }
 
         // @ for metadata
         if ch == '@' {
-            tokens.push(Token::At);
             chars.next();
+            tokens.push((Token::At, Span::new(file, start, start + 1)));
             continue;
         }
 
@@ -291,14 +404,14 @@ fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
         if ch == '"' {
             chars.next();
             let mut s = String::new();
-            while let Some(&next) = chars.peek() {
+            while let Some(&(_, next)) = chars.peek() {
                 if next == '"' {
                     chars.next();
                     break;
                 }
                 if next == '\\' {
                     chars.next();
-                    if let Some(&escaped) = chars.peek() {
+                    if let Some(&(_, escaped)) = chars.peek() {
                         match escaped {
                             'n' => s.push('\n'),
                             't' => s.push('\t'),
@@ -314,20 +427,21 @@ fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
                     chars.next();
                 }
             }
-            tokens.push(Token::String(s));
+            let end = offset(&mut chars);
+            tokens.push((Token::String(s), Span::new(file, start, end)));
             continue;
         }
 
         // Numbers
         if ch.is_ascii_digit()
-            || (ch == '-' && matches!(chars.clone().nth(1), Some(c) if c.is_ascii_digit()))
+            || (ch == '-' && matches!(chars.clone().nth(1), Some((_, c)) if c.is_ascii_digit()))
         {
             let mut num = String::new();
             if ch == '-' {
                 num.push(ch);
                 chars.next();
             }
-            while let Some(&next) = chars.peek() {
+            while let Some(&(_, next)) = chars.peek() {
                 if next.is_ascii_digit() || next == '.' {
                     num.push(next);
                     chars.next();
@@ -335,14 +449,15 @@ fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
                     break;
                 }
             }
-            tokens.push(Token::Number(num));
+            let end = offset(&mut chars);
+            tokens.push((Token::Number(num), Span::new(file, start, end)));
             continue;
         }
 
         // Identifiers
         if is_ident_start(ch) {
             let mut ident = String::new();
-            while let Some(&next) = chars.peek() {
+            while let Some(&(_, next)) = chars.peek() {
                 if is_ident_continue(next) {
                     ident.push(next);
                     chars.next();
@@ -350,21 +465,23 @@ fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
                     break;
                 }
             }
-            tokens.push(Token::Ident(ident));
+            let end = offset(&mut chars);
+            tokens.push((Token::Ident(ident), Span::new(file, start, end)));
             continue;
         }
 
         // Symbols
         if is_symbol(ch) {
-            tokens.push(Token::Symbol(ch));
             chars.next();
+            tokens.push((Token::Symbol(ch), Span::new(file, start, start + 1)));
             continue;
         }
 
-        return Err(ParseError::UnexpectedToken(ch.to_string()));
+        let span = Span::new(file, start, start + ch.len_utf8());
+        return Err(ParseError::UnexpectedToken(ch.to_string()).at(span));
     }
 
-    tokens.push(Token::Eof);
+    tokens.push((Token::Eof, Span::new(file, src.len(), src.len())));
     Ok(tokens)
 }
 
@@ -389,12 +506,32 @@ fn is_symbol(ch: char) -> bool {
 
 struct Parser {
     tokens: Vec<Token>,
+    spans: Vec<Span>,
     pos: usize,
+    /// The span of the last token consumed
+    last: Span,
+    /// Generic parameters of the definition being parsed, which type
+    /// references may name without a definition
+    generics: Vec<String>,
+    /// Named type references seen since the last item was finished
+    references: Vec<(String, Span)>,
+    /// Whether `_` is a wrapper's hole rather than `unit`
+    holes: bool,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+    fn new(tokens: Vec<(Token, Span)>) -> Self {
+        let (tokens, spans): (Vec<_>, Vec<_>) = tokens.into_iter().unzip();
+        let last = spans.first().copied().unwrap_or_default();
+        Self {
+            tokens,
+            spans,
+            pos: 0,
+            last,
+            generics: Vec::new(),
+            references: Vec::new(),
+            holes: false,
+        }
     }
 
     fn is_eof(&self) -> bool {
@@ -405,14 +542,30 @@ impl Parser {
         self.tokens.get(self.pos).unwrap_or(&Token::Eof)
     }
 
+    /// The span of the next token.
+    fn here(&self) -> Span {
+        self.spans
+            .get(self.pos)
+            .or(self.spans.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
     fn next(&mut self) -> Token {
         let tok = self.peek().clone();
+        self.last = self.here();
         if !matches!(tok, Token::Eof) {
             self.pos += 1;
         }
         tok
     }
 
+    /// Consume the next token, which the caller has already matched.
+    fn bump(&mut self) {
+        self.last = self.here();
+        self.pos += 1;
+    }
+
     fn expect_symbol(&mut self, expected: char) -> Result<(), ParseError> {
         match self.next() {
             Token::Symbol(ch) if ch == expected => Ok(()),
@@ -427,7 +580,7 @@ impl Parser {
 
     fn accept_symbol(&mut self, expected: char) -> bool {
         if matches!(self.peek(), Token::Symbol(ch) if *ch == expected) {
-            self.pos += 1;
+            self.bump();
             true
         } else {
             false
@@ -447,14 +600,14 @@ impl Parser {
 
     fn accept_ident(&mut self, expected: &str) -> bool {
         matches!(self.peek(), Token::Ident(name) if name == expected) && {
-            self.pos += 1;
+            self.bump();
             true
         }
     }
 
     fn accept_at(&mut self) -> bool {
         if matches!(self.peek(), Token::At) {
-            self.pos += 1;
+            self.bump();
             true
         } else {
             false
@@ -491,19 +644,30 @@ impl Parser {
 
 /// Parse a Pact interface definition from source string.
 pub fn parse_pact(src: &str) -> Result<PactInterface, ParseError> {
-    let tokens = tokenize(src)?;
-    let mut parser = Parser::new(tokens);
+    parse_pact_source(src, FileId::default())
+}
 
+/// [`parse_pact`] for the source registered as `file`, which every span in
+/// the result (or the error) points into.
+pub fn parse_pact_source(src: &str, file: FileId) -> Result<PactInterface, ParseError> {
+    let mut parser = Parser::new(tokenize(src, file)?);
+    parse_root(&mut parser, src.len()).map_err(|e| e.at(parser.last))
+}
+
+fn parse_root(parser: &mut Parser, len: usize) -> Result<PactInterface, ParseError> {
     // Expect `interface name { ... }`
+    let start = parser.here();
     if parser.accept_ident("interface") {
-        let interface = parse_interface(&mut parser)?;
+        let mut interface = parse_interface(parser)?;
+        interface.span = start.to(parser.last);
         parser.expect_eof()?;
         return Ok(interface);
     }
 
     // Or parse as anonymous root interface
     let mut interface = PactInterface::new("root");
-    parse_interface_body(&mut parser, &mut interface)?;
+    interface.span = Span::new(start.file, 0, len);
+    parse_interface_body(parser, &mut interface)?;
     parser.expect_eof()?;
     Ok(interface)
 }
@@ -512,12 +676,23 @@ pub fn parse_pact(src: &str) -> Result<PactInterface, ParseError> {
 ///
 /// The interface name will be derived from the filename (without .pact extension).
 pub fn parse_pact_file(path: impl AsRef<Path>) -> Result<PactInterface, PactFileError> {
+    parse_pact_file_in(&mut SourceMap::new(), path)
+}
+
+/// [`parse_pact_file`], registering the file's source with `sources` so the
+/// spans in the result (or the error) can be rendered.
+pub fn parse_pact_file_in(
+    sources: &mut SourceMap,
+    path: impl AsRef<Path>,
+) -> Result<PactInterface, PactFileError> {
     let path = path.as_ref();
-    let src =
-        std::fs::read_to_string(path).map_err(|e| PactFileError::Io(path.to_path_buf(), e))?;
+    let file = sources
+        .load(path)
+        .map_err(|e| PactFileError::Io(path.to_path_buf(), e))?;
+    let src = &sources.get(file).expect("just loaded").src;
 
     let mut interface =
-        parse_pact(&src).map_err(|e| PactFileError::Parse(path.to_path_buf(), e))?;
+        parse_pact_source(src, file).map_err(|e| PactFileError::Parse(path.to_path_buf(), e))?;
 
     // If the interface was parsed as "root", use the filename as the name
     if interface.name == "root" {
@@ -534,6 +709,14 @@ pub fn parse_pact_file(path: impl AsRef<Path>) -> Result<PactInterface, PactFile
 /// Returns a root interface containing all parsed interfaces as children.
 /// The directory structure is preserved in nested interfaces.
 pub fn parse_pact_dir(path: impl AsRef<Path>) -> Result<PactInterface, PactFileError> {
+    parse_pact_dir_in(&mut SourceMap::new(), path)
+}
+
+/// [`parse_pact_dir`], registering every file's source with `sources`.
+pub fn parse_pact_dir_in(
+    sources: &mut SourceMap,
+    path: impl AsRef<Path>,
+) -> Result<PactInterface, PactFileError> {
     let path = path.as_ref();
 
     if !path.is_dir() {
@@ -544,12 +727,39 @@ pub fn parse_pact_dir(path: impl AsRef<Path>) -> Result<PactInterface, PactFileE
 
     let mut root = PactInterface::new(dir_name);
 
-    parse_pact_dir_recursive(path, &mut root)?;
+    parse_pact_dir_recursive(sources, path, &mut root, None)?;
 
     Ok(root)
 }
 
-fn parse_pact_dir_recursive(dir: &Path, parent: &mut PactInterface) -> Result<(), PactFileError> {
+/// [`parse_pact_dir_with_registry`] for an editor, which has to cope with
+/// files mid-edit: every file's source is registered with `sources`, and a
+/// file that fails to parse is left out of the tree instead of failing the
+/// whole directory. Those files' errors are returned alongside.
+pub fn parse_pact_dir_lenient_in(
+    sources: &mut SourceMap,
+    path: impl AsRef<Path>,
+) -> Result<(PactInterface, TypeRegistry, Vec<PactFileError>), PactFileError> {
+    let path = path.as_ref();
+
+    if !path.is_dir() {
+        return Err(PactFileError::NotADirectory(path.to_path_buf()));
+    }
+
+    let dir_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("root");
+    let mut root = PactInterface::new(dir_name);
+    let mut skipped = Vec::new();
+    parse_pact_dir_recursive(sources, path, &mut root, Some(&mut skipped))?;
+    let registry = TypeRegistry::from_interface(&root);
+    Ok((root, registry, skipped))
+}
+
+fn parse_pact_dir_recursive(
+    sources: &mut SourceMap,
+    dir: &Path,
+    parent: &mut PactInterface,
+    mut skipped: Option<&mut Vec<PactFileError>>,
+) -> Result<(), PactFileError> {
     let entries = std::fs::read_dir(dir).map_err(|e| PactFileError::Io(dir.to_path_buf(), e))?;
 
     for entry in entries {
@@ -564,7 +774,7 @@ fn parse_pact_dir_recursive(dir: &Path, parent: &mut PactInterface) -> Result<()
                 .unwrap_or("unknown");
 
             let mut child = PactInterface::new(subdir_name);
-            parse_pact_dir_recursive(&path, &mut child)?;
+            parse_pact_dir_recursive(sources, &path, &mut child, skipped.as_deref_mut())?;
 
             if !child.children.is_empty()
                 || !child.types.is_empty()
@@ -575,8 +785,11 @@ fn parse_pact_dir_recursive(dir: &Path, parent: &mut PactInterface) -> Result<()
             }
         } else if path.extension().and_then(|s| s.to_str()) == Some("pact") {
             // Parse .pact file
-            let interface = parse_pact_file(&path)?;
-            parent.children.push(interface);
+            match (parse_pact_file_in(sources, &path), skipped.as_deref_mut()) {
+                (Ok(interface), _) => parent.children.push(interface),
+                (Err(e @ PactFileError::Parse(..)), Some(skipped)) => skipped.push(e),
+                (Err(e), _) => return Err(e),
+            }
         }
     }
 
@@ -627,11 +840,23 @@ impl std::error::Error for PactFileError {
 /// A registry for resolving types across multiple interfaces.
 ///
 /// The registry maps interface names to their exported types, allowing
-/// `use` statements to be resolved across files.
-#[derive(Debug, Clone, Default)]
+/// `use` statements to be resolved across files. It also collects the
+/// transforms the interfaces declare, next to the built-in ones.
+#[derive(Debug, Clone)]
 pub struct TypeRegistry {
     /// Map from interface name to its type definitions
     interfaces: HashMap<String, InterfaceTypes>,
+    /// Built-in and declared transforms
+    transforms: TransformRegistry,
+}
+
+impl Default for TypeRegistry {
+    fn default() -> Self {
+        Self {
+            interfaces: HashMap::new(),
+            transforms: TransformRegistry::with_builtins(),
+        }
+    }
 }
 
 /// Types exported by an interface.
@@ -672,6 +897,10 @@ impl TypeRegistry {
 
         self.interfaces.insert(interface.name.clone(), iface_types);
 
+        for transform in &interface.transforms {
+            self.transforms.register(Arc::new(transform.clone()));
+        }
+
         // Recursively add children
         for child in &interface.children {
             self.add_interface(child);
@@ -685,6 +914,11 @@ impl TypeRegistry {
             .and_then(|iface| iface.types.get(name))
     }
 
+    /// The built-in transforms and those the registered interfaces declare.
+    pub fn transforms(&self) -> &TransformRegistry {
+        &self.transforms
+    }
+
     /// Look up an interface by name.
     pub fn get_interface(&self, name: &str) -> Option<&PactInterface> {
         self.interfaces
@@ -737,6 +971,57 @@ impl TypeRegistry {
         }
     }
 
+    /// [`resolve_use`](Self::resolve_use)'s error, located at the declaration
+    /// and with a did-you-mean for a misspelled interface or item. `None` if
+    /// the declaration resolves. A transformed `use` is resolved with the
+    /// registry's [`transforms`](Self::transforms).
+    pub fn diagnose_use(&self, use_decl: &PactUse) -> Option<Diagnostic> {
+        if !use_decl.transform_args.is_empty() {
+            return self.diagnose_transformed_use(use_decl);
+        }
+        let error = self.resolve_use(use_decl).err()?;
+        let mut diagnostic = Diagnostic::new(error, Some(use_decl.span));
+        let close = match self.interfaces.get(&use_decl.interface) {
+            None => suggest(&use_decl.interface, self.interfaces()),
+            Some(iface) => use_decl
+                .items
+                .iter()
+                .find(|item| !iface.types.contains_key(*item))
+                .and_then(|item| suggest(item, iface.types.keys().map(String::as_str))),
+        };
+        if let Some(close) = close {
+            diagnostic = diagnostic.with_help(format!("did you mean `{close}`?"));
+        }
+        Some(diagnostic)
+    }
+
+    fn diagnose_transformed_use(&self, use_decl: &PactUse) -> Option<Diagnostic> {
+        let error = self
+            .resolve_use_with_transforms(use_decl, &self.transforms)
+            .err()?;
+        let mut diagnostic = Diagnostic::new(error, Some(use_decl.span));
+
+        // The first unknown name, from the outermost transform inwards
+        let mut transform = use_decl.interface.as_str();
+        let mut base = use_decl.transform_args[0].as_str();
+        let close = loop {
+            if !self.transforms.contains(transform) {
+                break suggest(transform, self.transforms.names());
+            }
+            match split_application(base) {
+                Some((inner, arg)) => (transform, base) = (inner, arg),
+                None if !self.interfaces.contains_key(base) => {
+                    break suggest(base, self.interfaces());
+                }
+                None => break None,
+            }
+        };
+        if let Some(close) = close {
+            diagnostic = diagnostic.with_help(format!("did you mean `{close}`?"));
+        }
+        Some(diagnostic)
+    }
+
     /// Create a resolved scope for an interface, including its own types
     /// and all types brought in via `use` statements.
     pub fn resolve_scope(
@@ -778,25 +1063,20 @@ impl TypeRegistry {
         } else {
             // Transform use declaration: use rpc(calculator)
             // interface field is the transform name, transform_args[0] is the base interface
-            let transform_name = &use_decl.interface;
             let base_interface_name = use_decl
                 .transform_args
                 .first()
                 .ok_or_else(|| "Transform requires at least one argument".to_string())?;
 
-            let transform = transforms
-                .get(transform_name)
-                .ok_or_else(|| format!("Unknown transform: {}", transform_name))?;
-
-            let base_interface = self
-                .get_interface(base_interface_name)
-                .ok_or_else(|| format!("Unknown interface: {}", base_interface_name))?;
-
-            let transformed = transform.transform(base_interface);
+            let transformed = self.get_transformed_interface(
+                &use_decl.interface,
+                base_interface_name,
+                transforms,
+            )?;
 
             // If items are specified, filter to just those
             if use_decl.items.is_empty() {
-                Ok(ResolvedUse::TransformedInterface(transformed))
+                Ok(ResolvedUse::TransformedInterface(Box::new(transformed)))
             } else {
                 // Return specific types from the transformed interface
                 let mut types = Vec::new();
@@ -848,26 +1128,19 @@ impl TypeRegistry {
                             .insert(typedef.name().to_string(), typedef.clone());
                     }
                     // Store the transformed interface for function resolution
-                    scope.transformed_interfaces.push(iface);
+                    scope.transformed_interfaces.push(*iface);
                 }
             }
         }
 
         // Resolve interface aliases
         for alias in &interface.aliases {
-            let transform = transforms
-                .get(&alias.transform)
-                .ok_or_else(|| format!("Unknown transform: {}", alias.transform))?;
-
             let base_name = alias.args.first().ok_or_else(|| {
                 format!("Interface alias {} requires a base interface", alias.name)
             })?;
 
-            let base_interface = self
-                .get_interface(base_name)
-                .ok_or_else(|| format!("Unknown interface: {}", base_name))?;
-
-            let transformed = transform.transform(base_interface);
+            let transformed =
+                self.get_transformed_interface(&alias.transform, base_name, transforms)?;
             scope
                 .aliased_interfaces
                 .insert(alias.name.clone(), transformed);
@@ -877,6 +1150,9 @@ impl TypeRegistry {
     }
 
     /// Get a transformed interface by applying a transform to a base interface.
+    ///
+    /// The base may itself be an application in source form, such as
+    /// `rpc(calculator)` for `traced(rpc(calculator))`; it is applied first.
     pub fn get_transformed_interface(
         &self,
         transform_name: &str,
@@ -887,6 +1163,11 @@ impl TypeRegistry {
             .get(transform_name)
             .ok_or_else(|| format!("Unknown transform: {}", transform_name))?;
 
+        if let Some((inner, base)) = split_application(base_interface_name) {
+            let base_interface = self.get_transformed_interface(inner, base, transforms)?;
+            return Ok(transform.transform(&base_interface));
+        }
+
         let base_interface = self
             .get_interface(base_interface_name)
             .ok_or_else(|| format!("Unknown interface: {}", base_interface_name))?;
@@ -895,13 +1176,32 @@ impl TypeRegistry {
     }
 }
 
+/// Split `rpc(calculator, ...)` into the transform and its first argument.
+fn split_application(arg: &str) -> Option<(&str, &str)> {
+    let (transform, args) = arg.split_once('(')?;
+    let args = args.strip_suffix(')')?;
+    let mut depth = 0;
+    let end = args
+        .char_indices()
+        .find(|&(_, ch)| {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            ch == ',' && depth == 0
+        })
+        .map_or(args.len(), |(i, _)| i);
+    Some((transform, args[..end].trim()))
+}
+
 /// Result of resolving a use declaration.
 #[derive(Debug, Clone)]
 pub enum ResolvedUse {
     /// A list of types to bring into scope
     Types(Vec<(String, TypeDef)>),
     /// A transformed interface (all exports wrapped)
-    TransformedInterface(PactInterface),
+    TransformedInterface(Box<PactInterface>),
 }
 
 /// A resolved scope including types and transformed interfaces.
@@ -941,18 +1241,7 @@ fn parse_interface(parser: &mut Parser) -> Result<PactInterface, ParseError> {
 fn parse_interface_alias(parser: &mut Parser, name: String) -> Result<InterfaceAlias, ParseError> {
     let transform = parser.expect_ident()?;
     parser.expect_symbol('(')?;
-
-    let mut args = Vec::new();
-    while !parser.accept_symbol(')') {
-        let arg = parser.expect_ident()?;
-        args.push(arg);
-        if !parser.accept_symbol(',') {
-            // Allow trailing comma or no comma before )
-            if !matches!(parser.peek(), Token::Symbol(')')) {
-                parser.expect_symbol(',')?;
-            }
-        }
-    }
+    let args = parse_transform_args(parser)?;
 
     Ok(InterfaceAlias {
         name,
@@ -965,6 +1254,8 @@ fn parse_interface_body(
     parser: &mut Parser,
     interface: &mut PactInterface,
 ) -> Result<(), ParseError> {
+    // Set by `@forward-compatible`, for the type definition that follows.
+    let mut forward_compatible = false;
     while !parser.is_eof() {
         // Skip semicolons
         if parser.accept_symbol(';') {
@@ -976,27 +1267,59 @@ fn parse_interface_body(
             break;
         }
 
-        // Metadata annotation
+        // Type annotation or metadata
         if parser.accept_at() {
+            if parser.accept_ident("forward-compatible")
+                || parser.accept_ident("forward_compatible")
+            {
+                forward_compatible = true;
+                continue;
+            }
             let meta = parse_metadata(parser)?;
             interface.metadata.push(meta);
+            interface.spans.references.append(&mut parser.references);
             continue;
         }
 
         // Keywords
         let keyword = parser.expect_ident()?;
-        match keyword.as_str() {
-            "type" => {
-                let type_item = parse_type_item(parser)?;
-                match type_item {
-                    TypeItem::TypeParam(tp) => interface.type_params.push(tp),
-                    TypeItem::TypeDef(td) => interface.types.push(td),
+        let start = parser.last;
+        let typedef = match keyword.as_str() {
+            "type" => match parse_type_item(parser)? {
+                TypeItem::TypeParam(tp) => {
+                    interface.type_params.push(tp);
+                    None
                 }
+                TypeItem::TypeDef(td) => Some(td),
+            },
+            "record" => Some(parse_record(parser)?),
+            "variant" => Some(parse_variant(parser)?),
+            "enum" => Some(parse_enum(parser)?),
+            "flags" => Some(parse_flags(parser)?),
+            _ => None,
+        };
+        if let Some(typedef) = typedef {
+            if std::mem::take(&mut forward_compatible) {
+                interface
+                    .forward_compatible
+                    .insert(typedef.name().to_string());
             }
-            "record" => interface.types.push(parse_record(parser)?),
-            "variant" => interface.types.push(parse_variant(parser)?),
-            "enum" => interface.types.push(parse_enum(parser)?),
-            "flags" => interface.types.push(parse_flags(parser)?),
+            let span = start.to(parser.last);
+            interface
+                .spans
+                .types
+                .insert(typedef.name().to_string(), span);
+            interface.types.push(typedef);
+            interface.spans.references.append(&mut parser.references);
+            continue;
+        }
+        if forward_compatible {
+            // The annotation applies only to a type definition.
+            return Err(ParseError::UnexpectedToken(keyword));
+        }
+        match keyword.as_str() {
+            // A type parameter, recorded above
+            "type" => {}
             "imports" => {
                 parser.expect_symbol('{')?;
                 parse_imports_block(parser, interface)?;
@@ -1022,15 +1345,21 @@ fn parse_interface_body(
                     parser.expect_symbol('{')?;
                     parse_interface_body(parser, &mut child)?;
                     parser.expect_symbol('}')?;
+                    child.span = start.to(parser.last);
                     interface.children.push(child);
                 }
             }
             "use" => {
-                let use_decl = parse_use(parser)?;
+                let use_decl = parse_use(parser, start)?;
                 interface.uses.push(use_decl);
             }
+            "transform" => {
+                let transform = parse_transform(parser)?;
+                interface.transforms.push(transform);
+            }
             _ => return Err(ParseError::UnexpectedToken(keyword)),
         }
+        interface.spans.references.append(&mut parser.references);
     }
 
     Ok(())
@@ -1087,7 +1416,10 @@ fn parse_metadata_value(parser: &mut Parser) -> Result<MetadataValue, ParseError
             }
             Ok(MetadataValue::Record(fields))
         }
-        _ => Err(ParseError::UnexpectedToken(format!("{:?}", parser.peek()))),
+        token => {
+            parser.next();
+            Err(ParseError::UnexpectedToken(format!("{:?}", token)))
+        }
     }
 }
 
@@ -1103,7 +1435,10 @@ fn parse_type_item(parser: &mut Parser) -> Result<TypeItem, ParseError> {
     if matches!(parser.peek(), Token::Symbol('<')) {
         let type_params = parse_type_param_list(parser)?;
         parser.expect_symbol('=')?;
-        let ty = parse_type(parser)?;
+        parser.generics = type_params.clone();
+        let ty = parse_type(parser);
+        parser.generics.clear();
+        let ty = ty?;
         return Ok(TypeItem::TypeDef(TypeDef::alias_generic(
             name,
             type_params,
@@ -1156,6 +1491,13 @@ fn parse_type_param_list(parser: &mut Parser) -> Result<Vec<String>, ParseError>
 fn parse_record(parser: &mut Parser) -> Result<TypeDef, ParseError> {
     let name = parser.expect_ident()?;
     let type_params = parse_type_param_list(parser)?;
+    parser.generics = type_params.clone();
+    let fields = parse_fields(parser);
+    parser.generics.clear();
+    Ok(TypeDef::record_generic(name, type_params, fields?))
+}
+
+fn parse_fields(parser: &mut Parser) -> Result<Vec<Field>, ParseError> {
     parser.expect_symbol('{')?;
     let mut fields = Vec::new();
 
@@ -1168,12 +1510,19 @@ fn parse_record(parser: &mut Parser) -> Result<TypeDef, ParseError> {
         parser.accept_symbol(';');
     }
 
-    Ok(TypeDef::record_generic(name, type_params, fields))
+    Ok(fields)
 }
 
 fn parse_variant(parser: &mut Parser) -> Result<TypeDef, ParseError> {
     let name = parser.expect_ident()?;
     let type_params = parse_type_param_list(parser)?;
+    parser.generics = type_params.clone();
+    let cases = parse_cases(parser);
+    parser.generics.clear();
+    Ok(TypeDef::variant_generic(name, type_params, cases?))
+}
+
+fn parse_cases(parser: &mut Parser) -> Result<Vec<Case>, ParseError> {
     parser.expect_symbol('{')?;
     let mut cases = Vec::new();
 
@@ -1191,7 +1540,7 @@ fn parse_variant(parser: &mut Parser) -> Result<TypeDef, ParseError> {
         parser.accept_symbol(';');
     }
 
-    Ok(TypeDef::variant_generic(name, type_params, cases))
+    Ok(cases)
 }
 
 fn parse_enum(parser: &mut Parser) -> Result<TypeDef, ParseError> {
@@ -1224,7 +1573,7 @@ fn parse_flags(parser: &mut Parser) -> Result<TypeDef, ParseError> {
     Ok(TypeDef::flags(name, flags))
 }
 
-fn parse_use(parser: &mut Parser) -> Result<PactUse, ParseError> {
+fn parse_use(parser: &mut Parser, start: Span) -> Result<PactUse, ParseError> {
     // Parse variants:
     // - use <interface>
     // - use <interface>.{item1, item2, ...}
@@ -1237,16 +1586,7 @@ fn parse_use(parser: &mut Parser) -> Result<PactUse, ParseError> {
 
     // Check for transform arguments: (arg1, arg2, ...)
     if parser.accept_symbol('(') {
-        while !parser.accept_symbol(')') {
-            let arg = parser.expect_ident()?;
-            transform_args.push(arg);
-            if !parser.accept_symbol(',') {
-                // Allow trailing comma or no comma before )
-                if !matches!(parser.peek(), Token::Symbol(')')) {
-                    parser.expect_symbol(',')?;
-                }
-            }
-        }
+        transform_args = parse_transform_args(parser)?;
     }
 
     // Check for dot-delimited items: .{item1, item2}
@@ -1268,9 +1608,128 @@ fn parse_use(parser: &mut Parser) -> Result<PactUse, ParseError> {
         interface: name,
         transform_args,
         items,
+        span: start.to(parser.last),
     })
 }
 
+/// Parse transform arguments up to and including the closing `)`. An
+/// argument is an interface name or, in source form, another application:
+/// `rpc(calculator)`.
+fn parse_transform_args(parser: &mut Parser) -> Result<Vec<String>, ParseError> {
+    let mut args = Vec::new();
+    while !parser.accept_symbol(')') {
+        let mut arg = parser.expect_ident()?;
+        if parser.accept_symbol('(') {
+            arg = format!("{}({})", arg, parse_transform_args(parser)?.join(", "));
+        }
+        args.push(arg);
+        if !parser.accept_symbol(',') {
+            // Allow trailing comma or no comma before )
+            if !matches!(parser.peek(), Token::Symbol(')')) {
+                parser.expect_symbol(',')?;
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Parse a transform declaration, after `transform`:
+///
+/// - `name<I> = wrap<I, wrapper>`, which wraps every result
+/// - `name<I> { ... }`, with any of `add params(p: T, ...)`,
+///   `wrap params in wrapper`, `wrap results in wrapper` and
+///   `add errors { case, ... }`
+///
+/// The interface parameter `<I>` may be left out.
+fn parse_transform(parser: &mut Parser) -> Result<DeclaredTransform, ParseError> {
+    let mut transform = DeclaredTransform::new(parser.expect_ident()?);
+    let param = if parser.accept_symbol('<') {
+        let param = parser.expect_ident()?;
+        parser.expect_symbol('>')?;
+        Some(param)
+    } else {
+        None
+    };
+
+    parser.holes = true;
+    let body = parse_transform_body(parser, &mut transform, param.as_deref());
+    parser.holes = false;
+    // The types a transform names are resolved where it is applied.
+    parser.references.clear();
+    body?;
+
+    Ok(transform)
+}
+
+fn parse_transform_body(
+    parser: &mut Parser,
+    transform: &mut DeclaredTransform,
+    param: Option<&str>,
+) -> Result<(), ParseError> {
+    if parser.accept_symbol('=') {
+        let keyword = parser.expect_ident()?;
+        if keyword != "wrap" {
+            return Err(ParseError::UnexpectedToken(keyword));
+        }
+        parser.expect_symbol('<')?;
+        let target = parser.expect_ident()?;
+        if param.is_some_and(|param| param != target) {
+            return Err(ParseError::UnexpectedToken(target));
+        }
+        parser.expect_symbol(',')?;
+        transform.wrap_results = Some(parse_wrapper(parser)?);
+        parser.expect_symbol('>')?;
+        return Ok(());
+    }
+
+    parser.expect_symbol('{')?;
+    while !parser.accept_symbol('}') {
+        if parser.accept_symbol(';') {
+            continue;
+        }
+        let keyword = parser.expect_ident()?;
+        match keyword.as_str() {
+            "add" if parser.accept_ident("params") => {
+                parser.expect_symbol('(')?;
+                transform.params.extend(parse_params(parser)?);
+                parser.expect_symbol(')')?;
+            }
+            "add" if parser.accept_ident("errors") => {
+                transform.errors.extend(parse_cases(parser)?);
+            }
+            "wrap" => {
+                let target = parser.expect_ident()?;
+                let wrapper = match target.as_str() {
+                    "params" => &mut transform.wrap_params,
+                    "results" => &mut transform.wrap_results,
+                    _ => return Err(ParseError::UnexpectedToken(target)),
+                };
+                let keyword = parser.expect_ident()?;
+                if keyword != "in" {
+                    return Err(ParseError::UnexpectedToken(keyword));
+                }
+                *wrapper = Some(parse_wrapper(parser)?);
+            }
+            _ => return Err(ParseError::UnexpectedToken(keyword)),
+        }
+    }
+    Ok(())
+}
+
+/// Parse a wrapper type: one with a `_` hole, or a bare name `f` for `f<_>`.
+fn parse_wrapper(parser: &mut Parser) -> Result<Type, ParseError> {
+    let wrapper = parse_type(parser)?;
+    if let Type::Ref(path) = &wrapper {
+        if let Some(name) = path.as_simple().filter(|&name| name != HOLE) {
+            return Ok(Type::app(name, vec![Type::named(HOLE)]));
+        }
+    }
+    if fill(&wrapper, Type::Unit) == wrapper {
+        return Err(ParseError::InvalidTypeRef(wrapper.to_string()));
+    }
+    Ok(wrapper)
+}
+
 fn parse_imports_block(
     parser: &mut Parser,
     interface: &mut PactInterface,
@@ -1319,16 +1778,21 @@ fn parse_exports_block(
         }
 
         let name = parser.expect_ident()?;
+        let start = parser.last;
 
         // Function: `name: func(...) -> ...`
         if parser.accept_symbol(':') {
             if parser.accept_ident("func") {
                 let func = parse_func_signature(parser, name)?;
+                let span = start.to(parser.last);
+                interface.spans.functions.insert(func.name.clone(), span);
                 interface.exports.push(PactExport::Function(func));
                 continue;
             }
             // Could be a type alias in exports
             let ty = parse_type(parser)?;
+            let span = start.to(parser.last);
+            interface.spans.types.insert(name.clone(), span);
             interface
                 .exports
                 .push(PactExport::Type(TypeDef::alias(name, ty)));
@@ -1383,6 +1847,7 @@ fn parse_results(parser: &mut Parser) -> Result<Vec<Type>, ParseError> {
 
 fn parse_type(parser: &mut Parser) -> Result<Type, ParseError> {
     let ident = parser.expect_ident()?;
+    let span = parser.last;
     match ident.as_str() {
         "bool" => Ok(Type::Bool),
         "u8" => Ok(Type::U8),
@@ -1399,6 +1864,7 @@ fn parse_type(parser: &mut Parser) -> Result<Type, ParseError> {
         "string" => Ok(Type::String),
         "self" => Ok(Type::self_ref()),
         "value" => Ok(Type::Value),
+        "_" if parser.holes => Ok(Type::named(HOLE)),
         "_" => Ok(Type::Tuple(vec![])), // Unit type, used in result<_, E> for void ok type
         "list" => parse_generic_type(parser, Type::list),
         "option" => parse_generic_type(parser, Type::option),
@@ -1411,6 +1877,9 @@ fn parse_type(parser: &mut Parser) -> Result<Type, ParseError> {
             // angle brackets is an ordinary named reference (which the
             // resolver treats as a type-parameter reference when the name is
             // an in-scope generic parameter).
+            if !parser.generics.contains(&ident) {
+                parser.references.push((ident.clone(), span));
+            }
             if matches!(parser.peek(), Token::Symbol('<')) {
                 let args = parse_angle_type_args(parser)?;
                 Ok(Type::app(ident, args))
@@ -1467,14 +1936,14 @@ fn parse_tuple(parser: &mut Parser) -> Result<Type, ParseError> {
 fn parse_result(parser: &mut Parser) -> Result<Type, ParseError> {
     parser.expect_symbol('<')?;
     // For compatibility with pack-guest-macros, `_` maps to Bool for ok type
-    let ok = if parser.accept_ident("_") {
+    let ok = if !parser.holes && parser.accept_ident("_") {
         Type::Bool
     } else {
         parse_type(parser)?
     };
     parser.expect_symbol(',')?;
     // For compatibility with pack-guest-macros, `_` maps to String for err type
-    let err = if parser.accept_ident("_") {
+    let err = if !parser.holes && parser.accept_ident("_") {
         Type::String
     } else {
         parse_type(parser)?
@@ -1667,6 +2136,28 @@ mod tests {
         assert_eq!(interface.version(), Some("1.0.0"));
     }
 
+    #[test]
+    fn parse_forward_compatible_annotation() {
+        let src = r#"
+            interface store {
+                @version: string = "1.0.0"
+                @forward-compatible
+                record entry { id: u64 }
+                record strict { id: u64 }
+            }
+        "#;
+
+        let interface = parse_pact(src).expect("parse");
+        assert_eq!(interface.version(), Some("1.0.0"));
+        assert!(interface.forward_compatible.contains("entry"));
+        assert!(!interface.forward_compatible.contains("strict"));
+
+        // The annotation must precede a type definition.
+        let err =
+            parse_pact("@forward-compatible\nexports { get: func() }").expect_err("misplaced");
+        assert!(matches!(err.kind(), ParseError::UnexpectedToken(t) if t == "exports"));
+    }
+
     #[test]
     fn parse_pact_file_test() {
         use std::io::Write;
@@ -2070,4 +2561,136 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn parse_transform_declarations() {
+        let src = r#"
+            transform async<I> = wrap<I, future>
+            transform traced<I> {
+                add params(span-id: string)
+                wrap params in option<_>
+                wrap results in result<_, string>
+                add errors { timeout, rejected(string) }
+            }
+        "#;
+
+        let interface = parse_pact(src).expect("parse");
+        assert_eq!(interface.transforms.len(), 2);
+        let async_ = &interface.transforms[0];
+        assert_eq!(async_.name, "async");
+        assert_eq!(
+            async_.wrap_results,
+            Some(Type::app("future", vec![Type::named("_")]))
+        );
+
+        let traced = &interface.transforms[1];
+        assert_eq!(traced.params, vec![Param::new("span-id", Type::String)]);
+        assert_eq!(traced.wrap_params, Some(Type::option(Type::named("_"))));
+        assert_eq!(
+            traced.wrap_results,
+            Some(Type::result(Type::named("_"), Type::String))
+        );
+        assert_eq!(traced.errors.len(), 2);
+        // Nothing in a transform is checked where it is declared.
+        assert!(interface.check_types(None).is_empty());
+
+        // A wrapper needs somewhere to put the wrapped type.
+        let err = parse_pact("transform bad { wrap results in list<u8> }").expect_err("no hole");
+        assert!(matches!(err.kind(), ParseError::InvalidTypeRef(_)));
+        let err = parse_pact("transform bad<I> = wrap<J, future>").expect_err("wrong param");
+        assert!(matches!(err.kind(), ParseError::UnexpectedToken(t) if t == "J"));
+    }
+
+    #[test]
+    fn declared_transforms_compose_with_builtins() {
+        let dir = std::env::temp_dir().join("pact_declared_transforms_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(
+            dir.join("calculator.pact"),
+            "exports {\n    add: func(a: s32, b: s32) -> s32\n}\n",
+        )
+        .expect("write");
+        std::fs::write(
+            dir.join("tracing.pact"),
+            "transform traced<I> {\n    add params(span-id: string)\n}\n",
+        )
+        .expect("write");
+        std::fs::write(
+            dir.join("caller.pact"),
+            "use traced(rpc(calculator))\ninterface client = traced(rpc(calculator))\n",
+        )
+        .expect("write");
+
+        let (_, registry) = parse_pact_dir_with_registry(&dir).expect("parse dir");
+        assert!(registry.transforms().contains("traced"));
+        assert!(registry.transforms().contains("rpc"));
+
+        let caller = registry.get_interface("caller").unwrap();
+        assert_eq!(caller.uses[0].transform_args, vec!["rpc(calculator)"]);
+        let scope = registry
+            .resolve_scope_with_transforms(caller, registry.transforms())
+            .expect("resolve scope");
+        assert!(scope.types.contains_key("rpc-error"));
+
+        let client = &scope.aliased_interfaces["client"];
+        assert_eq!(client.name, "traced(rpc(calculator))");
+        let PactExport::Function(add) = &client.exports[0] else {
+            panic!("Expected function export");
+        };
+        let params: Vec<_> = add.params.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(params, ["span-id", "a", "b"]);
+        assert_eq!(add.results[0].to_string(), "result<s32, rpc-error>");
+
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn errors_and_nodes_carry_spans() {
+        let mut sources = SourceMap::new();
+        let src = "interface api {\n    record user { id: u64, name: }\n}\n";
+        let file = sources.add("api.pact", src);
+        let err = parse_pact_source(src, file).expect_err("missing field type");
+        let span = err.span().expect("located");
+        assert_eq!(&src[span.start..span.end], "}");
+        assert!(matches!(err.kind(), ParseError::UnexpectedToken(_)));
+        let rendered = Diagnostic::from(&err).render(&sources);
+        assert!(rendered.contains(" --> api.pact:2:34\n"), "{rendered}");
+
+        let src = "interface api {\n    record user { id: u64 }\n    exports { get: func() -> user }\n}\n";
+        let interface = parse_pact_source(src, file).expect("parse");
+        let span = interface.spans.types["user"];
+        assert!(src[span.start..span.end].starts_with("record user"));
+        let span = interface.spans.functions["get"];
+        assert!(src[span.start..span.end].starts_with("get: func"));
+    }
+
+    #[test]
+    fn check_types_suggests_defined_names() {
+        let src = r#"
+            interface api {
+                record user { id: u64, tags: list<strng> }
+                record page<t> { items: list<t> }
+                exports {
+                    get: func(id: u64) -> usr
+                    list: func() -> page<user>
+                }
+            }
+        "#;
+        let interface = parse_pact(src).expect("parse");
+        let diagnostics = interface.check_types(None);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), d.help.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("undefined type `strng`", Some("did you mean `string`?")),
+                ("undefined type `usr`", Some("did you mean `user`?")),
+            ]
+        );
+        let span = diagnostics[1].span.expect("located");
+        assert_eq!(&src[span.start..span.end], "usr");
+    }
 }
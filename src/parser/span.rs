@@ -0,0 +1,244 @@
+//! Source locations and rendered diagnostics for Pact.
+//!
+//! Every token the Pact parser reads carries a [`Span`]: a byte range in one
+//! source file, named by a [`FileId`] handed out by a [`SourceMap`]. Parse and
+//! resolution errors become [`Diagnostic`]s, which render against the map the
+//! way rustc does:
+//!
+//! ```text
+//! error: undefined type `usr`
+//!  --> api.pact:3:27
+//!   |
+//! 3 |     get: func(id: u64) -> usr
+//!   |                           ^^^ not defined in scope
+//!   |
+//!   = help: did you mean `user`?
+//! ```
+
+use std::path::Path;
+
+/// A source file registered with a [`SourceMap`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(pub u32);
+
+/// A byte range in one source file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Self { file, start, end }
+    }
+
+    /// The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.file, self.start, other.end.max(self.start))
+    }
+}
+
+/// A source file's name and text.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub src: String,
+}
+
+impl SourceFile {
+    /// The 1-based line and column (in characters) of byte `offset`.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.src.len());
+        let before = &self.src[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (line, before[line_start..].chars().count() + 1)
+    }
+
+    /// The text of 1-based line `line`, without its newline.
+    fn line(&self, line: usize) -> &str {
+        self.src.lines().nth(line - 1).unwrap_or("")
+    }
+}
+
+/// The source files a set of spans point into.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `src` under `name`.
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        self.files.push(SourceFile {
+            name: name.into(),
+            src: src.into(),
+        });
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    /// Read and register the file at `path`.
+    pub fn load(&mut self, path: &Path) -> std::io::Result<FileId> {
+        let src = std::fs::read_to_string(path)?;
+        Ok(self.add(path.display().to_string(), src))
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0 as usize)
+    }
+
+    /// Every registered file, in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (FileId(i as u32), file))
+    }
+}
+
+/// A located error, ready to render against its source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+    /// A note printed under the caret.
+    pub label: Option<String>,
+    /// A trailing `help:` line, such as a did-you-mean suggestion.
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: Option<Span>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            label: None,
+            help: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Render the message, the offending line and a caret under the span. A
+    /// span whose file is not in `sources` renders as the message alone.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let mut out = format!("error: {}\n", self.message);
+        let located = self
+            .span
+            .and_then(|span| Some((span, sources.get(span.file)?)));
+        let Some((span, file)) = located else {
+            if let Some(help) = &self.help {
+                out.push_str(&format!("  = help: {help}\n"));
+            }
+            return out;
+        };
+
+        let (line, col) = file.line_col(span.start);
+        let text = file.line(line);
+        let gutter = " ".repeat(line.to_string().len());
+        // Underline the span's part of its first line, at least one column.
+        let width = file.src[span.start.min(file.src.len())..span.end.min(file.src.len())]
+            .lines()
+            .next()
+            .map_or(0, |s| s.chars().count())
+            .max(1);
+        out.push_str(&format!("{gutter}--> {}:{line}:{col}\n", file.name));
+        out.push_str(&format!("{gutter} |\n"));
+        out.push_str(&format!("{line} | {text}\n"));
+        out.push_str(&format!(
+            "{gutter} | {}{}",
+            " ".repeat(col - 1),
+            "^".repeat(width)
+        ));
+        if let Some(label) = &self.label {
+            out.push_str(&format!(" {label}"));
+        }
+        out.push('\n');
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{gutter} |\n{gutter} = help: {help}\n"));
+        }
+        out
+    }
+}
+
+/// The candidate closest to `name`, if it is close enough to be a likely typo.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|c| *c != name)
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= limit)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance between `a` and `b`, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_a_caret_under_the_span() {
+        let mut sources = SourceMap::new();
+        let src = "interface api {\n    get: func(id: u64) -> usr\n}\n";
+        let file = sources.add("api.pact", src);
+        let start = src.find("usr").unwrap();
+        let rendered = Diagnostic::new(
+            "undefined type `usr`",
+            Some(Span::new(file, start, start + 3)),
+        )
+        .with_label("not defined in scope")
+        .with_help("did you mean `user`?")
+        .render(&sources);
+        assert_eq!(
+            rendered,
+            "error: undefined type `usr`\n \
+             --> api.pact:2:27\n  \
+             |\n\
+             2 |     get: func(id: u64) -> usr\n  \
+             |                           ^^^ not defined in scope\n  \
+             |\n  \
+             = help: did you mean `user`?\n"
+        );
+    }
+
+    #[test]
+    fn suggests_only_close_names() {
+        let names = ["user", "user-id", "status"];
+        assert_eq!(suggest("usr", names), Some("user"));
+        assert_eq!(suggest("stauts", names), Some("status"));
+        assert_eq!(suggest("chain", names), None);
+    }
+}
@@ -1,8 +1,11 @@
 //! Schema-aware validation for graph buffers.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::abi::{encode, Decoder, GraphBuffer, GraphCodec, Limits, NodeKind, Value};
+use crate::abi::{
+    encode, Decoder, Encoder, GraphBuffer, GraphCodec, Limits, Node, NodeKind, Value, FLAG_COMPACT,
+};
+use crate::metadata::{hash_type_in, TypeHash};
 use crate::types::{Case, Field, Type, TypeDef};
 use thiserror::Error;
 
@@ -32,6 +35,8 @@ pub enum ValidationError {
         expected: usize,
         actual: usize,
     },
+    #[error("Schema hash mismatch: local {local}, peer {peer}")]
+    SchemaHashMismatch { local: TypeHash, peer: TypeHash },
 }
 
 pub fn validate_graph_against_type(
@@ -52,6 +57,11 @@ pub fn validate_graph_against_type(
     validate_type(buffer, buffer.root, root_type, None, &map, &mut assigned)
 }
 
+/// Decode `bytes` as `root_type`, validating the graph against the schema.
+///
+/// Buffers in either profile are accepted: a compact buffer (header flag
+/// [`FLAG_COMPACT`]) has its record and variant names rebuilt from `types`
+/// before validation.
 pub fn decode_with_schema(
     types: &[TypeDef],
     bytes: &[u8],
@@ -59,11 +69,32 @@ pub fn decode_with_schema(
     limits: Option<&Limits>,
 ) -> Result<Value, ValidationError> {
     let limits = limits.copied().unwrap_or_default();
-    let buffer = GraphBuffer::from_bytes_with_limits(bytes, &limits)
-        .map_err(|err| ValidationError::InvalidEncoding(err.to_string()))?;
+    let mut buffer = if is_compact(bytes) {
+        GraphBuffer::from_compact_bytes_with_limits(bytes, &limits)
+    } else {
+        GraphBuffer::from_bytes_with_limits(bytes, &limits)
+    }
+    .map_err(|err| ValidationError::InvalidEncoding(err.to_string()))?;
     buffer
         .validate_basic_with_limits(&limits)
         .map_err(|err| ValidationError::InvalidEncoding(err.to_string()))?;
+    if is_compact(bytes) {
+        let mut map = HashMap::new();
+        for def in types {
+            map.insert(def.name().to_string(), def);
+        }
+        let root = buffer.root;
+        let mut seen = HashSet::new();
+        rewrite_names(
+            &mut buffer,
+            root,
+            root_type,
+            None,
+            &map,
+            Rewrite::Restore,
+            &mut seen,
+        )?;
+    }
     validate_graph_against_type(types, &buffer, root_type)?;
 
     let decoder = Decoder::new(&buffer);
@@ -84,6 +115,297 @@ pub fn encode_with_schema(
     encode(value).map_err(|err| ValidationError::InvalidEncoding(err.to_string()))
 }
 
+/// Encode `value` in the compact profile: records are written positionally
+/// (no type or field names) and variants by tag alone. [`decode_with_schema`]
+/// rebuilds the names from the schema, so the two ends must agree on it —
+/// `peer_hash` is the hash the receiver advertises for `root_type` (from its
+/// `__pack_types`), and encoding is refused unless it matches ours.
+pub fn encode_compact_with_schema(
+    types: &[TypeDef],
+    value: &Value,
+    root_type: &Type,
+    peer_hash: &TypeHash,
+) -> Result<Vec<u8>, ValidationError> {
+    let local = hash_type_in(root_type, types);
+    if local != *peer_hash {
+        return Err(ValidationError::SchemaHashMismatch {
+            local,
+            peer: *peer_hash,
+        });
+    }
+    let mut map = HashMap::new();
+    for def in types {
+        map.insert(def.name().to_string(), def);
+    }
+    validate_value(value, root_type, None, &map)?;
+    let mut encoder = Encoder::new();
+    let root = value
+        .encode_graph(&mut encoder)
+        .map_err(|err| ValidationError::InvalidEncoding(err.to_string()))?;
+    let mut buffer = encoder.finish(root);
+    let mut seen = HashSet::new();
+    rewrite_names(
+        &mut buffer,
+        root,
+        root_type,
+        None,
+        &map,
+        Rewrite::Strip,
+        &mut seen,
+    )?;
+    Ok(buffer.to_bytes_with_flags(FLAG_COMPACT))
+}
+
+fn is_compact(bytes: &[u8]) -> bool {
+    bytes
+        .get(6..8)
+        .is_some_and(|flags| u16::from_le_bytes([flags[0], flags[1]]) & FLAG_COMPACT != 0)
+}
+
+/// Direction of a [`rewrite_names`] pass.
+#[derive(Clone, Copy)]
+enum Rewrite {
+    /// Full payloads → compact: drop type, field and case names.
+    Strip,
+    /// Compact payloads → full: rebuild the names from the schema.
+    Restore,
+}
+
+/// Walk the graph alongside `ty`, converting every `Record` / `Variant` node
+/// reached through a named type between the full and compact payload layouts.
+/// Node indices are unchanged, so child references stay valid.
+///
+/// Nodes whose kind doesn't match the schema are left alone;
+/// `validate_graph_against_type` reports them afterwards.
+fn rewrite_names(
+    buffer: &mut GraphBuffer,
+    index: u32,
+    ty: &Type,
+    self_name: Option<&str>,
+    types: &HashMap<String, &TypeDef>,
+    mode: Rewrite,
+    seen: &mut HashSet<u32>,
+) -> Result<(), ValidationError> {
+    let desugared;
+    let ty = if let Type::Map { .. } = ty {
+        desugared = ty.desugar_map();
+        &desugared
+    } else if let Type::Set(..) = ty {
+        desugared = ty.desugar_set();
+        &desugared
+    } else {
+        ty
+    };
+
+    match ty {
+        Type::Ref(path) => {
+            let name = if path.is_self_ref() {
+                self_name.ok_or(ValidationError::SelfRefOutsideType)?
+            } else {
+                path.as_simple().ok_or_else(|| {
+                    ValidationError::UnsupportedType(format!("qualified type path: {path}"))
+                })?
+            };
+            let def = types
+                .get(name)
+                .ok_or_else(|| ValidationError::UndefinedType(name.to_string()))?;
+            return rewrite_typedef(buffer, index, def, name, types, mode, seen);
+        }
+        Type::App { path, args } => {
+            let (name, inst) = instantiate_app(path, args, types)?;
+            return rewrite_typedef(buffer, index, &inst, &name, types, mode, seen);
+        }
+        _ => {}
+    }
+
+    if !seen.insert(index) {
+        return Ok(());
+    }
+    let node = buffer.nodes.get(index as usize).ok_or_else(|| {
+        ValidationError::InvalidEncoding(format!("Node index {index} out of range"))
+    })?;
+
+    let children: Vec<(u32, &Type)> = match (ty, node.kind) {
+        (Type::List(inner), NodeKind::List) => {
+            let mut cursor = PayloadCursor::new(&node.payload);
+            cursor.skip_value_type()?;
+            let count = cursor.read_u32()? as usize;
+            (0..count)
+                .map(|_| Ok((cursor.read_u32()?, inner.as_ref())))
+                .collect::<Result<_, ValidationError>>()?
+        }
+        (Type::Option(inner), NodeKind::Option) => {
+            let mut cursor = PayloadCursor::new(&node.payload);
+            cursor.skip_value_type()?;
+            match cursor.read_u8()? {
+                1 => vec![(cursor.read_u32()?, inner.as_ref())],
+                _ => Vec::new(),
+            }
+        }
+        (Type::Result { ok, err }, NodeKind::Result) => {
+            let mut cursor = PayloadCursor::new(&node.payload);
+            cursor.skip_value_type()?;
+            cursor.skip_value_type()?;
+            let branch = if cursor.read_u32()? == 0 { ok } else { err };
+            match cursor.read_u8()? {
+                1 => vec![(cursor.read_u32()?, branch.as_ref())],
+                _ => Vec::new(),
+            }
+        }
+        (Type::Tuple(items), NodeKind::Tuple) => {
+            let mut cursor = PayloadCursor::new(&node.payload);
+            let count = cursor.read_u32()? as usize;
+            let indices = (0..count)
+                .map(|_| cursor.read_u32())
+                .collect::<Result<Vec<_>, _>>()?;
+            indices.into_iter().zip(items).collect()
+        }
+        _ => Vec::new(),
+    };
+
+    for (child, child_ty) in children {
+        rewrite_names(buffer, child, child_ty, self_name, types, mode, seen)?;
+    }
+    Ok(())
+}
+
+fn rewrite_typedef(
+    buffer: &mut GraphBuffer,
+    index: u32,
+    def: &TypeDef,
+    self_name: &str,
+    types: &HashMap<String, &TypeDef>,
+    mode: Rewrite,
+    seen: &mut HashSet<u32>,
+) -> Result<(), ValidationError> {
+    if let TypeDef::Alias { ty, .. } = def {
+        return rewrite_names(buffer, index, ty, Some(self_name), types, mode, seen);
+    }
+    if !seen.insert(index) {
+        return Ok(());
+    }
+    let node = buffer.nodes.get_mut(index as usize).ok_or_else(|| {
+        ValidationError::InvalidEncoding(format!("Node index {index} out of range"))
+    })?;
+    match def {
+        TypeDef::Record { name, fields, .. } if node.kind == NodeKind::Record => {
+            let mut cursor = PayloadCursor::new(&node.payload);
+            let children = match mode {
+                Rewrite::Strip => {
+                    // [type_name, field_count, field_names*, child_indices*]
+                    let name_len = cursor.read_u32()? as usize;
+                    cursor.read_bytes(name_len)?;
+                    let count = cursor.read_u32()? as usize;
+                    for _ in 0..count {
+                        let len = cursor.read_u32()? as usize;
+                        cursor.read_bytes(len)?;
+                    }
+                    cursor.read_u32s(count)?
+                }
+                // [child_indices*]
+                Rewrite::Restore => cursor.read_u32s(node.payload.len() / 4)?,
+            };
+            cursor.finish(index)?;
+            if children.len() != fields.len() {
+                return Err(ValidationError::TypeMismatch {
+                    node: index,
+                    expected: format!("record({})", fields.len()),
+                    actual: format!("record({})", children.len()),
+                });
+            }
+            node.payload = match mode {
+                Rewrite::Strip => Vec::with_capacity(4 * children.len()),
+                Rewrite::Restore => {
+                    let mut payload = Vec::new();
+                    put_name(&mut payload, name);
+                    payload.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+                    for field in fields {
+                        put_name(&mut payload, &field.name);
+                    }
+                    payload
+                }
+            };
+            for child in &children {
+                node.payload.extend_from_slice(&child.to_le_bytes());
+            }
+            for (field, child) in fields.iter().zip(children) {
+                rewrite_names(buffer, child, &field.ty, Some(name), types, mode, seen)?;
+            }
+            Ok(())
+        }
+        TypeDef::Variant { name, cases, .. } if node.kind == NodeKind::Variant => {
+            let case_names: Vec<&str> = cases.iter().map(|case| case.name.as_str()).collect();
+            let (tag, children) = rewrite_variant_payload(node, index, name, &case_names, mode)?;
+            if let (Some(&child), Some(case)) = (children.first(), cases.get(tag as usize)) {
+                rewrite_names(buffer, child, &case.payload, Some(name), types, mode, seen)?;
+            }
+            Ok(())
+        }
+        TypeDef::Enum { name, cases } if node.kind == NodeKind::Variant => {
+            let case_names: Vec<&str> = cases.iter().map(String::as_str).collect();
+            rewrite_variant_payload(node, index, name, &case_names, mode)?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Convert one `Variant` node's payload between
+/// `[type_name, case_name, tag:u32, payload_count:u32, child_indices*]` and the
+/// compact `[tag:u32, child_indices*]`, returning the tag and children.
+fn rewrite_variant_payload(
+    node: &mut Node,
+    index: u32,
+    type_name: &str,
+    case_names: &[&str],
+    mode: Rewrite,
+) -> Result<(u32, Vec<u32>), ValidationError> {
+    let mut cursor = PayloadCursor::new(&node.payload);
+    let (tag, children) = match mode {
+        Rewrite::Strip => {
+            let name_len = cursor.read_u32()? as usize;
+            cursor.read_bytes(name_len)?;
+            let case_len = cursor.read_u32()? as usize;
+            cursor.read_bytes(case_len)?;
+            let tag = cursor.read_u32()?;
+            let count = cursor.read_u32()? as usize;
+            (tag, cursor.read_u32s(count)?)
+        }
+        Rewrite::Restore => {
+            let tag = cursor.read_u32()?;
+            let count = node.payload.len().saturating_sub(4) / 4;
+            (tag, cursor.read_u32s(count)?)
+        }
+    };
+    cursor.finish(index)?;
+    let mut payload = Vec::new();
+    if let Rewrite::Restore = mode {
+        let case = case_names
+            .get(tag as usize)
+            .ok_or(ValidationError::VariantTagOutOfRange {
+                node: index,
+                tag,
+                max: case_names.len(),
+            })?;
+        put_name(&mut payload, type_name);
+        put_name(&mut payload, case);
+    }
+    payload.extend_from_slice(&tag.to_le_bytes());
+    if let Rewrite::Restore = mode {
+        payload.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    }
+    for child in &children {
+        payload.extend_from_slice(&child.to_le_bytes());
+    }
+    node.payload = payload;
+    Ok((tag, children))
+}
+
+fn put_name(out: &mut Vec<u8>, name: &str) {
+    out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    out.extend_from_slice(name.as_bytes());
+}
+
 fn validate_type(
     buffer: &GraphBuffer,
     index: u32,
@@ -766,6 +1088,10 @@ impl<'a> PayloadCursor<'a> {
         Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
     }
 
+    fn read_u32s(&mut self, count: usize) -> Result<Vec<u32>, ValidationError> {
+        (0..count).map(|_| self.read_u32()).collect()
+    }
+
     fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ValidationError> {
         if self.pos + len > self.bytes.len() {
             return Err(ValidationError::InvalidEncoding(
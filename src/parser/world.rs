@@ -636,6 +636,7 @@ fn is_symbol(ch: char) -> bool {
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
 
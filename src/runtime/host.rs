@@ -20,11 +20,11 @@
 //! })?;
 //! ```
 
-use crate::abi::{decode, encode, PackType, Value};
+use crate::abi::{decode, encode_into, encoded_len, AbiError, GraphView, PackType, Value};
 use crate::interface_impl::InterfaceImpl;
 use crate::metadata::TypeHash;
 use crate::runtime::interceptor::CallInterceptor;
-use crate::runtime::RuntimeError;
+use crate::runtime::{RuntimeError, StoreData};
 use std::future::Future;
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -35,11 +35,12 @@ use wasmtime::{Caller, Engine, Linker, Memory};
 ///
 /// `CallInterceptor` is async (so impls can apply back-pressure on slow
 /// subscribers via `.await`). The sync `func_typed` / `func_typed_result`
-/// bridges register a sync wasmtime closure, so they need to block on the
-/// interceptor future. We require a tokio multi-thread runtime to be present
+/// bridges register a sync wasmtime closure, and the sync `Instance` calls
+/// exports without an executor, so both need to block on the interceptor
+/// future. We require a tokio multi-thread runtime to be present
 /// — `Handle::current()` panics otherwise, and `block_in_place` releases the
 /// current worker thread so the blocked future can make progress.
-fn block_on_interceptor<F: Future>(fut: F) -> F::Output {
+pub(crate) fn block_on_interceptor<F: Future>(fut: F) -> F::Output {
     let handle = tokio::runtime::Handle::current();
     tokio::task::block_in_place(|| handle.block_on(fut))
 }
@@ -48,27 +49,12 @@ fn block_on_interceptor<F: Future>(fut: F) -> F::Output {
 // Calling Convention Constants
 // ============================================================================
 
-/// Default input buffer offset (0-16KB)
-pub const INPUT_BUFFER_OFFSET: usize = 0;
-
-/// Offset for result pointer slot (4 bytes at 16KB)
-/// Used by new guest-allocates ABI: guest writes output ptr here
-pub const RESULT_PTR_OFFSET: usize = 16 * 1024;
-
-/// Offset for result length slot (4 bytes at 16KB + 4)
-/// Used by new guest-allocates ABI: guest writes output len here
-pub const RESULT_LEN_OFFSET: usize = 16 * 1024 + 4;
-
-// Legacy constants - kept for backward compatibility during transition
-/// Default output buffer offset (16KB) - DEPRECATED: use RESULT_PTR_OFFSET
-pub const OUTPUT_BUFFER_OFFSET: usize = 16 * 1024;
-
-/// Max size of a host-function return written to the fixed, host-owned scratch
-/// buffer (`OUTPUT_BUFFER_OFFSET+8`). Only the SYNC host-fn path uses that buffer
-/// and it lives in low memory below the PIC package base, so this stays a
-/// conservative fail-safe. The ASYNC path guest-allocates on the heap and is
-/// UNBOUNDED — that is where theater's large returns ride.
-pub const OUTPUT_BUFFER_CAPACITY: usize = 32 * 1024;
+// Defined alongside the engine-neutral `Guest` ABI helpers; re-exported here so
+// existing `runtime::host::*` paths keep working.
+pub use crate::engine::{
+    INPUT_BUFFER_OFFSET, OUTPUT_BUFFER_CAPACITY, OUTPUT_BUFFER_OFFSET, RESULT_LEN_OFFSET,
+    RESULT_PTR_OFFSET, RESULT_SLOTS_SIZE,
+};
 
 // ============================================================================
 // Error Handling Infrastructure
@@ -155,6 +141,9 @@ pub enum LinkerError {
 
     #[error("Type conversion error: {0}")]
     ConversionError(String),
+
+    #[error("Guest call failed: {0}")]
+    GuestCall(String),
 }
 
 impl From<RuntimeError> for LinkerError {
@@ -167,7 +156,7 @@ impl From<RuntimeError> for LinkerError {
 ///
 /// Used by typed host functions to access state and perform memory operations.
 pub struct Ctx<'a, T> {
-    caller: Caller<'a, T>,
+    caller: Caller<'a, StoreData<T>>,
     /// The host-owned shared memory. PIC packages don't export their memory, so
     /// the loader provides it here. `None` falls back to the exported "memory".
     memory: Option<Memory>,
@@ -175,7 +164,7 @@ pub struct Ctx<'a, T> {
 
 impl<'a, T> Ctx<'a, T> {
     /// Create a new context from a Caller (legacy: memory via export).
-    pub fn new(caller: Caller<'a, T>) -> Self {
+    pub fn new(caller: Caller<'a, StoreData<T>>) -> Self {
         Self {
             caller,
             memory: None,
@@ -183,7 +172,7 @@ impl<'a, T> Ctx<'a, T> {
     }
 
     /// Create a context with an explicit host-owned memory (the PIC path).
-    pub fn new_with_memory(caller: Caller<'a, T>, memory: Option<Memory>) -> Self {
+    pub fn new_with_memory(caller: Caller<'a, StoreData<T>>, memory: Option<Memory>) -> Self {
         Self { caller, memory }
     }
 
@@ -201,36 +190,50 @@ impl<'a, T> Ctx<'a, T> {
 
     /// Get a reference to the store data
     pub fn data(&self) -> &T {
-        self.caller.data()
+        self.caller.data().state()
     }
 
     /// Get a mutable reference to the store data
     pub fn data_mut(&mut self) -> &mut T {
-        self.caller.data_mut()
+        self.caller.data_mut().state_mut()
     }
 
     /// Get the underlying wasmi Caller for advanced operations
-    pub fn caller(&self) -> &Caller<'a, T> {
+    pub fn caller(&self) -> &Caller<'a, StoreData<T>> {
         &self.caller
     }
 
     /// Get the underlying wasmi Caller mutably
-    pub fn caller_mut(&mut self) -> &mut Caller<'a, T> {
+    pub fn caller_mut(&mut self) -> &mut Caller<'a, StoreData<T>> {
         &mut self.caller
     }
 
     /// Read a Value from WASM memory using the Graph ABI
     pub fn read_value(&mut self, ptr: i32, len: i32) -> Result<Value, LinkerError> {
-        let memory = self.resolve_memory()?;
+        let bytes = self.guest_bytes(ptr, len)?;
+        decode(bytes).map_err(|e| LinkerError::DecodingError(e.to_string()))
+    }
 
-        let ptr = ptr as usize;
-        let len = len as usize;
-        let mut buffer = vec![0u8; len];
-        memory
-            .read(&self.caller, ptr, &mut buffer)
-            .map_err(|e| LinkerError::MemoryError(e.to_string()))?;
+    /// Borrow a Graph ABI buffer in WASM memory as a [`GraphView`], without
+    /// copying it. Use this on hot paths that only read part of a message.
+    pub fn view_value(&mut self, ptr: i32, len: i32) -> Result<GraphView<'_>, LinkerError> {
+        let bytes = self.guest_bytes(ptr, len)?;
+        GraphView::from_bytes(bytes)
+            .map_err(|e| LinkerError::DecodingError(AbiError::from(e).to_string()))
+    }
 
-        decode(&buffer).map_err(|e| LinkerError::DecodingError(e.to_string()))
+    /// Borrow `len` bytes of guest memory at `ptr`.
+    fn guest_bytes(&mut self, ptr: i32, len: i32) -> Result<&[u8], LinkerError> {
+        let memory = self.resolve_memory()?;
+        let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+        memory
+            .data(&self.caller)
+            .get(ptr..ptr.saturating_add(len))
+            .ok_or_else(|| {
+                LinkerError::MemoryError(format!(
+                    "out of bounds memory access: {len} bytes at {ptr}"
+                ))
+            })
     }
 
     /// Write a Value to WASM memory at the specified location.
@@ -244,23 +247,24 @@ impl<'a, T> Ctx<'a, T> {
         out_cap: i32,
         value: &Value,
     ) -> Result<i32, LinkerError> {
-        let bytes = encode(value).map_err(|e| LinkerError::EncodingError(e.to_string()))?;
+        let len = encoded_len(value).map_err(|e| LinkerError::EncodingError(e.to_string()))?;
 
-        if bytes.len() > out_cap as usize {
+        if len > out_cap as usize {
             return Err(LinkerError::MemoryError(format!(
                 "output buffer too small: need {} bytes, have {} capacity",
-                bytes.len(),
-                out_cap
+                len, out_cap
             )));
         }
 
         let memory = self.resolve_memory()?;
-
-        memory
-            .write(&mut self.caller, out_ptr as usize, &bytes)
-            .map_err(|e| LinkerError::MemoryError(e.to_string()))?;
-
-        Ok(bytes.len() as i32)
+        encode_to_guest(memory.data_mut(&mut self.caller), out_ptr, len, value).map_err(
+            |e| match e {
+                HostFunctionErrorKind::Encode(msg) => LinkerError::EncodingError(msg),
+                other => LinkerError::MemoryError(other.to_string()),
+            },
+        )?;
+
+        Ok(len as i32)
     }
 
     /// Write a Value to WASM memory using the Graph ABI (legacy method).
@@ -275,6 +279,96 @@ impl<'a, T> Ctx<'a, T> {
         Ok((out_ptr, len))
     }
 
+    /// Call one of the calling guest's own Pack ABI exports from inside this
+    /// host function (a nested host→guest→host call).
+    ///
+    /// Every buffer the nested call uses — input, result slots and output —
+    /// comes from the guest's `__pack_alloc`, so it cannot clobber the buffers
+    /// of the call in progress. Guests without an allocator are refused:
+    /// their only buffers are the host scratch region, which the outer call
+    /// already occupies. Only sync stores can be re-entered this way.
+    pub fn call_with_value(&mut self, name: &str, input: &Value) -> Result<Value, LinkerError> {
+        if self.caller.engine().is_async() {
+            return Err(LinkerError::GuestCall(format!(
+                "cannot call '{name}' from a sync host function in an async store"
+            )));
+        }
+        if self.guest_func("__pack_alloc").is_none() {
+            return Err(LinkerError::GuestCall(format!(
+                "cannot call '{name}': nested calls need a guest that exports __pack_alloc"
+            )));
+        }
+        let func = self
+            .guest_func(name)
+            .ok_or_else(|| LinkerError::GuestCall(format!("no export named '{name}'")))?
+            .typed::<(i32, i32, i32, i32), i32>(&self.caller)
+            .map_err(|e| LinkerError::GuestCall(e.to_string()))?;
+
+        let input_len =
+            encoded_len(input).map_err(|e| LinkerError::EncodingError(e.to_string()))?;
+        let in_ptr = self.guest_alloc(input_len)?;
+        let slots = self.guest_alloc(RESULT_SLOTS_SIZE)?;
+        let memory = self.resolve_memory()?;
+        encode_to_guest(memory.data_mut(&mut self.caller), in_ptr, input_len, input)
+            .map_err(|e| LinkerError::EncodingError(e.to_string()))?;
+
+        let status = func
+            .call(
+                &mut self.caller,
+                (in_ptr, input_len as i32, slots, slots + 4),
+            )
+            .map_err(|e| LinkerError::GuestCall(e.to_string()))?;
+
+        let slot_bytes = self.guest_bytes(slots, RESULT_SLOTS_SIZE as i32)?;
+        let out_ptr =
+            i32::from_le_bytes([slot_bytes[0], slot_bytes[1], slot_bytes[2], slot_bytes[3]]);
+        let out_len =
+            i32::from_le_bytes([slot_bytes[4], slot_bytes[5], slot_bytes[6], slot_bytes[7]]);
+        self.guest_free(slots, RESULT_SLOTS_SIZE as i32)?;
+        self.guest_free(in_ptr, input_len as i32)?;
+
+        let result = if status != 0 {
+            let message = String::from_utf8_lossy(self.guest_bytes(out_ptr, out_len)?).into_owned();
+            Err(LinkerError::GuestCall(format!(
+                "function '{name}' returned error: {message}"
+            )))
+        } else {
+            self.read_value(out_ptr, out_len)
+        };
+        self.guest_free(out_ptr, out_len)?;
+        result
+    }
+
+    /// Look up a function exported by the calling guest.
+    fn guest_func(&mut self, name: &str) -> Option<wasmtime::Func> {
+        self.caller.get_export(name).and_then(|e| e.into_func())
+    }
+
+    /// Allocate `size` bytes through the guest's `__pack_alloc`.
+    fn guest_alloc(&mut self, size: usize) -> Result<i32, LinkerError> {
+        let alloc = self
+            .guest_func("__pack_alloc")
+            .ok_or_else(|| LinkerError::MemoryError("__pack_alloc not found".into()))?;
+        let ptr = alloc
+            .typed::<i32, i32>(&self.caller)
+            .and_then(|typed| typed.call(&mut self.caller, size as i32))
+            .map_err(|e| LinkerError::GuestCall(e.to_string()))?;
+        if ptr == 0 {
+            return Err(LinkerError::MemoryError("Guest allocation failed".into()));
+        }
+        Ok(ptr)
+    }
+
+    /// Hand a guest-allocated buffer back through `__pack_free`, if exported.
+    fn guest_free(&mut self, ptr: i32, len: i32) -> Result<(), LinkerError> {
+        if let Some(free) = self.guest_func("__pack_free") {
+            free.typed::<(i32, i32), ()>(&self.caller)
+                .and_then(|typed| typed.call(&mut self.caller, (ptr, len)))
+                .map_err(|e| LinkerError::GuestCall(e.to_string()))?;
+        }
+        Ok(())
+    }
+
     /// Read a string from WASM memory
     pub fn read_string(&mut self, ptr: i32, len: i32) -> Result<String, LinkerError> {
         let memory = self.resolve_memory()?;
@@ -294,7 +388,7 @@ impl<'a, T> Ctx<'a, T> {
 ///
 /// Generic over `T` which is the store data type.
 pub struct HostLinkerBuilder<'a, T> {
-    linker: &'a mut Linker<T>,
+    linker: &'a mut Linker<StoreData<T>>,
     engine: &'a Engine,
     error_handler: Option<ErrorHandler>,
     interceptor: Option<Arc<dyn CallInterceptor>>,
@@ -305,7 +399,7 @@ pub struct HostLinkerBuilder<'a, T> {
 
 impl<'a, T> HostLinkerBuilder<'a, T> {
     /// Create a new builder wrapping a wasmtime Linker
-    pub fn new(engine: &'a Engine, linker: &'a mut Linker<T>) -> Self {
+    pub fn new(engine: &'a Engine, linker: &'a mut Linker<StoreData<T>>) -> Self {
         Self {
             linker,
             engine,
@@ -328,6 +422,8 @@ impl<'a, T> HostLinkerBuilder<'a, T> {
     ///
     /// The interceptor is passed to all interface builders created from this
     /// linker builder, enabling automatic interception of every host function.
+    /// To install several, combine them with an
+    /// [`InterceptorChain`](super::InterceptorChain).
     pub fn set_interceptor(&mut self, interceptor: Arc<dyn CallInterceptor>) -> &mut Self {
         self.interceptor = Some(interceptor);
         self
@@ -429,7 +525,7 @@ impl<'a, T> HostLinkerBuilder<'a, T> {
     }
 
     /// Get the underlying wasmtime Linker for advanced operations
-    pub fn inner(&mut self) -> &mut Linker<T> {
+    pub fn inner(&mut self) -> &mut Linker<StoreData<T>> {
         self.linker
     }
 
@@ -457,37 +553,57 @@ fn resolve_caller_memory<T>(
     provided.or_else(|| caller.get_export("memory").and_then(|e| e.into_memory()))
 }
 
-/// Hand a `Value` back to the guest: encode it into the reserved host-return
-/// buffer and write (ptr, len) into the caller's result slots. The buffer sits
-/// at a fixed low offset that is safe for both legacy modules (their reserved
-/// I/O region) and PIC packages (the guard region *below* the package's
-/// `__memory_base`, so it can't corrupt package or allocator state). Host calls
-/// are sequential — the guest reads each result before its next host call — so
-/// one buffer suffices. Bounded by `OUTPUT_BUFFER_CAPACITY`.
+/// Hand a `Value` back to the guest: encode it into a return buffer and write
+/// (ptr, len) into the caller's result slots.
 ///
-/// A fixed buffer (rather than a guest `__pack_alloc` call) is required because
-/// a *sync* host function cannot call a guest export in an async store.
+/// In a sync store the buffer comes from the guest's `__pack_alloc` when it
+/// exports one, so a host function that re-enters the guest (see
+/// [`Ctx::call_with_value`]) can never have its return overwritten by a nested
+/// call's. Otherwise — an async store, where a *sync* host function cannot
+/// call a guest export, or a guest with no allocator — it falls back to the
+/// reserved host-return buffer. That buffer sits at a fixed low offset that is
+/// safe for both legacy modules (their reserved I/O region) and PIC packages
+/// (the guard region *below* the package's `__memory_base`, so it can't
+/// corrupt package or allocator state), and is bounded by
+/// `OUTPUT_BUFFER_CAPACITY`.
+///
+/// Returns the same ownership bit as [`write_host_output_async`].
 fn write_host_output<T>(
     caller: &mut Caller<'_, T>,
     memory: Option<Memory>,
     out_ptr_ptr: i32,
     out_len_ptr: i32,
     value: &Value,
-) -> Result<(), HostFunctionErrorKind> {
-    let bytes = encode(value).map_err(|e| HostFunctionErrorKind::Encode(e.to_string()))?;
+) -> Result<bool, HostFunctionErrorKind> {
+    let len = encoded_len(value).map_err(|e| HostFunctionErrorKind::Encode(e.to_string()))?;
     let memory = resolve_caller_memory(caller, memory)
         .ok_or_else(|| HostFunctionErrorKind::MemoryWrite("no guest memory available".into()))?;
-    if bytes.len() > OUTPUT_BUFFER_CAPACITY {
-        return Err(HostFunctionErrorKind::MemoryWrite(format!(
-            "host return {} bytes exceeds capacity {}",
-            bytes.len(),
-            OUTPUT_BUFFER_CAPACITY
-        )));
-    }
-    let data_ptr = (OUTPUT_BUFFER_OFFSET + 8) as i32;
-    memory
-        .write(&mut *caller, data_ptr as usize, &bytes)
-        .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
+    let alloc = if caller.engine().is_async() {
+        None
+    } else {
+        caller
+            .get_export("__pack_alloc")
+            .and_then(|e| e.into_func())
+    };
+    let (data_ptr, guest_allocated) = match alloc {
+        Some(f) => {
+            let ptr = f
+                .typed::<i32, i32>(&*caller)
+                .and_then(|typed| typed.call(&mut *caller, len as i32))
+                .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
+            (ptr, true)
+        }
+        None => {
+            if len > OUTPUT_BUFFER_CAPACITY {
+                return Err(HostFunctionErrorKind::MemoryWrite(format!(
+                    "host return {} bytes exceeds capacity {}",
+                    len, OUTPUT_BUFFER_CAPACITY
+                )));
+            }
+            ((OUTPUT_BUFFER_OFFSET + 8) as i32, false)
+        }
+    };
+    encode_to_guest(memory.data_mut(&mut *caller), data_ptr, len, value)?;
     memory
         .write(&mut *caller, out_ptr_ptr as usize, &data_ptr.to_le_bytes())
         .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
@@ -495,9 +611,29 @@ fn write_host_output<T>(
         .write(
             &mut *caller,
             out_len_ptr as usize,
-            &(bytes.len() as i32).to_le_bytes(),
+            &(len as i32).to_le_bytes(),
         )
         .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
+    Ok(guest_allocated)
+}
+
+/// Encode `value` directly into guest memory at `ptr`; `len` is its
+/// [`encoded_len`], already reserved there.
+fn encode_to_guest(
+    data: &mut [u8],
+    ptr: i32,
+    len: usize,
+    value: &Value,
+) -> Result<(), HostFunctionErrorKind> {
+    let start = ptr as u32 as usize;
+    let dst = data
+        .get_mut(start..start.saturating_add(len))
+        .ok_or_else(|| {
+            HostFunctionErrorKind::MemoryWrite(format!(
+                "out of bounds memory write: {len} bytes at {start}"
+            ))
+        })?;
+    encode_into(value, dst).map_err(|e| HostFunctionErrorKind::Encode(e.to_string()))?;
     Ok(())
 }
 
@@ -519,7 +655,8 @@ async fn write_host_output_async<T: Send>(
     out_len_ptr: i32,
     value: &Value,
 ) -> Result<bool, HostFunctionErrorKind> {
-    let bytes = encode(value).map_err(|e| HostFunctionErrorKind::Encode(e.to_string()))?;
+    // Size first so the value is encoded once, straight into the guest buffer.
+    let len = encoded_len(value).map_err(|e| HostFunctionErrorKind::Encode(e.to_string()))?;
     let memory = resolve_caller_memory(caller, memory)
         .ok_or_else(|| HostFunctionErrorKind::MemoryWrite("no guest memory available".into()))?;
     // Prefer guest allocation (unbounded — theater's large returns). Fall back
@@ -534,25 +671,22 @@ async fn write_host_output_async<T: Send>(
                 .typed::<i32, i32>(&*caller)
                 .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
             let ptr = typed
-                .call_async(&mut *caller, bytes.len() as i32)
+                .call_async(&mut *caller, len as i32)
                 .await
                 .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
             (ptr, true)
         }
         None => {
-            if bytes.len() > OUTPUT_BUFFER_CAPACITY {
+            if len > OUTPUT_BUFFER_CAPACITY {
                 return Err(HostFunctionErrorKind::MemoryWrite(format!(
                     "host return {} bytes exceeds capacity {}",
-                    bytes.len(),
-                    OUTPUT_BUFFER_CAPACITY
+                    len, OUTPUT_BUFFER_CAPACITY
                 )));
             }
             ((OUTPUT_BUFFER_OFFSET + 8) as i32, false)
         }
     };
-    memory
-        .write(&mut *caller, data_ptr as usize, &bytes)
-        .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
+    encode_to_guest(memory.data_mut(&mut *caller), data_ptr, len, value)?;
     memory
         .write(&mut *caller, out_ptr_ptr as usize, &data_ptr.to_le_bytes())
         .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
@@ -560,7 +694,7 @@ async fn write_host_output_async<T: Send>(
         .write(
             &mut *caller,
             out_len_ptr as usize,
-            &(bytes.len() as i32).to_le_bytes(),
+            &(len as i32).to_le_bytes(),
         )
         .map_err(|e| HostFunctionErrorKind::MemoryWrite(e.to_string()))?;
     Ok(guest_allocated)
@@ -575,7 +709,7 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
     /// # Example
     ///
     /// ```ignore
-    /// interface.func_raw("alloc", |caller: Caller<'_, MyState>, size: i32| -> i32 {
+    /// interface.func_raw("alloc", |caller: Caller<'_, StoreData<MyState>>, size: i32| -> i32 {
     ///     let mut offset = caller.data().alloc_offset.lock().unwrap();
     ///     let ptr = *offset;
     ///     *offset += size as usize;
@@ -585,7 +719,7 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
     pub fn func_raw<Params, Results>(
         &mut self,
         name: &str,
-        func: impl wasmtime::IntoFunc<T, Params, Results>,
+        func: impl wasmtime::IntoFunc<StoreData<T>, Params, Results>,
     ) -> Result<&mut Self, LinkerError> {
         self.linker
             .linker
@@ -602,8 +736,9 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
     ///
     /// The WASM function signature is `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`:
     /// - Input: in_ptr/in_len point to Graph ABI encoded input value
-    /// - Output: host writes data to OUTPUT_BUFFER_OFFSET, then writes ptr/len to the provided slots
-    /// - Returns 0 on success, -1 on error
+    /// - Output: host encodes the result into a return buffer, then writes ptr/len to the provided slots
+    /// - Returns 1 on success when the buffer is guest-allocated (the guest frees
+    ///   it), 0 on success when it is the host scratch buffer, -1 on error
     ///
     /// Errors during decode/encode are logged via the error handler (see
     /// `HostLinkerBuilder::on_error`). On error, returns -1.
@@ -637,7 +772,7 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
             .func_wrap(
                 &self.module_name,
                 name,
-                move |caller: Caller<'_, T>,
+                move |caller: Caller<'_, StoreData<T>>,
                       in_ptr: i32,
                       in_len: i32,
                       out_ptr_ptr: i32,
@@ -663,8 +798,8 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
                         }
                     };
 
-                    // Encode a Value and hand it back to the guest. PIC-safe:
-                    // guest-allocated buffer, no fixed output-buffer offset.
+                    // Encode a Value and hand it back to the guest. The status
+                    // tells the guest whether it owns (and must free) the buffer.
                     let write_output = |ctx: &mut Ctx<'_, T>, value: &Value| -> i32 {
                         match write_host_output(
                             &mut ctx.caller,
@@ -673,7 +808,8 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
                             out_len_ptr,
                             value,
                         ) {
-                            Ok(()) => 0,
+                            Ok(true) => 1,
+                            Ok(false) => 0,
                             Err(kind) => {
                                 report(kind);
                                 -1
@@ -788,7 +924,7 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
             .func_wrap(
                 &self.module_name,
                 name,
-                move |caller: Caller<'_, T>,
+                move |caller: Caller<'_, StoreData<T>>,
                       in_ptr: i32,
                       in_len: i32,
                       out_ptr_ptr: i32,
@@ -814,8 +950,8 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
                         }
                     };
 
-                    // Encode a Value and hand it back to the guest. PIC-safe:
-                    // guest-allocated buffer, no fixed output-buffer offset.
+                    // Encode a Value and hand it back to the guest. The status
+                    // tells the guest whether it owns (and must free) the buffer.
                     let write_output = |ctx: &mut Ctx<'_, T>, value: &Value| -> i32 {
                         match write_host_output(
                             &mut ctx.caller,
@@ -824,7 +960,8 @@ impl<T: 'static> InterfaceBuilder<'_, '_, T> {
                             out_len_ptr,
                             value,
                         ) {
-                            Ok(()) => 0,
+                            Ok(true) => 1,
+                            Ok(false) => 0,
                             Err(kind) => {
                                 report(kind);
                                 -1
@@ -966,7 +1103,7 @@ impl<T: Send + Clone + 'static> InterfaceBuilder<'_, '_, T> {
             .func_wrap_async(
                 &self.module_name,
                 name,
-                move |mut caller: Caller<'_, T>,
+                move |mut caller: Caller<'_, StoreData<T>>,
                       (in_ptr, in_len, out_ptr_ptr, out_len_ptr): (i32, i32, i32, i32)| {
                     let func = func.clone();
                     let error_handler = error_handler.clone();
@@ -975,7 +1112,7 @@ impl<T: Send + Clone + 'static> InterfaceBuilder<'_, '_, T> {
                     let func_name = func_name.clone();
 
                     // Clone state before entering async block
-                    let state = caller.data().clone();
+                    let state = caller.data().state().clone();
 
                     Box::new(async move {
                         // Helper to report errors
@@ -1138,7 +1275,7 @@ impl<T: Send + Clone + 'static> InterfaceBuilder<'_, '_, T> {
             .func_wrap_async(
                 &self.module_name,
                 name,
-                move |mut caller: Caller<'_, T>,
+                move |mut caller: Caller<'_, StoreData<T>>,
                       (in_ptr, in_len, out_ptr_ptr, out_len_ptr): (i32, i32, i32, i32)| {
                     let func = func.clone();
                     let error_handler = error_handler.clone();
@@ -1147,7 +1284,7 @@ impl<T: Send + Clone + 'static> InterfaceBuilder<'_, '_, T> {
                     let func_name = func_name.clone();
 
                     // Clone state before entering async block
-                    let state = caller.data().clone();
+                    let state = caller.data().state().clone();
 
                     Box::new(async move {
                         // Helper to report errors
@@ -1372,7 +1509,7 @@ impl HostFunctionProvider<HostState> for DefaultHostProvider {
             .interface("host")?
             .func_raw(
                 "log",
-                |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                |mut caller: Caller<'_, StoreData<HostState>>, ptr: i32, len: i32| {
                     let memory = caller
                         .get_export("memory")
                         .and_then(|e| e.into_memory())
@@ -1388,14 +1525,17 @@ impl HostFunctionProvider<HostState> for DefaultHostProvider {
                     }
                 },
             )?
-            .func_raw("alloc", |caller: Caller<'_, HostState>, size: i32| -> i32 {
-                let mut offset = caller.data().alloc_offset.lock().unwrap();
-                let ptr = *offset;
-                *offset += size as usize;
-                // Align to 8 bytes
-                *offset = (*offset + 7) & !7;
-                ptr as i32
-            })?;
+            .func_raw(
+                "alloc",
+                |caller: Caller<'_, StoreData<HostState>>, size: i32| -> i32 {
+                    let mut offset = caller.data().alloc_offset.lock().unwrap();
+                    let ptr = *offset;
+                    *offset += size as usize;
+                    // Align to 8 bytes
+                    *offset = (*offset + 7) & !7;
+                    ptr as i32
+                },
+            )?;
 
         Ok(())
     }
@@ -1408,7 +1548,7 @@ mod tests {
     #[test]
     fn test_interface_builder_creation() {
         let engine = Engine::default();
-        let mut linker = Linker::<()>::new(&engine);
+        let mut linker = Linker::<StoreData<()>>::new(&engine);
         let mut builder = HostLinkerBuilder::new(&engine, &mut linker);
 
         // Should accept various interface name formats
@@ -1420,12 +1560,13 @@ mod tests {
     #[test]
     fn test_func_raw_registration() -> Result<(), LinkerError> {
         let engine = Engine::default();
-        let mut linker = Linker::<()>::new(&engine);
+        let mut linker = Linker::<StoreData<()>>::new(&engine);
         let mut builder = HostLinkerBuilder::new(&engine, &mut linker);
 
-        builder
-            .interface("test")?
-            .func_raw("add", |_caller: Caller<'_, ()>, a: i32, b: i32| a + b)?;
+        builder.interface("test")?.func_raw(
+            "add",
+            |_caller: Caller<'_, StoreData<()>>, a: i32, b: i32| a + b,
+        )?;
 
         Ok(())
     }
@@ -1436,7 +1577,7 @@ mod tests {
         fn register(&self, builder: &mut HostLinkerBuilder<'_, ()>) -> Result<(), LinkerError> {
             builder
                 .interface("test")?
-                .func_raw("noop", |_: Caller<'_, ()>| {})?;
+                .func_raw("noop", |_: Caller<'_, StoreData<()>>| {})?;
             Ok(())
         }
     }
@@ -1444,7 +1585,7 @@ mod tests {
     #[test]
     fn test_provider_registration() {
         let engine = Engine::default();
-        let mut linker = Linker::<()>::new(&engine);
+        let mut linker = Linker::<StoreData<()>>::new(&engine);
         let mut builder = HostLinkerBuilder::new(&engine, &mut linker);
 
         let result = builder.register_provider(&TestProvider);
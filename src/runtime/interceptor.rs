@@ -13,6 +13,14 @@
 //!
 //! A replay interceptor returns `Some(recorded_output)` from `before_import`/`before_export`,
 //! short-circuiting the actual call and returning the previously recorded value.
+//!
+//! # Combining interceptors
+//!
+//! An instance holds one interceptor. [`InterceptorChain`] runs several in
+//! order behind that one slot, and [`FilteredInterceptor`] narrows any of them
+//! to the interfaces and functions whose names match a glob.
+
+use std::sync::Arc;
 
 use crate::abi::Value;
 use async_trait::async_trait;
@@ -51,3 +59,216 @@ pub trait CallInterceptor: Send + Sync {
     /// Called after an export function returns.
     async fn after_export(&self, function: &str, input: &Value, output: &Value);
 }
+
+/// Runs several interceptors as one.
+///
+/// `before_*` hooks run in order and stop at the first interceptor that
+/// returns `Some`; its value is the call's result. `after_*` hooks always run
+/// on every interceptor, in order, so a tracer placed after a replayer still
+/// sees the replayed output.
+///
+/// ```ignore
+/// let chain = InterceptorChain::new()
+///     .with(tracing)
+///     .with(FilteredInterceptor::new(metrics).interface("theater:simple/*"))
+///     .with(replay);
+/// instance.set_interceptor(Arc::new(chain));
+/// ```
+#[derive(Clone, Default)]
+pub struct InterceptorChain {
+    interceptors: Vec<Arc<dyn CallInterceptor>>,
+}
+
+impl InterceptorChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an interceptor to the end of the chain.
+    pub fn with(mut self, interceptor: impl CallInterceptor + 'static) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Append an interceptor that is shared with other owners.
+    pub fn push(&mut self, interceptor: Arc<dyn CallInterceptor>) -> &mut Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.interceptors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interceptors.is_empty()
+    }
+}
+
+#[async_trait]
+impl CallInterceptor for InterceptorChain {
+    async fn before_import(&self, interface: &str, function: &str, input: &Value) -> Option<Value> {
+        for interceptor in &self.interceptors {
+            if let Some(output) = interceptor.before_import(interface, function, input).await {
+                return Some(output);
+            }
+        }
+        None
+    }
+
+    async fn after_import(&self, interface: &str, function: &str, input: &Value, output: &Value) {
+        for interceptor in &self.interceptors {
+            interceptor
+                .after_import(interface, function, input, output)
+                .await;
+        }
+    }
+
+    async fn before_export(&self, function: &str, input: &Value) -> Option<Value> {
+        for interceptor in &self.interceptors {
+            if let Some(output) = interceptor.before_export(function, input).await {
+                return Some(output);
+            }
+        }
+        None
+    }
+
+    async fn after_export(&self, function: &str, input: &Value, output: &Value) {
+        for interceptor in &self.interceptors {
+            interceptor.after_export(function, input, output).await;
+        }
+    }
+}
+
+/// Applies an interceptor only to calls whose interface and function match.
+///
+/// Patterns are globs: `*` matches any run of characters (including `/`) and
+/// `?` matches one. A call passes if its interface matches any
+/// [`interface`](Self::interface) pattern and its function matches any
+/// [`function`](Self::function) pattern; with no patterns of a kind, every
+/// name passes. Exports have no interface and are matched as `""`, so an
+/// interface filter other than `*` leaves them out.
+///
+/// Calls that do not pass go straight through: `before_*` returns `None`
+/// and `after_*` does nothing.
+#[derive(Clone)]
+pub struct FilteredInterceptor {
+    inner: Arc<dyn CallInterceptor>,
+    interfaces: Vec<String>,
+    functions: Vec<String>,
+}
+
+impl FilteredInterceptor {
+    pub fn new(inner: impl CallInterceptor + 'static) -> Self {
+        Self::from_arc(Arc::new(inner))
+    }
+
+    /// Filter an interceptor that is shared with other owners.
+    pub fn from_arc(inner: Arc<dyn CallInterceptor>) -> Self {
+        Self {
+            inner,
+            interfaces: Vec::new(),
+            functions: Vec::new(),
+        }
+    }
+
+    /// Also pass calls on interfaces matching `pattern`, e.g. `theater:simple/*`.
+    pub fn interface(mut self, pattern: impl Into<String>) -> Self {
+        self.interfaces.push(pattern.into());
+        self
+    }
+
+    /// Also pass calls to functions matching `pattern`, e.g. `handle-*`.
+    pub fn function(mut self, pattern: impl Into<String>) -> Self {
+        self.functions.push(pattern.into());
+        self
+    }
+
+    /// Whether a call to `interface`.`function` reaches the inner interceptor.
+    pub fn matches(&self, interface: &str, function: &str) -> bool {
+        let any = |patterns: &[String], name: &str| {
+            patterns.is_empty() || patterns.iter().any(|p| glob_match(p, name))
+        };
+        any(&self.interfaces, interface) && any(&self.functions, function)
+    }
+}
+
+#[async_trait]
+impl CallInterceptor for FilteredInterceptor {
+    async fn before_import(&self, interface: &str, function: &str, input: &Value) -> Option<Value> {
+        if !self.matches(interface, function) {
+            return None;
+        }
+        self.inner.before_import(interface, function, input).await
+    }
+
+    async fn after_import(&self, interface: &str, function: &str, input: &Value, output: &Value) {
+        if self.matches(interface, function) {
+            self.inner
+                .after_import(interface, function, input, output)
+                .await;
+        }
+    }
+
+    async fn before_export(&self, function: &str, input: &Value) -> Option<Value> {
+        if !self.matches("", function) {
+            return None;
+        }
+        self.inner.before_export(function, input).await
+    }
+
+    async fn after_export(&self, function: &str, input: &Value, output: &Value) {
+        if self.matches("", function) {
+            self.inner.after_export(function, input, output).await;
+        }
+    }
+}
+
+/// Match `name` against a glob of literal characters, `*` and `?`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` seen, and the name position it is matched up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character and retry.
+                Some((sp, sn)) => {
+                    star = Some((sp, sn + 1));
+                    p = sp + 1;
+                    n = sn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn glob_matches_wildcards() {
+        assert!(glob_match("theater:simple/*", "theater:simple/runtime"));
+        assert!(glob_match("theater:*/runtime", "theater:simple/runtime"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("handle-?", "handle-a"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("theater:simple/*", "theater:other/runtime"));
+        assert!(!glob_match("handle-?", "handle-ab"));
+        assert!(!glob_match("a*b", "acb-c"));
+        assert!(!glob_match("", "x"));
+    }
+}
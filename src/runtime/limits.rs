@@ -0,0 +1,244 @@
+//! Resource limits for untrusted guests.
+//!
+//! A [`ResourceLimits`] policy is set once on a [`Runtime`](super::Runtime) or
+//! [`AsyncRuntime`](super::AsyncRuntime) and applied to every store it
+//! creates:
+//!
+//! - **Fuel** — a per-call budget. Every public call on an instance refills
+//!   the store to `fuel_per_call` before entering the guest, so one call can
+//!   never spend what a previous one left over. What the call actually spent
+//!   is available afterwards from `fuel_consumed`, for billing.
+//! - **Memory** — the largest any linear memory may grow to, in bytes,
+//!   enforced through a wasmtime [`ResourceLimiter`].
+//! - **Tables** — the most elements any table may grow to.
+//!
+//! Exceeding any of them fails the call (or the instantiation) with
+//! [`RuntimeError::ResourceExhausted`] rather than a generic trap.
+//!
+//! `Store::limiter` borrows its limiter from the store's data, so a runtime's
+//! stores hold a [`StoreData`]: the embedder's state next to the limiter.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use wasmtime::{AsContext, ResourceLimiter, Store, Trap};
+
+use super::RuntimeError;
+
+/// Per-store limits applied by a runtime. All limits default to unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Fuel available to each call (roughly one unit per wasm instruction).
+    pub fuel_per_call: Option<u64>,
+    /// Maximum size of any linear memory, in bytes.
+    pub max_memory_bytes: Option<usize>,
+    /// Maximum number of elements in any table.
+    pub max_table_elements: Option<usize>,
+}
+
+impl ResourceLimits {
+    /// No limits (the default).
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fuel_per_call(mut self, fuel: u64) -> Self {
+        self.fuel_per_call = Some(fuel);
+        self
+    }
+
+    pub fn with_max_memory_bytes(mut self, bytes: usize) -> Self {
+        self.max_memory_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_table_elements(mut self, elements: usize) -> Self {
+        self.max_table_elements = Some(elements);
+        self
+    }
+
+    /// Whether the engine must be built with fuel metering on.
+    pub(crate) fn meters_fuel(&self) -> bool {
+        self.fuel_per_call.is_some()
+    }
+
+    /// Create a store for `state` running under these limits: the limiter
+    /// the store's data carries is installed, and the store is fuelled for
+    /// its first call.
+    pub(crate) fn store<T>(
+        &self,
+        engine: &wasmtime::Engine,
+        state: T,
+    ) -> Result<Store<StoreData<T>>, RuntimeError> {
+        let mut store = Store::new(engine, StoreData::new(state, *self));
+        if self.max_memory_bytes.is_some() || self.max_table_elements.is_some() {
+            store.limiter(|data| &mut data.limiter);
+        }
+        self.refuel(&mut store)?;
+        Ok(store)
+    }
+
+    /// Refill the store's fuel to one call's budget.
+    pub(crate) fn refuel<T>(&self, store: &mut Store<T>) -> Result<(), RuntimeError> {
+        match self.fuel_per_call {
+            Some(fuel) => store.set_fuel(fuel).map_err(super::werr),
+            None => Ok(()),
+        }
+    }
+
+    /// Fuel spent since the last [`refuel`](Self::refuel), if fuel is metered.
+    pub(crate) fn fuel_consumed(&self, store: impl AsContext) -> Option<u64> {
+        let budget = self.fuel_per_call?;
+        let remaining = store.as_context().get_fuel().ok()?;
+        Some(budget.saturating_sub(remaining))
+    }
+
+    /// Turn a wasmtime error from a call into, or instantiation in, `store`
+    /// into a `RuntimeError`, recognising the traps raised by these limits.
+    pub(crate) fn classify(&self, err: wasmtime::Error, store: impl AsContext) -> RuntimeError {
+        if let Some(exhausted) = err.downcast_ref::<Exhausted>() {
+            return RuntimeError::ResourceExhausted {
+                kind: exhausted.kind,
+                used: exhausted.used,
+                limit: exhausted.limit,
+            };
+        }
+        if let (Some(Trap::OutOfFuel), Some(budget)) =
+            (err.downcast_ref::<Trap>(), self.fuel_per_call)
+        {
+            return RuntimeError::ResourceExhausted {
+                kind: ResourceKind::Fuel,
+                used: self.fuel_consumed(store).unwrap_or(budget),
+                limit: budget,
+            };
+        }
+        RuntimeError::WasmError(err.to_string())
+    }
+}
+
+/// Which limit a guest ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    /// Fuel, in units.
+    Fuel,
+    /// Linear memory, in bytes.
+    Memory,
+    /// Table size, in elements.
+    Table,
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceKind::Fuel => write!(f, "fuel"),
+            ResourceKind::Memory => write!(f, "memory"),
+            ResourceKind::Table => write!(f, "table"),
+        }
+    }
+}
+
+/// Raised from the limiter so a denied grow traps with a value we can
+/// recognise, instead of `memory.grow` quietly returning -1.
+#[derive(Debug)]
+struct Exhausted {
+    kind: ResourceKind,
+    used: u64,
+    limit: u64,
+}
+
+impl fmt::Display for Exhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} limit exceeded: {} > {}",
+            self.kind, self.used, self.limit
+        )
+    }
+}
+
+impl std::error::Error for Exhausted {}
+
+/// The data of every store a runtime creates: the embedder's state, which it
+/// derefs to, and the limiter enforcing the runtime's [`ResourceLimits`].
+///
+/// Host functions see it as the data of their `Caller`; the deref and
+/// [`Ctx::data`](super::Ctx::data) reach the state inside.
+pub struct StoreData<T> {
+    state: T,
+    limiter: Limiter,
+}
+
+impl<T> StoreData<T> {
+    pub(crate) fn new(state: T, limits: ResourceLimits) -> Self {
+        Self {
+            state,
+            limiter: Limiter(limits),
+        }
+    }
+
+    /// The embedder's state.
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+
+    /// The embedder's state, mutably.
+    pub fn state_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+}
+
+impl<T> Deref for StoreData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.state
+    }
+}
+
+impl<T> DerefMut for StoreData<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for StoreData<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoreData")
+            .field("state", &self.state)
+            .field("limits", &self.limiter.0)
+            .finish()
+    }
+}
+
+struct Limiter(ResourceLimits);
+
+impl ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        check(ResourceKind::Memory, desired, self.0.max_memory_bytes)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        check(ResourceKind::Table, desired, self.0.max_table_elements)
+    }
+}
+
+fn check(kind: ResourceKind, desired: usize, limit: Option<usize>) -> wasmtime::Result<bool> {
+    match limit {
+        Some(limit) if desired > limit => Err(wasmtime::Error::new(Exhausted {
+            kind,
+            used: desired as u64,
+            limit: limit as u64,
+        })),
+        _ => Ok(true),
+    }
+}
@@ -5,24 +5,36 @@
 mod host;
 pub mod interceptor;
 mod interface_check;
+mod limits;
+mod replay;
+mod snapshot;
 
 pub use host::{
     AsyncCtx, Ctx, DefaultHostProvider, ErrorHandler, HostFunctionError, HostFunctionErrorKind,
     HostFunctionProvider, HostLinkerBuilder, InterfaceBuilder, LinkerError, INPUT_BUFFER_OFFSET,
     OUTPUT_BUFFER_CAPACITY, OUTPUT_BUFFER_OFFSET, RESULT_LEN_OFFSET, RESULT_PTR_OFFSET,
+    RESULT_SLOTS_SIZE,
 };
-pub use interceptor::CallInterceptor;
+pub use interceptor::{CallInterceptor, FilteredInterceptor, InterceptorChain};
 pub use interface_check::{
     validate_instance_implements_interface, ExpectedSignature, InterfaceError,
 };
+pub use limits::{ResourceKind, ResourceLimits, StoreData};
+pub use replay::{
+    read_log, CallEvent, CallKind, Divergence, RecordingInterceptor, ReplayInterceptor, ValueDiff,
+};
+pub use snapshot::InstanceSnapshot;
 // Re-export the wasmtime types that appear in this module's public API
 // (AsyncRuntime::engine / wrap_module, AsyncCompiledModule::module) so
 // callers can name them without a direct wasmtime dependency.
 pub use wasmtime::{Engine, Module};
 
-use crate::abi::{decode, encode, Value};
+use crate::abi::{decode, encode, encode_into, encoded_len, GraphView, Value};
+use crate::engine::{call_pack_abi, call_pack_abi_status, now, PackAbiCalls};
 use crate::parser::{decode_with_schema, encode_with_schema, Interface};
 use crate::types::{Type, TypeDef};
+use host::block_on_interceptor;
+use std::borrow::Cow;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
@@ -51,6 +63,19 @@ pub enum RuntimeError {
 
     #[error("Memory error: {0}")]
     MemoryError(String),
+
+    #[error("Resource exhausted: {kind} used {used} of limit {limit}")]
+    ResourceExhausted {
+        kind: ResourceKind,
+        used: u64,
+        limit: u64,
+    },
+
+    #[error("Snapshot error: {0}")]
+    SnapshotError(String),
+
+    #[error("Call log error: {0}")]
+    CallLogError(String),
 }
 
 // ============================================================================
@@ -119,26 +144,60 @@ impl Default for HostImports {
 /// The package runtime
 pub struct Runtime {
     engine: Engine,
+    limits: ResourceLimits,
+    snapshots: bool,
 }
 
 impl Runtime {
     pub fn new() -> Self {
+        Self::with_limits(ResourceLimits::default())
+    }
+
+    /// Create a runtime whose instances run under `limits`.
+    ///
+    /// Fuel metering is only compiled in when `limits` sets a fuel budget.
+    pub fn with_limits(limits: ResourceLimits) -> Self {
         let mut config = Config::new();
         // Enable multi-memory so a *composed* actor (one memory per component,
         // wired by bridging shims — see `crate::compose`) loads here, not just on
         // the async path. A plain single-memory actor is unaffected.
         config.wasm_multi_memory(true);
+        config.consume_fuel(limits.meters_fuel());
         let engine = Engine::new(&config).expect("valid wasmtime config");
-        Self { engine }
+        Self {
+            engine,
+            limits,
+            snapshots: false,
+        }
+    }
+
+    /// Instrument every module this runtime loads so that
+    /// [`Instance::snapshot`] also captures its private mutable globals,
+    /// memories and tables. Each is exported under a `__pack_snapshot_*`
+    /// name, at the cost of a parse and re-emit when the module is loaded.
+    pub fn with_snapshots(mut self) -> Self {
+        self.snapshots = true;
+        self
+    }
+
+    /// The limits applied to every instance of this runtime.
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
     }
 
     /// Load a WASM module from bytes
     pub fn load_module(&self, wasm_bytes: &[u8]) -> Result<CompiledModule<'_>, RuntimeError> {
-        let module = Module::new(&self.engine, wasm_bytes)
+        let wasm_bytes = if self.snapshots {
+            snapshot::instrument(wasm_bytes)
+        } else {
+            Cow::Borrowed(wasm_bytes)
+        };
+        let module = Module::new(&self.engine, &wasm_bytes)
             .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
         Ok(CompiledModule {
             module,
             engine: &self.engine,
+            limits: self.limits,
         })
     }
 
@@ -230,6 +289,72 @@ pub(crate) fn assert_self_contained(module: &Module) -> Result<(), RuntimeError>
     Ok(())
 }
 
+/// Borrow `len` bytes of guest memory at `offset`.
+fn memory_slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8], RuntimeError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| {
+            RuntimeError::MemoryError(format!(
+                "out of bounds memory access: {len} bytes at offset {offset}"
+            ))
+        })
+}
+
+/// Read the `(out_ptr, out_len)` pair a Pack ABI export wrote into the
+/// result slots at `slots`.
+fn read_result_slots(data: &[u8], slots: usize) -> Result<(usize, usize), RuntimeError> {
+    let bytes = memory_slice(data, slots, RESULT_SLOTS_SIZE)?;
+    let word =
+        |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    Ok((word(0) as usize, word(4) as usize))
+}
+
+fn describe_hash(hash: Option<&crate::metadata::TypeHash>) -> String {
+    hash.map_or_else(|| "(none)".to_string(), |h| h.to_string())
+}
+
+/// Copy already-encoded `bytes` into guest memory at `offset`.
+fn copy_to_memory(data: &mut [u8], offset: usize, bytes: &[u8]) -> Result<(), RuntimeError> {
+    let data_len = data.len();
+    let dst = offset
+        .checked_add(bytes.len())
+        .and_then(|end| data.get_mut(offset..end))
+        .ok_or_else(|| {
+            RuntimeError::MemoryError(format!(
+                "out of bounds memory write: {} bytes at offset {offset} (memory is {data_len} bytes)",
+                bytes.len()
+            ))
+        })?;
+    dst.copy_from_slice(bytes);
+    Ok(())
+}
+
+/// Encode `value` straight into guest memory at `offset`, where `len` is its
+/// [`encoded_len`] — no intermediate host buffer.
+fn encode_to_memory(
+    data: &mut [u8],
+    offset: usize,
+    len: usize,
+    value: &Value,
+) -> Result<(), RuntimeError> {
+    let data_len = data.len();
+    let dst = offset
+        .checked_add(len)
+        .and_then(|end| data.get_mut(offset..end))
+        .ok_or_else(|| {
+            RuntimeError::MemoryError(format!(
+                "out of bounds memory write: {len} bytes at offset {offset} (memory is {data_len} bytes)"
+            ))
+        })?;
+    encode_into(value, dst).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
+    Ok(())
+}
+
+fn view_error(err: packr_abi::AbiError) -> RuntimeError {
+    RuntimeError::AbiError(crate::abi::AbiError::from(err).to_string())
+}
+
 // ============================================================================
 // Async Runtime
 // ============================================================================
@@ -270,11 +395,21 @@ const NO_EPOCH_DEADLINE: u64 = u64::MAX / 2;
 
 pub struct AsyncRuntime {
     engine: Engine,
+    limits: ResourceLimits,
+    snapshots: bool,
 }
 
 impl AsyncRuntime {
     /// Create a new async-enabled runtime.
     pub fn new() -> Self {
+        Self::with_limits(ResourceLimits::default())
+    }
+
+    /// Create an async runtime whose instances run under `limits`.
+    ///
+    /// Fuel complements the epoch deadline: the deadline bounds wall-clock
+    /// time, fuel bounds (and measures) the work a call actually does.
+    pub fn with_limits(limits: ResourceLimits) -> Self {
         let mut config = Config::new();
         config.async_support(true);
         // Enable multi-memory for composed modules that merge multiple WASM files
@@ -288,17 +423,41 @@ impl AsyncRuntime {
         // to no deadline (see the instantiate paths), so behaviour is unchanged
         // until a caller opts in.
         config.epoch_interruption(true);
+        config.consume_fuel(limits.meters_fuel());
         let engine = Engine::new(&config).expect("failed to create async engine");
-        Self { engine }
+        Self {
+            engine,
+            limits,
+            snapshots: false,
+        }
+    }
+
+    /// Instrument every module this runtime loads so that
+    /// [`AsyncInstance::snapshot`] also captures its private state; see
+    /// [`Runtime::with_snapshots`].
+    pub fn with_snapshots(mut self) -> Self {
+        self.snapshots = true;
+        self
+    }
+
+    /// The limits applied to every instance of this runtime.
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
     }
 
     /// Load a WASM module from bytes.
     pub fn load_module(&self, wasm_bytes: &[u8]) -> Result<AsyncCompiledModule<'_>, RuntimeError> {
-        let module = Module::new(&self.engine, wasm_bytes)
+        let wasm_bytes = if self.snapshots {
+            snapshot::instrument(wasm_bytes)
+        } else {
+            Cow::Borrowed(wasm_bytes)
+        };
+        let module = Module::new(&self.engine, &wasm_bytes)
             .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
         Ok(AsyncCompiledModule {
             module,
             engine: &self.engine,
+            limits: self.limits,
         })
     }
 
@@ -328,6 +487,7 @@ impl AsyncRuntime {
         AsyncCompiledModule {
             module,
             engine: &self.engine,
+            limits: self.limits,
         }
     }
 
@@ -364,14 +524,14 @@ impl Default for AsyncRuntime {
 /// with an in-wasm allocator module (real free) over a shared per-instance
 /// `Memory`.
 pub(crate) fn register_default_alloc<T: 'static>(
-    linker: &mut Linker<T>,
+    linker: &mut Linker<StoreData<T>>,
 ) -> Result<(), RuntimeError> {
     let next = std::sync::Arc::new(std::sync::Mutex::new(0usize));
     linker
         .func_wrap(
             "pack:alloc",
             "alloc",
-            move |mut caller: wasmtime::Caller<'_, T>, size: i32, align: i32| -> i32 {
+            move |mut caller: wasmtime::Caller<'_, StoreData<T>>, size: i32, align: i32| -> i32 {
                 let memory = match caller.get_export("memory").and_then(|e| e.into_memory()) {
                     Some(m) => m,
                     None => return 0,
@@ -402,7 +562,10 @@ pub(crate) fn register_default_alloc<T: 'static>(
         .func_wrap(
             "pack:alloc",
             "dealloc",
-            move |_caller: wasmtime::Caller<'_, T>, _ptr: i32, _size: i32, _align: i32| {
+            move |_caller: wasmtime::Caller<'_, StoreData<T>>,
+                  _ptr: i32,
+                  _size: i32,
+                  _align: i32| {
                 // Bump allocator: no-op. (1b's in-wasm module reclaims memory.)
             },
         )
@@ -414,6 +577,7 @@ pub(crate) fn register_default_alloc<T: 'static>(
 pub struct AsyncCompiledModule<'a> {
     module: Module,
     engine: &'a Engine,
+    limits: ResourceLimits,
 }
 
 impl AsyncCompiledModule<'_> {
@@ -428,26 +592,39 @@ impl AsyncCompiledModule<'_> {
 
     /// Instantiate the module with no imports (async).
     pub async fn instantiate_async(&self) -> Result<AsyncInstance<()>, RuntimeError> {
-        let mut store = Store::new(self.engine, ());
+        let mut store = self.limits.store(self.engine, ())?;
         // epoch_interruption is enabled engine-wide; default to no deadline so
         // the guest never traps unless a caller arms it via set_epoch_deadline.
         store.set_epoch_deadline(NO_EPOCH_DEADLINE);
-        let mut linker = Linker::<()>::new(self.engine);
+        let mut linker = Linker::new(self.engine);
         register_default_alloc(&mut linker)?;
 
         let instance = linker
             .instantiate_async(&mut store, &self.module)
             .await
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+            .map_err(|e| self.limits.classify(e, &store))?;
 
         Ok(AsyncInstance {
             store,
             instance,
             interceptor: None,
             memory: None,
+            limits: self.limits,
         })
     }
 
+    /// Instantiate the module and resume it from `snapshot` (async).
+    ///
+    /// See [`CompiledModule::restore`].
+    pub async fn restore_async(
+        &self,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<AsyncInstance<()>, RuntimeError> {
+        let mut instance = self.instantiate_async().await?;
+        instance.restore(snapshot).await?;
+        Ok(instance)
+    }
+
     /// Instantiate the module with a builder function for configuring host functions (async).
     ///
     /// This is the recommended method for async Theater-style integration.
@@ -494,7 +671,7 @@ impl AsyncCompiledModule<'_> {
         // host functions. Reject anything else BEFORE we build the store.
         assert_self_contained(&self.module)?;
 
-        let mut store = Store::new(self.engine, state);
+        let mut store = self.limits.store(self.engine, state)?;
         // epoch_interruption is enabled engine-wide; default to no deadline so
         // the guest never traps unless the caller arms it via set_epoch_deadline.
         store.set_epoch_deadline(NO_EPOCH_DEADLINE);
@@ -514,7 +691,7 @@ impl AsyncCompiledModule<'_> {
         let instance = linker
             .instantiate_async(&mut store, &self.module)
             .await
-            .map_err(werr)?;
+            .map_err(|e| self.limits.classify(e, &store))?;
 
         // Grab the actor's exported memory for host-side marshalling.
         let memory = instance
@@ -524,7 +701,10 @@ impl AsyncCompiledModule<'_> {
         // Static data is at absolute addresses (no relocation) — there is no
         // `__wasm_apply_data_relocs` to call. Run ctors for any static init.
         if let Ok(ctors) = instance.get_typed_func::<(), ()>(&mut store, "__wasm_call_ctors") {
-            ctors.call_async(&mut store, ()).await.map_err(werr)?;
+            ctors
+                .call_async(&mut store, ())
+                .await
+                .map_err(|e| self.limits.classify(e, &store))?;
         }
 
         Ok(AsyncInstance {
@@ -532,6 +712,7 @@ impl AsyncCompiledModule<'_> {
             instance,
             interceptor,
             memory,
+            limits: self.limits,
         })
     }
 
@@ -543,12 +724,13 @@ impl AsyncCompiledModule<'_> {
 
 /// An async WASM instance.
 pub struct AsyncInstance<T> {
-    store: Store<T>,
+    store: Store<StoreData<T>>,
     instance: WasmtimeInstance,
     interceptor: Option<Arc<dyn CallInterceptor>>,
     /// Host-owned shared memory for PIC packages (which don't export memory).
     /// `None` for legacy modules that export their own memory.
     memory: Option<Memory>,
+    limits: ResourceLimits,
 }
 
 impl<T: Send> AsyncInstance<T> {
@@ -608,20 +790,40 @@ impl<T: Send> AsyncInstance<T> {
         Ok(memory.data_size(&self.store))
     }
 
+    /// Fuel spent by the most recent call, when the runtime meters fuel
+    /// (see [`ResourceLimits::fuel_per_call`]). Read it after a call to bill
+    /// the guest for the work it did.
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        self.limits.fuel_consumed(&self.store)
+    }
+
     /// Encode a Value and write it to memory at the given offset.
     pub fn write_value(&mut self, offset: usize, value: &Value) -> Result<usize, RuntimeError> {
-        let bytes = encode(value).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
-        self.write_memory(offset, &bytes)?;
-        Ok(bytes.len())
+        let len = encoded_len(value).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
+        let memory = self.get_memory()?;
+        encode_to_memory(memory.data_mut(&mut self.store), offset, len, value)?;
+        Ok(len)
     }
 
     /// Read bytes from memory and decode them as a Value.
     pub fn read_value(&mut self, offset: usize, len: usize) -> Result<Value, RuntimeError> {
-        let bytes = self.read_memory(offset, len)?;
-        decode(&bytes).map_err(|e| RuntimeError::AbiError(e.to_string()))
+        let memory = self.get_memory()?;
+        let bytes = memory_slice(memory.data(&self.store), offset, len)?;
+        decode(bytes).map_err(|e| RuntimeError::AbiError(e.to_string()))
+    }
+
+    /// Borrow a CGRF buffer in memory as a [`GraphView`], without copying it.
+    ///
+    /// Nodes are decoded only as they are visited, so reading one field of a
+    /// large message costs nothing for the rest of it.
+    pub fn view_value(&mut self, offset: usize, len: usize) -> Result<GraphView<'_>, RuntimeError> {
+        let memory = self.get_memory()?;
+        let bytes = memory_slice(memory.data(&self.store), offset, len)?;
+        GraphView::from_bytes(bytes).map_err(view_error)
     }
 
     /// Set a call interceptor for recording/replaying export function calls.
+    /// To install several, combine them with an [`InterceptorChain`].
     pub fn set_interceptor(&mut self, interceptor: Arc<dyn CallInterceptor>) {
         self.interceptor = Some(interceptor);
     }
@@ -633,8 +835,9 @@ impl<T: Send> AsyncInstance<T> {
 
     /// Call a function using the Pack ABI (async).
     ///
-    /// If the guest exports `__pack_alloc`, input is dynamically allocated.
-    /// Otherwise, falls back to a fixed input buffer at INPUT_BUFFER_OFFSET.
+    /// If the guest exports `__pack_alloc`, the input buffer and the result
+    /// slots are allocated by the guest. Otherwise they use the host scratch
+    /// region at `INPUT_BUFFER_OFFSET` / `RESULT_PTR_OFFSET`.
     ///
     /// The WASM function signature is `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`:
     /// - Returns: 0 on success, -1 on error (error message in ptr/len)
@@ -653,78 +856,13 @@ impl<T: Send> AsyncInstance<T> {
             }
         }
 
-        // Encode input
-        let input_bytes = encode(input).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
-
-        // Try to allocate input buffer dynamically, fall back to fixed buffer
-        let (in_ptr, dynamic_input) = match self.call_pack_alloc_async(input_bytes.len()).await {
-            Ok(ptr) => (ptr, true),
-            Err(_) => (INPUT_BUFFER_OFFSET, false),
-        };
-
-        // Write input to buffer
-        let memory = self.get_memory()?;
-        memory
-            .write(&mut self.store, in_ptr, &input_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to write input".into()))?;
-
-        // Call the function
-        let func = self
-            .instance
-            .get_typed_func::<(i32, i32, i32, i32), i32>(&mut self.store, name)
-            .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
-
-        let status = func
-            .call_async(
-                &mut self.store,
-                (
-                    in_ptr as i32,
-                    input_bytes.len() as i32,
-                    RESULT_PTR_OFFSET as i32,
-                    RESULT_LEN_OFFSET as i32,
-                ),
-            )
-            .await
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
-
-        // Free the input buffer if dynamically allocated
-        if dynamic_input {
-            self.call_pack_free_async(in_ptr, input_bytes.len())
-                .await
-                .ok();
-        }
-
-        // Read result ptr/len from slots
-        let memory = self.get_memory()?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to read result ptr".into()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to read result len".into()))?;
-
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
-
-        // Check for error
-        if status != 0 {
-            // Read error message
-            let mut err_bytes = vec![0u8; out_len];
-            memory
-                .read(&self.store, out_ptr, &mut err_bytes)
-                .map_err(|_| RuntimeError::MemoryError("Failed to read error".into()))?;
-
-            // Free the error buffer
-            self.call_pack_free_async(out_ptr, out_len).await.ok();
-
-            let err_msg = String::from_utf8_lossy(&err_bytes).to_string();
-            return Err(RuntimeError::WasmError(format!(
-                "function '{}' returned error: {}",
-                name, err_msg
-            )));
-        }
+        // Size the input up front so it can be encoded straight into guest memory
+        let input_len = encoded_len(input).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
+        let (out_ptr, out_len) = self
+            .call_pack_abi_async(name, input_len, |data, in_ptr| {
+                encode_to_memory(data, in_ptr, input_len, input)
+            })
+            .await?;
 
         // Read output value
         let result = self.read_value(out_ptr, out_len)?;
@@ -740,6 +878,67 @@ impl<T: Send> AsyncInstance<T> {
         Ok(result)
     }
 
+    /// [`AsyncInstance::call_with_value_async`] with the input already
+    /// CGRF-encoded, returning the output's bytes undecoded — for forwarding a
+    /// call between guests without a decode/encode round trip. The call
+    /// interceptor, which sees values, is not consulted.
+    pub async fn call_with_bytes_async(
+        &mut self,
+        name: &str,
+        input: &[u8],
+    ) -> Result<Vec<u8>, RuntimeError> {
+        let (out_ptr, out_len) = self
+            .call_pack_abi_async(name, input.len(), |data, in_ptr| {
+                copy_to_memory(data, in_ptr, input)
+            })
+            .await?;
+        let output = self.read_memory(out_ptr, out_len)?;
+        self.call_pack_free_async(out_ptr, out_len).await.ok();
+        Ok(output)
+    }
+
+    /// [`AsyncInstance::call_with_bytes_async`] returning the export's status
+    /// along with its output — on error, the message — rather than turning a
+    /// non-zero status into an error: what a routed link hands its consumer.
+    pub(crate) async fn call_with_bytes_status_async(
+        &mut self,
+        name: &str,
+        input: &[u8],
+    ) -> Result<(i32, Vec<u8>), RuntimeError> {
+        self.limits.refuel(&mut self.store)?;
+        let (status, out_ptr, out_len) =
+            call_pack_abi_status(self, name, input.len(), |data, in_ptr| {
+                copy_to_memory(data, in_ptr, input)
+            })
+            .await?;
+        let output = self.read_memory(out_ptr, out_len)?;
+        self.call_pack_free_async(out_ptr, out_len).await.ok();
+        Ok((status, output))
+    }
+
+    /// One Pack ABI call of `name` (async): negotiate its buffers, let
+    /// `write_input` fill the `input_len`-byte input at the offset it is given,
+    /// call, and check the status. Returns the output's `(ptr, len)`, which the
+    /// caller reads and frees.
+    async fn call_pack_abi_async(
+        &mut self,
+        name: &str,
+        input_len: usize,
+        write_input: impl FnOnce(&mut [u8], usize) -> Result<(), RuntimeError>,
+    ) -> Result<(usize, usize), RuntimeError> {
+        // One fuel budget covers the whole call, allocation and free included
+        self.limits.refuel(&mut self.store)?;
+        call_pack_abi(self, name, input_len, write_input).await
+    }
+
+    /// Whether the guest exports `__pack_alloc`, and so allocates its own
+    /// call buffers.
+    fn has_allocator(&mut self) -> bool {
+        self.instance
+            .get_func(&mut self.store, "__pack_alloc")
+            .is_some()
+    }
+
     /// Call __pack_alloc to allocate a buffer in guest memory (async).
     async fn call_pack_alloc_async(&mut self, size: usize) -> Result<usize, RuntimeError> {
         let alloc_func = self
@@ -750,7 +949,7 @@ impl<T: Send> AsyncInstance<T> {
         let ptr = alloc_func
             .call_async(&mut self.store, size as i32)
             .await
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+            .map_err(|e| self.limits.classify(e, &self.store))?;
 
         if ptr == 0 {
             return Err(RuntimeError::MemoryError("Guest allocation failed".into()));
@@ -768,7 +967,7 @@ impl<T: Send> AsyncInstance<T> {
             free_func
                 .call_async(&mut self.store, (ptr as i32, len as i32))
                 .await
-                .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+                .map_err(|e| self.limits.classify(e, &self.store))?;
         }
         Ok(())
     }
@@ -785,9 +984,10 @@ impl<T: Send> AsyncInstance<T> {
             .get_typed_func::<(i32, i32), i32>(&mut self.store, name)
             .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
 
+        self.limits.refuel(&mut self.store)?;
         func.call_async(&mut self.store, (a, b))
             .await
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))
+            .map_err(|e| self.limits.classify(e, &self.store))
     }
 
     /// Read embedded type metadata from the package (async).
@@ -798,47 +998,7 @@ impl<T: Send> AsyncInstance<T> {
     pub async fn types(
         &mut self,
     ) -> Result<crate::metadata::PackageMetadata, crate::metadata::MetadataError> {
-        let types_func = self
-            .instance
-            .get_typed_func::<(i32, i32), i32>(&mut self.store, "__pack_types")
-            .map_err(|_| crate::metadata::MetadataError::NotFound)?;
-
-        let status = types_func
-            .call_async(
-                &mut self.store,
-                (RESULT_PTR_OFFSET as i32, RESULT_LEN_OFFSET as i32),
-            )
-            .await
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-
-        if status != 0 {
-            return Err(crate::metadata::MetadataError::CallFailed(
-                "non-zero status from __pack_types".into(),
-            ));
-        }
-
-        let memory = self
-            .get_memory()
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
-
-        // Read metadata bytes (static data, no __pack_free needed)
-        let mut metadata_bytes = vec![0u8; out_len];
-        memory
-            .read(&self.store, out_ptr, &mut metadata_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-
-        crate::metadata::decode_metadata(&metadata_bytes)
+        crate::metadata::decode_metadata(&self.types_bytes().await?)
     }
 
     /// Read embedded type metadata with interface hashes from the package (async).
@@ -847,47 +1007,109 @@ impl<T: Send> AsyncInstance<T> {
     pub async fn types_with_hashes(
         &mut self,
     ) -> Result<crate::metadata::MetadataWithHashes, crate::metadata::MetadataError> {
+        crate::metadata::decode_metadata_with_hashes(&self.types_bytes().await?)
+    }
+
+    /// Call `__pack_types` and copy out the raw metadata it points at.
+    async fn types_bytes(&mut self) -> Result<Vec<u8>, crate::metadata::MetadataError> {
         let types_func = self
             .instance
             .get_typed_func::<(i32, i32), i32>(&mut self.store, "__pack_types")
             .map_err(|_| crate::metadata::MetadataError::NotFound)?;
+        self.limits
+            .refuel(&mut self.store)
+            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+
+        let guest_allocated = self.has_allocator();
+        let slots = if guest_allocated {
+            self.call_pack_alloc_async(RESULT_SLOTS_SIZE)
+                .await
+                .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?
+        } else {
+            RESULT_PTR_OFFSET
+        };
 
         let status = types_func
-            .call_async(
-                &mut self.store,
-                (RESULT_PTR_OFFSET as i32, RESULT_LEN_OFFSET as i32),
-            )
+            .call_async(&mut self.store, (slots as i32, (slots + 4) as i32))
             .await
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
 
-        if status != 0 {
-            return Err(crate::metadata::MetadataError::CallFailed(
-                "non-zero status from __pack_types".into(),
-            ));
-        }
-
         let memory = self
             .get_memory()
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
+        let (out_ptr, out_len) = read_result_slots(memory.data(&self.store), slots)
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+        if guest_allocated {
+            self.call_pack_free_async(slots, RESULT_SLOTS_SIZE)
+                .await
+                .ok();
+        }
 
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
+        if status != 0 {
+            return Err(crate::metadata::MetadataError::CallFailed(
+                "non-zero status from __pack_types".into(),
+            ));
+        }
 
         // Read metadata bytes (static data, no __pack_free needed)
         let mut metadata_bytes = vec![0u8; out_len];
         memory
             .read(&self.store, out_ptr, &mut metadata_bytes)
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+        Ok(metadata_bytes)
+    }
 
-        crate::metadata::decode_metadata_with_hashes(&metadata_bytes)
+    /// Interface hash of this package's `__pack_types` metadata, recorded in
+    /// snapshots.
+    async fn snapshot_types_hash(
+        &mut self,
+    ) -> Result<Option<crate::metadata::TypeHash>, RuntimeError> {
+        match self
+            .types_bytes()
+            .await
+            .and_then(|bytes| snapshot::types_hash(&bytes))
+        {
+            Ok(hash) => Ok(Some(hash)),
+            Err(crate::metadata::MetadataError::NotFound) => Ok(None),
+            Err(e) => Err(RuntimeError::SnapshotError(e.to_string())),
+        }
+    }
+
+    /// Capture this instance's memories, mutable globals and tables
+    /// so it can be resumed later with [`restore`](Self::restore) (async).
+    ///
+    /// Take snapshots between calls: the guest is not running, so its stack
+    /// pointer and allocator are at rest.
+    ///
+    /// Private state is only reachable if the module was loaded by a runtime
+    /// built with `with_snapshots`; otherwise only exported items are captured.
+    pub async fn snapshot(&mut self) -> Result<InstanceSnapshot, RuntimeError> {
+        // Capture before asking for the types hash: reading `__pack_types`
+        // calls into the guest, which may touch its heap.
+        let snapshot = snapshot::capture(&mut self.store, &self.instance, self.memory)?;
+        Ok(snapshot.with_types_hash(self.snapshot_types_hash().await?))
+    }
+
+    /// Overwrite this instance's state with `snapshot` (async).
+    ///
+    /// Refused if the snapshot was taken from a package whose `__pack_types`
+    /// interfaces differ from this one's.
+    pub async fn restore(&mut self, snapshot: &InstanceSnapshot) -> Result<(), RuntimeError> {
+        let types_hash = self.snapshot_types_hash().await?;
+        if snapshot.types_hash() != types_hash.as_ref() {
+            return Err(RuntimeError::SnapshotError(format!(
+                "snapshot was taken from an incompatible module: types hash {} does not match {}",
+                describe_hash(snapshot.types_hash()),
+                describe_hash(types_hash.as_ref()),
+            )));
+        }
+        snapshot::apply(
+            &mut self.store,
+            &self.instance,
+            self.memory,
+            snapshot,
+            &self.limits,
+        )
     }
 }
 
@@ -898,20 +1120,38 @@ pub type AsyncHostFnResult<R> = Pin<Box<dyn Future<Output = R> + Send + 'static>
 pub struct CompiledModule<'a> {
     module: Module,
     engine: &'a Engine,
+    limits: ResourceLimits,
 }
 
 impl CompiledModule<'_> {
     /// Instantiate the module with no imports
     pub fn instantiate(&self) -> Result<Instance<()>, RuntimeError> {
-        let mut store = Store::new(self.engine, ());
-        let mut linker = Linker::<()>::new(self.engine);
+        let mut store = self.limits.store(self.engine, ())?;
+        let mut linker = Linker::new(self.engine);
         register_default_alloc(&mut linker)?;
 
         let instance = linker
             .instantiate(&mut store, &self.module)
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+            .map_err(|e| self.limits.classify(e, &store))?;
+
+        Ok(Instance {
+            store,
+            instance,
+            interceptor: None,
+            limits: self.limits,
+        })
+    }
 
-        Ok(Instance { store, instance })
+    /// Instantiate the module and resume it from `snapshot`.
+    ///
+    /// Refused if the snapshot was taken from a package whose `__pack_types`
+    /// metadata differs from this module's. To resume an instance that needs
+    /// host functions, instantiate it as usual and call
+    /// [`Instance::restore`].
+    pub fn restore(&self, snapshot: &InstanceSnapshot) -> Result<Instance<()>, RuntimeError> {
+        let mut instance = self.instantiate()?;
+        instance.restore(snapshot)?;
+        Ok(instance)
     }
 
     /// Instantiate the module with host imports (backward compatible API)
@@ -923,7 +1163,7 @@ impl CompiledModule<'_> {
         imports: HostImports,
     ) -> Result<InstanceWithHost, RuntimeError> {
         let state = imports.state.clone();
-        let mut linker = Linker::<HostState>::new(self.engine);
+        let mut linker = Linker::new(self.engine);
         register_default_alloc(&mut linker)?;
 
         // Use the new provider-based registration
@@ -932,15 +1172,16 @@ impl CompiledModule<'_> {
             .register(&mut builder)
             .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
 
-        let mut store = Store::new(self.engine, state.clone());
+        let mut store = self.limits.store(self.engine, state.clone())?;
         let instance = linker
             .instantiate(&mut store, &self.module)
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+            .map_err(|e| self.limits.classify(e, &store))?;
 
         Ok(InstanceWithHost {
             store,
             instance,
             state,
+            limits: self.limits,
         })
     }
 
@@ -962,16 +1203,21 @@ impl CompiledModule<'_> {
     /// ```
     pub fn instantiate_with_linker<T: 'static>(
         &self,
-        linker: Linker<T>,
+        linker: Linker<StoreData<T>>,
         state: T,
     ) -> Result<Instance<T>, RuntimeError> {
-        let mut store = Store::new(self.engine, state);
+        let mut store = self.limits.store(self.engine, state)?;
 
         let instance = linker
             .instantiate(&mut store, &self.module)
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+            .map_err(|e| self.limits.classify(e, &store))?;
 
-        Ok(Instance { store, instance })
+        Ok(Instance {
+            store,
+            instance,
+            interceptor: None,
+            limits: self.limits,
+        })
     }
 
     /// Instantiate the module with a builder function for configuring host functions.
@@ -1013,15 +1259,18 @@ impl CompiledModule<'_> {
 
 /// A running WASM instance
 pub struct Instance<T> {
-    store: Store<T>,
+    store: Store<StoreData<T>>,
     instance: WasmtimeInstance,
+    interceptor: Option<Arc<dyn CallInterceptor>>,
+    limits: ResourceLimits,
 }
 
 /// Instance with host imports - provides access to host state
 pub struct InstanceWithHost {
-    store: Store<HostState>,
+    store: Store<StoreData<HostState>>,
     instance: WasmtimeInstance,
     state: HostState,
+    limits: ResourceLimits,
 }
 
 impl InstanceWithHost {
@@ -1078,6 +1327,13 @@ impl InstanceWithHost {
         Ok(memory.data_size(&self.store))
     }
 
+    /// Fuel spent by the most recent call, when the runtime meters fuel
+    /// (see [`ResourceLimits::fuel_per_call`]). Read it after a call to bill
+    /// the guest for the work it did.
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        self.limits.fuel_consumed(&self.store)
+    }
+
     /// Call an exported function that takes two i32s and returns an i32
     pub fn call_i32_i32_to_i32(&mut self, name: &str, a: i32, b: i32) -> Result<i32, RuntimeError> {
         let func = self
@@ -1085,8 +1341,9 @@ impl InstanceWithHost {
             .get_typed_func::<(i32, i32), i32>(&mut self.store, name)
             .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
 
+        self.limits.refuel(&mut self.store)?;
         func.call(&mut self.store, (a, b))
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))
+            .map_err(|e| self.limits.classify(e, &self.store))
     }
 
     /// Call an exported function that takes two i64s and returns an i64
@@ -1096,8 +1353,9 @@ impl InstanceWithHost {
             .get_typed_func::<(i64, i64), i64>(&mut self.store, name)
             .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
 
+        self.limits.refuel(&mut self.store)?;
         func.call(&mut self.store, (a, b))
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))
+            .map_err(|e| self.limits.classify(e, &self.store))
     }
 
     /// Call an exported function that takes two i32s and returns nothing
@@ -1107,100 +1365,53 @@ impl InstanceWithHost {
             .get_typed_func::<(i32, i32), ()>(&mut self.store, name)
             .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
 
+        self.limits.refuel(&mut self.store)?;
         func.call(&mut self.store, (a, b))
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))
+            .map_err(|e| self.limits.classify(e, &self.store))
     }
 
     /// Encode a Value and write it to memory at the given offset.
     pub fn write_value(&mut self, offset: usize, value: &Value) -> Result<usize, RuntimeError> {
-        let bytes = encode(value).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
-        self.write_memory(offset, &bytes)?;
-        Ok(bytes.len())
+        let len = encoded_len(value).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
+        let memory = self.get_memory()?;
+        encode_to_memory(memory.data_mut(&mut self.store), offset, len, value)?;
+        Ok(len)
     }
 
     /// Read bytes from memory and decode them as a Value.
     pub fn read_value(&mut self, offset: usize, len: usize) -> Result<Value, RuntimeError> {
-        let bytes = self.read_memory(offset, len)?;
-        decode(&bytes).map_err(|e| RuntimeError::AbiError(e.to_string()))
+        let memory = self.get_memory()?;
+        let bytes = memory_slice(memory.data(&self.store), offset, len)?;
+        decode(bytes).map_err(|e| RuntimeError::AbiError(e.to_string()))
+    }
+
+    /// Borrow a CGRF buffer in memory as a [`GraphView`], without copying it.
+    ///
+    /// Nodes are decoded only as they are visited, so reading one field of a
+    /// large message costs nothing for the rest of it.
+    pub fn view_value(&mut self, offset: usize, len: usize) -> Result<GraphView<'_>, RuntimeError> {
+        let memory = self.get_memory()?;
+        let bytes = memory_slice(memory.data(&self.store), offset, len)?;
+        GraphView::from_bytes(bytes).map_err(view_error)
     }
 
     /// Call a function using the Pack ABI.
     ///
-    /// If the guest exports `__pack_alloc`, input is dynamically allocated.
-    /// Otherwise, falls back to a fixed input buffer at INPUT_BUFFER_OFFSET.
+    /// If the guest exports `__pack_alloc`, the input buffer and the result
+    /// slots are allocated by the guest. Otherwise they use the host scratch
+    /// region at `INPUT_BUFFER_OFFSET` / `RESULT_PTR_OFFSET`.
     ///
     /// The WASM function signature is `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`:
     /// - Returns: 0 on success, -1 on error (error message in ptr/len)
     pub fn call_with_value(&mut self, name: &str, input: &Value) -> Result<Value, RuntimeError> {
-        // Encode input
-        let input_bytes = encode(input).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
-
-        // Try to allocate input buffer dynamically, fall back to fixed buffer
-        let (in_ptr, dynamic_input) = match self.call_pack_alloc(input_bytes.len()) {
-            Ok(ptr) => (ptr, true),
-            Err(_) => (INPUT_BUFFER_OFFSET, false),
-        };
-
-        // Write input to buffer
-        let memory = self.get_memory()?;
-        memory
-            .write(&mut self.store, in_ptr, &input_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to write input".into()))?;
-
-        // Call the function
-        let func = self
-            .instance
-            .get_typed_func::<(i32, i32, i32, i32), i32>(&mut self.store, name)
-            .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
-
-        let status = func
-            .call(
-                &mut self.store,
-                (
-                    in_ptr as i32,
-                    input_bytes.len() as i32,
-                    RESULT_PTR_OFFSET as i32,
-                    RESULT_LEN_OFFSET as i32,
-                ),
-            )
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
-
-        // Free the input buffer if dynamically allocated
-        if dynamic_input {
-            self.call_pack_free(in_ptr, input_bytes.len()).ok();
-        }
-
-        // Read result ptr/len from slots
-        let memory = self.get_memory()?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to read result ptr".into()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to read result len".into()))?;
-
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
-
-        // Check for error
-        if status != 0 {
-            // Read error message
-            let mut err_bytes = vec![0u8; out_len];
-            memory
-                .read(&self.store, out_ptr, &mut err_bytes)
-                .map_err(|_| RuntimeError::MemoryError("Failed to read error".into()))?;
+        // Size the input up front so it can be encoded straight into guest memory
+        let input_len = encoded_len(input).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
 
-            // Free the error buffer
-            self.call_pack_free(out_ptr, out_len).ok();
-
-            let err_msg = String::from_utf8_lossy(&err_bytes).to_string();
-            return Err(RuntimeError::WasmError(format!(
-                "function '{}' returned error: {}",
-                name, err_msg
-            )));
-        }
+        // One fuel budget covers the whole call, allocation and free included
+        self.limits.refuel(&mut self.store)?;
+        let (out_ptr, out_len) = now(call_pack_abi(self, name, input_len, |data, in_ptr| {
+            encode_to_memory(data, in_ptr, input_len, input)
+        }))?;
 
         // Read output value
         let result = self.read_value(out_ptr, out_len)?;
@@ -1211,6 +1422,14 @@ impl InstanceWithHost {
         Ok(result)
     }
 
+    /// Whether the guest exports `__pack_alloc`, and so allocates its own
+    /// call buffers.
+    fn has_allocator(&mut self) -> bool {
+        self.instance
+            .get_func(&mut self.store, "__pack_alloc")
+            .is_some()
+    }
+
     /// Call __pack_alloc to allocate a buffer in guest memory.
     fn call_pack_alloc(&mut self, size: usize) -> Result<usize, RuntimeError> {
         let alloc_func = self
@@ -1220,7 +1439,7 @@ impl InstanceWithHost {
 
         let ptr = alloc_func
             .call(&mut self.store, size as i32)
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+            .map_err(|e| self.limits.classify(e, &self.store))?;
 
         if ptr == 0 {
             return Err(RuntimeError::MemoryError("Guest allocation failed".into()));
@@ -1237,7 +1456,7 @@ impl InstanceWithHost {
         {
             free_func
                 .call(&mut self.store, (ptr as i32, len as i32))
-                .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+                .map_err(|e| self.limits.classify(e, &self.store))?;
         }
         Ok(())
     }
@@ -1254,34 +1473,36 @@ impl InstanceWithHost {
             .instance
             .get_typed_func::<(i32, i32), i32>(&mut self.store, "__pack_types")
             .map_err(|_| crate::metadata::MetadataError::NotFound)?;
+        self.limits
+            .refuel(&mut self.store)
+            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+
+        let guest_allocated = self.has_allocator();
+        let slots = if guest_allocated {
+            self.call_pack_alloc(RESULT_SLOTS_SIZE)
+                .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?
+        } else {
+            RESULT_PTR_OFFSET
+        };
 
         let status = types_func
-            .call(
-                &mut self.store,
-                (RESULT_PTR_OFFSET as i32, RESULT_LEN_OFFSET as i32),
-            )
+            .call(&mut self.store, (slots as i32, (slots + 4) as i32))
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
 
-        if status != 0 {
-            return Err(crate::metadata::MetadataError::CallFailed(
-                "non-zero status from __pack_types".into(),
-            ));
-        }
-
         let memory = self
             .get_memory()
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
+        let (out_ptr, out_len) = read_result_slots(memory.data(&self.store), slots)
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+        if guest_allocated {
+            self.call_pack_free(slots, RESULT_SLOTS_SIZE).ok();
+        }
 
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
+        if status != 0 {
+            return Err(crate::metadata::MetadataError::CallFailed(
+                "non-zero status from __pack_types".into(),
+            ));
+        }
 
         // Read metadata bytes (static data, no __pack_free needed)
         let mut metadata_bytes = vec![0u8; out_len];
@@ -1302,34 +1523,36 @@ impl InstanceWithHost {
             .instance
             .get_typed_func::<(i32, i32), i32>(&mut self.store, "__pack_types")
             .map_err(|_| crate::metadata::MetadataError::NotFound)?;
+        self.limits
+            .refuel(&mut self.store)
+            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+
+        let guest_allocated = self.has_allocator();
+        let slots = if guest_allocated {
+            self.call_pack_alloc(RESULT_SLOTS_SIZE)
+                .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?
+        } else {
+            RESULT_PTR_OFFSET
+        };
 
         let status = types_func
-            .call(
-                &mut self.store,
-                (RESULT_PTR_OFFSET as i32, RESULT_LEN_OFFSET as i32),
-            )
+            .call(&mut self.store, (slots as i32, (slots + 4) as i32))
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
 
-        if status != 0 {
-            return Err(crate::metadata::MetadataError::CallFailed(
-                "non-zero status from __pack_types".into(),
-            ));
-        }
-
         let memory = self
             .get_memory()
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
+        let (out_ptr, out_len) = read_result_slots(memory.data(&self.store), slots)
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+        if guest_allocated {
+            self.call_pack_free(slots, RESULT_SLOTS_SIZE).ok();
+        }
 
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
+        if status != 0 {
+            return Err(crate::metadata::MetadataError::CallFailed(
+                "non-zero status from __pack_types".into(),
+            ));
+        }
 
         // Read metadata bytes (static data, no __pack_free needed)
         let mut metadata_bytes = vec![0u8; out_len];
@@ -1381,6 +1604,13 @@ impl<T> Instance<T> {
         Ok(memory.data_size(&self.store))
     }
 
+    /// Fuel spent by the most recent call, when the runtime meters fuel
+    /// (see [`ResourceLimits::fuel_per_call`]). Read it after a call to bill
+    /// the guest for the work it did.
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        self.limits.fuel_consumed(&self.store)
+    }
+
     /// Call an exported function that takes two i32s and returns an i32
     pub fn call_i32_i32_to_i32(&mut self, name: &str, a: i32, b: i32) -> Result<i32, RuntimeError> {
         let func = self
@@ -1388,8 +1618,9 @@ impl<T> Instance<T> {
             .get_typed_func::<(i32, i32), i32>(&mut self.store, name)
             .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
 
+        self.limits.refuel(&mut self.store)?;
         func.call(&mut self.store, (a, b))
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))
+            .map_err(|e| self.limits.classify(e, &self.store))
     }
 
     /// Call an exported function that takes two i64s and returns an i64
@@ -1399,8 +1630,9 @@ impl<T> Instance<T> {
             .get_typed_func::<(i64, i64), i64>(&mut self.store, name)
             .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
 
+        self.limits.refuel(&mut self.store)?;
         func.call(&mut self.store, (a, b))
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))
+            .map_err(|e| self.limits.classify(e, &self.store))
     }
 
     /// Call an exported function that takes two i32s and returns nothing
@@ -1410,8 +1642,9 @@ impl<T> Instance<T> {
             .get_typed_func::<(i32, i32), ()>(&mut self.store, name)
             .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
 
+        self.limits.refuel(&mut self.store)?;
         func.call(&mut self.store, (a, b))
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))
+            .map_err(|e| self.limits.classify(e, &self.store))
     }
 
     // ========================================================================
@@ -1421,94 +1654,68 @@ impl<T> Instance<T> {
     /// Encode a Value and write it to memory at the given offset.
     /// Returns the number of bytes written.
     pub fn write_value(&mut self, offset: usize, value: &Value) -> Result<usize, RuntimeError> {
-        let bytes = encode(value).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
-        self.write_memory(offset, &bytes)?;
-        Ok(bytes.len())
+        let len = encoded_len(value).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
+        let memory = self.get_memory()?;
+        encode_to_memory(memory.data_mut(&mut self.store), offset, len, value)?;
+        Ok(len)
     }
 
     /// Read bytes from memory and decode them as a Value.
     pub fn read_value(&mut self, offset: usize, len: usize) -> Result<Value, RuntimeError> {
-        let bytes = self.read_memory(offset, len)?;
-        decode(&bytes).map_err(|e| RuntimeError::AbiError(e.to_string()))
+        let memory = self.get_memory()?;
+        let bytes = memory_slice(memory.data(&self.store), offset, len)?;
+        decode(bytes).map_err(|e| RuntimeError::AbiError(e.to_string()))
+    }
+
+    /// Borrow a CGRF buffer in memory as a [`GraphView`], without copying it.
+    ///
+    /// Nodes are decoded only as they are visited, so reading one field of a
+    /// large message costs nothing for the rest of it.
+    pub fn view_value(&mut self, offset: usize, len: usize) -> Result<GraphView<'_>, RuntimeError> {
+        let memory = self.get_memory()?;
+        let bytes = memory_slice(memory.data(&self.store), offset, len)?;
+        GraphView::from_bytes(bytes).map_err(view_error)
+    }
+
+    /// Set a call interceptor for recording/replaying export function calls.
+    /// To install several, combine them with an [`InterceptorChain`].
+    pub fn set_interceptor(&mut self, interceptor: Arc<dyn CallInterceptor>) {
+        self.interceptor = Some(interceptor);
+    }
+
+    /// Get the current interceptor, if any.
+    pub fn interceptor(&self) -> Option<&Arc<dyn CallInterceptor>> {
+        self.interceptor.as_ref()
     }
 
     /// Call a function using the Pack ABI.
     ///
-    /// If the guest exports `__pack_alloc`, input is dynamically allocated.
-    /// Otherwise, falls back to a fixed input buffer at INPUT_BUFFER_OFFSET.
+    /// If the guest exports `__pack_alloc`, the input buffer and the result
+    /// slots are allocated by the guest. Otherwise they use the host scratch
+    /// region at `INPUT_BUFFER_OFFSET` / `RESULT_PTR_OFFSET`.
     ///
     /// The WASM function signature is `(in_ptr, in_len, out_ptr_ptr, out_len_ptr) -> status`:
     /// - Returns: 0 on success, -1 on error (error message in ptr/len)
+    ///
+    /// A call interceptor set with [`Instance::set_interceptor`] is run
+    /// blocking, which needs a tokio multi-thread runtime, as the sync
+    /// `func_typed` bridges do.
     pub fn call_with_value(&mut self, name: &str, input: &Value) -> Result<Value, RuntimeError> {
-        // Encode input
-        let input_bytes = encode(input).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
-
-        // Try to allocate input buffer dynamically, fall back to fixed buffer
-        let (in_ptr, dynamic_input) = match self.call_pack_alloc(input_bytes.len()) {
-            Ok(ptr) => (ptr, true),
-            Err(_) => (INPUT_BUFFER_OFFSET, false),
-        };
-
-        // Write input to buffer
-        let memory = self.get_memory()?;
-        memory
-            .write(&mut self.store, in_ptr, &input_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to write input".into()))?;
-
-        // Call the function
-        let func = self
-            .instance
-            .get_typed_func::<(i32, i32, i32, i32), i32>(&mut self.store, name)
-            .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
-
-        let status = func
-            .call(
-                &mut self.store,
-                (
-                    in_ptr as i32,
-                    input_bytes.len() as i32,
-                    RESULT_PTR_OFFSET as i32,
-                    RESULT_LEN_OFFSET as i32,
-                ),
-            )
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
-
-        // Free the input buffer if dynamically allocated
-        if dynamic_input {
-            self.call_pack_free(in_ptr, input_bytes.len()).ok();
+        // Check interceptor for short-circuit (replay)
+        if let Some(ref interceptor) = self.interceptor {
+            if let Some(recorded_output) =
+                block_on_interceptor(interceptor.before_export(name, input))
+            {
+                block_on_interceptor(interceptor.after_export(name, input, &recorded_output));
+                return Ok(recorded_output);
+            }
         }
 
-        // Read result ptr/len from slots
-        let memory = self.get_memory()?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to read result ptr".into()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
-            .map_err(|_| RuntimeError::MemoryError("Failed to read result len".into()))?;
-
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
-
-        // Check for error
-        if status != 0 {
-            // Read error message
-            let mut err_bytes = vec![0u8; out_len];
-            memory
-                .read(&self.store, out_ptr, &mut err_bytes)
-                .map_err(|_| RuntimeError::MemoryError("Failed to read error".into()))?;
-
-            // Free the error buffer
-            self.call_pack_free(out_ptr, out_len).ok();
-
-            let err_msg = String::from_utf8_lossy(&err_bytes).to_string();
-            return Err(RuntimeError::WasmError(format!(
-                "function '{}' returned error: {}",
-                name, err_msg
-            )));
-        }
+        // Size the input up front so it can be encoded straight into guest memory
+        let input_len = encoded_len(input).map_err(|e| RuntimeError::AbiError(e.to_string()))?;
+        let (out_ptr, out_len) = self.call_pack_abi(name, input_len, |data, in_ptr| {
+            encode_to_memory(data, in_ptr, input_len, input)
+        })?;
 
         // Read and decode output
         let result = self.read_value(out_ptr, out_len)?;
@@ -1516,9 +1723,69 @@ impl<T> Instance<T> {
         // Free the guest's output buffer if guest has __pack_free
         self.call_pack_free(out_ptr, out_len).ok();
 
+        // Notify interceptor of completed export call
+        if let Some(ref interceptor) = self.interceptor {
+            block_on_interceptor(interceptor.after_export(name, input, &result));
+        }
+
         Ok(result)
     }
 
+    /// [`Instance::call_with_value`] with the input already CGRF-encoded,
+    /// returning the output's bytes undecoded — for forwarding a call between
+    /// guests without a decode/encode round trip.
+    pub fn call_with_bytes(&mut self, name: &str, input: &[u8]) -> Result<Vec<u8>, RuntimeError> {
+        let (out_ptr, out_len) = self.call_pack_abi(name, input.len(), |data, in_ptr| {
+            copy_to_memory(data, in_ptr, input)
+        })?;
+        let output = self.read_memory(out_ptr, out_len)?;
+        self.call_pack_free(out_ptr, out_len).ok();
+        Ok(output)
+    }
+
+    /// [`Instance::call_with_bytes`] returning the export's status along with
+    /// its output — on error, the message — rather than turning a non-zero
+    /// status into an error: what a routed link hands its consumer.
+    pub(crate) fn call_with_bytes_status(
+        &mut self,
+        name: &str,
+        input: &[u8],
+    ) -> Result<(i32, Vec<u8>), RuntimeError> {
+        self.limits.refuel(&mut self.store)?;
+        let (status, out_ptr, out_len) = now(call_pack_abi_status(
+            self,
+            name,
+            input.len(),
+            |data, in_ptr| copy_to_memory(data, in_ptr, input),
+        ))?;
+        let output = self.read_memory(out_ptr, out_len)?;
+        self.call_pack_free(out_ptr, out_len).ok();
+        Ok((status, output))
+    }
+
+    /// One Pack ABI call of `name`: negotiate its buffers, let `write_input`
+    /// fill the `input_len`-byte input at the offset it is given, call, and
+    /// check the status. Returns the output's `(ptr, len)`, which the caller
+    /// reads and frees.
+    fn call_pack_abi(
+        &mut self,
+        name: &str,
+        input_len: usize,
+        write_input: impl FnOnce(&mut [u8], usize) -> Result<(), RuntimeError>,
+    ) -> Result<(usize, usize), RuntimeError> {
+        // One fuel budget covers the whole call, allocation and free included
+        self.limits.refuel(&mut self.store)?;
+        now(call_pack_abi(self, name, input_len, write_input))
+    }
+
+    /// Whether the guest exports `__pack_alloc`, and so allocates its own
+    /// call buffers.
+    fn has_allocator(&mut self) -> bool {
+        self.instance
+            .get_func(&mut self.store, "__pack_alloc")
+            .is_some()
+    }
+
     /// Call __pack_alloc to allocate a buffer in guest memory.
     fn call_pack_alloc(&mut self, size: usize) -> Result<usize, RuntimeError> {
         let alloc_func = self
@@ -1528,7 +1795,7 @@ impl<T> Instance<T> {
 
         let ptr = alloc_func
             .call(&mut self.store, size as i32)
-            .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+            .map_err(|e| self.limits.classify(e, &self.store))?;
 
         if ptr == 0 {
             return Err(RuntimeError::MemoryError("Guest allocation failed".into()));
@@ -1545,7 +1812,7 @@ impl<T> Instance<T> {
         {
             free_func
                 .call(&mut self.store, (ptr as i32, len as i32))
-                .map_err(|e| RuntimeError::WasmError(e.to_string()))?;
+                .map_err(|e| self.limits.classify(e, &self.store))?;
         }
         Ok(())
     }
@@ -1558,98 +1825,221 @@ impl<T> Instance<T> {
     pub fn types(
         &mut self,
     ) -> Result<crate::metadata::PackageMetadata, crate::metadata::MetadataError> {
+        crate::metadata::decode_metadata(&self.types_bytes()?)
+    }
+
+    /// Read embedded type metadata with interface hashes from the package.
+    ///
+    /// Like `types()`, but also decodes interface hashes for compatibility checking.
+    pub fn types_with_hashes(
+        &mut self,
+    ) -> Result<crate::metadata::MetadataWithHashes, crate::metadata::MetadataError> {
+        crate::metadata::decode_metadata_with_hashes(&self.types_bytes()?)
+    }
+
+    /// Call `__pack_types` and copy out the raw metadata it points at.
+    fn types_bytes(&mut self) -> Result<Vec<u8>, crate::metadata::MetadataError> {
         let types_func = self
             .instance
             .get_typed_func::<(i32, i32), i32>(&mut self.store, "__pack_types")
             .map_err(|_| crate::metadata::MetadataError::NotFound)?;
+        self.limits
+            .refuel(&mut self.store)
+            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+
+        let guest_allocated = self.has_allocator();
+        let slots = if guest_allocated {
+            self.call_pack_alloc(RESULT_SLOTS_SIZE)
+                .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?
+        } else {
+            RESULT_PTR_OFFSET
+        };
 
         let status = types_func
-            .call(
-                &mut self.store,
-                (RESULT_PTR_OFFSET as i32, RESULT_LEN_OFFSET as i32),
-            )
+            .call(&mut self.store, (slots as i32, (slots + 4) as i32))
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
 
-        if status != 0 {
-            return Err(crate::metadata::MetadataError::CallFailed(
-                "non-zero status from __pack_types".into(),
-            ));
-        }
-
         let memory = self
             .get_memory()
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
+        let (out_ptr, out_len) = read_result_slots(memory.data(&self.store), slots)
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+        if guest_allocated {
+            self.call_pack_free(slots, RESULT_SLOTS_SIZE).ok();
+        }
 
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
+        if status != 0 {
+            return Err(crate::metadata::MetadataError::CallFailed(
+                "non-zero status from __pack_types".into(),
+            ));
+        }
 
         // Read metadata bytes (static data, no __pack_free needed)
         let mut metadata_bytes = vec![0u8; out_len];
         memory
             .read(&self.store, out_ptr, &mut metadata_bytes)
             .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+        Ok(metadata_bytes)
+    }
 
-        crate::metadata::decode_metadata(&metadata_bytes)
+    /// Interface hash of this package's `__pack_types` metadata, recorded in
+    /// snapshots.
+    fn snapshot_types_hash(&mut self) -> Result<Option<crate::metadata::TypeHash>, RuntimeError> {
+        match self
+            .types_bytes()
+            .and_then(|bytes| snapshot::types_hash(&bytes))
+        {
+            Ok(hash) => Ok(Some(hash)),
+            Err(crate::metadata::MetadataError::NotFound) => Ok(None),
+            Err(e) => Err(RuntimeError::SnapshotError(e.to_string())),
+        }
     }
 
-    /// Read embedded type metadata with interface hashes from the package.
+    /// Capture this instance's memories, mutable globals and tables
+    /// so it can be resumed later with [`restore`](Self::restore).
     ///
-    /// Like `types()`, but also decodes interface hashes for compatibility checking.
-    pub fn types_with_hashes(
-        &mut self,
-    ) -> Result<crate::metadata::MetadataWithHashes, crate::metadata::MetadataError> {
-        let types_func = self
-            .instance
-            .get_typed_func::<(i32, i32), i32>(&mut self.store, "__pack_types")
-            .map_err(|_| crate::metadata::MetadataError::NotFound)?;
+    /// Take snapshots between calls: the guest is not running, so its stack
+    /// pointer and allocator are at rest.
+    ///
+    /// Private state is only reachable if the module was loaded by a runtime
+    /// built with `with_snapshots`; otherwise only exported items are captured.
+    pub fn snapshot(&mut self) -> Result<InstanceSnapshot, RuntimeError> {
+        // Capture before asking for the types hash: reading `__pack_types`
+        // calls into the guest, which may touch its heap.
+        let snapshot = snapshot::capture(&mut self.store, &self.instance, None)?;
+        Ok(snapshot.with_types_hash(self.snapshot_types_hash()?))
+    }
 
-        let status = types_func
-            .call(
-                &mut self.store,
-                (RESULT_PTR_OFFSET as i32, RESULT_LEN_OFFSET as i32),
-            )
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+    /// Overwrite this instance's state with `snapshot`.
+    ///
+    /// Refused if the snapshot was taken from a package whose `__pack_types`
+    /// interfaces differ from this one's.
+    pub fn restore(&mut self, snapshot: &InstanceSnapshot) -> Result<(), RuntimeError> {
+        let types_hash = self.snapshot_types_hash()?;
+        if snapshot.types_hash() != types_hash.as_ref() {
+            return Err(RuntimeError::SnapshotError(format!(
+                "snapshot was taken from an incompatible module: types hash {} does not match {}",
+                describe_hash(snapshot.types_hash()),
+                describe_hash(types_hash.as_ref()),
+            )));
+        }
+        snapshot::apply(
+            &mut self.store,
+            &self.instance,
+            None,
+            snapshot,
+            &self.limits,
+        )
+    }
+}
 
-        if status != 0 {
-            return Err(crate::metadata::MetadataError::CallFailed(
-                "non-zero status from __pack_types".into(),
-            ));
+// ============================================================================
+// Pack ABI calls
+// ============================================================================
+
+/// The wasmtime side of [`PackAbiCalls`] for the sync instance types, whose
+/// calls complete without suspending.
+macro_rules! sync_pack_abi_calls {
+    ($($ty:ty $(, $param:ident)?);* $(;)?) => {$(
+        impl$(<$param>)? PackAbiCalls for $ty {
+            type Error = RuntimeError;
+
+            fn has_export(&mut self, name: &str) -> bool {
+                self.instance.get_func(&mut self.store, name).is_some()
+            }
+
+            fn memory(&mut self) -> Result<&mut [u8], RuntimeError> {
+                let memory = self.get_memory()?;
+                Ok(memory.data_mut(&mut self.store))
+            }
+
+            fn read(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, RuntimeError> {
+                self.read_memory(offset, len)
+            }
+
+            async fn call_export(
+                &mut self,
+                name: &str,
+                args: (i32, i32, i32, i32),
+            ) -> Result<i32, RuntimeError> {
+                let func = self
+                    .instance
+                    .get_typed_func::<(i32, i32, i32, i32), i32>(&mut self.store, name)
+                    .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
+                func.call(&mut self.store, args)
+                    .map_err(|e| self.limits.classify(e, &self.store))
+            }
+
+            async fn alloc(&mut self, size: usize) -> Result<usize, RuntimeError> {
+                self.call_pack_alloc(size)
+            }
+
+            async fn free(&mut self, ptr: usize, len: usize) -> Result<(), RuntimeError> {
+                self.call_pack_free(ptr, len)
+            }
+
+            fn returned_error(name: &str, message: &str) -> RuntimeError {
+                pack_call_error(name, message)
+            }
         }
+    )*};
+}
 
-        let memory = self
-            .get_memory()
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        let mut ptr_bytes = [0u8; 4];
-        let mut len_bytes = [0u8; 4];
-        memory
-            .read(&self.store, RESULT_PTR_OFFSET, &mut ptr_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
-        memory
-            .read(&self.store, RESULT_LEN_OFFSET, &mut len_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+sync_pack_abi_calls! {
+    Instance<T>, T;
+    InstanceWithHost;
+}
 
-        let out_ptr = i32::from_le_bytes(ptr_bytes) as usize;
-        let out_len = i32::from_le_bytes(len_bytes) as usize;
+impl<T: Send> PackAbiCalls for AsyncInstance<T> {
+    type Error = RuntimeError;
 
-        // Read metadata bytes (static data, no __pack_free needed)
-        let mut metadata_bytes = vec![0u8; out_len];
-        memory
-            .read(&self.store, out_ptr, &mut metadata_bytes)
-            .map_err(|e| crate::metadata::MetadataError::CallFailed(e.to_string()))?;
+    fn has_export(&mut self, name: &str) -> bool {
+        self.instance.get_func(&mut self.store, name).is_some()
+    }
 
-        crate::metadata::decode_metadata_with_hashes(&metadata_bytes)
+    fn memory(&mut self) -> Result<&mut [u8], RuntimeError> {
+        let memory = self.get_memory()?;
+        Ok(memory.data_mut(&mut self.store))
+    }
+
+    fn read(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, RuntimeError> {
+        self.read_memory(offset, len)
+    }
+
+    async fn call_export(
+        &mut self,
+        name: &str,
+        args: (i32, i32, i32, i32),
+    ) -> Result<i32, RuntimeError> {
+        let func = self
+            .instance
+            .get_typed_func::<(i32, i32, i32, i32), i32>(&mut self.store, name)
+            .map_err(|e| RuntimeError::FunctionNotFound(e.to_string()))?;
+        func.call_async(&mut self.store, args)
+            .await
+            .map_err(|e| self.limits.classify(e, &self.store))
+    }
+
+    async fn alloc(&mut self, size: usize) -> Result<usize, RuntimeError> {
+        self.call_pack_alloc_async(size).await
+    }
+
+    async fn free(&mut self, ptr: usize, len: usize) -> Result<(), RuntimeError> {
+        self.call_pack_free_async(ptr, len).await
+    }
+
+    fn returned_error(name: &str, message: &str) -> RuntimeError {
+        pack_call_error(name, message)
     }
 }
 
+/// The error for a Pack ABI export that returned a non-zero status.
+fn pack_call_error(name: &str, message: &str) -> RuntimeError {
+    RuntimeError::WasmError(format!("function '{name}' returned error: {message}"))
+}
+
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
     use crate::abi::Value;
@@ -1767,6 +2157,39 @@ mod tests {
         assert_eq!(answer, 42);
     }
 
+    /// `view_value` reads a message straight out of linear memory: the string it
+    /// hands back points into the guest's memory, and a range past the end of
+    /// memory is an error rather than a panic.
+    #[test]
+    fn view_value_borrows_guest_memory() {
+        use crate::abi::ValueRef;
+
+        let wasm = wat::parse_str(r#"(module (memory (export "memory") 1))"#).expect("wat");
+        let runtime = Runtime::new();
+        let module = runtime.load_module(&wasm).expect("load_module");
+        let mut instance = module.instantiate().expect("instantiate");
+
+        let value = Value::Record {
+            type_name: "msg".into(),
+            fields: vec![
+                ("id".into(), Value::U64(7)),
+                ("body".into(), Value::String("hello".into())),
+            ],
+        };
+        let len = instance.write_value(1024, &value).expect("write_value");
+        assert_eq!(instance.read_value(1024, len).expect("read_value"), value);
+
+        let view = instance.view_value(1024, len).expect("view_value");
+        let ValueRef::Record(record) = view.root().expect("root") else {
+            panic!("expected a record");
+        };
+        let body = record.field("body").expect("field").expect("present");
+        assert_eq!(body.as_str(), Some("hello"));
+        drop(view);
+
+        assert!(instance.view_value(65536 - 4, 16).is_err());
+    }
+
     /// The runaway-guest kill switch: a guest stuck in an infinite loop must
     /// TRAP once its epoch deadline passes, so the call returns `Err` instead of
     /// pegging a core forever (the mail-spine decode-loop failure class). The
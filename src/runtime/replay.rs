@@ -0,0 +1,569 @@
+//! Deterministic record and replay of calls.
+//!
+//! [`RecordingInterceptor`] appends every import and export call it sees to a
+//! call log as a [`CallEvent`]: interface, function, input and output.
+//! [`ReplayInterceptor`] reads the log back, answers each import with its
+//! recorded output, and checks each export against its recording. The first
+//! call that does not line up is kept as a [`Divergence`], with a
+//! field-by-field diff of what was expected against what happened.
+//!
+//! Export events are produced by [`AsyncInstance::call_with_value_async`]
+//! and [`Instance::call_with_value`]. The sync instance blocks on the
+//! interceptor, so it needs a tokio multi-thread runtime, as the sync
+//! `func_typed` bridges do for imports.
+//!
+//! # Log format
+//!
+//! A log is the magic `PKRL` and a little-endian `u16` version, followed by
+//! one frame per event: a `u32` length and the event encoded with
+//! [`crate::abi::encode`] as a `call-event` record. Frames are only ever
+//! appended, so a log cut short by a crash still reads back up to its last
+//! complete event.
+//!
+//! [`AsyncInstance::call_with_value_async`]: super::AsyncInstance::call_with_value_async
+//! [`Instance::call_with_value`]: super::Instance::call_with_value
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use super::{CallInterceptor, RuntimeError};
+use crate::abi::{decode, encode, Value};
+
+const MAGIC: &[u8; 4] = b"PKRL";
+const VERSION: u16 = 1;
+
+const EVENT_TYPE: &str = "call-event";
+const KIND_TYPE: &str = "call-kind";
+
+/// Which side of the boundary a call crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    /// A host function called by the guest.
+    Import,
+    /// A guest export called by the host.
+    Export,
+}
+
+impl CallKind {
+    fn name(self) -> &'static str {
+        match self {
+            CallKind::Import => "import",
+            CallKind::Export => "export",
+        }
+    }
+}
+
+/// One recorded call. `interface` is empty for exports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEvent {
+    pub kind: CallKind,
+    pub interface: String,
+    pub function: String,
+    pub input: Value,
+    pub output: Value,
+}
+
+impl CallEvent {
+    fn to_value(&self) -> Value {
+        Value::Record {
+            type_name: EVENT_TYPE.into(),
+            fields: vec![
+                (
+                    "kind".into(),
+                    Value::Variant {
+                        type_name: KIND_TYPE.into(),
+                        case_name: self.kind.name().into(),
+                        tag: match self.kind {
+                            CallKind::Import => 0,
+                            CallKind::Export => 1,
+                        },
+                        payload: vec![],
+                    },
+                ),
+                ("interface".into(), Value::String(self.interface.clone())),
+                ("function".into(), Value::String(self.function.clone())),
+                ("input".into(), self.input.clone()),
+                ("output".into(), self.output.clone()),
+            ],
+        }
+    }
+
+    fn from_value(value: Value) -> Result<Self, String> {
+        let Value::Record { type_name, fields } = value else {
+            return Err("event is not a record".into());
+        };
+        if type_name != EVENT_TYPE {
+            return Err(format!("expected a {EVENT_TYPE} record, got {type_name}"));
+        }
+        let mut kind = None;
+        let mut interface = None;
+        let mut function = None;
+        let mut input = None;
+        let mut output = None;
+        for (name, value) in fields {
+            match (name.as_str(), value) {
+                ("kind", Value::Variant { case_name, .. }) => {
+                    kind = Some(match case_name.as_str() {
+                        "import" => CallKind::Import,
+                        "export" => CallKind::Export,
+                        other => return Err(format!("unknown call kind {other}")),
+                    })
+                }
+                ("interface", Value::String(s)) => interface = Some(s),
+                ("function", Value::String(s)) => function = Some(s),
+                ("input", v) => input = Some(v),
+                ("output", v) => output = Some(v),
+                (other, _) => return Err(format!("unexpected field {other}")),
+            }
+        }
+        let missing = |field: &str| format!("missing field {field}");
+        Ok(Self {
+            kind: kind.ok_or_else(|| missing("kind"))?,
+            interface: interface.ok_or_else(|| missing("interface"))?,
+            function: function.ok_or_else(|| missing("function"))?,
+            input: input.ok_or_else(|| missing("input"))?,
+            output: output.ok_or_else(|| missing("output"))?,
+        })
+    }
+}
+
+impl fmt::Display for CallEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            CallKind::Import => write!(f, "import {}.{}", self.interface, self.function),
+            CallKind::Export => write!(f, "export {}", self.function),
+        }
+    }
+}
+
+/// Parse a call log written by a [`RecordingInterceptor`].
+///
+/// A trailing partial frame (a log cut off mid-write) is ignored.
+pub fn read_log(bytes: &[u8]) -> Result<Vec<CallEvent>, RuntimeError> {
+    if bytes.len() < 6 || &bytes[..4] != MAGIC {
+        return Err(RuntimeError::CallLogError("not a call log".into()));
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != VERSION {
+        return Err(RuntimeError::CallLogError(format!(
+            "unsupported call log version {version}"
+        )));
+    }
+
+    let mut events = Vec::new();
+    let mut rest = &bytes[6..];
+    while rest.len() >= 4 {
+        let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let Some(frame) = rest.get(4..4 + len) else {
+            break;
+        };
+        let event = decode(frame)
+            .map_err(|e| e.to_string())
+            .and_then(CallEvent::from_value)
+            .map_err(|e| {
+                RuntimeError::CallLogError(format!("malformed event {}: {e}", events.len()))
+            })?;
+        events.push(event);
+        rest = &rest[4 + len..];
+    }
+    Ok(events)
+}
+
+/// A [`CallInterceptor`] that appends every call to a call log.
+///
+/// Calls run normally. Each event is written and flushed once its call
+/// returns, so the log on disk always ends at a complete event.
+pub struct RecordingInterceptor {
+    writer: Mutex<Box<dyn Write + Send>>,
+    error: Mutex<Option<io::Error>>,
+}
+
+impl RecordingInterceptor {
+    /// Record to `writer`, starting with the log header.
+    pub fn new(writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.flush()?;
+        Ok(Self {
+            writer: Mutex::new(writer),
+            error: Mutex::new(None),
+        })
+    }
+
+    /// Record to a new file at `path`, truncating any existing one.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Flush the log and report the first write that failed, if any.
+    ///
+    /// Interceptor hooks cannot return errors, so a failed write is kept
+    /// here and no further events are written after it.
+    pub fn finish(&self) -> io::Result<()> {
+        if let Some(err) = &*self.error.lock().unwrap() {
+            return Err(io::Error::new(err.kind(), err.to_string()));
+        }
+        self.writer.lock().unwrap().flush()
+    }
+
+    fn record(&self, event: CallEvent) {
+        let mut error = self.error.lock().unwrap();
+        if error.is_some() {
+            return;
+        }
+        let frame = match encode(&event.to_value()) {
+            Ok(frame) => frame,
+            Err(e) => {
+                *error = Some(io::Error::new(io::ErrorKind::InvalidData, e.to_string()));
+                return;
+            }
+        };
+        let mut writer = self.writer.lock().unwrap();
+        let result = writer
+            .write_all(&(frame.len() as u32).to_le_bytes())
+            .and_then(|_| writer.write_all(&frame))
+            .and_then(|_| writer.flush());
+        if let Err(e) = result {
+            *error = Some(e);
+        }
+    }
+}
+
+#[async_trait]
+impl CallInterceptor for RecordingInterceptor {
+    async fn before_import(&self, _: &str, _: &str, _: &Value) -> Option<Value> {
+        None
+    }
+
+    async fn after_import(&self, interface: &str, function: &str, input: &Value, output: &Value) {
+        self.record(CallEvent {
+            kind: CallKind::Import,
+            interface: interface.into(),
+            function: function.into(),
+            input: input.clone(),
+            output: output.clone(),
+        });
+    }
+
+    async fn before_export(&self, _: &str, _: &Value) -> Option<Value> {
+        None
+    }
+
+    async fn after_export(&self, function: &str, input: &Value, output: &Value) {
+        self.record(CallEvent {
+            kind: CallKind::Export,
+            interface: String::new(),
+            function: function.into(),
+            input: input.clone(),
+            output: output.clone(),
+        });
+    }
+}
+
+/// One difference between a recorded call and a replayed one.
+///
+/// `path` names where the two differ: `kind`, `interface` or `function` for
+/// the call itself, `input…` or `output…` for a part of a value (for example
+/// `input.items[2]`), or `call` when one side has no call at all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueDiff {
+    pub path: String,
+    pub expected: Option<Value>,
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for ValueDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |v: &Option<Value>| match v {
+            Some(v) => v.to_string(),
+            None => "nothing".into(),
+        };
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.path,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+/// The first call during replay that did not match the log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Position of the event in the log.
+    pub index: usize,
+    /// The recorded event, or `None` if the log had already ended.
+    pub expected: Option<CallEvent>,
+    pub differences: Vec<ValueDiff>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.expected {
+            Some(event) => write!(f, "replay diverged at event {} ({event})", self.index)?,
+            None => write!(f, "replay diverged at event {} (end of log)", self.index)?,
+        }
+        for diff in &self.differences {
+            write!(f, "\n  {diff}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// Compare two values, recording every leaf where they differ.
+fn diff_values(path: &str, expected: &Value, actual: &Value, out: &mut Vec<ValueDiff>) {
+    match (expected, actual) {
+        (
+            Value::Record {
+                type_name: a,
+                fields: fa,
+            },
+            Value::Record {
+                type_name: b,
+                fields: fb,
+            },
+        ) if a == b => {
+            for (name, ev) in fa {
+                let field = format!("{path}.{name}");
+                match fb.iter().find(|(n, _)| n == name) {
+                    Some((_, av)) => diff_values(&field, ev, av, out),
+                    None => out.push(ValueDiff {
+                        path: field,
+                        expected: Some(ev.clone()),
+                        actual: None,
+                    }),
+                }
+            }
+            for (name, av) in fb {
+                if !fa.iter().any(|(n, _)| n == name) {
+                    out.push(ValueDiff {
+                        path: format!("{path}.{name}"),
+                        expected: None,
+                        actual: Some(av.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Tuple(a), Value::Tuple(b))
+        | (Value::List { items: a, .. }, Value::List { items: b, .. }) => {
+            diff_items(path, a, b, out)
+        }
+        (
+            Value::Variant {
+                type_name: ta,
+                case_name: ca,
+                payload: a,
+                ..
+            },
+            Value::Variant {
+                type_name: tb,
+                case_name: cb,
+                payload: b,
+                ..
+            },
+        ) if ta == tb && ca == cb => diff_items(&format!("{path}::{ca}"), a, b, out),
+        (Value::Option { value: Some(a), .. }, Value::Option { value: Some(b), .. }) => {
+            diff_values(&format!("{path}.some"), a, b, out)
+        }
+        (Value::Result { value: Ok(a), .. }, Value::Result { value: Ok(b), .. }) => {
+            diff_values(&format!("{path}.ok"), a, b, out)
+        }
+        (Value::Result { value: Err(a), .. }, Value::Result { value: Err(b), .. }) => {
+            diff_values(&format!("{path}.err"), a, b, out)
+        }
+        _ if expected == actual => {}
+        _ => out.push(ValueDiff {
+            path: path.into(),
+            expected: Some(expected.clone()),
+            actual: Some(actual.clone()),
+        }),
+    }
+}
+
+fn diff_items(path: &str, expected: &[Value], actual: &[Value], out: &mut Vec<ValueDiff>) {
+    for i in 0..expected.len().max(actual.len()) {
+        let item = format!("{path}[{i}]");
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) => diff_values(&item, e, a, out),
+            (e, a) => out.push(ValueDiff {
+                path: item,
+                expected: e.cloned(),
+                actual: a.cloned(),
+            }),
+        }
+    }
+}
+
+struct ReplayState {
+    events: Vec<CallEvent>,
+    next: usize,
+    divergence: Option<Divergence>,
+}
+
+/// A [`CallInterceptor`] that replays a call log.
+///
+/// Imports are answered from the log without running the host function.
+/// Exports run for real, and their outputs are checked against the log.
+/// After the first divergence the interceptor stops serving recorded
+/// values and every call runs normally.
+pub struct ReplayInterceptor {
+    state: Mutex<ReplayState>,
+}
+
+impl ReplayInterceptor {
+    pub fn new(events: Vec<CallEvent>) -> Self {
+        Self {
+            state: Mutex::new(ReplayState {
+                events,
+                next: 0,
+                divergence: None,
+            }),
+        }
+    }
+
+    /// Replay a log read from `bytes`. See [`read_log`].
+    pub fn from_log(bytes: &[u8]) -> Result<Self, RuntimeError> {
+        Ok(Self::new(read_log(bytes)?))
+    }
+
+    /// The first divergence seen so far.
+    pub fn divergence(&self) -> Option<Divergence> {
+        self.state.lock().unwrap().divergence.clone()
+    }
+
+    /// Number of events replayed so far.
+    pub fn replayed(&self) -> usize {
+        self.state.lock().unwrap().next
+    }
+
+    /// Events not yet replayed.
+    pub fn remaining(&self) -> Vec<CallEvent> {
+        let state = self.state.lock().unwrap();
+        state.events[state.next..].to_vec()
+    }
+
+    /// Succeeds if every event in the log was replayed without divergence.
+    /// An unreplayed event is reported as a divergence at path `call`.
+    pub fn finish(&self) -> Result<(), Box<Divergence>> {
+        let state = self.state.lock().unwrap();
+        if let Some(divergence) = &state.divergence {
+            return Err(Box::new(divergence.clone()));
+        }
+        match state.events.get(state.next) {
+            None => Ok(()),
+            Some(event) => Err(Box::new(Divergence {
+                index: state.next,
+                expected: Some(event.clone()),
+                differences: vec![ValueDiff {
+                    path: "call".into(),
+                    expected: Some(Value::String(event.to_string())),
+                    actual: None,
+                }],
+            })),
+        }
+    }
+
+    /// Match an observed call against the next event. Returns its recorded
+    /// output and advances if they line up, records a divergence if not.
+    fn check(&self, actual: CallEvent, check_output: bool) -> Option<Value> {
+        let mut state = self.state.lock().unwrap();
+        if state.divergence.is_some() {
+            return None;
+        }
+        let index = state.next;
+        let Some(expected) = state.events.get(index) else {
+            state.divergence = Some(Divergence {
+                index,
+                expected: None,
+                differences: vec![ValueDiff {
+                    path: "call".into(),
+                    expected: None,
+                    actual: Some(Value::String(actual.to_string())),
+                }],
+            });
+            return None;
+        };
+
+        let mut differences = Vec::new();
+        if expected.kind != actual.kind {
+            differences.push(ValueDiff {
+                path: "kind".into(),
+                expected: Some(Value::String(expected.kind.name().into())),
+                actual: Some(Value::String(actual.kind.name().into())),
+            });
+        }
+        for (path, e, a) in [
+            ("interface", &expected.interface, &actual.interface),
+            ("function", &expected.function, &actual.function),
+        ] {
+            if e != a {
+                differences.push(ValueDiff {
+                    path: path.into(),
+                    expected: Some(Value::String(e.clone())),
+                    actual: Some(Value::String(a.clone())),
+                });
+            }
+        }
+        diff_values("input", &expected.input, &actual.input, &mut differences);
+        if check_output {
+            diff_values("output", &expected.output, &actual.output, &mut differences);
+        }
+
+        if differences.is_empty() {
+            let output = expected.output.clone();
+            state.next += 1;
+            Some(output)
+        } else {
+            state.divergence = Some(Divergence {
+                index,
+                expected: Some(expected.clone()),
+                differences,
+            });
+            None
+        }
+    }
+}
+
+#[async_trait]
+impl CallInterceptor for ReplayInterceptor {
+    async fn before_import(&self, interface: &str, function: &str, input: &Value) -> Option<Value> {
+        self.check(
+            CallEvent {
+                kind: CallKind::Import,
+                interface: interface.into(),
+                function: function.into(),
+                input: input.clone(),
+                output: Value::Tuple(vec![]),
+            },
+            false,
+        )
+    }
+
+    async fn after_import(&self, _: &str, _: &str, _: &Value, _: &Value) {}
+
+    async fn before_export(&self, _: &str, _: &Value) -> Option<Value> {
+        None
+    }
+
+    async fn after_export(&self, function: &str, input: &Value, output: &Value) {
+        self.check(
+            CallEvent {
+                kind: CallKind::Export,
+                interface: String::new(),
+                function: function.into(),
+                input: input.clone(),
+                output: output.clone(),
+            },
+            true,
+        );
+    }
+}
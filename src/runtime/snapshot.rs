@@ -0,0 +1,587 @@
+//! Snapshot and restore of a live instance.
+//!
+//! An [`InstanceSnapshot`] captures what a guest needs to resume where it
+//! left off: the contents of its linear memories, its mutable globals
+//! (including the `__stack_pointer` its shadow stack and allocator hang off),
+//! and the contents of its tables. It serialises to a flat blob with
+//! [`InstanceSnapshot::to_bytes`], so an actor can be checkpointed to disk
+//! and resumed in another process.
+//!
+//! Wasmtime can only reach a module's *exported* items, and compilers keep
+//! most of that state private. A runtime built with `with_snapshots` runs
+//! [`instrument`] on every module it loads, which exports each defined
+//! mutable global, memory and table that is not already exported, and every
+//! function an element segment can put in a table, under a
+//! `__pack_snapshot_*` name. Other runtimes load modules unchanged, as they
+//! do modules walrus cannot parse, and their snapshots capture only exported
+//! state.
+//!
+//! The default `pack:alloc` provider keeps its bump pointer on the host, but
+//! anchors it at the end of memory on first use, so a freshly restored
+//! instance allocates above the restored heap.
+//!
+//! A table entry is recorded as the name of an export of the function it
+//! holds, so a reference survives serialisation and resolves to the same
+//! function in the restored instance. Capturing a table that holds an
+//! unexported function (only possible without instrumentation) or a non-null
+//! `externref` fails with a snapshot error rather than losing the entry.
+//!
+//! Every snapshot records the interface hash of the module's `__pack_types`
+//! metadata: the Merkle hashes of its imported and exported interfaces. It is
+//! computed from the decoded interfaces, so two encodings of the same
+//! interface agree. Restoring onto a module whose interfaces differ is
+//! refused.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use wasmtime::{AsContextMut, Extern, Func, Instance, Memory, Mutability, Ref, Val};
+
+use super::{ResourceLimits, RuntimeError};
+use crate::metadata::{
+    compute_interface_hashes, decode_metadata, hash_interface, Binding, MetadataError, TypeHash,
+};
+
+/// Prefix of the exports [`instrument`] adds.
+const SNAPSHOT_EXPORT_PREFIX: &str = "__pack_snapshot_";
+
+/// Name a host-owned memory (the PIC path) is recorded under.
+const HOST_MEMORY: &str = "__pack_host_memory";
+
+const MAGIC: &[u8; 4] = b"PKSN";
+const VERSION: u16 = 2;
+
+/// Linear memory page size. Captured memories are whole pages.
+const WASM_PAGE_SIZE: usize = 64 * 1024;
+
+/// The captured state of an instance: memories, mutable globals and tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceSnapshot {
+    memories: Vec<(String, Vec<u8>)>,
+    globals: Vec<(String, GlobalValue)>,
+    tables: Vec<(String, Vec<Option<String>>)>,
+    types_hash: Option<TypeHash>,
+}
+
+/// The value of a captured global, bit-exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GlobalValue {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+    V128(u128),
+}
+
+impl InstanceSnapshot {
+    /// Interface hash of the module's `__pack_types` metadata, or `None` if
+    /// the module has none.
+    pub fn types_hash(&self) -> Option<&TypeHash> {
+        self.types_hash.as_ref()
+    }
+
+    pub(crate) fn with_types_hash(mut self, types_hash: Option<TypeHash>) -> Self {
+        self.types_hash = types_hash;
+        self
+    }
+
+    /// The captured contents of the memory exported as `name`.
+    pub fn memory(&self, name: &str) -> Option<&[u8]> {
+        self.memories
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, bytes)| bytes.as_slice())
+    }
+
+    /// Serialise the snapshot.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let memory_len: usize = self.memories.iter().map(|(_, m)| m.len()).sum();
+        let mut out = Vec::with_capacity(memory_len + 256);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        match &self.types_hash {
+            Some(hash) => {
+                out.push(1);
+                out.extend_from_slice(hash.as_bytes());
+            }
+            None => out.push(0),
+        }
+
+        out.extend_from_slice(&(self.memories.len() as u32).to_le_bytes());
+        for (name, bytes) in &self.memories {
+            write_bytes(&mut out, name.as_bytes());
+            write_bytes(&mut out, bytes);
+        }
+
+        out.extend_from_slice(&(self.globals.len() as u32).to_le_bytes());
+        for (name, value) in &self.globals {
+            write_bytes(&mut out, name.as_bytes());
+            let (tag, bits) = match *value {
+                GlobalValue::I32(v) => (0u8, v as u32 as u128),
+                GlobalValue::I64(v) => (1, v as u64 as u128),
+                GlobalValue::F32(v) => (2, v as u128),
+                GlobalValue::F64(v) => (3, v as u128),
+                GlobalValue::V128(v) => (4, v),
+            };
+            out.push(tag);
+            out.extend_from_slice(&bits.to_le_bytes());
+        }
+
+        out.extend_from_slice(&(self.tables.len() as u32).to_le_bytes());
+        for (name, entries) in &self.tables {
+            write_bytes(&mut out, name.as_bytes());
+            out.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+            for entry in entries {
+                match entry {
+                    Some(func) => {
+                        out.push(1);
+                        write_bytes(&mut out, func.as_bytes());
+                    }
+                    None => out.push(0),
+                }
+            }
+        }
+        out
+    }
+
+    /// Parse a snapshot produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RuntimeError> {
+        let mut r = Reader { bytes };
+        if r.take(4)? != MAGIC {
+            return Err(RuntimeError::SnapshotError("not a snapshot".into()));
+        }
+        let version = u16::from_le_bytes(r.array()?);
+        if version != VERSION {
+            return Err(RuntimeError::SnapshotError(format!(
+                "unsupported snapshot version {version}"
+            )));
+        }
+        let types_hash = match r.take(1)?[0] {
+            0 => None,
+            1 => Some(TypeHash::from_bytes(r.array()?)),
+            other => {
+                return Err(RuntimeError::SnapshotError(format!(
+                    "invalid types hash flag {other}"
+                )))
+            }
+        };
+
+        let mut memories = Vec::new();
+        for _ in 0..r.u32()? {
+            let name = r.string()?;
+            let bytes = r.bytes()?;
+            if bytes.len() % WASM_PAGE_SIZE != 0 {
+                return Err(RuntimeError::SnapshotError(format!(
+                    "memory '{name}' is {} bytes, not a whole number of pages",
+                    bytes.len()
+                )));
+            }
+            memories.push((name, bytes.to_vec()));
+        }
+
+        let mut globals = Vec::new();
+        for _ in 0..r.u32()? {
+            let name = r.string()?;
+            let tag = r.take(1)?[0];
+            let bits = u128::from_le_bytes(r.array()?);
+            let value = match tag {
+                0 => GlobalValue::I32(bits as u32 as i32),
+                1 => GlobalValue::I64(bits as u64 as i64),
+                2 => GlobalValue::F32(bits as u32),
+                3 => GlobalValue::F64(bits as u64),
+                4 => GlobalValue::V128(bits),
+                other => {
+                    return Err(RuntimeError::SnapshotError(format!(
+                        "invalid global tag {other}"
+                    )))
+                }
+            };
+            globals.push((name, value));
+        }
+
+        let mut tables = Vec::new();
+        for _ in 0..r.u32()? {
+            let name = r.string()?;
+            let mut entries = Vec::new();
+            for _ in 0..u64::from_le_bytes(r.array()?) {
+                let entry = match r.take(1)?[0] {
+                    0 => None,
+                    1 => Some(r.string()?),
+                    other => {
+                        return Err(RuntimeError::SnapshotError(format!(
+                            "invalid table entry flag {other}"
+                        )))
+                    }
+                };
+                entries.push(entry);
+            }
+            tables.push((name, entries));
+        }
+
+        if !r.bytes.is_empty() {
+            return Err(RuntimeError::SnapshotError(
+                "trailing bytes after snapshot".into(),
+            ));
+        }
+        Ok(Self {
+            memories,
+            globals,
+            tables,
+            types_hash,
+        })
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], RuntimeError> {
+        if self.bytes.len() < n {
+            return Err(RuntimeError::SnapshotError("truncated snapshot".into()));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], RuntimeError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u32(&mut self) -> Result<u32, RuntimeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], RuntimeError> {
+        let len = u64::from_le_bytes(self.array()?);
+        let len = usize::try_from(len)
+            .map_err(|_| RuntimeError::SnapshotError("snapshot entry too large".into()))?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String, RuntimeError> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|e| RuntimeError::SnapshotError(e.to_string()))
+    }
+}
+
+/// Export every defined mutable global, memory and table that is not
+/// already exported, and every function an element segment references, so
+/// a snapshot can reach it. Returns the input unchanged when there is
+/// nothing to add or walrus cannot parse it.
+pub(crate) fn instrument(wasm_bytes: &[u8]) -> Cow<'_, [u8]> {
+    let Ok(mut module) = walrus::Module::from_buffer(wasm_bytes) else {
+        return Cow::Borrowed(wasm_bytes);
+    };
+
+    let mut globals = Vec::new();
+    let mut memories = Vec::new();
+    let mut tables = Vec::new();
+    let mut funcs = Vec::new();
+    for export in module.exports.iter() {
+        match export.item {
+            walrus::ExportItem::Global(id) => globals.push(id),
+            walrus::ExportItem::Memory(id) => memories.push(id),
+            walrus::ExportItem::Table(id) => tables.push(id),
+            walrus::ExportItem::Function(id) => funcs.push(id),
+        }
+    }
+
+    let missing_globals: Vec<_> = module
+        .globals
+        .iter()
+        .filter(|g| g.mutable && matches!(g.kind, walrus::GlobalKind::Local(_)))
+        .map(|g| g.id())
+        .filter(|id| !globals.contains(id))
+        .collect();
+    let missing_memories: Vec<_> = module
+        .memories
+        .iter()
+        .filter(|m| m.import.is_none() && !memories.contains(&m.id()))
+        .map(|m| m.id())
+        .collect();
+    let missing_tables: Vec<_> = module
+        .tables
+        .iter()
+        .filter(|t| t.import.is_none() && !tables.contains(&t.id()))
+        .map(|t| t.id())
+        .collect();
+    let mut missing_funcs = Vec::new();
+    for element in module.elements.iter() {
+        let referenced: Vec<_> = match &element.items {
+            walrus::ElementItems::Functions(ids) => ids.clone(),
+            walrus::ElementItems::Expressions(_, exprs) => exprs
+                .iter()
+                .filter_map(|expr| match expr {
+                    walrus::ConstExpr::RefFunc(id) => Some(*id),
+                    _ => None,
+                })
+                .collect(),
+        };
+        for id in referenced {
+            if !funcs.contains(&id) && !missing_funcs.contains(&id) {
+                missing_funcs.push(id);
+            }
+        }
+    }
+    if missing_globals.is_empty()
+        && missing_memories.is_empty()
+        && missing_tables.is_empty()
+        && missing_funcs.is_empty()
+    {
+        return Cow::Borrowed(wasm_bytes);
+    }
+
+    for (i, id) in missing_globals.into_iter().enumerate() {
+        module
+            .exports
+            .add(&format!("{SNAPSHOT_EXPORT_PREFIX}global_{i}"), id);
+    }
+    for (i, id) in missing_memories.into_iter().enumerate() {
+        module
+            .exports
+            .add(&format!("{SNAPSHOT_EXPORT_PREFIX}memory_{i}"), id);
+    }
+    for (i, id) in missing_tables.into_iter().enumerate() {
+        module
+            .exports
+            .add(&format!("{SNAPSHOT_EXPORT_PREFIX}table_{i}"), id);
+    }
+    for (i, id) in missing_funcs.into_iter().enumerate() {
+        module
+            .exports
+            .add(&format!("{SNAPSHOT_EXPORT_PREFIX}func_{i}"), id);
+    }
+    Cow::Owned(module.emit_wasm())
+}
+
+/// Interface hash of raw `__pack_types` metadata: the Merkle hashes of the
+/// imported and of the exported interfaces, each folded in name order, then
+/// folded together.
+pub(crate) fn types_hash(metadata: &[u8]) -> Result<TypeHash, MetadataError> {
+    let arena = decode_metadata(metadata)?;
+    let section = |name: &'static str| {
+        let mut hashes = compute_interface_hashes(&arena, name);
+        hashes.sort_by(|a, b| a.name.cmp(&b.name));
+        let bindings: Vec<_> = hashes
+            .iter()
+            .map(|h| Binding {
+                name: &h.name,
+                hash: h.hash,
+            })
+            .collect();
+        Binding {
+            name,
+            hash: hash_interface(name, &[], &bindings),
+        }
+    };
+    Ok(hash_interface(
+        "package",
+        &[],
+        &[section("imports"), section("exports")],
+    ))
+}
+
+/// Capture the state of `instance`. `host_memory` is the host-owned memory
+/// of a PIC package, which the instance imports rather than exports.
+///
+/// Nothing here calls into the guest, so the snapshot records the instance
+/// exactly as it was; the caller attaches the types hash afterwards.
+pub(crate) fn capture(
+    mut store: impl AsContextMut,
+    instance: &Instance,
+    host_memory: Option<Memory>,
+) -> Result<InstanceSnapshot, RuntimeError> {
+    let mut snapshot = InstanceSnapshot {
+        memories: Vec::new(),
+        globals: Vec::new(),
+        tables: Vec::new(),
+        types_hash: None,
+    };
+
+    let exports: Vec<(String, Extern)> = instance
+        .exports(&mut store)
+        .map(|e| (e.name().to_string(), e.into_extern()))
+        .collect();
+    // Table entries are recorded by the name of an export of the same
+    // function, matched on the function's identity within this store.
+    let mut func_names: HashMap<usize, &str> = HashMap::new();
+    for (name, export) in &exports {
+        if let Extern::Func(func) = export {
+            func_names
+                .entry(func_key(&mut store, func))
+                .or_insert(name.as_str());
+        }
+    }
+    for (name, export) in &exports {
+        let name = name.clone();
+        match export {
+            Extern::Memory(memory) => {
+                let bytes = memory.data(&store).to_vec();
+                snapshot.memories.push((name, bytes));
+            }
+            Extern::Global(global) if global.ty(&store).mutability() == Mutability::Var => {
+                let value = match global.get(&mut store) {
+                    Val::I32(v) => GlobalValue::I32(v),
+                    Val::I64(v) => GlobalValue::I64(v),
+                    Val::F32(v) => GlobalValue::F32(v),
+                    Val::F64(v) => GlobalValue::F64(v),
+                    Val::V128(v) => GlobalValue::V128(v.as_u128()),
+                    _ => {
+                        return Err(RuntimeError::SnapshotError(format!(
+                            "global '{name}' holds a reference, which cannot be captured"
+                        )))
+                    }
+                };
+                snapshot.globals.push((name, value));
+            }
+            Extern::Table(table) => {
+                let mut entries = Vec::new();
+                for index in 0..table.size(&store) {
+                    let entry = match table.get(&mut store, index) {
+                        Some(Ref::Func(Some(func))) => {
+                            let key = func_key(&mut store, &func);
+                            let func = func_names.get(&key).ok_or_else(|| {
+                                RuntimeError::SnapshotError(format!(
+                                    "table '{name}' entry {index} holds a function that is not \
+                                     exported; load the module with `with_snapshots`"
+                                ))
+                            })?;
+                            Some(func.to_string())
+                        }
+                        Some(entry) if entry.is_null() => None,
+                        _ => {
+                            return Err(RuntimeError::SnapshotError(format!(
+                                "table '{name}' entry {index} holds a reference that cannot \
+                                 be captured"
+                            )))
+                        }
+                    };
+                    entries.push(entry);
+                }
+                snapshot.tables.push((name, entries));
+            }
+            _ => {}
+        }
+    }
+    // A self-contained actor's handle is just its exported memory, already
+    // captured above; only a PIC package's imported memory is separate.
+    if let Some(memory) = host_memory.filter(|_| snapshot.memories.is_empty()) {
+        let bytes = memory.data(&store).to_vec();
+        snapshot.memories.push((HOST_MEMORY.to_string(), bytes));
+    }
+    Ok(snapshot)
+}
+
+/// Overwrite the state of `instance` with `snapshot`. The caller has
+/// already checked the snapshot's types hash against the module.
+pub(crate) fn apply(
+    mut store: impl AsContextMut,
+    instance: &Instance,
+    host_memory: Option<Memory>,
+    snapshot: &InstanceSnapshot,
+    limits: &ResourceLimits,
+) -> Result<(), RuntimeError> {
+    for (name, bytes) in &snapshot.memories {
+        let memory = if name == HOST_MEMORY {
+            host_memory
+        } else {
+            instance.get_memory(&mut store, name)
+        }
+        .ok_or_else(|| missing("memory", name))?;
+
+        let current = memory.data_size(&store);
+        if current > bytes.len() {
+            return Err(RuntimeError::SnapshotError(format!(
+                "memory '{name}' is {current} bytes, larger than the snapshot's {}; \
+                 linear memory cannot shrink",
+                bytes.len()
+            )));
+        }
+        if current < bytes.len() {
+            let page = memory.page_size(&store) as usize;
+            let delta = (bytes.len() - current).div_ceil(page);
+            memory
+                .grow(&mut store, delta as u64)
+                .map_err(|e| limits.classify(e, &store))?;
+        }
+        let data = memory.data_mut(&mut store);
+        if data.len() != bytes.len() {
+            return Err(RuntimeError::SnapshotError(format!(
+                "memory '{name}' grew to {} bytes, not the snapshot's {}",
+                data.len(),
+                bytes.len()
+            )));
+        }
+        data.copy_from_slice(bytes);
+    }
+
+    for (name, value) in &snapshot.globals {
+        let global = instance
+            .get_global(&mut store, name)
+            .ok_or_else(|| missing("global", name))?;
+        let value = match *value {
+            GlobalValue::I32(v) => Val::I32(v),
+            GlobalValue::I64(v) => Val::I64(v),
+            GlobalValue::F32(v) => Val::F32(v),
+            GlobalValue::F64(v) => Val::F64(v),
+            GlobalValue::V128(v) => Val::V128(v.into()),
+        };
+        global
+            .set(&mut store, value)
+            .map_err(|e| RuntimeError::SnapshotError(format!("global '{name}': {e}")))?;
+    }
+
+    for (name, entries) in &snapshot.tables {
+        let table = instance
+            .get_table(&mut store, name)
+            .ok_or_else(|| missing("table", name))?;
+        let size = entries.len() as u64;
+        let current = table.size(&store);
+        if current > size {
+            return Err(RuntimeError::SnapshotError(format!(
+                "table '{name}' has {current} elements, more than the snapshot's {size}"
+            )));
+        }
+        let null = Ref::null(table.ty(&store).element().heap_type());
+        if current < size {
+            table
+                .grow(&mut store, size - current, null.clone())
+                .map_err(|e| limits.classify(e, &store))?;
+        }
+        for (index, entry) in entries.iter().enumerate() {
+            let value = match entry {
+                Some(func) => Ref::Func(Some(
+                    instance
+                        .get_func(&mut store, func)
+                        .ok_or_else(|| missing("function", func))?,
+                )),
+                None => null.clone(),
+            };
+            table
+                .set(&mut store, index as u64, value)
+                .map_err(|e| RuntimeError::SnapshotError(format!("table '{name}': {e}")))?;
+        }
+    }
+    Ok(())
+}
+
+/// Identity of `func` within its store: the address of its `VMFuncRef`,
+/// which every handle to the same function shares.
+fn func_key(store: impl AsContextMut, func: &Func) -> usize {
+    // SAFETY: the pointer is only compared, never dereferenced.
+    unsafe { func.to_raw(store) as usize }
+}
+
+fn missing(kind: &str, name: &str) -> RuntimeError {
+    RuntimeError::SnapshotError(format!("module has no {kind} '{name}' to restore"))
+}
@@ -21,7 +21,8 @@
 //! # Design Principles
 //!
 //! - **Asymmetric**: Actors implement the original interface, callers use transformed
-//! - **Extensible**: User-defined transformations via the trait
+//! - **Extensible**: User-defined transformations via the trait, or declared
+//!   in Pact source with `transform` (see [`DeclaredTransform`])
 //! - **Composable**: Transforms can be chained: `traced(rpc(calculator))`
 //!
 //! # Example
@@ -43,7 +44,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::parser::{PactExport, PactInterface};
-use crate::types::{Case, Type, TypeDef};
+use crate::types::{Case, Param, Type, TypeDef};
 
 // ============================================================================
 // Interface Transform Trait
@@ -197,6 +198,119 @@ impl InterfaceTransform for RpcTransform {
     }
 }
 
+// ============================================================================
+// Declared Transforms
+// ============================================================================
+
+/// Where a wrapper type puts the type it wraps: `result<_, timeout>`.
+pub(crate) const HOLE: &str = "_";
+
+/// Put `ty` in the holes of `wrapper`.
+pub(crate) fn fill(wrapper: &Type, ty: Type) -> Type {
+    wrapper.substitute(&HashMap::from([(HOLE.to_string(), ty)]))
+}
+
+/// A transform declared in Pact source.
+///
+/// ```pact
+/// transform async<I> = wrap<I, future>
+///
+/// transform traced<I> {
+///     add params(span-id: string)
+///     wrap results in option<_>
+///     add errors { timeout, rejected(string) }
+/// }
+/// ```
+///
+/// Each exported function is rewritten in this order: every parameter type
+/// is wrapped in `wrap_params`, then `params` are put in front; the result
+/// (`unit` if there is none) is wrapped in `wrap_results`, then, if there
+/// are `errors`, in `result<_, {name}-error>` with the `{name}-error`
+/// variant added to the interface, as `rpc` does with `rpc-error`.
+///
+/// A wrapper is a type with `_` where the wrapped type goes; a bare name
+/// such as `future` is short for `future<_>`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeclaredTransform {
+    /// The transform name
+    pub name: String,
+    /// Parameters put in front of every export's own
+    pub params: Vec<Param>,
+    /// Wrapper for every parameter type
+    pub wrap_params: Option<Type>,
+    /// Wrapper for every result type
+    pub wrap_results: Option<Type>,
+    /// Cases of the `{name}-error` variant every result is paired with
+    pub errors: Vec<Case>,
+}
+
+impl DeclaredTransform {
+    /// Create a transform that changes nothing.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The name of the variant holding `errors`.
+    pub fn error_type(&self) -> String {
+        format!("{}-error", self.name)
+    }
+
+    fn wrap_result(&self, ty: Type) -> Type {
+        let ty = match &self.wrap_results {
+            Some(wrapper) => fill(wrapper, ty),
+            None => ty,
+        };
+        if self.errors.is_empty() {
+            ty
+        } else {
+            Type::result(ty, Type::named(self.error_type()))
+        }
+    }
+}
+
+impl InterfaceTransform for DeclaredTransform {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn transform(&self, base: &PactInterface) -> PactInterface {
+        let mut result = base.clone();
+        result.name = format!("{}({})", self.name, base.name);
+
+        if !self.errors.is_empty() {
+            result
+                .types
+                .push(TypeDef::variant(self.error_type(), self.errors.clone()));
+        }
+
+        let wraps_results = self.wrap_results.is_some() || !self.errors.is_empty();
+        for export in &mut result.exports {
+            if let PactExport::Function(func) = export {
+                if let Some(wrapper) = &self.wrap_params {
+                    for param in &mut func.params {
+                        param.ty = fill(wrapper, param.ty.clone());
+                    }
+                }
+                func.params.splice(0..0, self.params.iter().cloned());
+
+                if wraps_results {
+                    if func.results.is_empty() {
+                        func.results.push(Type::Unit);
+                    }
+                    for ty in &mut func.results {
+                        *ty = self.wrap_result(std::mem::replace(ty, Type::Unit));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -323,6 +437,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_declared_transform() {
+        let src = r#"
+            interface calculator {
+                exports {
+                    add: func(a: s32, b: s32) -> s32
+                    reset: func()
+                }
+            }
+        "#;
+
+        let interface = parse_pact(src).expect("parse");
+        let mut traced = DeclaredTransform::new("traced");
+        traced.params.push(Param::new("span-id", Type::String));
+        traced.wrap_params = Some(Type::option(Type::named(HOLE)));
+        traced.errors.push(Case::unit("timeout"));
+        let transformed = traced.transform(&interface);
+
+        assert_eq!(transformed.name, "traced(calculator)");
+        assert!(transformed.types.iter().any(|t| t.name() == "traced-error"));
+
+        let PactExport::Function(add) = &transformed.exports[0] else {
+            panic!("Expected function export");
+        };
+        let params: Vec<_> = add.params.iter().map(|p| p.ty.to_string()).collect();
+        assert_eq!(params, ["string", "option<s32>", "option<s32>"]);
+        assert_eq!(add.results[0].to_string(), "result<s32, traced-error>");
+
+        let PactExport::Function(reset) = &transformed.exports[1] else {
+            panic!("Expected function export");
+        };
+        assert_eq!(reset.results[0].to_string(), "result<unit, traced-error>");
+    }
+
     #[test]
     fn test_transform_registry() {
         let registry = TransformRegistry::with_builtins();
@@ -829,6 +829,44 @@ impl TypePath {
     }
 }
 
+/// The type in Pact syntax, e.g. `result<list<user>, string>`.
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |types: &[Type]| {
+            types
+                .iter()
+                .map(Type::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Type::Unit => write!(f, "unit"),
+            Type::Bool => write!(f, "bool"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::S8 => write!(f, "s8"),
+            Type::S16 => write!(f, "s16"),
+            Type::S32 => write!(f, "s32"),
+            Type::S64 => write!(f, "s64"),
+            Type::F32 => write!(f, "f32"),
+            Type::F64 => write!(f, "f64"),
+            Type::Char => write!(f, "char"),
+            Type::String => write!(f, "string"),
+            Type::List(inner) => write!(f, "list<{inner}>"),
+            Type::Option(inner) => write!(f, "option<{inner}>"),
+            Type::Result { ok, err } => write!(f, "result<{ok}, {err}>"),
+            Type::Tuple(types) => write!(f, "tuple<{}>", join(types)),
+            Type::Map { key, value } => write!(f, "map<{key}, {value}>"),
+            Type::Set(elem) => write!(f, "set<{elem}>"),
+            Type::Ref(path) => write!(f, "{path}"),
+            Type::App { path, args } => write!(f, "{path}<{}>", join(args)),
+            Type::Value => write!(f, "value"),
+        }
+    }
+}
+
 impl std::fmt::Display for TypePath {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.is_self_ref() {
@@ -1,4 +1,7 @@
-use packr::abi::{decode, decode_prefix, encode, GraphBuffer, Node, NodeKind, Value, ValueType};
+use packr::abi::{
+    decode, decode_prefix, encode, encode_into, encoded_len, AbiError, GraphBuffer, Node, NodeKind,
+    Value, ValueType,
+};
 
 #[test]
 fn decode_prefix_tolerates_trailing_bytes() {
@@ -147,6 +150,7 @@ fn roundtrip_array_s32() {
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn roundtrip_array_f64() {
     let value = Value::List {
         elem_type: ValueType::F64,
@@ -222,3 +226,86 @@ fn roundtrip_compound_list_uses_list_node() {
     let buffer = GraphBuffer::from_bytes(&bytes).expect("from_bytes");
     assert_eq!(buffer.nodes[buffer.root as usize].kind, NodeKind::List);
 }
+
+#[test]
+fn encode_into_matches_encode() {
+    // Every node kind, nested: the exact-size writer must produce the same
+    // bytes as the Encoder path, and `encoded_len` must predict them exactly.
+    let value = Value::Record {
+        type_name: "envelope".into(),
+        fields: vec![
+            ("flag".into(), Value::Bool(true)),
+            ("small".into(), Value::S8(-3)),
+            ("wide".into(), Value::U64(u64::MAX)),
+            ("ratio".into(), Value::F32(0.25)),
+            ("letter".into(), Value::Char('λ')),
+            ("mask".into(), Value::Flags(0b101)),
+            (
+                "body".into(),
+                Value::List {
+                    elem_type: ValueType::U8,
+                    items: (0..=255u8).map(Value::U8).collect(),
+                },
+            ),
+            (
+                "tags".into(),
+                Value::List {
+                    elem_type: ValueType::String,
+                    items: vec![Value::String("a".into()), Value::String("bc".into())],
+                },
+            ),
+            (
+                "pair".into(),
+                Value::Tuple(vec![Value::U16(7), Value::S64(-9)]),
+            ),
+            (
+                "maybe".into(),
+                Value::Option {
+                    inner_type: ValueType::String,
+                    value: None,
+                },
+            ),
+            (
+                "outcome".into(),
+                Value::Result {
+                    ok_type: ValueType::Tuple(vec![]),
+                    err_type: ValueType::Variant("failure".into()),
+                    value: Err(Box::new(Value::Variant {
+                        type_name: "failure".into(),
+                        case_name: "timeout".into(),
+                        tag: 2,
+                        payload: vec![Value::U32(30)],
+                    })),
+                },
+            ),
+        ],
+    };
+
+    let expected = encode(&value).expect("encode");
+    assert_eq!(encoded_len(&value).expect("encoded_len"), expected.len());
+
+    let mut out = vec![0u8; expected.len()];
+    let written = encode_into(&value, &mut out).expect("encode_into");
+    assert_eq!(written, expected.len());
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn encode_into_rejects_short_buffer() {
+    let value = Value::String("hello, world".into());
+    let len = encoded_len(&value).expect("encoded_len");
+
+    let mut out = vec![0u8; len - 1];
+    assert!(matches!(
+        encode_into(&value, &mut out),
+        Err(AbiError::BufferTooSmall { need, .. }) if need == len
+    ));
+
+    // Mismatched list items fail the same way `encode` does.
+    let bad = Value::List {
+        elem_type: ValueType::U8,
+        items: vec![Value::U16(1)],
+    };
+    assert!(encode(&bad).is_err());
+    assert!(encoded_len(&bad).is_err());
+}
@@ -0,0 +1,291 @@
+//! Link adapters: a consumer and a provider built against different versions
+//! of an interface are linked when the difference is compatible, with the
+//! values rewritten in flight.
+//!
+//! Fixtures:
+//!   - `comp-users-app` (entry): imports `users` with `user { id, name }` and
+//!     `status { active, suspended(string) }`; `run(n)` calls `get`, `save` and
+//!     `set-status`.
+//!   - `comp-users-v2`: exports `users` with `name` renamed `full-name`, an
+//!     added `email: option<string>` and an `archived` case ahead of the
+//!     others.
+//!
+//! With `user.name` mapped to `full-name`: `run(1)` = `save({ 2, "user-1" })`
+//! + `set-status(1, suspended)` = `(2 * 100 + 6) + 2` = 208.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{
+    compose_with_options, diff_interfaces, interface_links, Component, ComposeOptions, FieldRename,
+    InterfaceLink, LinkCompatibility,
+};
+use packr::parser::{parse_pact, Arena, PactExport};
+use packr::runtime::Runtime;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn build_component(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_name = pkg.replace('-', "_");
+    let out = Path::new(manifest_dir).join(format!(
+        "packages/{pkg}/target/wasm32-unknown-unknown/release/{crate_name}.wasm"
+    ));
+    let manifest = Path::new(manifest_dir).join(format!("packages/{pkg}/Cargo.toml"));
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+        ])
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=--export-memory -C link-arg=--no-entry",
+        )
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+/// The app and the v2 provider, or `None` if the toolchain is unavailable.
+fn users_graph() -> Option<Vec<Component>> {
+    [
+        ("app", "comp-users-app", true),
+        ("users", "comp-users-v2", false),
+    ]
+    .iter()
+    .map(|(name, pkg, entry)| {
+        Some(Component {
+            name: name.to_string(),
+            wasm: std::fs::read(build_component(pkg)?).expect("read wasm"),
+            entry: *entry,
+        })
+    })
+    .collect()
+}
+
+fn users_link() -> InterfaceLink {
+    InterfaceLink {
+        consumer: "app".to_string(),
+        import: "users".to_string(),
+        provider: "users".to_string(),
+        export: "users".to_string(),
+    }
+}
+
+fn name_rename() -> Vec<FieldRename> {
+    vec![FieldRename {
+        record: "user".to_string(),
+        consumer: "name".to_string(),
+        provider: "full-name".to_string(),
+    }]
+}
+
+fn compose_users(components: Vec<Component>, options: &ComposeOptions) -> anyhow::Result<Vec<u8>> {
+    let links = interface_links(&components, &[users_link()])?;
+    compose_with_options(components, &links, options)
+}
+
+fn run(composite: &[u8], n: i64) -> Value {
+    let runtime = Runtime::new();
+    let module = runtime.load_module(composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    instance
+        .call_with_value("run", &Value::S64(n))
+        .expect("call run")
+}
+
+#[test]
+fn compatible_drift_is_adapted_in_flight() {
+    let Some(components) = users_graph() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let options = ComposeOptions {
+        renames: name_rename(),
+        ..ComposeOptions::default()
+    };
+    let composite = compose_users(components, &options).expect("compose through the adapter");
+    assert_eq!(run(&composite, 1), Value::S64(208));
+    assert_eq!(run(&composite, 12), Value::S64(1307 + 2));
+}
+
+/// The `users` interface as `pack_types!` embeds it: its types and functions
+/// in one arena.
+fn users_interface(body: &str) -> Arena {
+    let pact = parse_pact(&format!("interface users {{ {body} }}")).expect("parse pact");
+    let mut arena = Arena::new("users");
+    for ty in &pact.types {
+        arena.add_type(ty.clone());
+    }
+    for export in &pact.exports {
+        if let PactExport::Function(f) = export {
+            arena.add_function(f.clone());
+        }
+    }
+    arena
+}
+
+const USERS_V1: &str = r#"
+    record user { id: s64, name: string }
+    variant status { active, suspended(string) }
+    exports {
+        get: func(id: s64) -> user
+        save: func(u: user) -> s64
+        set-status: func(id: s64, s: status) -> s64
+    }
+"#;
+
+const USERS_V2: &str = r#"
+    record user { id: s64, full-name: string, email: option<string> }
+    variant status { archived, active, suspended(string) }
+    exports {
+        get: func(id: s64) -> user
+        save: func(u: user) -> s64
+        set-status: func(id: s64, s: status) -> s64
+    }
+"#;
+
+#[test]
+fn diff_classifies_each_function() {
+    let (v1, v2) = (users_interface(USERS_V1), users_interface(USERS_V2));
+
+    assert!(matches!(
+        diff_interfaces(&v1, &v1, &[]),
+        LinkCompatibility::Identical
+    ));
+    match diff_interfaces(&v1, &v2, &name_rename()) {
+        LinkCompatibility::Compatible(adapter) => {
+            let mut adapted: Vec<&str> = adapter.adapted_functions().collect();
+            adapted.sort();
+            assert_eq!(adapted, ["get", "save", "set-status"]);
+        }
+        other => panic!("expected a compatible link, got {other:?}"),
+    }
+
+    let incompatible =
+        |import: &Arena, export: &Arena, renames: &[FieldRename]| match diff_interfaces(
+            import, export, renames,
+        ) {
+            LinkCompatibility::Incompatible(reason) => reason,
+            other => panic!("expected an incompatible link, got {other:?}"),
+        };
+    // Without the rename, `user.name` has no counterpart in v2.
+    let reason = incompatible(&v1, &v2, &[]);
+    assert!(reason.contains("record `user`"), "{reason}");
+    assert!(reason.contains("field `name`"), "{reason}");
+    // Swapped, v1's `user` would have to supply v2's `full-name`.
+    let reason = incompatible(&v2, &v1, &[]);
+    assert!(reason.contains("field `full-name`"), "{reason}");
+    // A function only the provider has is never called across the link...
+    let v3 = users_interface(&USERS_V2.replace("exports {", "exports { count: func() -> s64"));
+    assert!(matches!(
+        diff_interfaces(&v1, &v3, &name_rename()),
+        LinkCompatibility::Compatible(_)
+    ));
+    // ...but one the consumer imports must exist on the provider.
+    let reason = incompatible(&v3, &v2, &[]);
+    assert!(reason.contains("no function `count`"), "{reason}");
+    // Primitives must match exactly.
+    let narrowed = users_interface(&USERS_V1.replace("user { id: s64", "user { id: s32"));
+    let reason = incompatible(&v1, &narrowed, &[]);
+    assert!(reason.contains("s32 cannot be read as s64"), "{reason}");
+}
+
+#[test]
+fn incompatible_drift_is_rejected() {
+    let Some(components) = users_graph() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let err = format!(
+        "{:#}",
+        compose_users(components, &ComposeOptions::default())
+            .expect_err("`user.name` has no counterpart")
+    );
+    assert!(err.contains("hash-checked link rejected"), "{err}");
+    assert!(err.contains("no adapter can bridge"), "{err}");
+    assert!(err.contains("record `user`"), "{err}");
+}
+
+#[test]
+fn manifest_renames_a_field() {
+    let (Some(app), Some(users)) = (
+        build_component("comp-users-app"),
+        build_component("comp-users-v2"),
+    ) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("packr-compose-adapter-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create manifest dir");
+    std::fs::copy(&app, dir.join("app.wasm")).expect("copy app wasm");
+    std::fs::copy(&users, dir.join("users.wasm")).expect("copy users wasm");
+    std::fs::write(
+        dir.join("users.toml"),
+        r#"
+[[component]]
+name = "app"
+wasm = "app.wasm"
+entry = true
+
+[[component]]
+name = "users"
+wasm = "users.wasm"
+
+[[link]]
+consumer = "app"
+interface = "users"
+provider = "users"
+
+[[rename]]
+record = "user"
+consumer = "name"
+provider = "full-name"
+"#,
+    )
+    .expect("write manifest");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_packr"))
+        .args(["compose", "users.toml", "-o", "composite.wasm"])
+        .current_dir(&dir)
+        .output()
+        .expect("run packr compose");
+    let composite = std::fs::read(dir.join("composite.wasm"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(run(&composite.expect("read composite"), 1), Value::S64(208));
+}
+
+#[test]
+fn checked_in_adapter_matches_its_source() {
+    let Some(built) = build_component("link-adapter") else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let built = std::fs::read(built).expect("read built adapter");
+    let checked_in =
+        std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/link_adapter.wasm"))
+            .expect("read assets/link_adapter.wasm");
+    assert!(
+        built == checked_in,
+        "assets/link_adapter.wasm is stale: copy \
+         packages/link-adapter/target/wasm32-unknown-unknown/release/link_adapter.wasm over it"
+    );
+}
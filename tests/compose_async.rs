@@ -26,6 +26,8 @@
 //!     and
 //!   - the async host fn actually ran (so the fiber genuinely suspended).
 
+#![cfg(feature = "wasmtime")]
+
 use packr::abi::Value;
 use packr::compose::{compose, Component, GraphLink};
 use packr::AsyncRuntime;
@@ -125,17 +127,7 @@ async fn compose_async_provider_suspends_under_sync_shim() {
         export_name: "double".to_string(),
     }];
 
-    let composite = match compose(components, &links) {
-        Ok(c) => c,
-        Err(e) => {
-            let msg = e.to_string();
-            if msg.contains("wasm-merge") || msg.contains("binaryen") {
-                eprintln!("SKIP: {msg}");
-                return;
-            }
-            panic!("compose failed: {e:?}");
-        }
-    };
+    let composite = compose(components, &links).unwrap_or_else(|e| panic!("compose failed: {e:?}"));
 
     // Isolation proof: the composite has exactly two memories.
     assert_eq!(
@@ -243,17 +235,7 @@ async fn compose_async_host_bridge_does_not_leak_entry_memory() {
         export_name: "double".to_string(),
     }];
 
-    let composite = match compose(components, &links) {
-        Ok(c) => c,
-        Err(e) => {
-            let msg = e.to_string();
-            if msg.contains("wasm-merge") || msg.contains("binaryen") {
-                eprintln!("SKIP: {msg}");
-                return;
-            }
-            panic!("compose failed: {e:?}");
-        }
-    };
+    let composite = compose(components, &links).unwrap_or_else(|e| panic!("compose failed: {e:?}"));
 
     let runtime = AsyncRuntime::new();
     let module = runtime
@@ -0,0 +1,225 @@
+//! Hash-based link resolution: `compose` wires every import interface whose
+//! Merkle hash matches exactly one other component's export, so explicit links
+//! are only needed to break ties.
+//!
+//! Fixtures:
+//!   - `comp-app2` (entry): imports `math.double` and `util.inc`, exports
+//!     `run(n) = inc(double(n))`.
+//!   - `math-real` / `math-mock`: both export `math { double: s64 -> s64 }`
+//!     (same hash; the mock always answers 100).
+//!   - `comp-util`: exports `util.inc`.
+//!   - `comp-app` + `math-wrong`: `math` imported as s64 -> s64 but exported
+//!     as s32 -> s32, so the hashes never match.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{compose, resolve_links, Component, GraphLink};
+use packr::metadata::encode_metadata_with_hashes;
+use packr::runtime::Runtime;
+use packr::{Arena, Function, Param, Type};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn build_component(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_name = pkg.replace('-', "_");
+    let out = Path::new(manifest_dir).join(format!(
+        "packages/{pkg}/target/wasm32-unknown-unknown/release/{crate_name}.wasm"
+    ));
+    let manifest = Path::new(manifest_dir).join(format!("packages/{pkg}/Cargo.toml"));
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+        ])
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=--export-memory -C link-arg=--no-entry",
+        )
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+/// Build `(name, package, entry)` fixtures, or `None` if the toolchain is missing.
+fn components(specs: &[(&str, &str, bool)]) -> Option<Vec<Component>> {
+    specs
+        .iter()
+        .map(|(name, pkg, entry)| {
+            let wasm = std::fs::read(build_component(pkg)?).expect("read wasm");
+            Some(Component {
+                name: name.to_string(),
+                wasm,
+                entry: *entry,
+            })
+        })
+        .collect()
+}
+
+fn run(composite: &[u8], n: i64) -> Value {
+    let runtime = Runtime::new();
+    let module = runtime.load_module(composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    instance
+        .call_with_value("run", &Value::S64(n))
+        .expect("call run")
+}
+
+#[test]
+fn unlinked_imports_are_wired_by_hash() {
+    let Some(components) = components(&[
+        ("app", "comp-app2", true),
+        ("math", "math-real", false),
+        ("util", "comp-util", false),
+    ]) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let links = resolve_links(&components, &[]).expect("resolve links");
+    let mut wired: Vec<_> = links
+        .iter()
+        .map(|l| {
+            format!(
+                "{}.{}.{} <- {}.{}",
+                l.consumer, l.import_module, l.import_name, l.provider, l.export_name
+            )
+        })
+        .collect();
+    wired.sort();
+    assert_eq!(
+        wired,
+        ["app.math.double <- math.double", "app.util.inc <- util.inc"]
+    );
+
+    let composite = compose(components, &[]).expect("compose with no explicit links");
+    assert_eq!(run(&composite, 21), Value::S64(43));
+}
+
+#[test]
+fn ambiguous_provider_needs_an_explicit_link() {
+    let Some(components) = components(&[
+        ("app", "comp-app2", true),
+        ("math", "math-real", false),
+        ("mock", "math-mock", false),
+        ("util", "comp-util", false),
+    ]) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let err = compose(components.clone(), &[])
+        .expect_err("two providers of `math` must be ambiguous")
+        .to_string();
+    assert!(
+        err.contains("ambiguous import")
+            && err.contains("interface `math`")
+            && err.contains("`math` (as `math`)")
+            && err.contains("`mock` (as `math`)"),
+        "expected an ambiguity listing both providers, got: {err}"
+    );
+
+    // One explicit link picks the mock; `util` is still resolved by hash.
+    let pin = GraphLink {
+        consumer: "app".to_string(),
+        import_module: "math".to_string(),
+        import_name: "double".to_string(),
+        provider: "mock".to_string(),
+        export_name: "double".to_string(),
+    };
+    let composite = compose(components, &[pin]).expect("compose with the tie broken");
+    assert_eq!(run(&composite, 21), Value::S64(101));
+}
+
+#[test]
+fn drifted_interface_is_left_to_the_hash_check() {
+    let Some(components) = components(&[("app", "comp-app", true), ("math", "math-wrong", false)])
+    else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    // The same-named export is linked despite its hash...
+    let links = resolve_links(&components, &[]).expect("resolve by name");
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].provider, "math");
+
+    // ...and the hash check says why the link cannot work.
+    let err = format!(
+        "{:#}",
+        compose(components, &[]).expect_err("s32 -> s32 cannot serve s64 -> s64")
+    );
+    assert!(
+        err.contains("hash-checked link rejected") && err.contains("no adapter can bridge"),
+        "expected the drift to be reported by the hash check, got: {err}"
+    );
+}
+
+/// A component whose `__pack_types` metadata is `metadata` and whose other
+/// imports and exports are `body`.
+fn wat_component(name: &str, metadata: &Arena, body: &str) -> Component {
+    let metadata = encode_metadata_with_hashes(metadata).expect("encode metadata");
+    let escaped: String = metadata.iter().map(|b| format!("\\{b:02x}")).collect();
+    let wat = format!(
+        r#"(module
+            {body}
+            (memory (export "memory") 1)
+            (data (i32.const 1024) "{escaped}"))"#
+    );
+    Component {
+        name: name.to_string(),
+        wasm: wat::parse_str(wat).expect("parse WAT"),
+        entry: false,
+    }
+}
+
+/// Package metadata declaring `double: func(n: s64) -> s64` in each of
+/// `interfaces`, under `section` ("imports" or "exports").
+fn math_metadata(section: &str, interfaces: &[&str]) -> Arena {
+    let mut list = Arena::new(section);
+    for name in interfaces {
+        let mut interface = Arena::new(*name);
+        interface.add_function(Function::with_signature(
+            "double",
+            vec![Param::new("n", Type::S64)],
+            vec![Type::S64],
+        ));
+        list.add_child(interface);
+    }
+    let mut package = Arena::new("package");
+    package.add_child(list);
+    package
+}
+
+#[test]
+fn provider_exporting_an_interface_twice_is_one_candidate() {
+    const PACK_FN: &str = "(param i32 i32 i32 i32) (result i32)";
+    let app = wat_component(
+        "app",
+        &math_metadata("imports", &["math"]),
+        &format!(r#"(import "math" "double" (func {PACK_FN}))"#),
+    );
+    let provider = wat_component(
+        "store",
+        // Listed twice, as a composite that merged two copies would.
+        &math_metadata("exports", &["math", "math"]),
+        &format!(r#"(func (export "math.double") {PACK_FN} (i32.const 0))"#),
+    );
+
+    let links = resolve_links(&[app, provider], &[]).expect("one provider is no ambiguity");
+    assert_eq!(links.len(), 1);
+    assert_eq!(
+        (links[0].provider.as_str(), links[0].export_name.as_str()),
+        ("store", "math.double")
+    );
+}
@@ -14,6 +14,8 @@
 //!
 //! It exercises both the direct `compose(...)` API and the manifest/CLI path.
 
+#![cfg(feature = "wasmtime")]
+
 use packr::abi::Value;
 use packr::compose::{compose, Component, GraphLink};
 use packr::runtime::Runtime;
@@ -144,17 +146,7 @@ fn compose_graph_three_components_two_links() {
         },
     ];
 
-    let composite = match compose(components, &links) {
-        Ok(c) => c,
-        Err(e) => {
-            let msg = e.to_string();
-            if msg.contains("wasm-merge") || msg.contains("binaryen") {
-                eprintln!("SKIP: {msg}");
-                return;
-            }
-            panic!("compose failed: {e:?}");
-        }
-    };
+    let composite = compose(components, &links).unwrap_or_else(|e| panic!("compose failed: {e:?}"));
 
     // Isolation proof: exactly three memories.
     assert_eq!(
@@ -240,10 +232,7 @@ export = "inc"
 
     if !status.success() {
         let _ = std::fs::remove_file(&out_path);
-        // wasm-merge may be unavailable in a bare shell — treat as skip only if
-        // the output was never produced (composition itself failing on toolchain).
-        eprintln!("SKIP: `packr compose` exited non-zero (wasm-merge/binaryen missing?)");
-        return;
+        panic!("`packr compose` failed: {status}");
     }
 
     let composite = std::fs::read(&out_path).expect("read cli composite");
@@ -12,6 +12,8 @@
 //! So building `comp-result-app` at all exercises the fix; composing + running it
 //! against `math-result` proves the `Result` actually decodes end-to-end.
 
+#![cfg(feature = "wasmtime")]
+
 use packr::abi::Value;
 use packr::compose::{compose, Component, GraphLink};
 use packr::runtime::Runtime;
@@ -90,17 +92,7 @@ fn import_from_decodes_a_result_return_without_a_shim() {
         export_name: "checked".to_string(),
     }];
 
-    let composite = match compose(components, &links) {
-        Ok(c) => c,
-        Err(e) => {
-            let msg = e.to_string();
-            if msg.contains("wasm-merge") || msg.contains("binaryen") {
-                eprintln!("SKIP: {msg}");
-                return;
-            }
-            panic!("compose failed: {e:?}");
-        }
-    };
+    let composite = compose(components, &links).unwrap_or_else(|e| panic!("compose failed: {e:?}"));
 
     let runtime = Runtime::new();
     let module = runtime.load_module(&composite).expect("load composite");
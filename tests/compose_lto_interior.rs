@@ -146,17 +146,8 @@ fn compose_strips_internalized_import_when_metadata_is_interior() {
     // DATA_ADDR is interior. Post-fix it locates the segment and slices at `rel`.
     // A wrong `rel` would decode garbage and still error, so reaching `Ok` also
     // proves the interior slice is correct.
-    let composite = match compose(components, &links) {
-        Ok(c) => c,
-        Err(e) => {
-            let msg = e.to_string();
-            if msg.contains("wasm-merge") || msg.contains("binaryen") {
-                eprintln!("SKIP: {msg}");
-                return;
-            }
-            panic!("compose over interior-metadata entry failed: {e:?}");
-        }
-    };
+    let composite = compose(components, &links)
+        .unwrap_or_else(|e| panic!("compose over interior-metadata entry failed: {e:?}"));
 
     // Internalization sanity, checked at the wasm-import level (robust — unlike the
     // static CGRF scan, which assumes the metadata sits at a segment's offset 0 and
@@ -172,4 +163,13 @@ fn compose_strips_internalized_import_when_metadata_is_interior() {
         import_modules.iter().any(|m| m == "theater:simple/runtime"),
         "residual host import `theater:simple/runtime` must survive, got {import_modules:?}"
     );
+
+    // The composite's regenerated metadata starts its own segment, so the static
+    // scan reads it even though the entry's could not be.
+    let cgrf = packr::metadata::find_cgrf_metadata(&composite)
+        .expect("scan composite")
+        .expect("composite carries __pack_types metadata");
+    let meta = packr::metadata::decode_metadata_with_hashes(&cgrf).expect("decode metadata");
+    let import_ifaces: Vec<&str> = meta.import_hashes.iter().map(|h| h.name.as_str()).collect();
+    assert_eq!(import_ifaces, ["theater:simple/runtime"]);
 }
@@ -0,0 +1,175 @@
+//! The composite's `__pack_types` is regenerated from every component: its
+//! imports are the residual imports of ALL components (not just the entry's),
+//! its exports are the entry's, and its interface hashes are recomputed — so a
+//! composite reads, hash-checks and composes again like any other package.
+//!
+//! Fixtures:
+//!   - `comp-app` (entry): imports `math.double`, exports `run(n) = double(n)`.
+//!   - `comp-async-math`: exports `math.double`, imports the host's `host.tick`.
+//!   - `math-real`: exports `math.double`, no imports.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{compose, Component};
+use packr::metadata::{
+    decode_metadata_with_hashes, find_cgrf_metadata, InterfaceHash, MetadataWithHashes,
+};
+use packr::runtime::Runtime;
+use packr::{AsyncCtx, AsyncRuntime};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn build_component(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_name = pkg.replace('-', "_");
+    let out = Path::new(manifest_dir).join(format!(
+        "packages/{pkg}/target/wasm32-unknown-unknown/release/{crate_name}.wasm"
+    ));
+    let manifest = Path::new(manifest_dir).join(format!("packages/{pkg}/Cargo.toml"));
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+        ])
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=--export-memory -C link-arg=--no-entry",
+        )
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+fn read(pkg: &str) -> Option<Vec<u8>> {
+    Some(std::fs::read(build_component(pkg)?).expect("read wasm"))
+}
+
+fn component(name: &str, wasm: Vec<u8>, entry: bool) -> Component {
+    Component {
+        name: name.to_string(),
+        wasm,
+        entry,
+    }
+}
+
+fn static_metadata(wasm: &[u8]) -> MetadataWithHashes {
+    let cgrf = find_cgrf_metadata(wasm)
+        .expect("scan wasm")
+        .expect("wasm carries __pack_types metadata");
+    decode_metadata_with_hashes(&cgrf).expect("decode metadata")
+}
+
+fn hashes(list: &[InterfaceHash]) -> Vec<(&str, String)> {
+    list.iter()
+        .map(|h| (h.name.as_str(), h.hash.to_string()))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn residual_imports_of_every_component_are_listed() {
+    let (Some(app), Some(provider)) = (read("comp-app"), read("comp-async-math")) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let (app_meta, provider_meta) = (static_metadata(&app), static_metadata(&provider));
+
+    let composite = compose(
+        vec![
+            component("app", app, true),
+            component("math", provider, false),
+        ],
+        &[],
+    )
+    .expect("compose");
+
+    // `math` is internalized; the provider's `host` import is now the
+    // composite's, with the provider's hash. The exports are the entry's.
+    let meta = static_metadata(&composite);
+    assert_eq!(meta.import_hashes.len(), 1, "{:?}", meta.import_hashes);
+    assert_eq!(meta.import_hashes[0].name, "host");
+    assert_eq!(
+        meta.import_hashes[0].hash,
+        provider_meta
+            .import_hashes
+            .iter()
+            .find(|h| h.name == "host")
+            .unwrap()
+            .hash
+    );
+    assert_eq!(hashes(&meta.export_hashes), hashes(&app_meta.export_hashes));
+
+    // Calling `__pack_types` answers the same bytes, every time.
+    let runtime = AsyncRuntime::new();
+    let module = runtime.load_module(&composite).expect("load composite");
+    let mut instance = module
+        .instantiate_with_host_async((), |builder| {
+            builder
+                .interface("host")?
+                .func_async("tick", |_ctx: AsyncCtx<()>, _: Value| async move {
+                    Value::Tuple(vec![])
+                })?;
+            Ok(())
+        })
+        .await
+        .expect("instantiate composite");
+    for _ in 0..2 {
+        let live = instance
+            .types_with_hashes()
+            .await
+            .expect("call __pack_types");
+        assert_eq!(hashes(&live.import_hashes), hashes(&meta.import_hashes));
+        assert_eq!(hashes(&live.export_hashes), hashes(&meta.export_hashes));
+    }
+    assert_eq!(
+        instance
+            .call_with_value_async("run", &Value::S64(21))
+            .await
+            .expect("run"),
+        Value::S64(42)
+    );
+}
+
+#[test]
+fn composite_composes_again() {
+    let (Some(app), Some(math)) = (read("comp-app"), read("math-real")) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let math_meta = static_metadata(&math);
+
+    // A one-component composite presents math-real's surface unchanged...
+    let packaged = compose(vec![component("math", math, true)], &[]).expect("compose math");
+    assert_eq!(
+        hashes(&static_metadata(&packaged).export_hashes),
+        hashes(&math_meta.export_hashes)
+    );
+
+    // ...so it is matched by hash and linked like the original package.
+    let composite = compose(
+        vec![
+            component("app", app, true),
+            component("math", packaged, false),
+        ],
+        &[],
+    )
+    .expect("compose app against the packaged composite");
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    assert_eq!(
+        instance
+            .call_with_value("run", &Value::S64(21))
+            .expect("run"),
+        Value::S64(42)
+    );
+}
@@ -0,0 +1,197 @@
+//! Multi-interface providers: one component's export interfaces are routed to
+//! different consumers, and one consumer imports interfaces from different
+//! providers. Resolution and hash checks are per interface.
+//!
+//! Fixtures:
+//!   - `comp-store`: exports `db { get }` (k * 10) and `cache { get, peek }`
+//!     (k + 100, k), as `db.get`, `cache.get`, `cache.peek`.
+//!   - `comp-cached`: imports `cache`, exports `lookup.find(n) = cache.get(n)`.
+//!   - `comp-store-app` (entry): imports `kv { get }` and `lookup { find }`,
+//!     exports `run(n) = kv.get(n) + find(n)`. `kv` is `db` under another name.
+//!
+//! With `kv` linked to `comp-store`'s `db`: `run(1)` = 10 + 101 = 111.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{compose, interface_links, resolve_links, Component, InterfaceLink};
+use packr::runtime::Runtime;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn build_component(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_name = pkg.replace('-', "_");
+    let out = Path::new(manifest_dir).join(format!(
+        "packages/{pkg}/target/wasm32-unknown-unknown/release/{crate_name}.wasm"
+    ));
+    let manifest = Path::new(manifest_dir).join(format!("packages/{pkg}/Cargo.toml"));
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+        ])
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=--export-memory -C link-arg=--no-entry",
+        )
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+/// The three fixtures, or `None` if the toolchain is unavailable.
+fn store_graph() -> Option<Vec<Component>> {
+    [
+        ("app", "comp-store-app", true),
+        ("cached", "comp-cached", false),
+        ("store", "comp-store", false),
+    ]
+    .iter()
+    .map(|(name, pkg, entry)| {
+        Some(Component {
+            name: name.to_string(),
+            wasm: std::fs::read(build_component(pkg)?).expect("read wasm"),
+            entry: *entry,
+        })
+    })
+    .collect()
+}
+
+fn kv_link(export: &str) -> InterfaceLink {
+    InterfaceLink {
+        consumer: "app".to_string(),
+        import: "kv".to_string(),
+        provider: "store".to_string(),
+        export: export.to_string(),
+    }
+}
+
+fn run(composite: &[u8], n: i64) -> Value {
+    let runtime = Runtime::new();
+    let module = runtime.load_module(composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    instance
+        .call_with_value("run", &Value::S64(n))
+        .expect("call run")
+}
+
+#[test]
+fn one_provider_serves_two_consumers() {
+    let Some(components) = store_graph() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let links = interface_links(&components, &[kv_link("db")]).expect("expand kv link");
+    let links = resolve_links(&components, &links).expect("resolve by hash");
+    let mut wired: Vec<String> = links
+        .iter()
+        .map(|l| {
+            format!(
+                "{}.{}.{} <- {}.{}",
+                l.consumer, l.import_module, l.import_name, l.provider, l.export_name
+            )
+        })
+        .collect();
+    wired.sort();
+    assert_eq!(
+        wired,
+        [
+            "app.kv.get <- store.db.get",
+            "app.lookup.find <- cached.lookup.find",
+            "cached.cache.get <- store.cache.get",
+        ]
+    );
+
+    let composite = compose(components, &links).expect("compose");
+    assert_eq!(run(&composite, 1), Value::S64(111));
+    assert_eq!(run(&composite, 4), Value::S64(144));
+}
+
+#[test]
+fn renamed_interface_links_compare_imported_functions() {
+    let Some(components) = store_graph() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    // `cache` hashes differently from `kv` because it also has `peek`, but
+    // `kv` imports only `get`, which `cache` declares identically.
+    let links = interface_links(&components, &[kv_link("cache")]).expect("expand kv link");
+    let links = resolve_links(&components, &links).expect("resolve by hash");
+    let composite = compose(components.clone(), &links).expect("kv.get is cache.get");
+    assert_eq!(run(&composite, 1), Value::S64(101 + 101));
+
+    let err = format!(
+        "{:#}",
+        interface_links(&components, &[kv_link("queue")]).expect_err("no `queue` interface")
+    );
+    assert!(err.contains("does not export `queue.get`"), "{err}");
+}
+
+#[test]
+fn manifest_interface_link() {
+    let (Some(app), Some(cached), Some(store)) = (
+        build_component("comp-store-app"),
+        build_component("comp-cached"),
+        build_component("comp-store"),
+    ) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("packr-compose-multi-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create manifest dir");
+    std::fs::copy(&app, dir.join("app.wasm")).expect("copy app wasm");
+    std::fs::copy(&cached, dir.join("cached.wasm")).expect("copy cached wasm");
+    std::fs::copy(&store, dir.join("store.wasm")).expect("copy store wasm");
+    std::fs::write(
+        dir.join("store.toml"),
+        r#"
+[[component]]
+name = "app"
+wasm = "app.wasm"
+entry = true
+
+[[component]]
+name = "cached"
+wasm = "cached.wasm"
+
+[[component]]
+name = "store"
+wasm = "store.wasm"
+
+[[link]]
+consumer = "app"
+interface = "kv"
+provider = "store"
+export = "db"
+"#,
+    )
+    .expect("write manifest");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_packr"))
+        .args(["compose", "store.toml", "-o", "composite.wasm"])
+        .current_dir(&dir)
+        .output()
+        .expect("run packr compose");
+    let composite = std::fs::read(dir.join("composite.wasm"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(run(&composite.expect("read composite"), 1), Value::S64(111));
+}
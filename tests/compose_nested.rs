@@ -0,0 +1,231 @@
+//! Nested composition: a composite is itself a component of another composite.
+//!
+//! The inner "math stack" composes `comp-math-stack` (entry; exports
+//! `math.double` = `inc(n) * 2`, imports `util.inc` + the host's `host.tick`)
+//! with `comp-util` (exports `util.inc`). The outer composite then links
+//! `comp-app` (entry; `run(n) = double(n)`) against the whole stack as ONE
+//! component.
+//!
+//! Asserts that the stack's two memories stay distinct in the outer module
+//! (three memories in all), that its residual `host.tick` is still bridged
+//! through the outer entry's memory, and that `run(21)` = `inc(21) * 2` = 44.
+//! The manifest path nests the stack as a sub-manifest.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{compose, Component};
+use packr::metadata::{decode_metadata_with_hashes, find_cgrf_metadata};
+use packr::{AsyncCtx, AsyncRuntime};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+fn build_component(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_name = pkg.replace('-', "_");
+    let out = Path::new(manifest_dir).join(format!(
+        "packages/{pkg}/target/wasm32-unknown-unknown/release/{crate_name}.wasm"
+    ));
+    let manifest = Path::new(manifest_dir).join(format!("packages/{pkg}/Cargo.toml"));
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+        ])
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=--export-memory -C link-arg=--no-entry",
+        )
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+/// Build the three fixtures, or `None` if the toolchain is unavailable.
+fn build_all() -> Option<[PathBuf; 3]> {
+    Some([
+        build_component("comp-app")?,
+        build_component("comp-math-stack")?,
+        build_component("comp-util")?,
+    ])
+}
+
+fn component(name: &str, wasm: Vec<u8>, entry: bool) -> Component {
+    Component {
+        name: name.to_string(),
+        wasm,
+        entry,
+    }
+}
+
+fn memory_count(wasm: &[u8]) -> usize {
+    let module = walrus::Module::from_buffer(wasm).expect("composite parses");
+    module.memories.iter().count()
+}
+
+fn import_interfaces(wasm: &[u8]) -> Vec<String> {
+    let cgrf = find_cgrf_metadata(wasm)
+        .expect("scan composite")
+        .expect("composite carries __pack_types metadata");
+    let meta = decode_metadata_with_hashes(&cgrf).expect("decode metadata");
+    meta.import_hashes.into_iter().map(|h| h.name).collect()
+}
+
+/// Run `run(21)` with an async `host.tick`, returning the result and tick count.
+async fn run(composite: &[u8]) -> (Value, usize) {
+    let runtime = AsyncRuntime::new();
+    let module = runtime.load_module(composite).expect("load composite");
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let mut instance = module
+        .instantiate_with_host_async(ticks.clone(), |builder| {
+            builder.interface("host")?.func_async(
+                "tick",
+                |ctx: AsyncCtx<Arc<AtomicUsize>>, _: Value| async move {
+                    tokio::task::yield_now().await;
+                    ctx.data().fetch_add(1, Ordering::SeqCst);
+                    Value::Tuple(vec![])
+                },
+            )?;
+            Ok(())
+        })
+        .await
+        .expect("instantiate composite");
+    let result = instance
+        .call_with_value_async("run", &Value::S64(21))
+        .await
+        .expect("call run");
+    (result, ticks.load(Ordering::SeqCst))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn composite_nests_as_a_component() {
+    let Some([app, stack, util]) = build_all() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let read = |p: &PathBuf| std::fs::read(p).expect("read wasm");
+
+    let inner = compose(
+        vec![
+            component("stack", read(&stack), true),
+            component("util", read(&util), false),
+        ],
+        &[],
+    )
+    .expect("compose the math stack");
+    assert_eq!(memory_count(&inner), 2);
+    assert_eq!(import_interfaces(&inner), ["host"]);
+
+    let outer = compose(
+        vec![
+            component("app", read(&app), true),
+            component("math", inner, false),
+        ],
+        &[],
+    )
+    .expect("compose the app against the stack");
+    assert_eq!(
+        memory_count(&outer),
+        3,
+        "the nested stack's memories must stay distinct"
+    );
+    assert_eq!(import_interfaces(&outer), ["host"]);
+
+    assert_eq!(run(&outer).await, (Value::S64(44), 1));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn manifest_components_may_be_sub_manifests() {
+    let Some([app, stack, util]) = build_all() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    // Sub-manifest paths resolve against the manifest that names them, and
+    // wasm paths against the sub-manifest's own directory.
+    let dir = std::env::temp_dir().join(format!("packr-compose-nested-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("stack")).expect("create manifest dirs");
+    std::fs::copy(&stack, dir.join("stack/stack.wasm")).expect("copy stack wasm");
+    std::fs::copy(&util, dir.join("stack/util.wasm")).expect("copy util wasm");
+    std::fs::copy(&app, dir.join("app.wasm")).expect("copy app wasm");
+    std::fs::write(
+        dir.join("stack/stack.toml"),
+        r#"
+[[component]]
+name = "stack"
+wasm = "stack.wasm"
+entry = true
+
+[[component]]
+name = "util"
+wasm = "util.wasm"
+"#,
+    )
+    .expect("write stack manifest");
+    std::fs::write(
+        dir.join("app.toml"),
+        r#"
+[[component]]
+name = "app"
+wasm = "app.wasm"
+entry = true
+
+[[component]]
+name = "math"
+manifest = "stack/stack.toml"
+"#,
+    )
+    .expect("write app manifest");
+    // A manifest that (indirectly) includes itself is rejected.
+    std::fs::write(
+        dir.join("loop.toml"),
+        r#"
+[[component]]
+name = "app"
+wasm = "app.wasm"
+entry = true
+
+[[component]]
+name = "again"
+manifest = "loop.toml"
+"#,
+    )
+    .expect("write looping manifest");
+
+    let packr = |manifest: &str| {
+        Command::new(env!("CARGO_BIN_EXE_packr"))
+            .args(["compose", manifest, "-o"])
+            .arg(dir.join("out.wasm"))
+            .current_dir(&dir)
+            .output()
+            .expect("run packr compose")
+    };
+    let ok = packr("app.toml");
+    let looped = packr("loop.toml");
+    let composite = std::fs::read(dir.join("out.wasm"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(
+        ok.status.success(),
+        "{}",
+        String::from_utf8_lossy(&ok.stderr)
+    );
+    let composite = composite.expect("read composite");
+    assert_eq!(memory_count(&composite), 3);
+    assert_eq!(run(&composite).await, (Value::S64(44), 1));
+
+    assert!(!looped.status.success());
+    let stderr = String::from_utf8_lossy(&looped.stderr);
+    assert!(stderr.contains("includes itself"), "{stderr}");
+}
@@ -6,6 +6,8 @@
 //!   - the result is `42` (the call crossed the memory gap and doubled), and
 //!   - the composite has exactly TWO memories (the components stayed isolated).
 
+#![cfg(feature = "wasmtime")]
+
 use packr::abi::Value;
 use packr::compose::{compose_pair, Link};
 use packr::runtime::Runtime;
@@ -94,18 +96,8 @@ fn compose_pair_double_across_two_memories() {
         export_name: "double".to_string(),
     };
 
-    let composite = match compose_pair(&consumer, &provider, &link) {
-        Ok(c) => c,
-        Err(e) => {
-            // wasm-merge (binaryen) may be unavailable in a bare CI shell.
-            let msg = e.to_string();
-            if msg.contains("wasm-merge") || msg.contains("binaryen") {
-                eprintln!("SKIP: {msg}");
-                return;
-            }
-            panic!("compose_pair failed: {e:?}");
-        }
-    };
+    let composite = compose_pair(&consumer, &provider, &link)
+        .unwrap_or_else(|e| panic!("compose_pair failed: {e:?}"));
 
     // Isolation proof: the composite has exactly two memories.
     assert_eq!(
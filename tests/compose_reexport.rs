@@ -0,0 +1,239 @@
+//! Re-exports: `compose_with_exports` keeps selected NON-entry exports on the
+//! composite's surface, each behind a shim from the caller's memory 0 into the
+//! providing component's memory, and lists them in the regenerated metadata.
+//!
+//! Fixtures (a "library bundle" whose surface spans three components):
+//!   - `comp-app` (entry): imports `math.double`, exports `run(n) = double(n)`.
+//!   - `math-real`: exports `math.double` (n * 2), linked to the app.
+//!   - `comp-util`: exports `util.inc` (n + 1), used by nobody inside.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{compose_with_exports, Component, Reexport};
+use packr::metadata::{decode_metadata_with_hashes, find_cgrf_metadata, MetadataWithHashes};
+use packr::runtime::Runtime;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn build_component(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_name = pkg.replace('-', "_");
+    let out = Path::new(manifest_dir).join(format!(
+        "packages/{pkg}/target/wasm32-unknown-unknown/release/{crate_name}.wasm"
+    ));
+    let manifest = Path::new(manifest_dir).join(format!("packages/{pkg}/Cargo.toml"));
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+        ])
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=--export-memory -C link-arg=--no-entry",
+        )
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+/// The bundle's three components, or `None` if the toolchain is unavailable.
+fn bundle() -> Option<Vec<Component>> {
+    [
+        ("app", "comp-app", true),
+        ("math", "math-real", false),
+        ("util", "comp-util", false),
+    ]
+    .iter()
+    .map(|(name, pkg, entry)| {
+        Some(Component {
+            name: name.to_string(),
+            wasm: std::fs::read(build_component(pkg)?).expect("read wasm"),
+            entry: *entry,
+        })
+    })
+    .collect()
+}
+
+fn reexport(component: &str, export_name: &str, as_name: &str) -> Reexport {
+    Reexport {
+        component: component.to_string(),
+        export_name: export_name.to_string(),
+        as_name: as_name.to_string(),
+    }
+}
+
+fn static_metadata(wasm: &[u8]) -> MetadataWithHashes {
+    let cgrf = find_cgrf_metadata(wasm)
+        .expect("scan wasm")
+        .expect("wasm carries __pack_types metadata");
+    decode_metadata_with_hashes(&cgrf).expect("decode metadata")
+}
+
+fn export_functions(meta: &MetadataWithHashes) -> Vec<String> {
+    let exports = meta
+        .arena
+        .children
+        .iter()
+        .find(|c| c.name == "exports")
+        .expect("exports section");
+    let mut names: Vec<String> = exports
+        .children
+        .iter()
+        .flat_map(|i| {
+            i.functions
+                .iter()
+                .map(move |f| format!("{}.{}", i.name, f.name))
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn provider_exports_are_kept_on_the_composite() {
+    let Some(components) = bundle() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let util_meta = static_metadata(&components[2].wasm);
+
+    let composite = compose_with_exports(
+        components,
+        &[],
+        &[
+            reexport("util", "inc", "inc"),
+            reexport("math", "double", "twice"),
+        ],
+    )
+    .expect("compose the bundle");
+
+    // Bare `as` names keep the provider's interface; `util` is unchanged, so
+    // it hashes exactly as comp-util's own export does.
+    let meta = static_metadata(&composite);
+    assert_eq!(export_functions(&meta), [".run", "math.twice", "util.inc"]);
+    let hash = |m: &MetadataWithHashes| {
+        m.export_hashes
+            .iter()
+            .find(|h| h.name == "util")
+            .map(|h| h.hash.to_string())
+    };
+    assert_eq!(hash(&meta), hash(&util_meta));
+
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    let mut call = |name: &str, n: i64| {
+        instance
+            .call_with_value(name, &Value::S64(n))
+            .unwrap_or_else(|e| panic!("call {name}: {e}"))
+    };
+    assert_eq!(call("run", 21), Value::S64(42));
+    assert_eq!(call("inc", 41), Value::S64(42));
+    assert_eq!(call("twice", 5), Value::S64(10));
+    // Repeated calls: every shim frees what it allocates.
+    for n in 0..64 {
+        assert_eq!(call("inc", n), Value::S64(n + 1));
+    }
+}
+
+#[test]
+fn reexport_must_not_shadow_an_entry_export() {
+    let Some(components) = bundle() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let err = compose_with_exports(components.clone(), &[], &[reexport("util", "inc", "run")])
+        .expect_err("`run` is already the entry's");
+    let err = format!("{err:#}");
+    assert!(err.contains("collides"), "{err}");
+
+    let err = compose_with_exports(components, &[], &[reexport("util", "dec", "dec")])
+        .expect_err("comp-util has no `dec`");
+    let err = format!("{err:#}");
+    assert!(err.contains("no export `dec`"), "{err}");
+}
+
+#[test]
+fn manifest_export_section() {
+    let (Some(app), Some(math), Some(util)) = (
+        build_component("comp-app"),
+        build_component("math-real"),
+        build_component("comp-util"),
+    ) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("packr-compose-reexport-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create manifest dir");
+    std::fs::copy(&app, dir.join("app.wasm")).expect("copy app wasm");
+    std::fs::copy(&math, dir.join("math.wasm")).expect("copy math wasm");
+    std::fs::copy(&util, dir.join("util.wasm")).expect("copy util wasm");
+    std::fs::write(
+        dir.join("bundle.toml"),
+        r#"
+[[component]]
+name = "app"
+wasm = "app.wasm"
+entry = true
+
+[[component]]
+name = "math"
+wasm = "math.wasm"
+
+[[component]]
+name = "util"
+wasm = "util.wasm"
+
+[[export]]
+component = "util"
+export = "inc"
+
+[[export]]
+component = "math"
+export = "double"
+as = "math.double"
+"#,
+    )
+    .expect("write manifest");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_packr"))
+        .args(["compose", "bundle.toml", "-o", "bundle.wasm"])
+        .current_dir(&dir)
+        .output()
+        .expect("run packr compose");
+    let composite = std::fs::read(dir.join("bundle.wasm"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let composite = composite.expect("read composite");
+    assert_eq!(
+        export_functions(&static_metadata(&composite)),
+        [".run", "math.double", "util.inc"]
+    );
+
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    assert_eq!(
+        instance
+            .call_with_value("math.double", &Value::S64(8))
+            .expect("call math.double"),
+        Value::S64(16)
+    );
+}
@@ -0,0 +1,334 @@
+//! The routed backend: the same components and links as `compose`, run as
+//! separate instances with every link forwarded through the host.
+//!
+//!   - `comp-app` + `math-real`: `run(21)` = 42, and `math` is callable on its
+//!     own;
+//!   - `comp-app2` + `math-real` + `comp-util` (linked by hash): 43;
+//!   - reloading `math` with `math-mock` (same hash) changes `run` to 100 with
+//!     `app` untouched, and `math-wrong` (different hash) is refused;
+//!   - `comp-app` + `comp-async-math`: the provider's async host import
+//!     suspends inside a routed call, and a call dropped while suspended
+//!     leaves both components callable;
+//!   - a provider export that fails hands its consumer the same status and
+//!     message routed as fused.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{compose, AsyncCompositionRuntime, Component, CompositionRuntime, GraphLink};
+use packr::{AsyncCtx, AsyncRuntime, Runtime};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn build_component(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_name = pkg.replace('-', "_");
+    let out = Path::new(manifest_dir).join(format!(
+        "packages/{pkg}/target/wasm32-unknown-unknown/release/{crate_name}.wasm"
+    ));
+    let manifest = Path::new(manifest_dir).join(format!("packages/{pkg}/Cargo.toml"));
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+        ])
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=--export-memory -C link-arg=--no-entry",
+        )
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+/// Build `(name, package, entry)` components, or `None` if the toolchain is
+/// missing.
+fn components(specs: &[(&str, &str, bool)]) -> Option<Vec<Component>> {
+    specs
+        .iter()
+        .map(|(name, pkg, entry)| {
+            Some(Component {
+                name: name.to_string(),
+                wasm: std::fs::read(build_component(pkg)?).expect("read wasm"),
+                entry: *entry,
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn links_are_routed_between_instances() {
+    let (Some(pair), Some(graph)) = (
+        components(&[("app", "comp-app", true), ("math", "math-real", false)]),
+        components(&[
+            ("app", "comp-app2", true),
+            ("math", "math-real", false),
+            ("util", "comp-util", false),
+        ]),
+    ) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let routed = CompositionRuntime::new(pair, &[]).expect("route pair");
+    let links: Vec<String> = routed
+        .links()
+        .iter()
+        .map(|l| {
+            format!(
+                "{}.{}.{} -> {}.{}",
+                l.consumer, l.import_module, l.import_name, l.provider, l.export_name
+            )
+        })
+        .collect();
+    assert_eq!(links, ["app.math.double -> math.double"]);
+    for _ in 0..3 {
+        let result = routed.call_with_value("run", &Value::S64(21)).expect("run");
+        assert_eq!(result, Value::S64(42));
+    }
+    let doubled = routed
+        .call_component("math", "double", &Value::S64(5))
+        .expect("call math directly");
+    assert_eq!(doubled, Value::S64(10));
+
+    let routed = CompositionRuntime::new(graph, &[]).expect("route graph");
+    assert_eq!(
+        routed.call_with_value("run", &Value::S64(21)).expect("run"),
+        Value::S64(43)
+    );
+}
+
+#[test]
+fn reload_swaps_one_component() {
+    let (Some(pair), Some(mock), Some(wrong)) = (
+        components(&[("app", "comp-app", true), ("math", "math-real", false)]),
+        build_component("math-mock"),
+        build_component("math-wrong"),
+    ) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let mut routed = CompositionRuntime::new(pair, &[]).expect("route pair");
+    assert_eq!(
+        routed.call_with_value("run", &Value::S64(21)).expect("run"),
+        Value::S64(42)
+    );
+
+    routed
+        .reload("math", std::fs::read(mock).expect("read wasm"))
+        .expect("reload a hash-matching provider");
+    assert_eq!(
+        routed.call_with_value("run", &Value::S64(21)).expect("run"),
+        Value::S64(100)
+    );
+
+    let err = format!(
+        "{:#}",
+        routed
+            .reload("math", std::fs::read(wrong).expect("read wasm"))
+            .expect_err("`math-wrong` drifted from `app`'s import")
+    );
+    assert!(err.contains("reloading component `math`"), "{err}");
+    assert!(err.contains("different hash"), "{err}");
+    // The refused build left the running one in place.
+    assert_eq!(
+        routed.call_with_value("run", &Value::S64(21)).expect("run"),
+        Value::S64(100)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn async_host_import_suspends_a_routed_call() {
+    let Some(components) = components(&[
+        ("app", "comp-app", true),
+        ("math", "comp-async-math", false),
+    ]) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let routed = AsyncCompositionRuntime::with_host(
+        AsyncRuntime::new(),
+        components,
+        &[],
+        ticks.clone(),
+        |builder| {
+            builder.interface("host")?.func_async(
+                "tick",
+                |ctx: AsyncCtx<Arc<AtomicUsize>>, _: Value| async move {
+                    tokio::task::yield_now().await;
+                    ctx.data().fetch_add(1, Ordering::SeqCst);
+                    Value::Tuple(vec![])
+                },
+            )?;
+            Ok(())
+        },
+    )
+    .await
+    .expect("route pair");
+    for _ in 0..3 {
+        let result = routed
+            .call_with_value("run", &Value::S64(21))
+            .await
+            .expect("run");
+        assert_eq!(result, Value::S64(42));
+    }
+    assert_eq!(ticks.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn dropped_call_puts_the_instances_back() {
+    let Some(components) = components(&[
+        ("app", "comp-app", true),
+        ("math", "comp-async-math", false),
+    ]) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    // The first `tick` never finishes in time, so that call is dropped while
+    // both `app` and `math` are out of their slots.
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let routed = AsyncCompositionRuntime::with_host(
+        AsyncRuntime::new(),
+        components,
+        &[],
+        ticks,
+        |builder| {
+            builder.interface("host")?.func_async(
+                "tick",
+                |ctx: AsyncCtx<Arc<AtomicUsize>>, _: Value| async move {
+                    if ctx.data().fetch_add(1, Ordering::SeqCst) == 0 {
+                        tokio::time::sleep(Duration::from_secs(3600)).await;
+                    }
+                    Value::Tuple(vec![])
+                },
+            )?;
+            Ok(())
+        },
+    )
+    .await
+    .expect("route pair");
+
+    let dropped = tokio::time::timeout(
+        Duration::from_millis(50),
+        routed.call_with_value("run", &Value::S64(21)),
+    )
+    .await;
+    assert!(dropped.is_err(), "the first call should have timed out");
+
+    let result = routed
+        .call_with_value("run", &Value::S64(21))
+        .await
+        .expect("run after a dropped call");
+    assert_eq!(result, Value::S64(42));
+}
+
+/// `app.run` forwards its input and result slots to the `math.double` import
+/// and returns its status.
+const FORWARDING_APP: &str = r#"
+(module
+    (import "math" "double" (func $double (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 1)
+    (global $heap (mut i32) (i32.const 4096))
+    (func (export "__pack_alloc") (param $size i32) (result i32)
+        global.get $heap
+        global.get $heap
+        local.get $size
+        i32.add
+        global.set $heap)
+    (func (export "__pack_free") (param i32 i32))
+    (func (export "run") (param i32 i32 i32 i32) (result i32)
+        (call $double (local.get 0) (local.get 1) (local.get 2) (local.get 3))))
+"#;
+
+/// `math.double` always fails with status -1 and the message `boom`.
+const FAILING_MATH: &str = r#"
+(module
+    (memory (export "memory") 1)
+    (global $heap (mut i32) (i32.const 4096))
+    (data (i32.const 1024) "boom")
+    (func (export "__pack_alloc") (param $size i32) (result i32)
+        global.get $heap
+        global.get $heap
+        local.get $size
+        i32.add
+        global.set $heap)
+    (func (export "__pack_free") (param i32 i32))
+    (func (export "double") (param i32 i32 i32 i32) (result i32)
+        (i32.store (local.get 2) (i32.const 1024))
+        (i32.store (local.get 3) (i32.const 4))
+        (i32.const -1)))
+"#;
+
+fn failing_pair() -> Vec<Component> {
+    [("app", FORWARDING_APP, true), ("math", FAILING_MATH, false)]
+        .into_iter()
+        .map(|(name, wat, entry)| Component {
+            name: name.to_string(),
+            wasm: wat::parse_str(wat).expect("parse WAT"),
+            entry,
+        })
+        .collect()
+}
+
+fn math_link() -> Vec<GraphLink> {
+    vec![GraphLink {
+        consumer: "app".to_string(),
+        import_module: "math".to_string(),
+        import_name: "double".to_string(),
+        provider: "math".to_string(),
+        export_name: "double".to_string(),
+    }]
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn provider_error_status_matches_the_fused_backend() {
+    let expected = "function 'run' returned error: boom";
+
+    let fused = compose(failing_pair(), &math_link()).expect("compose");
+    let runtime = Runtime::new();
+    let err = runtime
+        .load_module(&fused)
+        .expect("load composite")
+        .instantiate()
+        .expect("instantiate composite")
+        .call_with_value("run", &Value::S64(21))
+        .expect_err("fused call must fail");
+    assert_eq!(err.to_string(), format!("WASM execution error: {expected}"));
+
+    let routed = CompositionRuntime::new(failing_pair(), &math_link()).expect("route pair");
+    let err = routed
+        .call_with_value("run", &Value::S64(21))
+        .expect_err("routed call must fail");
+    assert_eq!(
+        format!("{:#}", err.root_cause()),
+        format!("WASM execution error: {expected}")
+    );
+
+    let routed = AsyncCompositionRuntime::new(failing_pair(), &math_link())
+        .await
+        .expect("route pair");
+    let err = routed
+        .call_with_value("run", &Value::S64(21))
+        .await
+        .expect_err("async routed call must fail");
+    assert_eq!(
+        format!("{:#}", err.root_cause()),
+        format!("WASM execution error: {expected}")
+    );
+}
@@ -0,0 +1,545 @@
+//! Shared-memory composition (`MemoryMode::Shared`, `memory = "shared"`): the
+//! components are PIC side modules linked into ONE memory, each at its own
+//! base, and cross-component calls pass pointers directly.
+//!
+//! The multi-memory topologies are rebuilt as side modules (the fixtures'
+//! `pic` feature + the `docs/pic-composition.md` RUSTFLAGS) and must compose to
+//! the same results with a single memory:
+//!   - `comp-app` + `math-real` (pair): `run(21)` = 42.
+//!   - `comp-app2` + `math-real` + `comp-util` (graph, linked by hash): 43.
+//!   - `comp-app` + `comp-async-math`: a non-entry residual `host.tick` under
+//!     the async runtime.
+//!   - the re-export bundle: `comp-util`'s `inc` on the composite surface.
+//!   - `comp-actor` + `math-real` (actor): `theater:simple/actor.init` with the
+//!     residual `runtime.log`, and again with its metadata forced interior to
+//!     the data segment, as under LTO.
+//!   - `gen-node` + `gen-sm` (generic): the reconciled link, `run(41)` = 42.
+//!   - `comp-app` + `math-wrong` (hash check): rejected at compose time.
+//!   - `comp-result-app` + `math-result` (import result): `Ok(42)` / `Err`.
+//!
+//! Plus the native PIC pair `adder-pic` + `doubler-pic`, whose large results
+//! must not leak across many calls.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{
+    compose_with_options, Component, ComposeOptions, GraphLink, MemoryMode, Reexport,
+};
+use packr::runtime::Runtime;
+use packr::{AsyncCtx, AsyncRuntime};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use walrus::ir::{Instr, Value as IrValue};
+use walrus::{DataKind, ExportItem, FunctionKind, Module};
+
+const PIC_RUSTFLAGS: &str = "-C relocation-model=pic -C link-arg=--experimental-pic \
+     -C link-arg=-shared -C link-arg=--import-memory -C link-arg=--export=__wasm_call_ctors";
+
+/// Build one package as a PIC side module. The `*-pic` packages always are one;
+/// the other fixtures opt in through their `pic` feature, into a separate
+/// target dir so their plain build is untouched.
+fn build_side_module(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let package_dir = Path::new(manifest_dir).join(format!("packages/{pkg}"));
+    let (target_dir, crate_name, features): (PathBuf, String, &[&str]) =
+        match pkg.strip_suffix("-pic") {
+            Some(stem) => (
+                package_dir.join("target"),
+                format!("{}_pic_package", stem.replace('-', "_")),
+                &[],
+            ),
+            None => (
+                package_dir.join("target/pic"),
+                pkg.replace('-', "_"),
+                &["--features", "pic"],
+            ),
+        };
+    let out = target_dir.join(format!("wasm32-unknown-unknown/release/{crate_name}.wasm"));
+
+    let status = Command::new("cargo")
+        .args(["build", "--manifest-path"])
+        .arg(package_dir.join("Cargo.toml"))
+        .args([
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+            "--target-dir",
+        ])
+        .arg(&target_dir)
+        .args(features)
+        .env("RUSTFLAGS", PIC_RUSTFLAGS)
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+/// Build `(name, package, entry)` side modules, or `None` if the toolchain is
+/// missing.
+fn components(specs: &[(&str, &str, bool)]) -> Option<Vec<Component>> {
+    specs
+        .iter()
+        .map(|(name, pkg, entry)| {
+            Some(Component {
+                name: name.to_string(),
+                wasm: std::fs::read(build_side_module(pkg)?).expect("read wasm"),
+                entry: *entry,
+            })
+        })
+        .collect()
+}
+
+fn shared(exports: Vec<Reexport>) -> ComposeOptions {
+    ComposeOptions {
+        exports,
+        memory: MemoryMode::Shared,
+        ..ComposeOptions::default()
+    }
+}
+
+fn memory_count(wasm: &[u8]) -> usize {
+    let module = walrus::Module::from_buffer(wasm).expect("composite parses");
+    module.memories.iter().count()
+}
+
+/// A link from `consumer`'s `module.name` import to the same-named export of
+/// the component called `module`.
+fn link(consumer: &str, module: &str, name: &str) -> GraphLink {
+    GraphLink {
+        consumer: consumer.to_string(),
+        import_module: module.to_string(),
+        import_name: name.to_string(),
+        provider: module.to_string(),
+        export_name: name.to_string(),
+    }
+}
+
+fn call(composite: &[u8], func: &str, input: Value) -> Value {
+    let runtime = Runtime::new();
+    let module = runtime.load_module(composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    instance
+        .call_with_value(func, &input)
+        .unwrap_or_else(|e| panic!("call {func}: {e}"))
+}
+
+#[test]
+fn pair_and_graph_topologies_share_one_memory() {
+    let (Some(pair), Some(graph)) = (
+        components(&[("app", "comp-app", true), ("math", "math-real", false)]),
+        components(&[
+            ("app", "comp-app2", true),
+            ("math", "math-real", false),
+            ("util", "comp-util", false),
+        ]),
+    ) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let composite = compose_with_options(pair, &[], &shared(vec![])).expect("compose pair");
+    assert_eq!(memory_count(&composite), 1);
+    assert_eq!(call(&composite, "run", Value::S64(21)), Value::S64(42));
+
+    let composite = compose_with_options(graph, &[], &shared(vec![])).expect("compose graph");
+    assert_eq!(memory_count(&composite), 1);
+    assert_eq!(call(&composite, "run", Value::S64(21)), Value::S64(43));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn non_entry_host_import_in_shared_memory() {
+    let Some(components) = components(&[
+        ("app", "comp-app", true),
+        ("math", "comp-async-math", false),
+    ]) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let composite = compose_with_options(components, &[], &shared(vec![])).expect("compose");
+    assert_eq!(memory_count(&composite), 1);
+
+    let runtime = AsyncRuntime::new();
+    let module = runtime.load_module(&composite).expect("load composite");
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let mut instance = module
+        .instantiate_with_host_async(ticks.clone(), |builder| {
+            builder.interface("host")?.func_async(
+                "tick",
+                |ctx: AsyncCtx<Arc<AtomicUsize>>, _: Value| async move {
+                    tokio::task::yield_now().await;
+                    ctx.data().fetch_add(1, Ordering::SeqCst);
+                    Value::Tuple(vec![])
+                },
+            )?;
+            Ok(())
+        })
+        .await
+        .expect("instantiate composite");
+    for _ in 0..3 {
+        let result = instance
+            .call_with_value_async("run", &Value::S64(21))
+            .await
+            .expect("call run");
+        assert_eq!(result, Value::S64(42));
+    }
+    assert_eq!(ticks.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn reexports_in_shared_memory() {
+    let Some(components) = components(&[
+        ("app", "comp-app", true),
+        ("math", "math-real", false),
+        ("util", "comp-util", false),
+    ]) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let inc = Reexport {
+        component: "util".to_string(),
+        export_name: "inc".to_string(),
+        as_name: "inc".to_string(),
+    };
+    let composite =
+        compose_with_options(components, &[], &shared(vec![inc])).expect("compose bundle");
+    assert_eq!(call(&composite, "inc", Value::S64(41)), Value::S64(42));
+    assert_eq!(call(&composite, "run", Value::S64(21)), Value::S64(42));
+}
+
+#[test]
+fn pic_packages_share_one_memory_without_leaking() {
+    let Some(components) =
+        components(&[("adder", "adder-pic", true), ("math", "doubler-pic", false)])
+    else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    // adder-pic's typed imports don't hash-match doubler-pic's `value` exports,
+    // so both are linked explicitly.
+    let links: Vec<GraphLink> = ["double", "big"]
+        .iter()
+        .map(|f| GraphLink {
+            consumer: "adder".to_string(),
+            import_module: "math".to_string(),
+            import_name: f.to_string(),
+            provider: "math".to_string(),
+            export_name: f.to_string(),
+        })
+        .collect();
+    let composite = compose_with_options(components, &links, &shared(vec![])).expect("compose");
+    assert_eq!(memory_count(&composite), 1);
+
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    assert_eq!(
+        instance
+            .call_with_value("process", &Value::S64(5))
+            .expect("process"),
+        Value::S64(11)
+    );
+
+    // Every large provider result is freed by the next call through the shim,
+    // so after a warm-up the memory stops growing.
+    let relay = |instance: &mut packr::runtime::Instance<()>, n: i64| match instance
+        .call_with_value("relay_big", &Value::S64(n))
+        .expect("relay_big")
+    {
+        Value::List { items, .. } => items.len(),
+        other => panic!("expected a list, got {other:?}"),
+    };
+    for _ in 0..8 {
+        assert_eq!(relay(&mut instance, 4096), 4096);
+    }
+    let warm = instance.memory_size().expect("memory size");
+    for _ in 0..64 {
+        assert_eq!(relay(&mut instance, 4096), 4096);
+    }
+    assert_eq!(instance.memory_size().expect("memory size"), warm);
+}
+
+/// Instantiate the composite with a counting `theater:simple/runtime.log` and
+/// return the `doubled` field of `theater:simple/actor.init`'s state.
+async fn actor_init(composite: &[u8]) -> (Value, usize) {
+    let runtime = AsyncRuntime::new();
+    let module = runtime.load_module(composite).expect("load composite");
+    let logs = Arc::new(AtomicUsize::new(0));
+    let mut instance = module
+        .instantiate_with_host_async(logs.clone(), |builder| {
+            builder.interface("theater:simple/runtime")?.func_async(
+                "log",
+                |ctx: AsyncCtx<Arc<AtomicUsize>>, _: Value| async move {
+                    ctx.data().fetch_add(1, Ordering::SeqCst);
+                    Value::Tuple(vec![])
+                },
+            )?;
+            Ok(())
+        })
+        .await
+        .expect("instantiate composite");
+    let result = instance
+        .call_with_value_async("theater:simple/actor.init", &Value::Tuple(vec![]))
+        .await
+        .expect("call init");
+    let Value::Result {
+        value: Ok(state), ..
+    } = result
+    else {
+        panic!("init must return Ok(state), got {result:?}");
+    };
+    let Value::Record { fields, .. } = *state else {
+        panic!("init state must be a record");
+    };
+    let doubled = fields
+        .into_iter()
+        .find(|(k, _)| k == "doubled")
+        .map(|(_, v)| v)
+        .expect("state has a `doubled` field");
+    (doubled, logs.load(Ordering::SeqCst))
+}
+
+/// The interfaces the composite's regenerated `__pack_types` still imports.
+fn metadata_imports(composite: &[u8]) -> Vec<String> {
+    let cgrf = packr::metadata::find_cgrf_metadata(composite)
+        .expect("scan composite")
+        .expect("composite carries __pack_types metadata");
+    let meta = packr::metadata::decode_metadata_with_hashes(&cgrf).expect("decode metadata");
+    meta.import_hashes.into_iter().map(|h| h.name).collect()
+}
+
+/// Move a side module's CGRF metadata `pad` bytes into its data segment, as
+/// LTO's merged `.rodata` can leave it. The segment sits at `__memory_base`,
+/// so the bytes are prepended and `__pack_types`'s relative DATA_ADDR bumped
+/// to follow them. Everything else in the segment moves too: the result only
+/// composes, it does not run.
+fn force_metadata_interior(side_module: &[u8], pad: i32) -> Vec<u8> {
+    let mut module = Module::from_buffer(side_module).expect("side module parses");
+    let data_id = module
+        .data
+        .iter()
+        .find(|d| matches!(d.kind, DataKind::Active { .. }))
+        .map(|d| d.id())
+        .expect("side module has a data segment");
+    let data = module.data.get_mut(data_id);
+    assert!(
+        data.value.starts_with(b"CGRF"),
+        "metadata starts the segment"
+    );
+    let mut shifted = vec![0u8; pad as usize];
+    shifted.extend_from_slice(&data.value);
+    data.value = shifted;
+
+    let pack_types = module
+        .exports
+        .iter()
+        .find_map(|e| match e.item {
+            ExportItem::Function(f) if e.name == "__pack_types" => Some(f),
+            _ => None,
+        })
+        .expect("side module exports __pack_types");
+    let FunctionKind::Local(local) = &mut module.funcs.get_mut(pack_types).kind else {
+        panic!("__pack_types is a local function");
+    };
+    let entry = local.entry_block();
+    let data_addr = local
+        .block_mut(entry)
+        .instrs
+        .iter_mut()
+        .find_map(|(instr, _)| match instr {
+            Instr::Const(c) => match &mut c.value {
+                IrValue::I32(addr) => Some(addr),
+                _ => None,
+            },
+            _ => None,
+        })
+        .expect("__pack_types bakes in its DATA_ADDR");
+    *data_addr += pad;
+    module.emit_wasm()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn actor_in_shared_memory() {
+    let Some(components) = components(&[("app", "comp-actor", true), ("math", "math-real", false)])
+    else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let links = [link("app", "math", "double")];
+    let composite = compose_with_options(components, &links, &shared(vec![])).expect("compose");
+    assert_eq!(memory_count(&composite), 1);
+    assert_eq!(metadata_imports(&composite), ["theater:simple/runtime"]);
+
+    let (doubled, logs) = actor_init(&composite).await;
+    assert_eq!(doubled, Value::S64(42));
+    assert!(logs >= 1, "init calls the residual runtime.log");
+}
+
+#[test]
+fn interior_metadata_in_shared_memory() {
+    let Some(mut components) =
+        components(&[("app", "comp-actor", true), ("math", "math-real", false)])
+    else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    components[0].wasm = force_metadata_interior(&components[0].wasm, 32);
+    let links = [link("app", "math", "double")];
+    let composite = compose_with_options(components, &links, &shared(vec![]))
+        .unwrap_or_else(|e| panic!("compose over interior metadata: {e:?}"));
+
+    let module = Module::from_buffer(&composite).expect("composite parses");
+    let import_modules: Vec<&str> = module.imports.iter().map(|i| i.module.as_str()).collect();
+    assert!(!import_modules.contains(&"math"), "{import_modules:?}");
+    assert!(
+        import_modules.contains(&"theater:simple/runtime"),
+        "{import_modules:?}"
+    );
+    assert_eq!(metadata_imports(&composite), ["theater:simple/runtime"]);
+}
+
+#[test]
+fn generic_link_in_shared_memory() {
+    let Some(components) = components(&[("node", "gen-node", true), ("sm", "gen-sm", false)])
+    else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let links = [link("node", "sm", "apply")];
+    let composite = compose_with_options(components, &links, &shared(vec![]))
+        .expect("compose reconciles s := s64");
+    assert_eq!(memory_count(&composite), 1);
+    assert_eq!(call(&composite, "run", Value::S64(41)), Value::S64(42));
+}
+
+#[test]
+fn mismatched_hashes_are_rejected_in_shared_memory() {
+    let Some(components) = components(&[("app", "comp-app", true), ("math", "math-wrong", false)])
+    else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let links = [link("app", "math", "double")];
+    let err = compose_with_options(components, &links, &shared(vec![]))
+        .expect_err("s64 import against an s32 export")
+        .to_string();
+    assert!(
+        err.contains("hash-checked link rejected") && err.contains("interface `math`"),
+        "{err}"
+    );
+}
+
+#[test]
+fn import_result_in_shared_memory() {
+    let Some(components) = components(&[
+        ("app", "comp-result-app", true),
+        ("mathr", "math-result", false),
+    ]) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let links = [link("app", "mathr", "checked")];
+    let composite = compose_with_options(components, &links, &shared(vec![])).expect("compose");
+    assert_eq!(memory_count(&composite), 1);
+
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    match instance
+        .call_with_value("run", &Value::S64(21))
+        .expect("run(21)")
+    {
+        Value::Result { value: Ok(v), .. } => assert_eq!(*v, Value::S64(42)),
+        other => panic!("run(21) must be Ok(42), got {other:?}"),
+    }
+    match instance
+        .call_with_value("run", &Value::S64(-1))
+        .expect("run(-1)")
+    {
+        Value::Result { value: Err(e), .. } => {
+            assert_eq!(*e, Value::String("negative".to_string()))
+        }
+        other => panic!("run(-1) must be Err, got {other:?}"),
+    }
+}
+
+#[test]
+fn shared_memory_needs_side_modules() {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let plain = Path::new(manifest_dir)
+        .join("packages/comp-app/target/wasm32-unknown-unknown/release/comp_app.wasm");
+    let (Ok(plain), Some(math)) = (std::fs::read(plain), build_side_module("math-real")) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+    let components = vec![
+        Component {
+            name: "app".to_string(),
+            wasm: plain,
+            entry: true,
+        },
+        Component {
+            name: "math".to_string(),
+            wasm: std::fs::read(math).expect("read wasm"),
+            entry: false,
+        },
+    ];
+    let err = compose_with_options(components, &[], &shared(vec![]))
+        .expect_err("a self-contained component cannot share a memory")
+        .to_string();
+    assert!(err.contains("`app` is not a PIC side module"), "{err}");
+}
+
+#[test]
+fn manifest_selects_shared_memory() {
+    let (Some(app), Some(math)) = (
+        build_side_module("comp-app"),
+        build_side_module("math-real"),
+    ) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("packr-compose-shared-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create manifest dir");
+    std::fs::copy(&app, dir.join("app.wasm")).expect("copy app wasm");
+    std::fs::copy(&math, dir.join("math.wasm")).expect("copy math wasm");
+    std::fs::write(
+        dir.join("compose.toml"),
+        r#"
+memory = "shared"
+
+[[component]]
+name = "app"
+wasm = "app.wasm"
+entry = true
+
+[[component]]
+name = "math"
+wasm = "math.wasm"
+"#,
+    )
+    .expect("write manifest");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_packr"))
+        .args(["compose", "compose.toml", "-o", "out.wasm"])
+        .current_dir(&dir)
+        .output()
+        .expect("run packr compose");
+    let composite = std::fs::read(dir.join("out.wasm"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let composite = composite.expect("read composite");
+    assert_eq!(memory_count(&composite), 1);
+    assert_eq!(call(&composite, "run", Value::S64(21)), Value::S64(42));
+}
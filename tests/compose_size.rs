@@ -0,0 +1,184 @@
+//! Dead-code elimination and the size report.
+//!
+//! Composes `comp-app` + `math-real` with a spare `comp-util` nothing imports:
+//!   - `run(21)` is still 42;
+//!   - `util` leaves nothing behind: no functions, no data, no memory;
+//!   - `math` loses what only its stripped exports reached (its old
+//!     `__pack_types`), and keeps `double` and its allocator;
+//!   - `packr compose --json` prints the same report.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::compose::{compose_with_report, Component, ComposeOptions, SizeReport, GENERATED};
+use packr::runtime::Runtime;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn build_component(pkg: &str) -> Option<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_name = pkg.replace('-', "_");
+    let out = Path::new(manifest_dir).join(format!(
+        "packages/{pkg}/target/wasm32-unknown-unknown/release/{crate_name}.wasm"
+    ));
+    let manifest = Path::new(manifest_dir).join(format!("packages/{pkg}/Cargo.toml"));
+
+    let status = Command::new("cargo")
+        .args([
+            "build",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+        ])
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=--export-memory -C link-arg=--no-entry",
+        )
+        .status();
+
+    match status {
+        Ok(s) if s.success() && out.exists() => Some(out),
+        _ if out.exists() => Some(out),
+        _ => None,
+    }
+}
+
+/// The app, its provider and a spare component, or `None` if the toolchain is
+/// unavailable.
+fn components() -> Option<Vec<Component>> {
+    [
+        ("app", "comp-app", true),
+        ("math", "math-real", false),
+        ("util", "comp-util", false),
+    ]
+    .iter()
+    .map(|(name, pkg, entry)| {
+        Some(Component {
+            name: name.to_string(),
+            wasm: std::fs::read(build_component(pkg)?).expect("read wasm"),
+            entry: *entry,
+        })
+    })
+    .collect()
+}
+
+fn memory_count(wasm: &[u8]) -> usize {
+    let module = walrus::Module::from_buffer(wasm).expect("composite parses");
+    module.memories.iter().count()
+}
+
+fn run(composite: &[u8], n: i64) -> Value {
+    let runtime = Runtime::new();
+    let module = runtime.load_module(composite).expect("load composite");
+    let mut instance = module.instantiate().expect("instantiate composite");
+    instance
+        .call_with_value("run", &Value::S64(n))
+        .expect("call run")
+}
+
+fn component<'a>(report: &'a SizeReport, name: &str) -> &'a packr::compose::ComponentSize {
+    report
+        .components
+        .iter()
+        .find(|c| c.name == name)
+        .unwrap_or_else(|| panic!("no `{name}` in {report:?}"))
+}
+
+#[test]
+fn unreachable_components_and_exports_are_dropped() {
+    let Some(components) = components() else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let (composite, report) = compose_with_report(components, &[], &ComposeOptions::default())
+        .expect("compose with a spare component");
+    assert_eq!(run(&composite, 21), Value::S64(42));
+    assert_eq!(memory_count(&composite), 2);
+    assert_eq!(report.after, composite.len());
+    assert!(report.after < report.before, "{report:?}");
+
+    let names: Vec<&str> = report.components.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["app", "math", "util", GENERATED]);
+
+    let util = component(&report, "util");
+    assert!(
+        util.before.functions > 0 && util.before.data > 0,
+        "{util:?}"
+    );
+    assert_eq!((util.after.functions, util.after.bytes()), (0, 0));
+
+    let math = component(&report, "math");
+    assert!(math.after.functions > 0, "{math:?}");
+    assert!(math.after.functions < math.before.functions, "{math:?}");
+
+    // The shims, bridges and metadata compose generated are all reachable.
+    let generated = component(&report, GENERATED);
+    assert_eq!(generated.before, generated.after);
+}
+
+#[test]
+fn cli_prints_the_report_as_json() {
+    let (Some(app), Some(math), Some(util)) = (
+        build_component("comp-app"),
+        build_component("math-real"),
+        build_component("comp-util"),
+    ) else {
+        eprintln!("SKIP: fixtures unavailable (wasm target / cargo).");
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("packr-compose-size-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create manifest dir");
+    std::fs::copy(&app, dir.join("app.wasm")).expect("copy app wasm");
+    std::fs::copy(&math, dir.join("math.wasm")).expect("copy math wasm");
+    std::fs::copy(&util, dir.join("util.wasm")).expect("copy util wasm");
+    std::fs::write(
+        dir.join("compose.toml"),
+        r#"
+[[component]]
+name = "app"
+wasm = "app.wasm"
+entry = true
+
+[[component]]
+name = "math"
+wasm = "math.wasm"
+
+[[component]]
+name = "util"
+wasm = "util.wasm"
+"#,
+    )
+    .expect("write manifest");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_packr"))
+        .args(["compose", "compose.toml", "-o", "out.wasm", "--json"])
+        .current_dir(&dir)
+        .output()
+        .expect("run packr compose");
+    let composite = std::fs::read(dir.join("out.wasm"));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let report: serde_json::Value =
+        serde_json::from_slice(&out.stdout).expect("stdout is the JSON report");
+    assert_eq!(
+        report["after"].as_u64(),
+        Some(composite.expect("read composite").len() as u64)
+    );
+    let util = report["components"]
+        .as_array()
+        .expect("components")
+        .iter()
+        .find(|c| c["name"] == "util")
+        .expect("util's row");
+    assert_eq!(util["after"]["functions"], 0);
+    assert!(util["before"]["code"].as_u64() > Some(0), "{util}");
+}
@@ -0,0 +1,141 @@
+//! WasmEngine backend tests
+//!
+//! The same guest and host functions are driven through every enabled
+//! backend; each must agree on core calls, memory access and the Pack ABI.
+
+use packr::abi::Value;
+use packr::engine::{Guest, HostFunctions, Val, ValType, WasmEngine};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
+
+/// A guest with a bump allocator, a Pack-ABI echo, a failing export, and
+/// exports that forward to raw and typed host imports.
+const GUEST_MODULE: &str = r#"
+(module
+    (import "test:host" "offset" (func $offset (param i32) (result i32)))
+    (import "test:host" "double" (func $double (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 2)
+    (global $heap (mut i32) (i32.const 65536))
+    (data (i32.const 1024) "boom")
+
+    (func (export "add") (param i32 i32) (result i32)
+        local.get 0
+        local.get 1
+        i32.add)
+
+    (func (export "add_offset") (param i32) (result i32)
+        local.get 0
+        call $offset)
+
+    (func (export "__pack_alloc") (param $size i32) (result i32)
+        (local $ptr i32)
+        global.get $heap
+        local.set $ptr
+        global.get $heap
+        local.get $size
+        i32.add
+        global.set $heap
+        local.get $ptr)
+
+    (func (export "__pack_free") (param i32 i32))
+
+    (func (export "echo") (param $in i32) (param $len i32) (param $out_ptr i32) (param $out_len i32) (result i32)
+        local.get $out_ptr
+        local.get $in
+        i32.store
+        local.get $out_len
+        local.get $len
+        i32.store
+        i32.const 0)
+
+    (func (export "fail") (param i32 i32) (param $out_ptr i32) (param $out_len i32) (result i32)
+        local.get $out_ptr
+        i32.const 1024
+        i32.store
+        local.get $out_len
+        i32.const 4
+        i32.store
+        i32.const -1)
+
+    (func (export "forward") (param i32 i32 i32 i32) (result i32)
+        local.get 0
+        local.get 1
+        local.get 2
+        local.get 3
+        call $double)
+)
+"#;
+
+fn host_functions(offset: Arc<AtomicI32>) -> HostFunctions {
+    let mut host = HostFunctions::new();
+    host.func_raw(
+        "test:host",
+        "offset",
+        &[ValType::I32],
+        &[ValType::I32],
+        move |_guest, args| match args {
+            [Val::I32(n)] => Ok(vec![Val::I32(n + offset.load(Ordering::SeqCst))]),
+            _ => unreachable!("signature is checked by the engine"),
+        },
+    )
+    .func_typed("test:host", "double", |n: i64| n * 2);
+    host
+}
+
+fn exercise<E: WasmEngine>(engine: E) {
+    let wasm = wat::parse_str(GUEST_MODULE).expect("failed to parse WAT");
+    let module = engine.compile(&wasm).expect("compile");
+    let offset = Arc::new(AtomicI32::new(100));
+    let mut instance = engine
+        .instantiate(&module, &host_functions(offset.clone()))
+        .expect("instantiate");
+
+    // Core calls, typed and untyped.
+    assert_eq!(instance.call_typed::<_, i32>("add", (2, 3)).unwrap(), 5);
+    assert_eq!(
+        instance.call("add", &[Val::I32(-1), Val::I32(1)]).unwrap(),
+        vec![Val::I32(0)]
+    );
+    assert!(instance.call_typed::<_, i64>("add", (2, 3)).is_err());
+    assert!(instance.call("missing", &[]).is_err());
+
+    // Raw host import, with state shared through the closure.
+    assert_eq!(instance.call_typed::<_, i32>("add_offset", 1).unwrap(), 101);
+    offset.store(7, Ordering::SeqCst);
+    assert_eq!(instance.call_typed::<_, i32>("add_offset", 1).unwrap(), 8);
+
+    // Memory access.
+    instance.write_memory(2048, b"pack").unwrap();
+    assert_eq!(instance.read_memory(2048, 4).unwrap(), b"pack");
+    assert!(instance.read_memory(2 * 65536 - 2, 4).is_err());
+
+    // Pack ABI: guest-allocated input, echoed back.
+    let value = Value::Tuple(vec![Value::String("hello".into()), Value::U32(42)]);
+    assert_eq!(instance.call_with_value("echo", &value).unwrap(), value);
+
+    // Pack ABI through a typed host import.
+    assert_eq!(
+        instance
+            .call_with_value("forward", &Value::S64(21))
+            .unwrap(),
+        Value::S64(42)
+    );
+
+    // A nonzero status surfaces the guest's error message.
+    let err = instance
+        .call_with_value("fail", &Value::Bool(true))
+        .unwrap_err();
+    assert!(err.to_string().contains("boom"), "{err}");
+}
+
+#[cfg(feature = "wasmtime")]
+#[test]
+fn wasmtime_backend() {
+    exercise(packr::engine::WasmtimeEngine::new());
+}
+
+#[cfg(feature = "wasmi")]
+#[test]
+fn wasmi_backend() {
+    exercise(packr::engine::WasmiEngine::new());
+}
@@ -1,7 +1,9 @@
 //! Integration tests for the new host function registration API
 
+#![cfg(feature = "wasmtime")]
+
 use packr::abi::Value;
-use packr::runtime::{HostLinkerBuilder, LinkerError};
+use packr::runtime::{HostLinkerBuilder, LinkerError, StoreData};
 use packr::Runtime;
 use wasmtime::Caller;
 
@@ -43,7 +45,7 @@ fn test_namespaced_interface_registration() {
         .instantiate_with_host(state, |builder| {
             builder.interface("theater:simple/runtime")?.func_raw(
                 "add_offset",
-                |caller: Caller<'_, TestState>, a: i32, b: i32| -> i32 {
+                |caller: Caller<'_, StoreData<TestState>>, a: i32, b: i32| -> i32 {
                     a + b + caller.data().actor_id
                 },
             )?;
@@ -165,12 +167,15 @@ fn test_multiple_interfaces() {
         .instantiate_with_host((), |builder| {
             builder
                 .interface("api:v1/math")?
-                .func_raw("add", |_: Caller<'_, ()>, a: i32, b: i32| a + b)?;
+                .func_raw("add", |_: Caller<'_, StoreData<()>>, a: i32, b: i32| a + b)?;
 
             builder
                 .interface("api:v1/util")?
                 // Takes two args but only uses first (for signature compatibility)
-                .func_raw("double", |_: Caller<'_, ()>, x: i32, _unused: i32| x * 2)?;
+                .func_raw(
+                    "double",
+                    |_: Caller<'_, StoreData<()>>, x: i32, _unused: i32| x * 2,
+                )?;
 
             Ok(())
         })
@@ -192,8 +197,8 @@ fn test_provider_pattern() {
         fn register(&self, builder: &mut HostLinkerBuilder<'_, ()>) -> Result<(), LinkerError> {
             builder
                 .interface("math")?
-                .func_raw("add", |_: Caller<'_, ()>, a: i32, b: i32| a + b)?
-                .func_raw("mul", |_: Caller<'_, ()>, a: i32, b: i32| a * b)?;
+                .func_raw("add", |_: Caller<'_, StoreData<()>>, a: i32, b: i32| a + b)?
+                .func_raw("mul", |_: Caller<'_, StoreData<()>>, a: i32, b: i32| a * b)?;
             Ok(())
         }
     }
@@ -4,6 +4,8 @@
 //! theater's chain-subscription redesign (PR #105) depends on to gate the
 //! producer rate by the slowest subscriber.
 
+#![cfg(feature = "wasmtime")]
+
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
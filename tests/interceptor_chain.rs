@@ -0,0 +1,204 @@
+//! Interceptor chain / filter tests
+//!
+//! An `InterceptorChain` must stop `before_*` at the first interceptor that
+//! answers, still give every interceptor the `after_*` hooks, and a
+//! `FilteredInterceptor` must only see the interfaces and functions it
+//! was narrowed to.
+
+#![cfg(feature = "wasmtime")]
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use packr::abi::Value;
+use packr::{AsyncCtx, AsyncRuntime, CallInterceptor, FilteredInterceptor, InterceptorChain};
+
+/// `run` calls `app:math.double` with its input, passes the answer to
+/// `theater:simple/runtime.log`, and returns what `log` returned.
+const ACTOR: &str = r#"
+(module
+    (import "app:math" "double" (func $double (param i32 i32 i32 i32) (result i32)))
+    (import "theater:simple/runtime" "log" (func $log (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 2)
+    (global $heap (mut i32) (i32.const 65536))
+
+    (func $alloc (export "__pack_alloc") (param $size i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr (global.get $heap))
+        (global.set $heap (i32.add (global.get $heap) (local.get $size)))
+        (local.get $ptr))
+
+    (func (export "__pack_free") (param i32 i32))
+
+    (func (export "run") (param $in i32) (param $len i32) (param $out_ptr i32) (param $out_len i32) (result i32)
+        (local $a i32)
+        (local $b i32)
+        (local $status i32)
+        (local.set $a (call $alloc (i32.const 8)))
+        (local.set $status
+            (call $double (local.get $in) (local.get $len)
+                (local.get $a) (i32.add (local.get $a) (i32.const 4))))
+        (if (i32.lt_s (local.get $status) (i32.const 0))
+            (then (return (local.get $status))))
+        (local.set $b (call $alloc (i32.const 8)))
+        (local.set $status
+            (call $log (i32.load (local.get $a)) (i32.load offset=4 (local.get $a))
+                (local.get $b) (i32.add (local.get $b) (i32.const 4))))
+        (if (i32.lt_s (local.get $status) (i32.const 0))
+            (then (return (local.get $status))))
+        (i32.store (local.get $out_ptr) (i32.load (local.get $b)))
+        (i32.store (local.get $out_len) (i32.load offset=4 (local.get $b)))
+        (i32.const 0))
+)
+"#;
+
+/// Records every hook it sees as `"<hook> <name> <value>"`.
+#[derive(Clone, Default)]
+struct Trace(Arc<Mutex<Vec<String>>>);
+
+impl Trace {
+    fn seen(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn push(&self, line: String) {
+        self.0.lock().unwrap().push(line);
+    }
+}
+
+#[async_trait]
+impl CallInterceptor for Trace {
+    async fn before_import(&self, interface: &str, function: &str, _: &Value) -> Option<Value> {
+        self.push(format!("before {interface}.{function}"));
+        None
+    }
+
+    async fn after_import(&self, interface: &str, function: &str, _: &Value, output: &Value) {
+        self.push(format!("after {interface}.{function} {output}"));
+    }
+
+    async fn before_export(&self, function: &str, _: &Value) -> Option<Value> {
+        self.push(format!("before {function}"));
+        None
+    }
+
+    async fn after_export(&self, function: &str, _: &Value, output: &Value) {
+        self.push(format!("after {function} {output}"));
+    }
+}
+
+/// Answers every import with a fixed value.
+struct Stub(Value);
+
+#[async_trait]
+impl CallInterceptor for Stub {
+    async fn before_import(&self, _: &str, _: &str, _: &Value) -> Option<Value> {
+        Some(self.0.clone())
+    }
+
+    async fn after_import(&self, _: &str, _: &str, _: &Value, _: &Value) {}
+
+    async fn before_export(&self, _: &str, _: &Value) -> Option<Value> {
+        None
+    }
+
+    async fn after_export(&self, _: &str, _: &Value, _: &Value) {}
+}
+
+async fn run(interceptor: InterceptorChain, input: i64) -> Value {
+    let wasm = wat::parse_str(ACTOR).expect("failed to parse WAT");
+    let runtime = AsyncRuntime::new();
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    let mut instance = module
+        .instantiate_with_host_and_interceptor_async((), Some(Arc::new(interceptor)), |builder| {
+            builder
+                .interface("app:math")?
+                .func_async("double", |_ctx: AsyncCtx<()>, n: i64| async move { n * 2 })?;
+            builder
+                .interface("theater:simple/runtime")?
+                .func_async("log", |_ctx: AsyncCtx<()>, n: i64| async move { n })?;
+            Ok(())
+        })
+        .await
+        .expect("failed to instantiate");
+    instance
+        .call_with_value_async("run", &Value::S64(input))
+        .await
+        .expect("run")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn chain_short_circuits_before_and_fans_out_after() {
+    let (first, last) = (Trace::default(), Trace::default());
+    let chain = InterceptorChain::new()
+        .with(first.clone())
+        .with(FilteredInterceptor::new(Stub(Value::S64(100))).interface("app:*"))
+        .with(last.clone());
+
+    // `double` is answered by the stub; `log` runs for real on its answer.
+    assert_eq!(run(chain, 3).await, Value::S64(100));
+
+    assert_eq!(
+        first.seen(),
+        [
+            "before run",
+            "before app:math.double",
+            "after app:math.double 100s64",
+            "before theater:simple/runtime.log",
+            "after theater:simple/runtime.log 100s64",
+            "after run 100s64",
+        ]
+    );
+    // The stub answered `double` before `last` was asked about it.
+    assert_eq!(
+        last.seen(),
+        [
+            "before run",
+            "after app:math.double 100s64",
+            "before theater:simple/runtime.log",
+            "after theater:simple/runtime.log 100s64",
+            "after run 100s64",
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn filter_narrows_to_matching_calls() {
+    let host_only = Trace::default();
+    let by_function = Trace::default();
+    let chain = InterceptorChain::new()
+        .with(FilteredInterceptor::new(host_only.clone()).interface("theater:simple/*"))
+        .with(FilteredInterceptor::new(by_function.clone()).function("dou*"));
+
+    assert_eq!(run(chain, 3).await, Value::S64(6));
+
+    assert_eq!(
+        host_only.seen(),
+        [
+            "before theater:simple/runtime.log",
+            "after theater:simple/runtime.log 6s64",
+        ]
+    );
+    assert_eq!(
+        by_function.seen(),
+        ["before app:math.double", "after app:math.double 6s64"]
+    );
+}
+
+#[test]
+fn filter_patterns_combine() {
+    let filter = FilteredInterceptor::new(Trace::default())
+        .interface("theater:simple/*")
+        .interface("app:math")
+        .function("handle-*");
+
+    assert!(filter.matches("theater:simple/runtime", "handle-send"));
+    assert!(filter.matches("app:math", "handle-x"));
+    assert!(!filter.matches("app:math", "double"));
+    assert!(!filter.matches("theater:other/runtime", "handle-send"));
+    // Exports have no interface.
+    assert!(!filter.matches("", "handle-send"));
+    assert!(FilteredInterceptor::new(Trace::default())
+        .function("run")
+        .matches("", "run"));
+}
@@ -2,6 +2,9 @@
 //!
 //! Tests that validate WASM modules implement Pact interfaces correctly.
 
+#![cfg(feature = "wasmtime")]
+#![allow(deprecated)]
+
 use packr::parser::parse_interface;
 use packr::runtime::InterfaceError;
 use packr::Runtime;
@@ -0,0 +1,150 @@
+//! Nested host→guest→host call tests
+//!
+//! A host function may re-enter its guest through `Ctx::call_with_value`.
+//! Every buffer of the nested call must come from the guest allocator, so
+//! nothing the outer call is still holding gets overwritten.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::runtime::LinkerError;
+use packr::{Ctx, Runtime};
+use std::sync::{Arc, Mutex};
+
+/// `outer` gets a host return buffer from `tag`, then calls `reenter`, which
+/// re-enters the guest at `inner`, which calls `tag` again. `outer` returns
+/// the buffer from its first `tag` call, so a nested return written over it
+/// would show up in the result.
+const NESTING_MODULE: &str = r#"
+(module
+    (import "test" "tag" (func $tag (param i32 i32 i32 i32) (result i32)))
+    (import "test" "reenter" (func $reenter (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 2)
+    (global $heap (mut i32) (i32.const 65536))
+
+    (func $alloc (export "__pack_alloc") (param $size i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr (global.get $heap))
+        (global.set $heap (i32.add (global.get $heap) (local.get $size)))
+        (local.get $ptr))
+
+    (func (export "__pack_free") (param i32 i32))
+
+    (func (export "outer") (param $in i32) (param $len i32) (param $out_ptr i32) (param $out_len i32) (result i32)
+        (local $held i32)
+        (local $scratch i32)
+        (local $status i32)
+        (local.set $held (call $alloc (i32.const 8)))
+        (local.set $status
+            (call $tag (local.get $in) (local.get $len)
+                (local.get $held) (i32.add (local.get $held) (i32.const 4))))
+        (if (i32.lt_s (local.get $status) (i32.const 0))
+            (then (return (local.get $status))))
+
+        (local.set $scratch (call $alloc (i32.const 8)))
+        (local.set $status
+            (call $reenter (local.get $in) (local.get $len)
+                (local.get $scratch) (i32.add (local.get $scratch) (i32.const 4))))
+        (if (i32.lt_s (local.get $status) (i32.const 0))
+            (then (return (local.get $status))))
+
+        (i32.store (local.get $out_ptr) (i32.load (local.get $held)))
+        (i32.store (local.get $out_len) (i32.load offset=4 (local.get $held)))
+        (i32.const 0))
+
+    (func (export "inner") (param $in i32) (param $len i32) (param $out_ptr i32) (param $out_len i32) (result i32)
+        (local $slots i32)
+        (local $status i32)
+        (local.set $slots (call $alloc (i32.const 8)))
+        (local.set $status
+            (call $tag (local.get $in) (local.get $len)
+                (local.get $slots) (i32.add (local.get $slots) (i32.const 4))))
+        (if (i32.lt_s (local.get $status) (i32.const 0))
+            (then (return (local.get $status))))
+        (i32.store (local.get $out_ptr) (i32.load (local.get $slots)))
+        (i32.store (local.get $out_len) (i32.load offset=4 (local.get $slots)))
+        (i32.const 0))
+)
+"#;
+
+/// The same shape without an allocator: `outer` forwards straight to
+/// `reenter` using fixed slots.
+const NO_ALLOCATOR_MODULE: &str = r#"
+(module
+    (import "test" "reenter" (func $reenter (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 1)
+
+    (func (export "outer") (param $in i32) (param $len i32) (param $out_ptr i32) (param $out_len i32) (result i32)
+        (local $status i32)
+        (local.set $status
+            (call $reenter (local.get $in) (local.get $len) (i32.const 4096) (i32.const 4100)))
+        (if (i32.lt_s (local.get $status) (i32.const 0))
+            (then (return (local.get $status))))
+        (i32.store (local.get $out_ptr) (i32.load (i32.const 4096)))
+        (i32.store (local.get $out_len) (i32.load (i32.const 4100)))
+        (i32.const 0))
+)
+"#;
+
+type Nested = Arc<Mutex<Vec<Result<Value, String>>>>;
+
+fn instantiate(wat: &str, nested: Nested) -> packr::runtime::Instance<()> {
+    let wasm = wat::parse_str(wat).expect("failed to parse WAT");
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    module
+        .instantiate_with_host((), move |builder| {
+            builder
+                .interface("test")?
+                .func_typed("tag", |_ctx: &mut Ctx<'_, ()>, s: String| {
+                    format!("tagged:{s}")
+                })?
+                .func_typed("reenter", move |ctx: &mut Ctx<'_, ()>, s: String| {
+                    let result = ctx
+                        .call_with_value("inner", &Value::String(format!("nested:{s}")))
+                        .map_err(|e: LinkerError| e.to_string());
+                    nested.lock().unwrap().push(result);
+                    String::from("reentered")
+                })?;
+            Ok(())
+        })
+        .expect("failed to instantiate")
+}
+
+#[test]
+fn nested_calls_do_not_clobber_outer_buffers() {
+    let nested = Nested::default();
+    let mut instance = instantiate(NESTING_MODULE, nested.clone());
+
+    let output = instance
+        .call_with_value("outer", &Value::String("hello".into()))
+        .expect("outer call");
+    assert_eq!(output, Value::String("tagged:hello".into()));
+    assert_eq!(
+        nested.lock().unwrap().as_slice(),
+        [Ok(Value::String("tagged:nested:hello".into()))]
+    );
+
+    // Repeated calls keep working: no buffer is left at a fixed address.
+    let output = instance
+        .call_with_value("outer", &Value::String("again".into()))
+        .expect("second outer call");
+    assert_eq!(output, Value::String("tagged:again".into()));
+    assert_eq!(nested.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn nested_call_requires_guest_allocator() {
+    let nested = Nested::default();
+    let mut instance = instantiate(NO_ALLOCATOR_MODULE, nested.clone());
+
+    // The top-level call still works through the host scratch region.
+    let output = instance
+        .call_with_value("outer", &Value::String("hello".into()))
+        .expect("outer call");
+    assert_eq!(output, Value::String("reentered".into()));
+
+    let nested = nested.lock().unwrap();
+    let err = nested[0].as_ref().expect_err("nested call must be refused");
+    assert!(err.contains("__pack_alloc"), "{err}");
+}
@@ -0,0 +1,215 @@
+//! Drives the `pact-lsp` binary over stdio with a scripted client.
+//!
+//! The workspace is two files, `types.pact` defining `user` and `api.pact`
+//! using it, so every feature is exercised across a `use` declaration:
+//! diagnostics on open and save (with a third file that does not parse),
+//! go-to-definition, hover with the type's hash, completion, and rename.
+
+use packr::{hash_type_in, parse_pact_dir_with_registry, Type};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const TYPES: &str = "record user {\n    id: u64,\n    name: string\n}\n";
+const API: &str = "use types.{user}\n\nexports {\n    get: func(id: u64) -> user\n}\n";
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// Notifications read while waiting for something else
+    pending: VecDeque<Value>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_pact-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("spawn pact-lsp");
+        Client {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            pending: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            assert!(
+                self.stdout.read_line(&mut line).unwrap() > 0,
+                "server hung up"
+            );
+            match line.trim_end().split_once(": ") {
+                Some(("Content-Length", n)) => length = n.parse().unwrap(),
+                None if line.trim_end().is_empty() => break,
+                _ => {}
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Send a request and wait for its response.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.receive();
+            if message["id"] == id {
+                return message;
+            }
+            self.pending.push_back(message);
+        }
+    }
+
+    /// Wait for the diagnostics published for `uri`.
+    fn diagnostics(&mut self, uri: &str) -> Vec<Value> {
+        loop {
+            let message = match self.pending.pop_front() {
+                Some(message) => message,
+                None => self.receive(),
+            };
+            if message["method"] == "textDocument/publishDiagnostics"
+                && message["params"]["uri"] == uri
+            {
+                return message["params"]["diagnostics"].as_array().unwrap().clone();
+            }
+        }
+    }
+}
+
+fn uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+fn at(uri: &str, line: u32, character: u32) -> Value {
+    json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": line, "character": character },
+    })
+}
+
+fn labels(response: &Value) -> Vec<&str> {
+    let items = response["result"].as_array().expect("completion items");
+    items.iter().map(|i| i["label"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn scripted_session_across_a_use() {
+    let dir = std::env::temp_dir().join(format!("packr-pact-lsp-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("types.pact"), TYPES).unwrap();
+    std::fs::write(dir.join("api.pact"), API).unwrap();
+    let (types_uri, api_uri) = (uri(&dir.join("types.pact")), uri(&dir.join("api.pact")));
+
+    let mut client = Client::start();
+    let init = client.request("initialize", json!({ "rootUri": uri(&dir) }));
+    assert_eq!(init["result"]["capabilities"]["renameProvider"], true);
+    client.notify("initialized", json!({}));
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": {
+            "uri": api_uri, "languageId": "pact", "version": 1, "text": API,
+        } }),
+    );
+    assert_eq!(client.diagnostics(&api_uri), Vec::<Value>::new());
+
+    // `user` in `-> user` is defined in types.pact.
+    let definition = client.request("textDocument/definition", at(&api_uri, 3, 27));
+    assert_eq!(definition["result"]["uri"], types_uri);
+    assert_eq!(
+        definition["result"]["range"],
+        json!({ "start": { "line": 0, "character": 7 }, "end": { "line": 0, "character": 11 } })
+    );
+
+    let (_, registry) = parse_pact_dir_with_registry(&dir).unwrap();
+    let types = &registry.get_interface("types").unwrap().types;
+    let hash = hash_type_in(&Type::named("user"), types).to_hex();
+    let hover = client.request("textDocument/hover", at(&api_uri, 3, 27));
+    let contents = hover["result"]["contents"]["value"].as_str().unwrap();
+    assert!(contents.contains("record user {"), "{contents}");
+    assert!(contents.contains(&hash), "{contents}");
+
+    // Members of the used interface, then everything in scope.
+    let completion = client.request("textDocument/completion", at(&api_uri, 0, 11));
+    assert_eq!(labels(&completion), ["user"]);
+    let completion = client.request("textDocument/completion", at(&api_uri, 3, 30));
+    let labels = labels(&completion);
+    assert!(
+        labels.contains(&"user") && labels.contains(&"u64"),
+        "{labels:?}"
+    );
+
+    let mut rename = at(&types_uri, 0, 9);
+    rename["newName"] = json!("account");
+    let edit = client.request("textDocument/rename", rename);
+    let changes = &edit["result"]["changes"];
+    let edited = |uri: &str| {
+        let edits = changes[uri].as_array().unwrap();
+        let mut starts: Vec<_> = edits.iter().map(|e| e["range"]["start"].clone()).collect();
+        starts.sort_by_key(|s| (s["line"].as_u64(), s["character"].as_u64()));
+        assert!(edits.iter().all(|e| e["newText"] == "account"));
+        starts
+    };
+    assert_eq!(edited(&types_uri), [json!({ "line": 0, "character": 7 })]);
+    assert_eq!(
+        edited(&api_uri),
+        [
+            json!({ "line": 0, "character": 11 }),
+            json!({ "line": 3, "character": 26 })
+        ]
+    );
+
+    // A typo is reported when the file is saved, and a file that does not
+    // parse is reported without hiding the rest. The server answers in
+    // order, so everything published before now has been read already.
+    client.pending.clear();
+    std::fs::write(dir.join("api.pact"), API.replace("-> user", "-> usr")).unwrap();
+    std::fs::write(dir.join("draft.pact"), "record {\n").unwrap();
+    client.notify(
+        "textDocument/didSave",
+        json!({ "textDocument": { "uri": api_uri } }),
+    );
+    let diagnostics = client.diagnostics(&api_uri);
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    let message = diagnostics[0]["message"].as_str().unwrap();
+    assert!(message.contains("undefined type `usr`"), "{message}");
+    assert!(message.contains("did you mean `user`?"), "{message}");
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 3, "character": 26 })
+    );
+    let draft = client.diagnostics(&uri(&dir.join("draft.pact")));
+    assert_eq!(draft.len(), 1, "{draft:?}");
+
+    let unknown = client.request("workspace/symbol", json!({ "query": "" }));
+    assert_eq!(unknown["error"]["code"], -32601);
+
+    let shutdown = client.request("shutdown", Value::Null);
+    assert_eq!(shutdown["result"], Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(client.child.wait().unwrap().success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,274 @@
+//! Record / replay tests
+//!
+//! A `RecordingInterceptor` log must replay through a `ReplayInterceptor`
+//! without the original host functions, and the first call that does not
+//! match the log must be reported with a diff pointing at what changed.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::runtime::{read_log, AsyncInstance, CallInterceptor, CallKind, Instance};
+use packr::{AsyncCtx, AsyncRuntime, Ctx, RecordingInterceptor, ReplayInterceptor, Runtime};
+use std::io::Write;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+/// A self-contained actor whose `run` export forwards its input to the
+/// `test:host.double` import and returns what the host answered.
+const FORWARDING_ACTOR: &str = r#"
+(module
+    (import "test:host" "double" (func $double (param i32 i32 i32 i32) (result i32)))
+    (memory (export "memory") 2)
+    (global $heap (mut i32) (i32.const 65536))
+
+    (func $alloc (export "__pack_alloc") (param $size i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr (global.get $heap))
+        (global.set $heap (i32.add (global.get $heap) (local.get $size)))
+        (local.get $ptr))
+
+    (func (export "__pack_free") (param i32 i32))
+
+    (func (export "run") (param $in i32) (param $len i32) (param $out_ptr i32) (param $out_len i32) (result i32)
+        (local $slots i32)
+        (local $status i32)
+        (local.set $slots (call $alloc (i32.const 8)))
+        (local.set $status
+            (call $double (local.get $in) (local.get $len)
+                (local.get $slots) (i32.add (local.get $slots) (i32.const 4))))
+        (if (i32.lt_s (local.get $status) (i32.const 0))
+            (then (return (local.get $status))))
+        (i32.store (local.get $out_ptr) (i32.load (local.get $slots)))
+        (i32.store (local.get $out_len) (i32.load offset=4 (local.get $slots)))
+        (i32.const 0))
+)
+"#;
+
+/// An in-memory log the test can read back while the recorder holds it.
+#[derive(Clone, Default)]
+struct SharedLog(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Instantiate the actor with a host `double` that multiplies by `factor`.
+async fn instantiate(interceptor: Arc<dyn CallInterceptor>, factor: i64) -> AsyncInstance<()> {
+    let wasm = wat::parse_str(FORWARDING_ACTOR).expect("failed to parse WAT");
+    let runtime = AsyncRuntime::new();
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    module
+        .instantiate_with_host_and_interceptor_async((), Some(interceptor), move |builder| {
+            builder
+                .interface("test:host")?
+                .func_async("double", move |_ctx: AsyncCtx<()>, n: i64| async move {
+                    n * factor
+                })?;
+            Ok(())
+        })
+        .await
+        .expect("failed to instantiate")
+}
+
+/// [`instantiate`] on the sync runtime, with the interceptor set on both the
+/// imports and the instance.
+fn instantiate_sync(interceptor: Arc<dyn CallInterceptor>, factor: i64) -> Instance<()> {
+    let wasm = wat::parse_str(FORWARDING_ACTOR).expect("failed to parse WAT");
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    let imports = interceptor.clone();
+    let mut instance = module
+        .instantiate_with_host((), move |builder| {
+            builder.set_interceptor(imports);
+            builder
+                .interface("test:host")?
+                .func_typed("double", move |_ctx: &mut Ctx<'_, ()>, n: i64| n * factor)?;
+            Ok(())
+        })
+        .expect("failed to instantiate");
+    instance.set_interceptor(interceptor);
+    instance
+}
+
+/// Record `run(3)` and `run(4)` against the real host.
+async fn record() -> Vec<u8> {
+    let log = SharedLog::default();
+    let recorder = Arc::new(RecordingInterceptor::new(log.clone()).unwrap());
+    let mut instance = instantiate(recorder.clone(), 2).await;
+    for n in [3, 4] {
+        let output = instance
+            .call_with_value_async("run", &Value::S64(n))
+            .await
+            .expect("run");
+        assert_eq!(output, Value::S64(n * 2));
+    }
+    recorder.finish().unwrap();
+    let bytes = log.0.lock().unwrap().clone();
+    bytes
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn recording_captures_imports_and_exports_in_order() {
+    let events = read_log(&record().await).expect("read log");
+
+    let shape: Vec<_> = events
+        .iter()
+        .map(|e| (e.kind, e.interface.as_str(), e.function.as_str()))
+        .collect();
+    assert_eq!(
+        shape,
+        [
+            (CallKind::Import, "test:host", "double"),
+            (CallKind::Export, "", "run"),
+            (CallKind::Import, "test:host", "double"),
+            (CallKind::Export, "", "run"),
+        ]
+    );
+    assert_eq!(events[2].input, Value::S64(4));
+    assert_eq!(events[2].output, Value::S64(8));
+
+    // A log cut off mid-frame still reads back up to its last full event.
+    let bytes = record().await;
+    assert_eq!(read_log(&bytes[..bytes.len() - 1]).unwrap().len(), 3);
+    assert!(read_log(b"not a log").is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn replay_serves_recorded_imports() {
+    let replay = Arc::new(ReplayInterceptor::from_log(&record().await).unwrap());
+
+    // The host now triples, but imports are answered from the log.
+    let mut instance = instantiate(replay.clone(), 3).await;
+    for n in [3, 4] {
+        let output = instance
+            .call_with_value_async("run", &Value::S64(n))
+            .await
+            .expect("run");
+        assert_eq!(output, Value::S64(n * 2));
+    }
+    assert_eq!(replay.replayed(), 4);
+    assert_eq!(replay.finish(), Ok(()));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn replay_reports_first_divergence() {
+    let replay = Arc::new(ReplayInterceptor::from_log(&record().await).unwrap());
+    let mut instance = instantiate(replay.clone(), 2).await;
+
+    instance
+        .call_with_value_async("run", &Value::S64(3))
+        .await
+        .expect("run");
+    // The second recorded call was run(4).
+    let output = instance
+        .call_with_value_async("run", &Value::S64(5))
+        .await
+        .expect("run");
+    assert_eq!(output, Value::S64(10), "diverged calls run normally");
+
+    let divergence = replay.finish().expect_err("divergence");
+    assert_eq!(divergence.index, 2);
+    assert_eq!(divergence.differences.len(), 1);
+    let diff = &divergence.differences[0];
+    assert_eq!(diff.path, "input");
+    assert_eq!(diff.expected, Some(Value::S64(4)));
+    assert_eq!(diff.actual, Some(Value::S64(5)));
+    assert!(divergence.to_string().contains("import test:host.double"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn replay_reports_unreplayed_events() {
+    let replay = Arc::new(ReplayInterceptor::from_log(&record().await).unwrap());
+    let mut instance = instantiate(replay.clone(), 2).await;
+    instance
+        .call_with_value_async("run", &Value::S64(3))
+        .await
+        .expect("run");
+
+    let divergence = replay.finish().expect_err("log not exhausted");
+    assert_eq!(divergence.index, 2);
+    assert_eq!(divergence.differences[0].path, "call");
+    assert_eq!(replay.remaining().len(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn sync_instance_records_and_replays_exports() {
+    let log = SharedLog::default();
+    let recorder = Arc::new(RecordingInterceptor::new(log.clone()).unwrap());
+    let mut instance = instantiate_sync(recorder.clone(), 2);
+    for n in [3, 4] {
+        let output = instance
+            .call_with_value("run", &Value::S64(n))
+            .expect("run");
+        assert_eq!(output, Value::S64(n * 2));
+    }
+    recorder.finish().unwrap();
+    let recorded = log.0.lock().unwrap().clone();
+    assert_eq!(
+        read_log(&recorded).unwrap(),
+        read_log(&record().await).unwrap(),
+        "sync and async instances record the same log"
+    );
+
+    let replay = Arc::new(ReplayInterceptor::from_log(&recorded).unwrap());
+    let mut instance = instantiate_sync(replay.clone(), 3);
+    for n in [3, 4] {
+        let output = instance
+            .call_with_value("run", &Value::S64(n))
+            .expect("run");
+        assert_eq!(output, Value::S64(n * 2));
+    }
+    assert_eq!(replay.replayed(), 4);
+    assert_eq!(replay.finish(), Ok(()));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn replay_cli_reexecutes_log() {
+    let tmp = std::env::temp_dir();
+    let id = std::process::id();
+    let wasm_path = tmp.join(format!("packr-replay-{id}.wasm"));
+    let log_path = tmp.join(format!("packr-replay-{id}.log"));
+    let forged_path = tmp.join(format!("packr-replay-forged-{id}.log"));
+    std::fs::write(&wasm_path, wat::parse_str(FORWARDING_ACTOR).unwrap()).unwrap();
+    std::fs::write(&log_path, record().await).unwrap();
+
+    // A log claiming the actor answered 7 where it will really answer 6.
+    let forged = RecordingInterceptor::create(&forged_path).unwrap();
+    let (input, double) = (Value::S64(3), Value::S64(6));
+    forged
+        .after_import("test:host", "double", &input, &double)
+        .await;
+    forged.after_export("run", &input, &Value::S64(7)).await;
+    forged.finish().unwrap();
+
+    let replay = |log: &std::path::Path| {
+        Command::new(env!("CARGO_BIN_EXE_packr"))
+            .args(["replay", wasm_path.to_str().unwrap(), log.to_str().unwrap()])
+            .output()
+            .expect("run packr replay")
+    };
+    let ok = replay(&log_path);
+    let diverged = replay(&forged_path);
+    for path in [&wasm_path, &log_path, &forged_path] {
+        let _ = std::fs::remove_file(path);
+    }
+
+    assert!(
+        ok.status.success(),
+        "{}",
+        String::from_utf8_lossy(&ok.stderr)
+    );
+    assert!(!diverged.status.success());
+    let stderr = String::from_utf8_lossy(&diverged.stderr);
+    assert!(
+        stderr.contains("output: expected 7s64, got 6s64"),
+        "{stderr}"
+    );
+}
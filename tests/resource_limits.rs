@@ -0,0 +1,188 @@
+//! Resource limit tests
+//!
+//! Fuel, memory and table limits set on a runtime must stop a guest that
+//! exceeds them with a distinct `ResourceExhausted` error, and leave guests
+//! that stay within them untouched.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::abi::Value;
+use packr::runtime::RuntimeError;
+use packr::{AsyncRuntime, ResourceKind, ResourceLimits, Runtime};
+
+/// A guest that can spin, burn a measured amount of work, grow its memory,
+/// and grow its table.
+const GREEDY_MODULE: &str = r#"
+(module
+    (memory (export "memory") 1)
+    (table 1 funcref)
+
+    (func (export "spin") (param i32 i32)
+        (loop $forever
+            br $forever))
+
+    ;; Count down from `n`; cost is linear in `n`.
+    (func (export "count") (param $n i32) (param i32) (result i32)
+        (block $done
+            (loop $again
+                local.get $n
+                i32.eqz
+                br_if $done
+                local.get $n
+                i32.const 1
+                i32.sub
+                local.set $n
+                br $again))
+        local.get $n)
+
+    (func (export "grow_memory") (param $pages i32) (param i32) (result i32)
+        local.get $pages
+        memory.grow)
+
+    (func (export "grow_table") (param $elems i32) (param i32) (result i32)
+        ref.null func
+        local.get $elems
+        table.grow 0)
+
+    ;; Pack ABI export that never returns.
+    (func (export "process") (param i32 i32 i32 i32) (result i32)
+        (loop $forever
+            br $forever)
+        i32.const 0)
+)
+"#;
+
+fn exhausted(err: RuntimeError) -> (ResourceKind, u64, u64) {
+    match err {
+        RuntimeError::ResourceExhausted { kind, used, limit } => (kind, used, limit),
+        other => panic!("expected ResourceExhausted, got {other:?}"),
+    }
+}
+
+#[test]
+fn fuel_budget_stops_runaway_guest() {
+    let wasm = wat::parse_str(GREEDY_MODULE).expect("failed to parse WAT");
+    let runtime = Runtime::with_limits(ResourceLimits::default().with_fuel_per_call(100_000));
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    let mut instance = module.instantiate().expect("failed to instantiate");
+
+    let err = instance.call_i32_i32("spin", 0, 0).unwrap_err();
+    assert_eq!(
+        exhausted(err),
+        (ResourceKind::Fuel, 100_000, 100_000),
+        "spin should burn the whole budget"
+    );
+    // The error reports what was spent, as `fuel_consumed` does.
+    assert_eq!(instance.fuel_consumed(), Some(100_000));
+
+    // The budget is per call: the next call starts with a full tank.
+    assert_eq!(instance.call_i32_i32_to_i32("count", 10, 0).unwrap(), 0);
+    let small = instance.fuel_consumed().expect("fuel is metered");
+    assert_eq!(instance.call_i32_i32_to_i32("count", 1000, 0).unwrap(), 0);
+    let large = instance.fuel_consumed().expect("fuel is metered");
+    assert!(
+        small > 0 && large > small,
+        "consumption tracks work: {small} vs {large}"
+    );
+
+    // Pack ABI calls are metered too.
+    let err = instance
+        .call_with_value("process", &Value::S64(1))
+        .unwrap_err();
+    assert_eq!(exhausted(err).0, ResourceKind::Fuel);
+}
+
+#[test]
+fn memory_limit_stops_growth() {
+    let wasm = wat::parse_str(GREEDY_MODULE).expect("failed to parse WAT");
+    let limit = 4 * 65536;
+    let runtime = Runtime::with_limits(ResourceLimits::default().with_max_memory_bytes(limit));
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    let mut instance = module.instantiate().expect("failed to instantiate");
+
+    // Growing within the limit works (memory.grow returns the old size).
+    assert_eq!(
+        instance.call_i32_i32_to_i32("grow_memory", 3, 0).unwrap(),
+        1
+    );
+
+    let err = instance
+        .call_i32_i32_to_i32("grow_memory", 1, 0)
+        .unwrap_err();
+    assert_eq!(
+        exhausted(err),
+        (ResourceKind::Memory, 5 * 65536, limit as u64)
+    );
+    assert_eq!(instance.memory_size().unwrap(), limit);
+}
+
+#[test]
+fn memory_limit_rejects_oversized_module() {
+    let wasm = wat::parse_str(r#"(module (memory (export "memory") 8))"#).unwrap();
+    let runtime = Runtime::with_limits(ResourceLimits::default().with_max_memory_bytes(65536));
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    let err = module.instantiate().err().expect("instantiation must fail");
+    assert_eq!(exhausted(err).0, ResourceKind::Memory);
+}
+
+#[test]
+fn table_limit_stops_growth() {
+    let wasm = wat::parse_str(GREEDY_MODULE).expect("failed to parse WAT");
+    let runtime = Runtime::with_limits(ResourceLimits::default().with_max_table_elements(8));
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    let mut instance = module.instantiate().expect("failed to instantiate");
+
+    assert_eq!(instance.call_i32_i32_to_i32("grow_table", 7, 0).unwrap(), 1);
+    let err = instance
+        .call_i32_i32_to_i32("grow_table", 1, 0)
+        .unwrap_err();
+    assert_eq!(exhausted(err), (ResourceKind::Table, 9, 8));
+}
+
+#[test]
+fn unlimited_runtime_does_not_meter() {
+    let wasm = wat::parse_str(GREEDY_MODULE).expect("failed to parse WAT");
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    let mut instance = module.instantiate().expect("failed to instantiate");
+
+    assert_eq!(instance.call_i32_i32_to_i32("count", 1000, 0).unwrap(), 0);
+    assert_eq!(instance.fuel_consumed(), None);
+    assert_eq!(
+        instance.call_i32_i32_to_i32("grow_memory", 16, 0).unwrap(),
+        1
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn async_runtime_applies_limits() {
+    let wasm = wat::parse_str(GREEDY_MODULE).expect("failed to parse WAT");
+    let runtime = AsyncRuntime::with_limits(
+        ResourceLimits::default()
+            .with_fuel_per_call(50_000)
+            .with_max_memory_bytes(2 * 65536),
+    );
+    let module = runtime.load_module(&wasm).expect("failed to load module");
+    let mut instance = module.instantiate_async().await.expect("instantiate");
+
+    let err = instance
+        .call_i32_i32_to_i32_async("count", i32::MAX, 0)
+        .await
+        .unwrap_err();
+    assert_eq!(exhausted(err).0, ResourceKind::Fuel);
+
+    let err = instance
+        .call_i32_i32_to_i32_async("grow_memory", 2, 0)
+        .await
+        .unwrap_err();
+    assert_eq!(exhausted(err).0, ResourceKind::Memory);
+
+    assert_eq!(
+        instance
+            .call_i32_i32_to_i32_async("count", 10, 0)
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(instance.fuel_consumed().unwrap() > 0);
+}
@@ -1,7 +1,11 @@
-use packr::abi::{encode, GraphBuffer, Node, NodeKind, Value, ValueType};
+// These tests exercise the legacy interface parser directly.
+#![allow(deprecated)]
+
+use packr::abi::{decode, encode, GraphBuffer, Node, NodeKind, Value, ValueType};
+use packr::metadata::{hash_type_in, HASH_STRING};
 use packr::parser::{
-    decode_with_schema, encode_with_schema, parse_interface, validate_graph_against_type,
-    ValidationError,
+    decode_with_schema, encode_compact_with_schema, encode_with_schema, parse_interface,
+    validate_graph_against_type, ValidationError,
 };
 use packr::types::Type;
 
@@ -211,3 +215,99 @@ fn encode_with_schema_rejects_record_field_order() {
         _ => panic!("unexpected error: {err:?}"),
     }
 }
+
+#[test]
+fn compact_profile_roundtrips_through_schema() {
+    let src = r#"
+        interface api {
+            enum level { low, high }
+            record entry { key: string, level: level, children: list<tree> }
+            variant tree { leaf(entry), empty }
+        }
+    "#;
+    let interface = parse_interface(src).expect("parse");
+    let root = Type::named("tree");
+
+    let entry = |key: &str, tag: usize, children: Vec<Value>| Value::Record {
+        type_name: "entry".to_string(),
+        fields: vec![
+            ("key".to_string(), Value::String(key.to_string())),
+            (
+                "level".to_string(),
+                Value::Variant {
+                    type_name: "level".to_string(),
+                    case_name: ["low", "high"][tag].to_string(),
+                    tag,
+                    payload: vec![],
+                },
+            ),
+            (
+                "children".to_string(),
+                Value::List {
+                    elem_type: ValueType::Variant("tree".to_string()),
+                    items: children,
+                },
+            ),
+        ],
+    };
+    let leaf = |entry: Value| Value::Variant {
+        type_name: "tree".to_string(),
+        case_name: "leaf".to_string(),
+        tag: 0,
+        payload: vec![entry],
+    };
+    let empty = Value::Variant {
+        type_name: "tree".to_string(),
+        case_name: "empty".to_string(),
+        tag: 1,
+        payload: vec![],
+    };
+    let value = leaf(entry(
+        "root",
+        1,
+        vec![leaf(entry("a", 0, vec![])), empty.clone(), empty],
+    ));
+
+    let hash = hash_type_in(&root, &interface.types);
+    let compact =
+        encode_compact_with_schema(&interface.types, &value, &root, &hash).expect("compact");
+    let full = encode_with_schema(&interface.types, &value, &root).expect("full");
+    assert!(compact.len() < full.len());
+
+    // Names come back from the schema; either profile decodes the same.
+    let decoded = decode_with_schema(&interface.types, &compact, &root, None).expect("decode");
+    assert_eq!(decoded, value);
+    assert_eq!(
+        decode_with_schema(&interface.types, &full, &root, None).expect("decode full"),
+        value
+    );
+
+    // Without a schema the compact buffer is refused rather than misread.
+    assert!(decode(&compact).is_err());
+}
+
+#[test]
+fn compact_profile_requires_matching_schema_hash() {
+    let src = r#"
+        interface api {
+            record config { name: string, enabled: bool }
+        }
+    "#;
+    let interface = parse_interface(src).expect("parse");
+    let value = Value::Record {
+        type_name: "config".to_string(),
+        fields: vec![
+            ("name".to_string(), Value::String("x".to_string())),
+            ("enabled".to_string(), Value::Bool(true)),
+        ],
+    };
+
+    let err = encode_compact_with_schema(
+        &interface.types,
+        &value,
+        &Type::named("config"),
+        &HASH_STRING,
+    )
+    .expect_err("peer hash differs");
+    assert!(matches!(err, ValidationError::SchemaHashMismatch { .. }));
+}
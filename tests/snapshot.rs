@@ -0,0 +1,324 @@
+//! Instance snapshot / restore tests
+//!
+//! A snapshot must carry memory, private mutable globals and table entries
+//! across a restore, survive serialisation, and be refused by a module whose
+//! `__pack_types` interfaces differ.
+
+#![cfg(feature = "wasmtime")]
+
+use packr::metadata::{encode_metadata, encode_metadata_with_hashes};
+use packr::runtime::RuntimeError;
+use packr::{Arena, AsyncRuntime, Function, InstanceSnapshot, Param, Runtime, Type};
+
+/// Package metadata exporting `bump: func(n: s32) -> result`.
+fn counter_interface(result: Type) -> Arena {
+    let mut interface = Arena::new("test:snapshot/counter");
+    interface.add_function(Function::with_signature(
+        "bump",
+        vec![Param::new("n", Type::S32)],
+        vec![result],
+    ));
+    let mut exports = Arena::new("exports");
+    exports.add_child(interface);
+    let mut package = Arena::new("package");
+    package.add_child(exports);
+    package
+}
+
+fn metadata_v1() -> Vec<u8> {
+    encode_metadata(&counter_interface(Type::S32)).unwrap()
+}
+
+fn metadata_v2() -> Vec<u8> {
+    encode_metadata(&counter_interface(Type::S64)).unwrap()
+}
+
+/// A guest whose state lives in a private counter, a private stack pointer,
+/// its memory and a private table. `metadata` is what `__pack_types` returns.
+fn stateful_module(metadata: &[u8]) -> Vec<u8> {
+    let escaped: String = metadata.iter().map(|b| format!("\\{b:02x}")).collect();
+    let wat = format!(
+        r#"
+(module
+    (memory (export "memory") 1)
+    (table $t 1 funcref)
+    (global $__stack_pointer (mut i32) (i32.const 65536))
+    (global $counter (mut i64) (i64.const 0))
+    (data (i32.const 1024) "{escaped}")
+
+    ;; Bump the counter, push it on the "stack", and return it.
+    (func (export "bump") (param i32 i32) (result i32)
+        (global.set $counter (i64.add (global.get $counter) (i64.const 1)))
+        (global.set $__stack_pointer (i32.sub (global.get $__stack_pointer) (i32.const 8)))
+        (i64.store (global.get $__stack_pointer) (global.get $counter))
+        (i32.wrap_i64 (global.get $counter)))
+
+    (func (export "stack_pointer") (param i32 i32) (result i32)
+        (global.get $__stack_pointer))
+
+    (func (export "grow_memory") (param i32 i32) (result i32)
+        (memory.grow (local.get 0)))
+
+    (func (export "grow_table") (param i32 i32) (result i32)
+        (table.grow $t (ref.null func) (local.get 0)))
+
+    (func (export "table_size") (param i32 i32) (result i32)
+        (table.size $t))
+
+    (func (export "__pack_types") (param $out_ptr i32) (param $out_len i32) (result i32)
+        (i32.store (local.get $out_ptr) (i32.const 1024))
+        (i32.store (local.get $out_len) (i32.const {len}))
+        (i32.const 0))
+)
+"#,
+        len = metadata.len()
+    );
+    wat::parse_str(wat).expect("failed to parse WAT")
+}
+
+fn call(instance: &mut packr::Instance<()>, name: &str, arg: i32) -> i32 {
+    instance.call_i32_i32_to_i32(name, arg, 0).unwrap()
+}
+
+fn bump(instance: &mut packr::Instance<()>) -> i32 {
+    call(instance, "bump", 0)
+}
+
+#[test]
+fn restore_resumes_memory_globals_and_tables() {
+    let runtime = Runtime::new().with_snapshots();
+    let module = runtime
+        .load_module(&stateful_module(&metadata_v1()))
+        .expect("failed to load module");
+    let mut instance = module.instantiate().expect("failed to instantiate");
+
+    for _ in 0..3 {
+        bump(&mut instance);
+    }
+    instance.write_memory(2048, b"checkpoint").unwrap();
+    assert_eq!(call(&mut instance, "grow_memory", 1), 1);
+    assert_eq!(call(&mut instance, "grow_table", 4), 1);
+
+    let snapshot = instance.snapshot().expect("snapshot");
+    assert!(snapshot.types_hash().is_some());
+    let snapshot = InstanceSnapshot::from_bytes(&snapshot.to_bytes()).expect("round trip");
+
+    let mut restored = module.restore(&snapshot).expect("restore");
+    assert_eq!(restored.read_memory(2048, 10).unwrap(), b"checkpoint");
+    assert_eq!(restored.memory_size().unwrap(), 2 * 65536);
+    assert_eq!(call(&mut restored, "table_size", 0), 5);
+    assert_eq!(call(&mut restored, "stack_pointer", 0), 65536 - 24);
+
+    // Both instances carry on independently from the checkpoint.
+    assert_eq!(bump(&mut restored), 4);
+    assert_eq!(bump(&mut instance), 4);
+    assert_eq!(bump(&mut instance), 5);
+
+    // Restoring in place rolls a live instance back.
+    instance.restore(&snapshot).expect("restore in place");
+    assert_eq!(bump(&mut instance), 4);
+    assert_eq!(call(&mut instance, "stack_pointer", 0), 65536 - 32);
+}
+
+#[test]
+fn restore_refuses_incompatible_module() {
+    let runtime = Runtime::new();
+    let v1 = runtime
+        .load_module(&stateful_module(&metadata_v1()))
+        .unwrap();
+    let v2 = runtime
+        .load_module(&stateful_module(&metadata_v2()))
+        .unwrap();
+
+    let snapshot = v1.instantiate().unwrap().snapshot().unwrap();
+    let err = v2
+        .restore(&snapshot)
+        .err()
+        .expect("restore must be refused");
+    match err {
+        RuntimeError::SnapshotError(msg) => assert!(msg.contains("incompatible"), "{msg}"),
+        other => panic!("expected SnapshotError, got {other:?}"),
+    }
+}
+
+#[test]
+fn restore_accepts_another_encoding_of_the_same_interface() {
+    let runtime = Runtime::new();
+    let plain = metadata_v1();
+    let hashed = encode_metadata_with_hashes(&counter_interface(Type::S32)).unwrap();
+    assert_ne!(plain, hashed);
+
+    let v1 = runtime.load_module(&stateful_module(&plain)).unwrap();
+    let v1_hashed = runtime.load_module(&stateful_module(&hashed)).unwrap();
+    let snapshot = v1.instantiate().unwrap().snapshot().unwrap();
+    v1_hashed
+        .restore(&snapshot)
+        .expect("same interface must restore");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn modules_are_instrumented_only_with_snapshots() {
+    let snapshot_exports = |runtime: &AsyncRuntime| {
+        let module = runtime
+            .load_module(&stateful_module(&metadata_v1()))
+            .unwrap();
+        module
+            .module()
+            .exports()
+            .filter(|e| e.name().starts_with("__pack_snapshot_"))
+            .count()
+    };
+    assert_eq!(snapshot_exports(&AsyncRuntime::new()), 0);
+    // The stack pointer, the counter and the table.
+    assert_eq!(snapshot_exports(&AsyncRuntime::new().with_snapshots()), 3);
+}
+
+#[test]
+fn corrupt_snapshot_is_rejected() {
+    let runtime = Runtime::new();
+    let module = runtime
+        .load_module(&stateful_module(&metadata_v1()))
+        .unwrap();
+    let bytes = module.instantiate().unwrap().snapshot().unwrap().to_bytes();
+
+    assert!(InstanceSnapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(InstanceSnapshot::from_bytes(b"not a snapshot").is_err());
+}
+
+#[test]
+fn partial_page_memory_is_rejected() {
+    let mut bytes = b"PKSN".to_vec();
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.push(0); // no types hash
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(&6u64.to_le_bytes());
+    bytes.extend_from_slice(b"memory");
+    bytes.extend_from_slice(&100u64.to_le_bytes());
+    bytes.extend_from_slice(&[0; 100]);
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // globals
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // tables
+
+    let err = InstanceSnapshot::from_bytes(&bytes).unwrap_err();
+    assert!(matches!(err, RuntimeError::SnapshotError(_)), "{err}");
+}
+
+/// A guest that puts `$one` in its private table and can swap it for `$two`
+/// at run time.
+const TABLE_MODULE: &str = r#"
+(module
+    (memory (export "memory") 1)
+    (type $ret (func (result i32)))
+    (table $t 1 funcref)
+    (elem (table $t) (i32.const 0) func $one)
+    (elem declare func $two)
+    (func $one (result i32) (i32.const 1))
+    (func $two (result i32) (i32.const 2))
+
+    (func (export "swap") (param i32 i32) (result i32)
+        (table.set $t (i32.const 0) (ref.func $two))
+        (i32.const 0))
+
+    (func (export "slot") (param i32 i32) (result i32)
+        (call_indirect $t (type $ret) (local.get 0)))
+)
+"#;
+
+#[test]
+fn restore_carries_table_entries() {
+    let runtime = Runtime::new().with_snapshots();
+    let module = runtime
+        .load_module(&wat::parse_str(TABLE_MODULE).unwrap())
+        .unwrap();
+    let mut instance = module.instantiate().unwrap();
+    call(&mut instance, "swap", 0);
+    assert_eq!(call(&mut instance, "slot", 0), 2);
+
+    let snapshot = instance.snapshot().expect("snapshot");
+    let snapshot = InstanceSnapshot::from_bytes(&snapshot.to_bytes()).expect("round trip");
+    let mut restored = module.restore(&snapshot).expect("restore");
+    assert_eq!(call(&mut restored, "slot", 0), 2);
+}
+
+#[test]
+fn unexported_table_function_is_refused() {
+    // Export the table but not the functions in it, and load without
+    // instrumentation, so the entry has no name to be recorded under.
+    let wat = TABLE_MODULE.replace(
+        "(table $t 1 funcref)",
+        "(table $t (export \"t\") 1 funcref)",
+    );
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&wat::parse_str(wat).unwrap()).unwrap();
+    let err = module.instantiate().unwrap().snapshot().unwrap_err();
+    assert!(matches!(err, RuntimeError::SnapshotError(_)), "{err}");
+}
+
+#[test]
+fn snapshot_is_taken_before_reading_types() {
+    // `__pack_alloc` leaves a mark in memory every time it is called, as a
+    // real allocator updates its heap. Reading `__pack_types` allocates.
+    let metadata = metadata_v1();
+    let escaped: String = metadata.iter().map(|b| format!("\\{b:02x}")).collect();
+    let wat = format!(
+        r#"
+(module
+    (memory (export "memory") 1)
+    (data (i32.const 1024) "{escaped}")
+    (func (export "__pack_alloc") (param i32) (result i32)
+        (i32.store8 (i32.const 512) (i32.const 1))
+        (i32.const 4096))
+    (func (export "__pack_free") (param i32 i32))
+    (func (export "__pack_types") (param $out_ptr i32) (param $out_len i32) (result i32)
+        (i32.store (local.get $out_ptr) (i32.const 1024))
+        (i32.store (local.get $out_len) (i32.const {len}))
+        (i32.const 0))
+)
+"#,
+        len = metadata.len()
+    );
+    let runtime = Runtime::new();
+    let module = runtime.load_module(&wat::parse_str(wat).unwrap()).unwrap();
+    let mut instance = module.instantiate().unwrap();
+
+    let snapshot = instance.snapshot().expect("snapshot");
+    assert!(snapshot.types_hash().is_some());
+    assert_eq!(snapshot.memory("memory").unwrap()[512], 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn async_snapshot_round_trip() {
+    let runtime = AsyncRuntime::new().with_snapshots();
+    let module = runtime
+        .load_module(&stateful_module(&metadata_v1()))
+        .unwrap();
+    let mut instance = module.instantiate_async().await.unwrap();
+
+    instance.write_memory(4096, b"async state").unwrap();
+    let snapshot = instance.snapshot().await.expect("snapshot");
+
+    let mut restored = module.restore_async(&snapshot).await.expect("restore");
+    assert_eq!(restored.read_memory(4096, 11).unwrap(), b"async state");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn self_contained_actor_memory_is_captured_once() {
+    let wat = r#"
+    (module
+        (memory (export "memory") 1)
+        (func (export "__pack_alloc") (param i32) (result i32) (i32.const 1024))
+        (func (export "__pack_free") (param i32 i32)))
+    "#;
+    let runtime = AsyncRuntime::new();
+    let module = runtime.load_module(&wat::parse_str(wat).unwrap()).unwrap();
+    let mut instance = module
+        .instantiate_with_host_async((), |_| Ok(()))
+        .await
+        .unwrap();
+
+    instance.write_memory(2048, b"actor").unwrap();
+    let bytes = instance.snapshot().await.unwrap().to_bytes();
+    assert!(bytes.len() < 2 * 65536, "memory recorded twice");
+
+    let snapshot = InstanceSnapshot::from_bytes(&bytes).unwrap();
+    assert_eq!(&snapshot.memory("memory").unwrap()[2048..2053], b"actor");
+}
@@ -1,5 +1,7 @@
 //! Integration tests for embedded type metadata.
 
+#![cfg(feature = "wasmtime")]
+
 use packr::abi::{encode, Value, ValueType};
 use packr::metadata::{decode_metadata, MetadataError, TypeDesc};
 use packr::runtime::Runtime;
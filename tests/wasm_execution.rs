@@ -2,6 +2,8 @@
 //!
 //! These tests verify that we can load and run WASM modules through the Runtime.
 
+#![cfg(feature = "wasmtime")]
+
 use packr::abi::{Value, ValueType};
 use packr::Runtime;
 use std::path::Path;